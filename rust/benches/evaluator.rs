@@ -0,0 +1,109 @@
+//! Benchmarks of the Evaluator with the PlaintextBackend. Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use zki_sieve::consumers::evaluator::{Evaluator, PlaintextBackend};
+use zki_sieve::producers::examples::example_header;
+use zki_sieve::structs::function::{ForLoopBody, Function};
+use zki_sieve::structs::iterators::{IterExprListElement::Single, IterExprWireNumber};
+use zki_sieve::structs::relation::{ARITH, FOR, FUNCTION};
+use zki_sieve::structs::wire::WireListElement::WireRange;
+use zki_sieve::{Gate, Relation, WireId};
+
+/// The number of wires computed by each circuit.
+const WIRES: u64 = 100_000;
+
+fn relation(functions: Vec<Function>, gates: Vec<Gate>) -> Relation {
+    Relation {
+        header: example_header(),
+        gate_mask: ARITH,
+        feat_mask: FOR | FUNCTION,
+        functions,
+        gates,
+    }
+}
+
+/// A flat circuit alternating additions and multiplications of the two previous wires. The ids of
+/// the wires are multiples of `stride`, to measure sparse ids.
+fn flat_relation(stride: WireId) -> Relation {
+    let mut gates = vec![Gate::Constant(0, vec![1]), Gate::Constant(stride, vec![2])];
+    for i in 2..WIRES {
+        let (out, left, right) = (i * stride, (i - 1) * stride, (i - 2) * stride);
+        gates.push(if i % 2 == 0 {
+            Gate::Add(out, left, right)
+        } else {
+            Gate::Mul(out, left, right)
+        });
+    }
+    relation(vec![], gates)
+}
+
+/// The same computation in a loop, with a body called once per wire, either as a function or as
+/// an anonymous body.
+fn loop_relation(anonymous: bool) -> Relation {
+    use IterExprWireNumber::*;
+
+    let body = vec![Gate::Mul(3, 1, 2), Gate::Add(0, 3, 1)];
+    let i = || IterExprName("i".to_string());
+    let minus = |offset| IterExprSub(Box::new(i()), Box::new(IterExprConst(offset)));
+    let outputs = vec![Single(i())];
+    let inputs = vec![Single(minus(1)), Single(minus(2))];
+
+    let (functions, loop_body) = if anonymous {
+        (
+            vec![],
+            ForLoopBody::IterExprAnonCall(outputs, inputs, 0, 0, body),
+        )
+    } else {
+        (
+            vec![Function::new("step".to_string(), 1, 2, 0, 0, body)],
+            ForLoopBody::IterExprCall("step".to_string(), outputs, inputs),
+        )
+    };
+    let gates = vec![
+        Gate::Constant(0, vec![1]),
+        Gate::Constant(1, vec![2]),
+        Gate::For(
+            "i".to_string(),
+            2,
+            WIRES - 1,
+            vec![WireRange(2, WIRES - 1)],
+            loop_body,
+        ),
+    ];
+    relation(functions, gates)
+}
+
+fn evaluate(relation: &Relation) {
+    let mut backend = PlaintextBackend::default();
+    let mut evaluator = Evaluator::default();
+    evaluator.ingest_relation(relation, &mut backend).unwrap();
+    black_box(evaluator);
+}
+
+fn bench_evaluator(c: &mut Criterion) {
+    let flat = flat_relation(1);
+    c.bench_function("evaluate flat circuit", |b| b.iter(|| evaluate(&flat)));
+
+    let sparse = flat_relation(1 << 20);
+    c.bench_function("evaluate flat circuit with sparse ids", |b| {
+        b.iter(|| evaluate(&sparse))
+    });
+
+    let function_loop = loop_relation(false);
+    c.bench_function("evaluate loop calling a function", |b| {
+        b.iter(|| evaluate(&function_loop))
+    });
+
+    let anonymous_loop = loop_relation(true);
+    c.bench_function("evaluate loop with an anonymous body", |b| {
+        b.iter(|| evaluate(&anonymous_loop))
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_evaluator
+}
+criterion_main!(benches);
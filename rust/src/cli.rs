@@ -2,25 +2,37 @@ extern crate serde;
 extern crate serde_json;
 
 use num_bigint::BigUint;
+use std::convert::TryFrom;
 use std::fs::File;
-use std::io::{copy, stdin, stdout, BufReader};
+use std::io::{copy, stdin, stdout, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use structopt::clap::AppSettings::*;
 pub use structopt::StructOpt;
 
-use crate::consumers::evaluator::PlaintextBackend;
+use crate::consumers::evaluator::{PlaintextBackend, ZKBackend};
 use crate::consumers::exp_definable::ExpandDefinable;
 use crate::consumers::flattening::IRFlattener;
+use crate::consumers::repartition::Repartitioner;
 use crate::consumers::{
+    constraint_analysis::ConstraintAnalyzer,
+    consumption::ConsumptionCounter,
     evaluator::Evaluator,
-    source::{has_sieve_extension, list_workspace_files},
+    limits::Limits,
+    lints::{LintLevels, Severity},
+    source::{
+        has_sieve_extension, iterate_json_lines, list_workspace_files, open_file, Compression,
+    },
     stats::Stats,
     validator::Validator,
 };
 use crate::producers::from_r1cs::FromR1CSConverter;
-use crate::producers::sink::MemorySink;
-use crate::{FilesSink, Message, Messages, Result, Sink, Source};
+use crate::producers::rotating_sink::RotatingFilesSink;
+use crate::producers::sink::{CompressedFilesSink, FileWriter, MemorySink};
+use crate::structs::field_presets::parse_field_order;
+use crate::structs::message::MessageView;
+use crate::structs::value_serde::{ValueFormat, WithValueFormat};
 use crate::{Instance, Relation, Witness};
+use crate::{Message, Messages, Result, Sink, Source};
 use zkinterface::WorkspaceSink;
 
 const ABOUT: &str = "
@@ -31,6 +43,9 @@ The tools below work within a workspace directory given after the tool name (`wo
 Create an example statement:
     zki_sieve example workspace
 
+Or in a well-known field (bn254, bls12-381, goldilocks, mersenne61, curve25519, gf2), or any prime:
+    zki_sieve example --field-order bn254 workspace
+
 Print a statement in different forms:
     zki_sieve to-text workspace
     zki_sieve to-json workspace
@@ -39,6 +54,9 @@ Print a statement in different forms:
 Validate and evaluate a proving system:
     zki_sieve valid-eval-metrics workspace
 
+Workspaces may contain files compressed with gzip (.sieve.gz) or Zstandard (.sieve.zst). Write them with --compress:
+    zki_sieve example --compress zstd workspace
+
 ";
 
 #[derive(Debug, StructOpt)]
@@ -58,15 +76,21 @@ pub struct Options {
     ///
     /// to-text       Print the content in a human-readable form.
     ///
-    /// to-json       Convert to JSON on a single line.
+    /// to-json       Convert to JSON on a single line, or to JSON Lines with one message per line with --lines.
     ///
-    /// to-yaml       Convert to YAML.
+    /// to-yaml       Convert to YAML, or to one YAML document per message with --lines.
     ///
-    /// validate      Validate the format and semantics of a statement, as seen by a verifier.
+    /// to-dot        Render relations as Graphviz graphs, gate by gate or as a summary with --summary.
+    ///
+    /// validate      Validate the format and semantics of a statement, as seen by a verifier. Lints are reported as warnings, see --allow and --deny.
     ///
     /// evaluate      Evaluate a circuit as prover to check that the statement is true, i.e. the witness satisfies the circuit.
     ///
-    /// metrics       Calculate statistics about the circuit.
+    /// lint          Find witnesses and wires that are not constrained by any assertion, or only by a linear one.
+    ///
+    /// consumption   Count the instance and witness values consumed by the relation, and check them against the Instance and Witness messages.
+    ///
+    /// metrics       Calculate statistics about the circuit, and its cost after inlining and unrolling with --effective.
     ///
     /// valid-eval-metrics    Combined validate, evaluate, and metrics.
     ///
@@ -74,17 +98,29 @@ pub struct Options {
     ///
     /// ir-to-zkif    Convert SIEVE IR files into R1CS zkinterface (takes 3 files for witness, instance, and relation, or a directory with 3 files).
     ///
+    /// circom-to-ir  Convert a circom .r1cs file, and optionally a .wtns file, into SIEVE IR.
+    ///
+    /// ir-to-circom  Convert SIEVE IR files into circom .r1cs and .wtns files (in the directory specified by --out).
+    ///
+    /// ir-to-plonk   Convert SIEVE IR files into a Plonkish table and witness (in the directory specified by --out).
+    ///
+    /// check-plonk   Check that a Plonkish witness satisfies a table (takes the table and witness files).
+    ///
     /// flatten       Flatten a SIEVE IR circuit (takes files and directories, output resulting circuit in stdout or directory specified by --out).
     ///
     /// expand-definable    Expand definable gates in SIEVE IR relation (e.g. addConstant, mulConstant, or convert between And/Xor and Mul/Add).
     ///
     /// list-validations    Lists all the checks performed by the validator.
     ///
-    /// cat           Concatenate .sieve files to stdout to pipe to another program.
+    /// split         Split the messages of a workspace into numbered files of bounded size, in the directory specified by --out (see --max-file-bytes and --max-file-messages).
+    ///
+    /// repartition   Rebuild the messages of a statement with --message-len gates or values each, merging small messages and splitting large ones, in the directory specified by --out.
+    ///
+    /// cat           Concatenate .sieve files to stdout to pipe to another program, decompressing them.
     #[structopt(default_value = "help")]
     pub tool: String,
 
-    /// The tools work in a workspace directory containing .sieve files.
+    /// The tools work in a workspace directory containing .sieve files, possibly compressed as .sieve.gz or .sieve.zst files.
     ///
     /// Alternatively, a list of .sieve files can be provided explicitly.
     ///
@@ -92,8 +128,9 @@ pub struct Options {
     #[structopt(default_value = ".")]
     pub paths: Vec<PathBuf>,
 
-    /// Which field to use when generating circuits.
-    #[structopt(short, long, default_value = "101")]
+    /// Which field to use when generating circuits: a number, or the name of a well-known field
+    /// (bn254, bls12-381, goldilocks, mersenne61, curve25519, gf2).
+    #[structopt(short, long, default_value = "101", parse(try_from_str = parse_field_order))]
     pub field_order: BigUint,
 
     /// `example --incorrect` will generate an incorrect witness useful for negative tests.
@@ -112,9 +149,81 @@ pub struct Options {
     #[structopt(short, long, default_value = "-")]
     pub out: PathBuf,
 
+    /// `--compress gzip|zstd` compresses the files written into a workspace directory, as .sieve.gz or .sieve.zst files.
+    #[structopt(long, default_value = "none")]
+    pub compress: Compression,
+
+    /// `split --max-file-bytes N` starts a new file before a message that would take a file over N bytes, before compression.
+    #[structopt(long)]
+    pub max_file_bytes: Option<u64>,
+
+    /// `split --max-file-messages N` starts a new file once a file holds N messages.
+    #[structopt(long)]
+    pub max_file_messages: Option<u64>,
+
+    /// `repartition --message-len N` sets the target number of gates (including function bodies) or values per message.
+    #[structopt(long, default_value = "100000")]
+    pub message_len: usize,
+
+    /// `to-json --lines` and `to-yaml --lines` write each message as it is read, on its own line or YAML document, instead of loading the whole statement. `from-json --lines` reads messages one at a time in the same way.
+    #[structopt(long)]
+    pub lines: bool,
+
+    /// `to-json --value-format decimal|hex` and `to-yaml --value-format decimal|hex` write values as decimal or hexadecimal strings instead of arrays of bytes. `from-json` and `from-yaml` read any of these forms.
+    #[structopt(long, default_value = "bytes")]
+    pub value_format: ValueFormat,
+
     /// Target gate set for expanding definable gates.
     #[structopt(long)]
     pub gate_set: Option<String>,
+
+    /// `to-dot --summary` renders functions, calls, loops and switches instead of individual gates.
+    #[structopt(long)]
+    pub summary: bool,
+
+    /// `to-dot --depth` limits the depth of the gates (or the nesting of loops and switches with --summary) to render.
+    #[structopt(long)]
+    pub depth: Option<usize>,
+
+    /// `to-dot --highlight` highlights the fan-in cone of the given wire.
+    #[structopt(long)]
+    pub highlight: Option<u64>,
+
+    /// `validate --allow LINT` ignores a lint (or all of them with "all"). See list-validations for the lints.
+    #[structopt(long, number_of_values = 1)]
+    pub allow: Vec<String>,
+
+    /// `validate --deny LINT` reports a lint as a violation instead of a warning (or all of them with "all").
+    #[structopt(long, number_of_values = 1)]
+    pub deny: Vec<String>,
+
+    /// `metrics --effective` also counts the gates executed after inlining functions, unrolling loops and multiplexing switches, with a profile of each function.
+    #[structopt(long)]
+    pub effective: bool,
+
+    /// `--max-unrolled-gates N` limits the number of gates processed by the tools that validate, evaluate, measure, lint or convert circuits, counting function bodies once per call and loop bodies once per iteration.
+    #[structopt(long)]
+    pub max_unrolled_gates: Option<u64>,
+
+    /// `--max-loop-iterations N` limits the number of iterations of a loop.
+    #[structopt(long)]
+    pub max_loop_iterations: Option<u64>,
+
+    /// `--max-depth N` limits the nesting depth of calls, loops and switches.
+    #[structopt(long)]
+    pub max_depth: Option<usize>,
+
+    /// `--max-wire-list-size N` limits the number of wires in a wire list or a range, once expanded.
+    #[structopt(long)]
+    pub max_wire_list_size: Option<u64>,
+
+    /// `--max-live-wires N` limits the number of wires alive at the same time in a scope.
+    #[structopt(long)]
+    pub max_live_wires: Option<u64>,
+
+    /// `--max-value-bytes N` limits the length of values.
+    #[structopt(long)]
+    pub max_value_bytes: Option<usize>,
 }
 
 pub fn cli(options: &Options) -> Result<()> {
@@ -122,19 +231,31 @@ pub fn cli(options: &Options) -> Result<()> {
         "example" => main_example(options),
         "bool-example" => main_boolean_example(options),
         "to-text" => main_text(&load_messages(options)?),
-        "to-json" => main_json(&load_messages(options)?),
+        "to-json" if options.lines => main_json_lines(&stream_messages(options)?, options),
+        "to-json" => main_json(&load_messages(options)?, options),
+        "from-json" if options.lines => from_json_lines(options),
         "from-json" => from_json(options),
-        "to-yaml" => main_yaml(&load_messages(options)?),
+        "to-yaml" if options.lines => main_yaml_lines(&stream_messages(options)?, options),
+        "to-yaml" => main_yaml(&load_messages(options)?, options),
+        "to-dot" => main_dot(options),
         "from-yaml" => from_yaml(options),
-        "validate" => main_validate(&stream_messages(options)?),
-        "evaluate" => main_evaluate(&stream_messages(options)?),
-        "metrics" => main_metrics(&stream_messages(options)?),
-        "valid-eval-metrics" => main_valid_eval_metrics(&stream_messages(options)?),
+        "validate" => main_validate(&stream_messages(options)?, options),
+        "evaluate" => main_evaluate(&stream_messages(options)?, options),
+        "lint" => main_lint(&stream_messages(options)?, options),
+        "consumption" => main_consumption(&stream_messages(options)?),
+        "metrics" => main_metrics(&stream_messages(options)?, options),
+        "valid-eval-metrics" => main_valid_eval_metrics(&stream_messages(options)?, options),
         "zkif-to-ir" => main_zkif_to_ir(options),
         "ir-to-zkif" => main_ir_to_r1cs(options),
+        "circom-to-ir" => main_circom_to_ir(options),
+        "ir-to-circom" => main_ir_to_circom(options),
+        "ir-to-plonk" => main_ir_to_plonk(options),
+        "check-plonk" => main_check_plonk(options),
         "flatten" => main_ir_flattening(options),
         "expand-definable" => main_expand_definable(options),
         "list-validations" => main_list_validations(),
+        "split" => main_split(options),
+        "repartition" => main_repartition(options),
         "cat" => main_cat(options),
         "simulate" => Err("`simulate` was renamed to `evaluate`".into()),
         "stats" => Err("`stats` was renamed to `metrics`".into()),
@@ -155,12 +276,58 @@ fn load_messages(opts: &Options) -> Result<Messages> {
     stream_messages(opts)?.read_all_messages()
 }
 
+/// The conservative limits, overridden by the options.
+fn limits(opts: &Options) -> Limits {
+    let mut limits = Limits::conservative();
+    if let Some(max) = opts.max_unrolled_gates {
+        limits.max_unrolled_gates = max;
+    }
+    if let Some(max) = opts.max_loop_iterations {
+        limits.max_loop_iterations = max;
+    }
+    if let Some(max) = opts.max_depth {
+        limits.max_depth = max;
+    }
+    if let Some(max) = opts.max_wire_list_size {
+        limits.max_wire_list_size = max;
+    }
+    if let Some(max) = opts.max_live_wires {
+        limits.max_live_wires = max;
+    }
+    if let Some(max) = opts.max_value_bytes {
+        limits.max_value_bytes = max;
+    }
+    limits
+}
+
+fn new_evaluator<B: ZKBackend>(opts: &Options) -> Evaluator<B> {
+    let mut evaluator = Evaluator::default();
+    evaluator.set_limits(limits(opts));
+    evaluator
+}
+
 fn stream_messages(opts: &Options) -> Result<Source> {
     let mut source = Source::from_dirs_and_files(&opts.paths)?;
     source.print_filenames = true;
     Ok(source)
 }
 
+/// Go through the messages of a source without copying them: uncompressed files are mapped into
+/// memory, and relations are read in place so that they are not held twice in memory.
+fn for_each_message_view(source: &Source, mut ingest: impl FnMut(&MessageView)) -> Result<()> {
+    if source.can_map_files() {
+        let mapped = source.map_files()?;
+        for msg in mapped.iter_message_views() {
+            ingest(&msg?);
+        }
+    } else {
+        for buffer in source.iter_buffers() {
+            ingest(&MessageView::try_from(&buffer[..])?);
+        }
+    }
+    Ok(())
+}
+
 fn main_example(opts: &Options) -> Result<()> {
     use crate::producers::examples::*;
 
@@ -170,7 +337,7 @@ fn main_example(opts: &Options) -> Result<()> {
     let witness = if opts.incorrect {
         example_witness_incorrect_h(&header)
     } else {
-        example_witness_h(&header)
+        example_witness_h(&header)?
     };
     write_example(opts, &instance, &witness, &relation)?;
     Ok(())
@@ -207,27 +374,93 @@ fn write_example(
         witness.write_into(&mut stdout())?;
         relation.write_into(&mut stdout())?;
     } else if has_sieve_extension(out_dir) {
-        let mut file = File::create(out_dir)?;
+        // Compressed according to the extension.
+        let mut file = FileWriter::create(out_dir)?;
         instance.write_into(&mut file)?;
         witness.write_into(&mut file)?;
         relation.write_into(&mut file)?;
+        file.finish()?;
         eprintln!(
             "Written Instance, Witness, and Relation into {}",
             out_dir.display()
         );
     } else {
-        let mut sink = FilesSink::new_clean(out_dir)?;
+        let mut sink = CompressedFilesSink::new_clean(out_dir, opts.compress)?;
         sink.print_filenames();
         sink.push_instance_message(&instance)?;
         sink.push_witness_message(&witness)?;
         sink.push_relation_message(&relation)?;
+        sink.finish()?;
     }
     Ok(())
 }
 
+/// Check that --out is a directory which does not contain the input files, because it is cleaned
+/// before they are read.
+fn ensure_output_workspace(opts: &Options, tool: &str) -> Result<()> {
+    let out_dir = &opts.out;
+    if out_dir == Path::new("-") || has_sieve_extension(out_dir) {
+        return Err(format!("{} requires a directory as output value", tool).into());
+    }
+    if out_dir.exists() {
+        let out_dir = out_dir.canonicalize()?;
+        for path in list_workspace_files(&opts.paths)? {
+            if path == Path::new("-") {
+                continue;
+            }
+            let parent = path.canonicalize()?.parent().map(Path::to_path_buf);
+            if parent.as_ref() == Some(&out_dir) {
+                return Err(
+                    format!("{} requires an output directory other than the input", tool).into(),
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main_split(opts: &Options) -> Result<()> {
+    let out_dir = &opts.out;
+    ensure_output_workspace(opts, "split")?;
+    if opts.max_file_bytes.is_none() && opts.max_file_messages.is_none() {
+        return Err("split requires --max-file-bytes or --max-file-messages".into());
+    }
+
+    let source = stream_messages(opts)?;
+    let mut sink = RotatingFilesSink::new_clean(out_dir, opts.compress)?;
+    sink.max_file_bytes = opts.max_file_bytes;
+    sink.max_file_messages = opts.max_file_messages;
+    sink.print_filenames = true;
+    for buffer in source.iter_buffers() {
+        sink.push_message_buffer(&buffer)?;
+    }
+    eprintln!(
+        "Written {} files into {}",
+        sink.file_count(),
+        out_dir.display()
+    );
+    sink.finish()
+}
+
+fn main_repartition(opts: &Options) -> Result<()> {
+    ensure_output_workspace(opts, "repartition")?;
+    let source = stream_messages(opts)?;
+    let sink = CompressedFilesSink::new_clean(&opts.out, opts.compress)?;
+    sink.print_filenames();
+    let mut repartitioner = Repartitioner::new(sink, opts.message_len);
+    let mut result = Ok(());
+    for_each_message_view(&source, |msg| {
+        if result.is_ok() {
+            result = repartitioner.ingest_message_view(msg);
+        }
+    })?;
+    result?;
+    repartitioner.finish()?.finish()
+}
+
 fn main_cat(opts: &Options) -> Result<()> {
     for path in list_workspace_files(&opts.paths)? {
-        let mut file = File::open(&path)?;
+        let mut file = open_file(&path)?;
         let mut stdout = stdout();
         copy(&mut file, &mut stdout)?;
     }
@@ -238,12 +471,39 @@ fn main_text(_messages: &Messages) -> Result<()> {
     Err("Text form is not implemented yet.".into())
 }
 
-fn main_json(messages: &Messages) -> Result<()> {
-    serde_json::to_writer(stdout(), messages)?;
+fn main_json(messages: &Messages, opts: &Options) -> Result<()> {
+    serde_json::to_writer(stdout(), &WithValueFormat::new(opts.value_format, messages))?;
     println!();
     Ok(())
 }
 
+/// Write one message per line, as it is read.
+fn main_json_lines(source: &Source, opts: &Options) -> Result<()> {
+    let out = stdout();
+    let mut out = BufWriter::new(out.lock());
+    for msg in source.iter_messages() {
+        serde_json::to_writer(&mut out, &WithValueFormat::new(opts.value_format, &msg?))?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn from_json_lines(options: &Options) -> Result<()> {
+    let reader: Box<dyn Read> = match &options.resource[..] {
+        "-" => Box::new(stdin()),
+        _ => Box::new(File::open(&options.resource)?),
+    };
+    let mut file = BufWriter::new(File::create("from_json.sieve")?);
+    for msg in iterate_json_lines(BufReader::new(reader)) {
+        let msg = msg?;
+        msg.ensure_values_in_field()?;
+        msg.write_into(&mut file)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
 fn from_json(options: &Options) -> Result<()> {
     let messages: Messages = match &options.resource[..] {
         "-" => serde_json::from_reader(stdin())?,
@@ -253,25 +513,27 @@ fn from_json(options: &Options) -> Result<()> {
             serde_json::from_reader(reader)?
         }
     };
-    let mut file = File::create("from_json.sieve")?;
-    for instance in messages.instances {
-        instance.write_into(&mut file)?;
-    }
-    for witness in messages.witnesses {
-        witness.write_into(&mut file)?;
-    }
-    for relation in messages.relations {
-        relation.write_into(&mut file)?;
-    }
-    Ok(())
+    write_checked_messages(messages, &mut File::create("from_json.sieve")?)
 }
 
-fn main_yaml(messages: &Messages) -> Result<()> {
-    serde_yaml::to_writer(stdout(), messages)?;
+fn main_yaml(messages: &Messages, opts: &Options) -> Result<()> {
+    serde_yaml::to_writer(stdout(), &WithValueFormat::new(opts.value_format, messages))?;
     println!();
     Ok(())
 }
 
+/// Write one YAML document per message, as it is read.
+fn main_yaml_lines(source: &Source, opts: &Options) -> Result<()> {
+    let out = stdout();
+    let mut out = BufWriter::new(out.lock());
+    for msg in source.iter_messages() {
+        serde_yaml::to_writer(&mut out, &WithValueFormat::new(opts.value_format, &msg?))?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
 fn from_yaml(options: &Options) -> Result<()> {
     let messages: Messages = match &options.resource[..] {
         "-" => serde_yaml::from_reader(stdin())?,
@@ -281,15 +543,43 @@ fn from_yaml(options: &Options) -> Result<()> {
             serde_yaml::from_reader(reader)?
         }
     };
-    let mut file = File::create("from_yaml.sieve")?;
-    for instance in messages.instances {
-        instance.write_into(&mut file)?;
-    }
-    for witness in messages.witnesses {
-        witness.write_into(&mut file)?;
+    write_checked_messages(messages, &mut File::create("from_yaml.sieve")?)
+}
+
+/// Write messages read from JSON or YAML, whose values must be in the field.
+fn write_checked_messages(messages: Messages, file: &mut impl Write) -> Result<()> {
+    let instances = messages.instances.into_iter().map(Message::Instance);
+    let witnesses = messages.witnesses.into_iter().map(Message::Witness);
+    let relations = messages.relations.into_iter().map(Message::Relation);
+    for msg in instances.chain(witnesses).chain(relations) {
+        msg.ensure_values_in_field()?;
+        msg.write_into(file)?;
     }
-    for relation in messages.relations {
-        relation.write_into(&mut file)?;
+    Ok(())
+}
+
+fn main_dot(opts: &Options) -> Result<()> {
+    use crate::consumers::to_dot::{write_dataflow_dot, write_summary_dot, DotOptions};
+
+    let dot_options = DotOptions {
+        max_depth: opts.depth,
+        highlight: opts.highlight,
+        limits: limits(opts),
+    };
+    let mut out: Box<dyn std::io::Write> = if opts.out == Path::new("-") {
+        Box::new(stdout())
+    } else {
+        Box::new(File::create(&opts.out)?)
+    };
+
+    for msg in stream_messages(opts)?.iter_messages() {
+        if let Message::Relation(relation) = msg? {
+            if opts.summary {
+                write_summary_dot(&relation, &dot_options, &mut out)?;
+            } else {
+                write_dataflow_dot(&relation, &dot_options, &mut out)?;
+            }
+        }
     }
     Ok(())
 }
@@ -299,53 +589,102 @@ fn main_list_validations() -> Result<()> {
     Ok(())
 }
 
-fn main_validate(source: &Source) -> Result<()> {
+fn main_validate(source: &Source, opts: &Options) -> Result<()> {
+    let mut lint_levels = LintLevels::default();
+    for name in opts.allow.iter() {
+        lint_levels.set_by_name(name, Severity::Allow)?;
+    }
+    for name in opts.deny.iter() {
+        lint_levels.set_by_name(name, Severity::Deny)?;
+    }
+
     // Validate semantics as verifier.
     let mut validator = Validator::new_as_prover();
-    for msg in source.iter_messages() {
-        validator.ingest_message(&msg?);
+    validator.set_lint_levels(lint_levels);
+    validator.set_limits(limits(opts));
+    for_each_message_view(source, |msg| validator.ingest_message_view(msg))?;
+    let (violations, warnings) = validator.get_violations_and_warnings();
+    if !warnings.is_empty() {
+        eprintln!("\nWarnings:\n- {}", warnings.join("\n- "));
     }
     print_violations(
-        &validator.get_violations(),
+        &violations,
         "The statement",
         "COMPLIANT with the specification",
     )
 }
 
-fn main_evaluate(source: &Source) -> Result<()> {
+fn main_evaluate(source: &Source, opts: &Options) -> Result<()> {
     let mut zkinterpreter = PlaintextBackend::default();
     // Validate semantics as verifier.
-    let evaluator = Evaluator::from_messages(source.iter_messages(), &mut zkinterpreter);
+    let mut evaluator = new_evaluator(opts);
+    for_each_message_view(source, |msg| {
+        evaluator.ingest_message_view(msg, &mut zkinterpreter)
+    })?;
     print_violations(&evaluator.get_violations(), "The statement", "TRUE")
 }
 
-fn main_metrics(source: &Source) -> Result<()> {
-    let mut stats = Stats::default();
+fn main_lint(source: &Source, opts: &Options) -> Result<()> {
+    let mut analyzer = ConstraintAnalyzer::default();
+    analyzer.set_limits(limits(opts));
     for msg in source.iter_messages() {
-        stats.ingest_message(&msg?);
+        analyzer.ingest_message(&msg?);
     }
+    analyzer.check_limits()?;
+    let warnings = analyzer.get_warnings();
+    eprintln!();
+    if warnings.is_empty() {
+        eprintln!("All witnesses and wires are constrained!");
+        Ok(())
+    } else {
+        eprintln!("Warnings:\n- {}\n", warnings.join("\n- "));
+        Err(format!("Found {} under-constrained wires.", warnings.len()).into())
+    }
+}
+
+fn main_consumption(source: &Source) -> Result<()> {
+    let counter = ConsumptionCounter::from_messages(source.iter_messages())?;
+    serde_json::to_writer_pretty(stdout(), &counter)?;
+    println!();
+    print_violations(
+        &counter.get_violations(),
+        "The number of instance and witness values",
+        "EXACTLY what the relation consumes",
+    )
+}
+
+fn main_metrics(source: &Source, opts: &Options) -> Result<()> {
+    let mut stats = if opts.effective {
+        Stats::effective()
+    } else {
+        Stats::default()
+    };
+    stats.set_limits(limits(opts));
+    for_each_message_view(source, |msg| stats.ingest_message_view(msg))?;
+    stats.check_limits()?;
     serde_json::to_writer_pretty(stdout(), &stats)?;
     println!();
     Ok(())
 }
 
 /// Joint validate, evaluate, and metrics.
-fn main_valid_eval_metrics(source: &Source) -> Result<()> {
+fn main_valid_eval_metrics(source: &Source, opts: &Options) -> Result<()> {
     // Validate semantics as prover.
     let mut validator = Validator::new_as_prover();
+    validator.set_limits(limits(opts));
     // Check whether the statement is true.
     let mut zkinterpreter = PlaintextBackend::default();
-    let mut evaluator: Evaluator<PlaintextBackend> = Evaluator::default();
+    let mut evaluator: Evaluator<PlaintextBackend> = new_evaluator(opts);
     // Measure metrics on the circuit.
     let mut stats = Stats::default();
+    stats.set_limits(limits(opts));
 
     // Feed messages to all consumers (read files or stdin only once).
-    for msg in source.iter_messages() {
-        let msg = msg?;
-        validator.ingest_message(&msg);
-        evaluator.ingest_message(&msg, &mut zkinterpreter);
-        stats.ingest_message(&msg);
-    }
+    for_each_message_view(source, |msg| {
+        validator.ingest_message_view(msg);
+        evaluator.ingest_message_view(msg, &mut zkinterpreter);
+        stats.ingest_message_view(msg);
+    })?;
 
     let res1 = print_violations(
         &validator.get_violations(),
@@ -353,8 +692,11 @@ fn main_valid_eval_metrics(source: &Source) -> Result<()> {
         "COMPLIANT with the specification",
     );
     let res2 = print_violations(&evaluator.get_violations(), "The statement", "TRUE");
-    let res3 = serde_json::to_writer_pretty(stdout(), &stats);
-    println!();
+    let res3 = stats.check_limits();
+    if res3.is_ok() {
+        serde_json::to_writer_pretty(stdout(), &stats)?;
+        println!();
+    }
 
     res1?;
     res2?;
@@ -418,8 +760,10 @@ fn main_zkif_to_ir(opts: &Options) -> Result<()> {
         return Err("IR flattening requires a directory as output value".into());
     } else {
         // instantiate the converter
-        let mut converter =
-            FromR1CSConverter::new(FilesSink::new_clean(out_dir).unwrap(), &zki_header);
+        let mut converter = FromR1CSConverter::new(
+            CompressedFilesSink::new_clean(out_dir, opts.compress)?,
+            &zki_header,
+        );
 
         // Ingest all non-header messages
         for message in workspace.iter_messages() {
@@ -431,7 +775,54 @@ fn main_zkif_to_ir(opts: &Options) -> Result<()> {
                 _ => {}
             }
         }
-        converter.finish();
+        converter.finish().finish()?;
+    }
+
+    Ok(())
+}
+
+// Convert from circom .r1cs and .wtns files.
+// Expects one .r1cs file and at most one .wtns file, places the IR into the file or dir specified by --out.
+fn main_circom_to_ir(opts: &Options) -> Result<()> {
+    use crate::producers::from_circom::{circom_to_ir, CircomR1CS, CircomWitness};
+
+    fn with_extension<'a>(paths: &'a [PathBuf], ext: &str) -> Vec<&'a PathBuf> {
+        paths
+            .iter()
+            .filter(|path| path.extension().map_or(false, |e| e == ext))
+            .collect()
+    }
+    let r1cs_paths = with_extension(&opts.paths, "r1cs");
+    let wtns_paths = with_extension(&opts.paths, "wtns");
+    if r1cs_paths.len() != 1 || wtns_paths.len() > 1 {
+        return Err("circom-to-ir requires one .r1cs file and at most one .wtns file".into());
+    }
+
+    let r1cs = CircomR1CS::from_file(r1cs_paths[0])?;
+    let witness = match wtns_paths.first() {
+        Some(path) => Some(CircomWitness::from_file(path)?),
+        None => {
+            eprintln!("No .wtns file given, instance values are set to zero.");
+            None
+        }
+    };
+
+    let out_dir = &opts.out;
+    if out_dir == Path::new("-") {
+        let s: Source = circom_to_ir(MemorySink::default(), &r1cs, witness.as_ref())?.into();
+        for msg in s.iter_messages() {
+            let msg = msg?;
+            msg.write_into(&mut stdout())?;
+        }
+    } else if has_sieve_extension(&out_dir) {
+        return Err("circom-to-ir requires a directory as output value".into());
+    } else {
+        circom_to_ir(
+            CompressedFilesSink::new_clean(out_dir, opts.compress)?,
+            &r1cs,
+            witness.as_ref(),
+        )?
+        .finish()?;
     }
 
     Ok(())
@@ -445,11 +836,12 @@ fn main_ir_flattening(opts: &Options) -> Result<()> {
 
     if out_dir == Path::new("-") {
         let mut flattener = IRFlattener::new(MemorySink::default());
-        let mut evaluator = Evaluator::default();
+        let mut evaluator = new_evaluator(opts);
 
         for msg in source.iter_messages() {
             evaluator.ingest_message(&msg?, &mut flattener);
         }
+        print_violations(&evaluator.get_violations(), "The flattening", "SUCCESSFUL")?;
 
         let s: Source = flattener.finish().into();
         for msg in s.iter_messages() {
@@ -459,13 +851,15 @@ fn main_ir_flattening(opts: &Options) -> Result<()> {
     } else if has_sieve_extension(&out_dir) {
         return Err("IR flattening requires a directory as output value".into());
     } else {
-        let mut flattener = IRFlattener::new(FilesSink::new_clean(out_dir)?);
-        let mut evaluator = Evaluator::default();
+        let mut flattener =
+            IRFlattener::new(CompressedFilesSink::new_clean(out_dir, opts.compress)?);
+        let mut evaluator = new_evaluator(opts);
 
         for msg in source.iter_messages() {
             evaluator.ingest_message(&msg?, &mut flattener);
         }
-        flattener.finish();
+        print_violations(&evaluator.get_violations(), "The flattening", "SUCCESSFUL")?;
+        flattener.finish().finish()?;
     }
 
     Ok(())
@@ -499,7 +893,7 @@ fn main_ir_to_r1cs(opts: &Options) -> Result<()> {
             use_witness,
             opts.modular_reduce,
         );
-        let mut evaluator = Evaluator::default();
+        let mut evaluator = new_evaluator(opts);
 
         for msg in source.iter_messages() {
             evaluator.ingest_message(&msg?, &mut to_r1cs);
@@ -510,6 +904,103 @@ fn main_ir_to_r1cs(opts: &Options) -> Result<()> {
     Ok(())
 }
 
+// Convert to circom .r1cs and .wtns files.
+// Expects one instance, witness, and relation only.
+fn main_ir_to_circom(opts: &Options) -> Result<()> {
+    use crate::consumers::to_circom::CircomSink;
+    use crate::consumers::to_r1cs::ToR1CSConverter;
+
+    let source = stream_messages(opts)?;
+    let mut use_witness = false;
+    for m in source.iter_messages() {
+        // if there is at least one witness message, then we'll convert them as well.
+        if let Message::Witness(_) = m? {
+            use_witness = true;
+        }
+    }
+
+    let out_dir = &opts.out;
+    if out_dir == Path::new("-") || has_sieve_extension(&out_dir) {
+        return Err("IR->circom converter requires a directory as output value".into());
+    }
+
+    let mut to_r1cs = ToR1CSConverter::new(CircomSink::default(), use_witness, opts.modular_reduce);
+    let mut evaluator = new_evaluator(opts);
+    for msg in source.iter_messages() {
+        evaluator.ingest_message(&msg?, &mut to_r1cs);
+    }
+    print_violations(
+        &evaluator.get_violations(),
+        "The conversion to circom",
+        "SUCCESSFUL",
+    )?;
+
+    for path in to_r1cs.finish()?.write_into_dir(out_dir)? {
+        eprintln!("Written {}", path.display());
+    }
+    Ok(())
+}
+
+// Convert to a Plonkish table.
+// Expects one instance, witness, and relation only.
+fn main_ir_to_plonk(opts: &Options) -> Result<()> {
+    use crate::consumers::to_plonk::ToPlonkConverter;
+
+    let source = stream_messages(opts)?;
+    let mut use_witness = false;
+    for m in source.iter_messages() {
+        // if there is at least one witness message, then we'll convert them as well.
+        if let Message::Witness(_) = m? {
+            use_witness = true;
+        }
+    }
+
+    let out_dir = &opts.out;
+    if out_dir == Path::new("-") || has_sieve_extension(&out_dir) {
+        return Err("IR->Plonk converter requires a directory as output value".into());
+    }
+
+    let mut to_plonk = ToPlonkConverter::new(use_witness);
+    let mut evaluator = new_evaluator(opts);
+    for msg in source.iter_messages() {
+        evaluator.ingest_message(&msg?, &mut to_plonk);
+    }
+    print_violations(
+        &evaluator.get_violations(),
+        "The conversion to Plonk",
+        "SUCCESSFUL",
+    )?;
+    let (table, witness) = to_plonk.finish();
+
+    std::fs::create_dir_all(out_dir)?;
+    let table_path = out_dir.join("circuit.plonk");
+    let mut file = BufWriter::new(File::create(&table_path)?);
+    table.write_into(&mut file)?;
+    file.flush()?;
+    eprintln!("Written {}", table_path.display());
+
+    if let Some(witness) = witness {
+        let witness_path = out_dir.join("witness.plonk");
+        let mut file = BufWriter::new(File::create(&witness_path)?);
+        witness.write_into(&mut file)?;
+        file.flush()?;
+        eprintln!("Written {}", witness_path.display());
+    }
+    Ok(())
+}
+
+// Check a Plonkish table and witness, as written by ir-to-plonk.
+fn main_check_plonk(opts: &Options) -> Result<()> {
+    use crate::consumers::to_plonk::{PlonkTable, PlonkWitness};
+
+    if opts.paths.len() != 2 {
+        return Err("check-plonk requires a table file and a witness file".into());
+    }
+    let table = PlonkTable::read_from(&mut BufReader::new(File::open(&opts.paths[0])?))?;
+    let witness = PlonkWitness::read_from(&mut BufReader::new(File::open(&opts.paths[1])?))?;
+    print_violations(&table.check(&witness), "The Plonk witness", "VALID")
+}
+
 // Expand definable gates in IR1, like.
 // Expects a set of dirs and files, places the expanded relations into the file or dir specified by --out.
 fn main_expand_definable(opts: &Options) -> Result<()> {
@@ -523,7 +1014,7 @@ fn main_expand_definable(opts: &Options) -> Result<()> {
             Ok(gate_mask) => {
                 if out_dir == Path::new("-") {
                     let mut expander = ExpandDefinable::new(MemorySink::default(), gate_mask);
-                    let mut evaluator = Evaluator::default();
+                    let mut evaluator = new_evaluator(opts);
 
                     for msg in source.iter_messages() {
                         evaluator.ingest_message(&msg?, &mut expander);
@@ -537,14 +1028,16 @@ fn main_expand_definable(opts: &Options) -> Result<()> {
                 } else if has_sieve_extension(&out_dir) {
                     return Err("IR flattening requires a directory as output value".into());
                 } else {
-                    let mut expander =
-                        ExpandDefinable::new(FilesSink::new_clean(out_dir)?, gate_mask);
-                    let mut evaluator = Evaluator::default();
+                    let mut expander = ExpandDefinable::new(
+                        CompressedFilesSink::new_clean(out_dir, opts.compress)?,
+                        gate_mask,
+                    );
+                    let mut evaluator = new_evaluator(opts);
 
                     for msg in source.iter_messages() {
                         evaluator.ingest_message(&msg?, &mut expander);
                     }
-                    expander.finish();
+                    expander.finish().finish()?;
                 }
             }
             Err(a) => return Err(a),
@@ -586,6 +1079,24 @@ fn test_cli() -> Result<()> {
         modular_reduce: false,
         out: PathBuf::from("-"),
         gate_set: None,
+        summary: false,
+        depth: None,
+        highlight: None,
+        allow: vec![],
+        deny: vec![],
+        effective: false,
+        max_unrolled_gates: None,
+        max_loop_iterations: None,
+        max_depth: None,
+        max_wire_list_size: None,
+        max_live_wires: None,
+        max_value_bytes: None,
+        value_format: ValueFormat::Bytes,
+        lines: false,
+        message_len: 100000,
+        max_file_bytes: None,
+        max_file_messages: None,
+        compress: Compression::None,
     })?;
 
     cli(&Options {
@@ -597,8 +1108,88 @@ fn test_cli() -> Result<()> {
         modular_reduce: false,
         out: PathBuf::from("-"),
         gate_set: None,
+        summary: false,
+        depth: None,
+        highlight: None,
+        allow: vec![],
+        deny: vec![],
+        effective: false,
+        max_unrolled_gates: None,
+        max_loop_iterations: None,
+        max_depth: None,
+        max_wire_list_size: None,
+        max_live_wires: None,
+        max_value_bytes: None,
+        value_format: ValueFormat::Bytes,
+        lines: false,
+        message_len: 100000,
+        max_file_bytes: None,
+        max_file_messages: None,
+        compress: Compression::None,
+    })?;
+
+    cli(&Options {
+        tool: "consumption".to_string(),
+        paths: vec![arithmetic_workspace.clone()],
+        field_order: BigUint::from(101 as u32),
+        incorrect: false,
+        resource: "-".to_string(),
+        modular_reduce: false,
+        out: PathBuf::from("-"),
+        gate_set: None,
+        summary: false,
+        depth: None,
+        highlight: None,
+        allow: vec![],
+        deny: vec![],
+        effective: false,
+        max_unrolled_gates: None,
+        max_loop_iterations: None,
+        max_depth: None,
+        max_wire_list_size: None,
+        max_live_wires: None,
+        max_value_bytes: None,
+        value_format: ValueFormat::Bytes,
+        lines: false,
+        message_len: 100000,
+        max_file_bytes: None,
+        max_file_messages: None,
+        compress: Compression::None,
     })?;
 
+    // The converters and the graph drawer stop at the limits like the other tools.
+    for tool in ["ir-to-circom", "ir-to-plonk", "to-dot"].iter() {
+        let res = cli(&Options {
+            tool: tool.to_string(),
+            paths: vec![arithmetic_workspace.clone()],
+            field_order: BigUint::from(101 as u32),
+            incorrect: false,
+            resource: "-".to_string(),
+            modular_reduce: false,
+            out: PathBuf::from(format!("local/test_cli/limited_{}", tool)),
+            gate_set: None,
+            summary: true,
+            depth: None,
+            highlight: None,
+            allow: vec![],
+            deny: vec![],
+            effective: false,
+            max_unrolled_gates: Some(1),
+            max_loop_iterations: None,
+            max_depth: None,
+            max_wire_list_size: None,
+            max_live_wires: None,
+            max_value_bytes: None,
+            value_format: ValueFormat::Bytes,
+            lines: false,
+            message_len: 100000,
+            max_file_bytes: None,
+            max_file_messages: None,
+            compress: Compression::None,
+        });
+        assert!(res.is_err(), "{} ignored --max-unrolled-gates", tool);
+    }
+
     let boolean_workspace = PathBuf::from("local/test_cli/boolean_example");
 
     cli(&Options {
@@ -610,6 +1201,24 @@ fn test_cli() -> Result<()> {
         modular_reduce: false,
         out: PathBuf::from("-"),
         gate_set: None,
+        summary: false,
+        depth: None,
+        highlight: None,
+        allow: vec![],
+        deny: vec![],
+        effective: false,
+        max_unrolled_gates: None,
+        max_loop_iterations: None,
+        max_depth: None,
+        max_wire_list_size: None,
+        max_live_wires: None,
+        max_value_bytes: None,
+        value_format: ValueFormat::Bytes,
+        lines: false,
+        message_len: 100000,
+        max_file_bytes: None,
+        max_file_messages: None,
+        compress: Compression::None,
     })?;
 
     cli(&Options {
@@ -621,6 +1230,24 @@ fn test_cli() -> Result<()> {
         modular_reduce: false,
         out: PathBuf::from("-"),
         gate_set: None,
+        summary: false,
+        depth: None,
+        highlight: None,
+        allow: vec![],
+        deny: vec![],
+        effective: false,
+        max_unrolled_gates: None,
+        max_loop_iterations: None,
+        max_depth: None,
+        max_wire_list_size: None,
+        max_live_wires: None,
+        max_value_bytes: None,
+        value_format: ValueFormat::Bytes,
+        lines: false,
+        message_len: 100000,
+        max_file_bytes: None,
+        max_file_messages: None,
+        compress: Compression::None,
     })?;
 
     Ok(())
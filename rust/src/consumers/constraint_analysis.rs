@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+
+use crate::consumers::limits::Limits;
+use crate::consumers::unroller::{self, Body, GateVisitor, Unroller};
+use crate::structs::wire::WireRanges;
+use crate::{Gate, Message, Relation, Result, WireId};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum NodeKind {
+    Witness,
+    Instance,
+    Constant,
+    Computed,
+}
+
+/// A set of ids which only remembers its first two elements.
+/// This is enough to tell apart "none", "exactly one", and "several".
+#[derive(Clone, Copy, Debug, Default)]
+struct CappedSet([Option<usize>; 2]);
+
+impl CappedSet {
+    fn insert(&mut self, id: usize) {
+        if self.0.contains(&Some(id)) {
+            return;
+        }
+        if let Some(slot) = self.0.iter_mut().find(|slot| slot.is_none()) {
+            *slot = Some(id);
+        }
+    }
+
+    fn extend(&mut self, other: &CappedSet) {
+        for id in other.0.iter().flatten() {
+            self.insert(*id);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().flatten().count()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0[0].is_none()
+    }
+}
+
+/// A value of the unrolled circuit: one wire of one execution of a body.
+#[derive(Clone, Debug)]
+struct Node {
+    kind: NodeKind,
+    wire: WireId,
+    location: usize,
+    // The nodes this one is computed from, and whether it depends linearly on each of them.
+    inputs: Vec<(usize, bool)>,
+    // Whether the value only depends on constants and instances.
+    public: bool,
+    // The witnesses this value depends on.
+    witnesses: CappedSet,
+}
+
+type Scope = unroller::Scope<usize>;
+
+/// Where a body is in the circuit, and the conditions of the switches around it.
+#[derive(Clone, Debug, Default)]
+struct BodyContext {
+    location: usize,
+    conditions: Vec<usize>,
+}
+
+/// The nodes of the unrolled circuit, built from the gates given by an `Unroller`.
+#[derive(Clone, Debug)]
+struct Dataflow {
+    nodes: Vec<Node>,
+    // AssertZero => the tested node, and the conditions of the enclosing switches.
+    assertions: Vec<(usize, Vec<usize>)>,
+    // Descriptions of the bodies in which the nodes were created. The first one is the relation.
+    locations: Vec<String>,
+    location_ids: HashMap<String, usize>,
+}
+
+impl Default for Dataflow {
+    fn default() -> Self {
+        Dataflow {
+            nodes: vec![],
+            assertions: vec![],
+            locations: vec![String::new()],
+            location_ids: HashMap::new(),
+        }
+    }
+}
+
+/// ConstraintAnalyzer finds witnesses that are not properly constrained by the relation.
+///
+/// It follows the dataflow through functions, loops and switches (as if they were inlined and
+/// unrolled) and reports:
+/// - witnesses that never reach an `AssertZero`, so a cheating prover can choose them freely,
+/// - wires that are computed but never reach an `AssertZero`,
+/// - witnesses whose only constraint is a single linear `AssertZero` shared with other witnesses,
+///   so their value may not be unique.
+///
+/// In a switch, the assertions of a branch are multiplied by the weight of the branch, which
+/// depends on the condition. They are linear only if the condition is public.
+///
+/// Like `Stats`, it stops at the first exceeded limit or recursive function, see `check_limits`.
+#[derive(Clone, Debug, Default)]
+pub struct ConstraintAnalyzer {
+    // The scope of the relation, the functions and the limits, kept between messages.
+    unroller: Unroller<usize>,
+    graph: Dataflow,
+    // The number of gates of the relation, to describe locations.
+    gate_count: usize,
+}
+
+impl ConstraintAnalyzer {
+    pub fn from_messages(messages: impl Iterator<Item = Result<Message>>) -> Result<Self> {
+        let mut analyzer = ConstraintAnalyzer::default();
+        for msg in messages {
+            analyzer.ingest_message(&msg?);
+        }
+        Ok(analyzer)
+    }
+
+    pub fn ingest_message(&mut self, msg: &Message) {
+        // Only the relation matters, values are not needed.
+        if let Message::Relation(relation) = msg {
+            self.ingest_relation(relation);
+        }
+    }
+
+    /// Set the limits on the resources used to go through the circuit.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.unroller.set_limits(limits);
+    }
+
+    /// Return an error if a limit was exceeded or a function is recursive, in which case the
+    /// warnings are incomplete.
+    pub fn check_limits(&self) -> Result<()> {
+        self.unroller.check_limits()
+    }
+
+    pub fn ingest_relation(&mut self, relation: &Relation) {
+        if self.unroller.error.is_some() {
+            return;
+        }
+        for f in relation.functions.iter() {
+            self.unroller
+                .define_function(f.name.clone(), f.output_count, f.body.clone());
+        }
+        // Recursive functions cannot be unrolled.
+        let new_functions = relation.functions.iter().map(|f| &f.name);
+        if let Err(err) = self.unroller.check_recursion(new_functions) {
+            self.unroller.fail(err);
+            return;
+        }
+
+        self.unroller.ingest_relation_gates(
+            &mut self.graph,
+            &relation.gates,
+            self.gate_count,
+            &BodyContext::default(),
+        );
+        self.gate_count += relation.gates.len();
+    }
+
+    /// Return a description of each under-constrained witness or wire, in the order of the circuit.
+    pub fn get_warnings(self) -> Vec<String> {
+        let node_count = self.graph.nodes.len();
+
+        // Go backwards from the assertions: which assertions does each node reach, and does it
+        // reach any of them through a non-linear operation.
+        let mut reached = vec![CappedSet::default(); node_count];
+        let mut non_linear = vec![false; node_count];
+        let mut assertion_witnesses = vec![];
+
+        for (id, (node, conditions)) in self.graph.assertions.iter().enumerate() {
+            let weighted_linearly = conditions.iter().all(|c| self.graph.nodes[*c].public);
+            reached[*node].insert(id);
+            non_linear[*node] |= !weighted_linearly;
+
+            let mut witnesses = self.graph.nodes[*node].witnesses;
+            for condition in conditions {
+                reached[*condition].insert(id);
+                non_linear[*condition] = true;
+                witnesses.extend(&self.graph.nodes[*condition].witnesses);
+            }
+            assertion_witnesses.push(witnesses);
+        }
+
+        // Nodes are created after their inputs, so a reverse pass visits all consumers first.
+        for id in (0..node_count).rev() {
+            if reached[id].is_empty() {
+                continue;
+            }
+            let (assertions, node_non_linear) = (reached[id], non_linear[id]);
+            for (input, linear) in self.graph.nodes[id].inputs.iter() {
+                reached[*input].extend(&assertions);
+                non_linear[*input] |= node_non_linear || !*linear;
+            }
+        }
+
+        // A wire of a function or a loop is executed many times. Report it only if no execution
+        // is properly constrained.
+        let mut order = vec![];
+        // (kind, location, wire) => (reaches an assertion, only constrained linearly)
+        let mut findings: HashMap<(NodeKind, usize, WireId), (bool, bool)> = HashMap::new();
+
+        for (id, node) in self.graph.nodes.iter().enumerate() {
+            if node.kind == NodeKind::Instance || node.kind == NodeKind::Constant {
+                continue;
+            }
+            let is_reached = !reached[id].is_empty();
+            let only_linear = node.kind == NodeKind::Witness
+                && reached[id].len() == 1
+                && !non_linear[id]
+                && reached[id].0[0].map_or(false, |a| assertion_witnesses[a].len() > 1);
+
+            let key = (node.kind, node.location, node.wire);
+            match findings.get_mut(&key) {
+                Some((any_reached, all_linear)) => {
+                    *any_reached |= is_reached;
+                    *all_linear &= only_linear;
+                }
+                None => {
+                    order.push(key);
+                    findings.insert(key, (is_reached, only_linear));
+                }
+            }
+        }
+
+        let mut warnings = vec![];
+        for key in order {
+            let (kind, location, wire) = key;
+            let (any_reached, all_linear) = findings[&key];
+            let location = match &self.graph.locations[location][..] {
+                "" => "".to_string(),
+                description => format!(" in {}", description),
+            };
+
+            if kind == NodeKind::Witness {
+                if !any_reached {
+                    warnings.push(format!(
+                        "Witness wire {}{} never reaches an AssertZero, so a cheating prover can choose its value.",
+                        wire, location
+                    ));
+                } else if all_linear {
+                    warnings.push(format!(
+                        "Witness wire {}{} is only constrained by one linear AssertZero together with other witnesses, so its value may not be unique.",
+                        wire, location
+                    ));
+                }
+            } else if !any_reached {
+                warnings.push(format!(
+                    "Wire {}{} is computed but never reaches an AssertZero.",
+                    wire, location
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+impl GateVisitor for Dataflow {
+    type Entry = usize;
+    type Context = BodyContext;
+
+    const NAME: &'static str = "ConstraintAnalyzer";
+
+    fn visit_gate(
+        &mut self,
+        unroller: &mut Unroller<usize>,
+        scope: &mut Scope,
+        gate: &Gate,
+        gate_index: usize,
+        context: &BodyContext,
+    ) {
+        use Gate::*;
+
+        let location = context.location;
+        match gate {
+            Constant(out, _) => {
+                self.create_node(scope, *out, NodeKind::Constant, location, vec![]);
+            }
+
+            Instance(out) => {
+                self.create_node(scope, *out, NodeKind::Instance, location, vec![]);
+            }
+
+            Witness(out) => {
+                self.create_node(scope, *out, NodeKind::Witness, location, vec![]);
+            }
+
+            AssertZero(inp) => {
+                if let Some(node) = scope.get(inp) {
+                    self.assertions.push((*node, context.conditions.clone()));
+                }
+            }
+
+            Copy(out, inp)
+            | AddConstant(out, inp, _)
+            | MulConstant(out, inp, _)
+            | Not(out, inp) => {
+                let inputs = self.linear_inputs(scope, &[*inp]);
+                self.create_node(scope, *out, NodeKind::Computed, location, inputs);
+            }
+
+            Add(out, left, right) | Xor(out, left, right) => {
+                let inputs = self.linear_inputs(scope, &[*left, *right]);
+                self.create_node(scope, *out, NodeKind::Computed, location, inputs);
+            }
+
+            Mul(out, left, right) | And(out, left, right) => {
+                // A product is linear in one factor if the other one is public.
+                let (left, right) = (scope.get(left).copied(), scope.get(right).copied());
+                let is_public = |node: Option<usize>| node.map_or(false, |n| self.nodes[n].public);
+                let mut inputs = vec![];
+                if let Some(left) = left {
+                    inputs.push((left, is_public(right)));
+                }
+                if let Some(right) = right {
+                    inputs.push((right, is_public(left)));
+                }
+                self.create_node(scope, *out, NodeKind::Computed, location, inputs);
+            }
+
+            // Each digit of the outputs depends on all the digits of the inputs, not linearly.
+            Convert(_, output_wires, _, input_wires) => {
+                let (outputs, inputs) = match unroller.wire_views(output_wires, input_wires) {
+                    Some(wires) => wires,
+                    None => return,
+                };
+                let inputs: Vec<(usize, bool)> = inputs
+                    .ids()
+                    .filter_map(|wire| scope.get(&wire).map(|node| (*node, false)))
+                    .collect();
+                for output in outputs.ids() {
+                    self.create_node(scope, output, NodeKind::Computed, location, inputs.clone());
+                }
+            }
+
+            Switch(condition, output_wires, _, branches) => {
+                let outputs = match unroller.wirelist_view(output_wires) {
+                    Some(outputs) => outputs,
+                    None => return,
+                };
+                let condition = scope.get(condition).copied();
+                let mut branch_context = context.clone();
+                branch_context.conditions.extend(condition);
+
+                // Each output is the sum of the outputs of the branches, multiplied by weights
+                // computed from the condition.
+                let mut merged = vec![vec![]; outputs.len()];
+                for branch in branches.iter().enumerate() {
+                    let results = match unroller.call_branch(
+                        self,
+                        scope,
+                        branch,
+                        outputs.len(),
+                        gate_index,
+                        &branch_context,
+                    ) {
+                        Some(results) => results,
+                        None => continue,
+                    };
+                    let weight_is_public = condition.map_or(true, |c| self.nodes[c].public);
+                    for (merged, result) in merged.iter_mut().zip(results) {
+                        if let Some(node) = result {
+                            merged.push((node, weight_is_public));
+                        }
+                    }
+                }
+
+                for (wire, mut inputs) in outputs.ids().zip(merged) {
+                    if let Some(condition) = condition {
+                        inputs.push((condition, false));
+                    }
+                    self.create_node(scope, wire, NodeKind::Computed, location, inputs);
+                }
+            }
+
+            // The other gates are handled by the Unroller.
+            _ => unreachable!(),
+        }
+    }
+
+    fn body_context(
+        &mut self,
+        context: &BodyContext,
+        gate_index: usize,
+        body: Body,
+    ) -> BodyContext {
+        let location = match body {
+            Body::Function(name) => self.location(format!("function `{}`", name)),
+            Body::AnonCall => self.body_location(context.location, gate_index, "anonymous call"),
+            Body::SwitchBranch(branch_index) => self.body_location(
+                context.location,
+                gate_index,
+                &format!("switch branch {}", branch_index),
+            ),
+            Body::Loop(iterator_name) => self.body_location(
+                context.location,
+                gate_index,
+                &format!("loop over {}", iterator_name),
+            ),
+        };
+        BodyContext {
+            location,
+            conditions: context.conditions.clone(),
+        }
+    }
+
+    /// Give the outputs of a call to the wires of the caller. They are new nodes so that an unused
+    /// output is reported where the call is, and not in the body.
+    fn assign_outputs(
+        &mut self,
+        _: &mut Unroller<usize>,
+        scope: &mut Scope,
+        outputs: impl WireRanges,
+        results: Vec<Option<usize>>,
+        context: &BodyContext,
+    ) {
+        let mut results = results.into_iter();
+        for wire in outputs.ids() {
+            let inputs = match results.next().flatten() {
+                Some(node) => vec![(node, true)],
+                None => vec![],
+            };
+            self.create_node(scope, wire, NodeKind::Computed, context.location, inputs);
+        }
+    }
+}
+
+impl Dataflow {
+    fn create_node(
+        &mut self,
+        scope: &mut Scope,
+        wire: WireId,
+        kind: NodeKind,
+        location: usize,
+        inputs: Vec<(usize, bool)>,
+    ) {
+        let id = self.nodes.len();
+        let mut witnesses = CappedSet::default();
+        let public = match kind {
+            NodeKind::Instance | NodeKind::Constant => true,
+            NodeKind::Witness => {
+                witnesses.insert(id);
+                false
+            }
+            NodeKind::Computed => {
+                for (input, _) in inputs.iter() {
+                    witnesses.extend(&self.nodes[*input].witnesses);
+                }
+                inputs.iter().all(|(input, _)| self.nodes[*input].public)
+            }
+        };
+
+        self.nodes.push(Node {
+            kind,
+            wire,
+            location,
+            inputs,
+            public,
+            witnesses,
+        });
+        scope.insert(wire, id);
+    }
+
+    fn linear_inputs(&self, scope: &Scope, wires: &[WireId]) -> Vec<(usize, bool)> {
+        wires
+            .iter()
+            .filter_map(|wire| scope.get(wire).map(|node| (*node, true)))
+            .collect()
+    }
+
+    fn location(&mut self, description: String) -> usize {
+        if let Some(id) = self.location_ids.get(&description) {
+            return *id;
+        }
+        self.locations.push(description.clone());
+        self.location_ids
+            .insert(description, self.locations.len() - 1);
+        self.locations.len() - 1
+    }
+
+    /// The location of the body of a gate, e.g. "gate 3 (switch branch 1)".
+    fn body_location(&mut self, parent: usize, gate_index: usize, what: &str) -> usize {
+        let description = match &self.locations[parent][..] {
+            "" => format!("gate {} ({})", gate_index, what),
+            parent => format!("{} > gate {} ({})", parent, gate_index, what),
+        };
+        self.location(description)
+    }
+}
+
+#[test]
+fn test_constraint_analysis() {
+    use crate::producers::examples::example_header;
+    use crate::structs::function::Function;
+    use crate::structs::relation::{ADD, FUNCTION, MUL};
+    use crate::structs::wire::WireListElement::*;
+    use crate::Gate::*;
+
+    let relation = Relation {
+        header: example_header(),
+        gate_mask: ADD | MUL,
+        feat_mask: FUNCTION,
+        functions: vec![Function::new(
+            "square".to_string(),
+            1,
+            1,
+            0,
+            0,
+            vec![Mul(0, 1, 1)],
+        )],
+        gates: vec![
+            Witness(0),
+            Witness(1),
+            Witness(2),
+            Instance(3),
+            // w0 * w0 + i3 == 0
+            Mul(4, 0, 0),
+            Add(5, 4, 3),
+            AssertZero(5),
+            // w1 + w2 == 0
+            Add(6, 1, 2),
+            AssertZero(6),
+            // w7 * i3 is never checked.
+            Witness(7),
+            Mul(8, 7, 3),
+            // square(w9) == 0
+            Witness(9),
+            Call("square".to_string(), vec![Wire(10)], vec![Wire(9)]),
+            AssertZero(10),
+            // The square of w7 is not checked either.
+            Call("square".to_string(), vec![Wire(11)], vec![Wire(7)]),
+        ],
+    };
+
+    let mut analyzer = ConstraintAnalyzer::default();
+    analyzer.ingest_relation(&relation);
+    let warnings = analyzer.get_warnings();
+
+    assert_eq!(
+        warnings,
+        vec![
+            "Witness wire 1 is only constrained by one linear AssertZero together with other witnesses, so its value may not be unique.",
+            "Witness wire 2 is only constrained by one linear AssertZero together with other witnesses, so its value may not be unique.",
+            "Witness wire 7 never reaches an AssertZero, so a cheating prover can choose its value.",
+            "Wire 8 is computed but never reaches an AssertZero.",
+            "Wire 11 is computed but never reaches an AssertZero.",
+        ]
+    );
+}
+
+#[test]
+fn test_constraint_analysis_limits() {
+    use crate::producers::examples::example_relation;
+    use crate::structs::function::Function;
+    use crate::structs::wire::WireListElement::*;
+    use crate::Gate::*;
+
+    // Recursive functions are rejected instead of being unrolled forever.
+    let mut relation = example_relation();
+    relation.functions.push(Function::new(
+        "rec::f".to_string(),
+        1,
+        1,
+        0,
+        0,
+        vec![Call("rec::f".to_string(), vec![Wire(0)], vec![Wire(1)])],
+    ));
+    let mut analyzer = ConstraintAnalyzer::default();
+    analyzer.ingest_relation(&relation);
+    assert_eq!(
+        analyzer.check_limits().unwrap_err().to_string(),
+        "The function rec::f is recursive: rec::f -> rec::f."
+    );
+
+    // Nested bodies spend the budget.
+    let mut gates = vec![Witness(0), AssertZero(0)];
+    for _ in 0..10 {
+        gates = vec![AnonCall(vec![], vec![], 0, 0, gates)];
+    }
+    relation.functions = vec![];
+    relation.gates = gates;
+    let mut analyzer = ConstraintAnalyzer::default();
+    analyzer.set_limits(Limits {
+        max_depth: 5,
+        ..Limits::default()
+    });
+    analyzer.ingest_relation(&relation);
+    assert_eq!(
+        analyzer.check_limits().unwrap_err().to_string(),
+        "Calls, loops and switches are nested more than 5 times (max_depth)."
+    );
+
+    let mut analyzer = ConstraintAnalyzer::default();
+    analyzer.set_limits(Limits {
+        max_unrolled_gates: 5,
+        ..Limits::default()
+    });
+    analyzer.ingest_relation(&relation);
+    assert!(analyzer.check_limits().is_err());
+
+    let mut analyzer = ConstraintAnalyzer::default();
+    analyzer.ingest_relation(&relation);
+    assert!(analyzer.check_limits().is_ok());
+    assert_eq!(analyzer.get_warnings(), Vec::<String>::new());
+}
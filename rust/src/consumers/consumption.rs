@@ -0,0 +1,415 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::max;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::structs::function::{CaseInvoke, ForLoopBody};
+use crate::{Gate, Message, Relation, Result, TypeId};
+
+/// The number of instance and witness values consumed by some gates.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct Consumption {
+    pub instance_count: u64,
+    pub witness_count: u64,
+}
+
+impl Consumption {
+    fn add(&self, other: &Consumption) -> Result<Consumption> {
+        Ok(Consumption {
+            instance_count: checked(self.instance_count.checked_add(other.instance_count))?,
+            witness_count: checked(self.witness_count.checked_add(other.witness_count))?,
+        })
+    }
+
+    fn max(&self, other: &Consumption) -> Consumption {
+        Consumption {
+            instance_count: max(self.instance_count, other.instance_count),
+            witness_count: max(self.witness_count, other.witness_count),
+        }
+    }
+
+    fn scale(&self, factor: u64) -> Result<Consumption> {
+        Ok(Consumption {
+            instance_count: checked(self.instance_count.checked_mul(factor))?,
+            witness_count: checked(self.witness_count.checked_mul(factor))?,
+        })
+    }
+}
+
+/// The values consumed by some gates, for each type of values, as each type has its own streams.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct TypedConsumption(pub BTreeMap<TypeId, Consumption>);
+
+impl TypedConsumption {
+    fn single(type_id: TypeId, consumption: Consumption) -> TypedConsumption {
+        TypedConsumption(std::iter::once((type_id, consumption)).collect())
+    }
+
+    /// The values of one type consumed, if any.
+    pub fn of_type(&self, type_id: TypeId) -> Consumption {
+        self.0.get(&type_id).cloned().unwrap_or_default()
+    }
+
+    fn add(&self, other: &TypedConsumption) -> Result<TypedConsumption> {
+        let mut sum = self.clone();
+        for (type_id, consumption) in &other.0 {
+            let entry = sum.0.entry(*type_id).or_default();
+            *entry = entry.add(consumption)?;
+        }
+        Ok(sum)
+    }
+
+    fn max(&self, other: &TypedConsumption) -> TypedConsumption {
+        let mut max = self.clone();
+        for (type_id, consumption) in &other.0 {
+            let entry = max.0.entry(*type_id).or_default();
+            *entry = entry.max(consumption);
+        }
+        max
+    }
+
+    fn scale(&self, factor: u64) -> Result<TypedConsumption> {
+        let mut scaled = BTreeMap::new();
+        for (type_id, consumption) in &self.0 {
+            scaled.insert(*type_id, consumption.scale(factor)?);
+        }
+        Ok(TypedConsumption(scaled))
+    }
+}
+
+fn checked(count: Option<u64>) -> Result<u64> {
+    count.ok_or_else(|| "The relation consumes more than 2^64 values.".into())
+}
+
+/// ConsumptionCounter computes how many instance and witness values the relations consume, from
+/// their structure alone, without values. Loops consume the values of their body once per
+/// iteration, and switches the maximum over their branches.
+///
+/// It also counts the values provided by Instance and Witness messages, to report any excess or
+/// shortfall. The values of each type are counted separately.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ConsumptionCounter {
+    /// The values consumed by one call of each function.
+    pub functions: BTreeMap<String, TypedConsumption>,
+    /// The values consumed by the gates of the relations.
+    pub total: TypedConsumption,
+    /// The number of instance values provided of each type, if there was any Instance message.
+    pub provided_instances: Option<BTreeMap<TypeId, u64>>,
+    /// The number of witness values provided of each type, if there was any Witness message.
+    pub provided_witnesses: Option<BTreeMap<TypeId, u64>>,
+}
+
+impl ConsumptionCounter {
+    pub fn from_messages(messages: impl Iterator<Item = Result<Message>>) -> Result<Self> {
+        let mut counter = ConsumptionCounter::default();
+        for msg in messages {
+            counter.ingest_message(&msg?)?;
+        }
+        Ok(counter)
+    }
+
+    pub fn ingest_message(&mut self, msg: &Message) -> Result<()> {
+        match msg {
+            Message::Instance(instance) => provide(
+                &mut self.provided_instances,
+                instance.type_id,
+                instance.common_inputs.len(),
+            )?,
+            Message::Witness(witness) => provide(
+                &mut self.provided_witnesses,
+                witness.type_id,
+                witness.short_witness.len(),
+            )?,
+            Message::Relation(relation) => self.ingest_relation(relation)?,
+        }
+        Ok(())
+    }
+
+    pub fn ingest_relation(&mut self, relation: &Relation) -> Result<()> {
+        for f in relation.functions.iter() {
+            let consumption = self.count_gates(&f.body)?;
+            self.functions.insert(f.name.clone(), consumption);
+        }
+        self.total = self.total.add(&self.count_gates(&relation.gates)?)?;
+        Ok(())
+    }
+
+    /// Compare the values consumed with the values provided, type by type. Witnesses are not
+    /// checked if no Witness message was given, e.g. for a verifier.
+    pub fn get_violations(&self) -> Vec<String> {
+        let mut violations = vec![];
+        let no_values = BTreeMap::new();
+        let instances = self.provided_instances.as_ref().unwrap_or(&no_values);
+        for type_id in self.type_ids(instances) {
+            check_provided(
+                &mut violations,
+                &kind("instance", type_id),
+                self.total.of_type(type_id).instance_count,
+                instances.get(&type_id).cloned().unwrap_or(0),
+            );
+        }
+        if let Some(witnesses) = &self.provided_witnesses {
+            for type_id in self.type_ids(witnesses) {
+                check_provided(
+                    &mut violations,
+                    &kind("witness", type_id),
+                    self.total.of_type(type_id).witness_count,
+                    witnesses.get(&type_id).cloned().unwrap_or(0),
+                );
+            }
+        }
+        violations
+    }
+
+    /// The types that are consumed or provided.
+    fn type_ids(&self, provided: &BTreeMap<TypeId, u64>) -> BTreeSet<TypeId> {
+        self.total
+            .0
+            .keys()
+            .chain(provided.keys())
+            .cloned()
+            .collect()
+    }
+
+    fn count_gates(&self, gates: &[Gate]) -> Result<TypedConsumption> {
+        let mut consumption = TypedConsumption::default();
+        for gate in gates {
+            consumption = consumption.add(&self.count_gate(0, gate)?)?;
+        }
+        Ok(consumption)
+    }
+
+    /// The values consumed by a gate on the wires of the type `type_id`.
+    fn count_gate(&self, type_id: TypeId, gate: &Gate) -> Result<TypedConsumption> {
+        Ok(match gate {
+            Gate::Instance(_) => TypedConsumption::single(
+                type_id,
+                Consumption {
+                    instance_count: 1,
+                    witness_count: 0,
+                },
+            ),
+            Gate::Witness(_) => TypedConsumption::single(
+                type_id,
+                Consumption {
+                    instance_count: 0,
+                    witness_count: 1,
+                },
+            ),
+            Gate::Typed(type_id, gate) => self.count_gate(*type_id, gate)?,
+            Gate::AnonCall(_, _, _, _, subcircuit) => self.count_gates(subcircuit)?,
+            Gate::Call(name, _, _) => self.function(name)?,
+            Gate::Switch(_, _, _, branches) => {
+                let mut max_consumption = TypedConsumption::default();
+                for branch in branches {
+                    let branch_consumption = match branch {
+                        CaseInvoke::AbstractGateCall(name, _) => self.function(name)?,
+                        CaseInvoke::AbstractAnonCall(_, _, _, subcircuit) => {
+                            self.count_gates(subcircuit)?
+                        }
+                    };
+                    max_consumption = max_consumption.max(&branch_consumption);
+                }
+                max_consumption
+            }
+            Gate::For(_, start, end, _, body) => {
+                let iteration = match body {
+                    ForLoopBody::IterExprCall(name, _, _) => self.function(name)?,
+                    ForLoopBody::IterExprAnonCall(_, _, _, _, subcircuit) => {
+                        self.count_gates(subcircuit)?
+                    }
+                };
+                if end < start {
+                    TypedConsumption::default()
+                } else {
+                    // Do not overflow on a loop over all u64 values that consumes nothing.
+                    iteration.scale(end - start)?.add(&iteration)?
+                }
+            }
+            _ => TypedConsumption::default(),
+        })
+    }
+
+    fn function(&self, name: &str) -> Result<TypedConsumption> {
+        self.functions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Unknown function {}.", name).into())
+    }
+}
+
+/// Count the values of a message of the type `type_id`.
+fn provide(
+    provided: &mut Option<BTreeMap<TypeId, u64>>,
+    type_id: TypeId,
+    len: usize,
+) -> Result<()> {
+    let count = provided
+        .get_or_insert_with(BTreeMap::new)
+        .entry(type_id)
+        .or_insert(0);
+    *count = checked(count.checked_add(len as u64))?;
+    Ok(())
+}
+
+/// Name the values of a kind, mentioning their type unless it is the default one.
+fn kind(kind: &str, type_id: TypeId) -> String {
+    if type_id == 0 {
+        kind.to_string()
+    } else {
+        format!("{} (type {})", kind, type_id)
+    }
+}
+
+fn check_provided(violations: &mut Vec<String>, kind: &str, consumed: u64, provided: u64) {
+    if provided < consumed {
+        violations.push(format!(
+            "The relation consumes {} {} values, but only {} are provided ({} missing).",
+            consumed,
+            kind,
+            provided,
+            consumed - provided
+        ));
+    } else if provided > consumed {
+        violations.push(format!(
+            "{} {} values are provided, but the relation consumes only {} ({} in excess).",
+            provided,
+            kind,
+            consumed,
+            provided - consumed
+        ));
+    }
+}
+
+#[test]
+fn test_consumption_counter() -> Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::function::Function;
+
+    let instance = example_instance();
+    let witness = example_witness();
+    let relation = example_relation();
+
+    let counter = ConsumptionCounter::from_messages(
+        vec![
+            Ok(Message::Instance(instance.clone())),
+            Ok(Message::Witness(witness.clone())),
+            Ok(Message::Relation(relation.clone())),
+        ]
+        .into_iter(),
+    )?;
+    assert_eq!(
+        counter.total,
+        TypedConsumption::single(
+            0,
+            Consumption {
+                instance_count: instance.common_inputs.len() as u64,
+                witness_count: witness.short_witness.len() as u64,
+            }
+        )
+    );
+    assert_eq!(
+        counter.functions["com.example::mul"],
+        TypedConsumption::default()
+    );
+    assert_eq!(counter.get_violations(), Vec::<String>::new());
+
+    // A function called by a switch and a loop.
+    let mut relation = Relation::default();
+    relation.functions.push(Function::new(
+        "two_witnesses".to_string(),
+        0,
+        0,
+        0,
+        2,
+        vec![Gate::Witness(0), Gate::Witness(1)],
+    ));
+    relation.gates = vec![
+        Gate::Switch(
+            0,
+            vec![],
+            vec![vec![0], vec![1]],
+            vec![
+                CaseInvoke::AbstractGateCall("two_witnesses".to_string(), vec![]),
+                CaseInvoke::AbstractAnonCall(
+                    vec![],
+                    1,
+                    1,
+                    vec![Gate::Instance(0), Gate::Witness(1)],
+                ),
+            ],
+        ),
+        Gate::For(
+            "i".to_string(),
+            10,
+            19,
+            vec![],
+            ForLoopBody::IterExprCall("two_witnesses".to_string(), vec![], vec![]),
+        ),
+    ];
+
+    let mut counter = ConsumptionCounter::default();
+    counter.ingest_relation(&relation)?;
+    assert_eq!(
+        counter.functions["two_witnesses"].of_type(0),
+        Consumption {
+            instance_count: 0,
+            witness_count: 2,
+        }
+    );
+    assert_eq!(
+        counter.total.of_type(0),
+        Consumption {
+            instance_count: 1,
+            witness_count: 22,
+        }
+    );
+
+    // Values missing or in excess.
+    counter.ingest_message(&Message::Instance(instance.clone()))?;
+    counter.ingest_message(&Message::Witness(witness.clone()))?;
+    assert_eq!(
+        counter.get_violations(),
+        vec![
+            "3 instance values are provided, but the relation consumes only 1 (2 in excess).",
+            "The relation consumes 22 witness values, but only 4 are provided (18 missing).",
+        ]
+    );
+
+    // Each type has its own values.
+    let mut relation = Relation::default();
+    relation.gates = vec![
+        Gate::Instance(0),
+        Gate::Typed(1, Box::new(Gate::Instance(1))),
+        Gate::Typed(1, Box::new(Gate::Witness(2))),
+        Gate::Typed(1, Box::new(Gate::Witness(3))),
+    ];
+    let mut typed_instance = instance;
+    typed_instance.type_id = 1;
+    typed_instance.common_inputs.truncate(1);
+    let counter = ConsumptionCounter::from_messages(
+        vec![
+            Ok(Message::Instance(typed_instance)),
+            Ok(Message::Witness(witness)),
+            Ok(Message::Relation(relation)),
+        ]
+        .into_iter(),
+    )?;
+    assert_eq!(
+        counter.total.of_type(1),
+        Consumption {
+            instance_count: 1,
+            witness_count: 2,
+        }
+    );
+    assert_eq!(
+        counter.get_violations(),
+        vec![
+            "The relation consumes 1 instance values, but only 0 are provided (1 missing).",
+            "4 witness values are provided, but the relation consumes only 0 (4 in excess).",
+            "The relation consumes 2 witness (type 1) values, but only 0 are provided (2 missing).",
+        ]
+    );
+
+    Ok(())
+}
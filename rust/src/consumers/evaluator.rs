@@ -1,8 +1,12 @@
-use crate::structs::function::{CaseInvoke, ForLoopBody};
-use crate::structs::iterators::evaluate_iterexpr_list;
+use crate::consumers::limits::{Budget, Limits};
+use crate::consumers::wire_scope::WireScope;
+use crate::structs::call_graph::CallGraph;
+use crate::structs::function::{CaseInvoke, ForLoopBody, Function};
+use crate::structs::message::MessageView;
 use crate::structs::relation::{contains_feature, BOOL};
-use crate::structs::wire::expand_wirelist;
-use crate::{Gate, Header, Instance, Message, Relation, Result, WireId, Witness};
+use crate::structs::relation_view::RelationView;
+use crate::structs::wire::WireRanges;
+use crate::{Gate, Header, Instance, Message, Relation, Result, TypeId, Value, WireId, Witness};
 use num_bigint::BigUint;
 use num_traits::identities::{One, Zero};
 use std::collections::{HashMap, VecDeque};
@@ -73,6 +77,35 @@ pub trait ZKBackend {
     /// Both cases should return a `Self::Wire` so the ZKBackend should have a specific wire value
     /// to handle it when in verifier mode.
     fn witness(&mut self, val: Option<Self::FieldElement>) -> Result<Self::Wire>;
+
+    /// Declares the fields of the types 1, 2, … of the statement, after `set_field` which is
+    /// the field of the type 0 (since IR version 2.0.0). By default, only the type 0 is supported.
+    fn set_types(&mut self, types: &[Value]) -> Result<()> {
+        if types.is_empty() {
+            Ok(())
+        } else {
+            Err("This backend does not support multiple types".into())
+        }
+    }
+    /// Selects the type of the wires of the next gates. It is reset to 0 after each typed gate.
+    fn set_type(&mut self, type_id: TypeId) -> Result<()> {
+        if type_id == 0 {
+            Ok(())
+        } else {
+            Err(format!("This backend does not support the type {}", type_id).into())
+        }
+    }
+    /// Converts the digits of a number in the field of `in_type` (most significant first) into
+    /// `output_count` digits of the same number in the field of `out_type`.
+    fn convert(
+        &mut self,
+        _output_count: usize,
+        _out_type: TypeId,
+        _in_type: TypeId,
+        _inputs: &[&Self::Wire],
+    ) -> Result<Vec<Self::Wire>> {
+        Err("This backend does not support conversion gates".into())
+    }
 }
 
 /// Used to evaluate a 'multiplication' in either the arithmetic case or the boolean,
@@ -135,6 +168,43 @@ struct FunctionDeclaration {
     input_count: usize,
 }
 
+/// The pools of instance or witness values, one queue per type.
+#[derive(Clone)]
+struct TypedQueues<F> {
+    queues: Vec<VecDeque<F>>,
+}
+
+impl<F> Default for TypedQueues<F> {
+    fn default() -> Self {
+        TypedQueues { queues: vec![] }
+    }
+}
+
+impl<F> TypedQueues<F> {
+    fn push_back(&mut self, type_id: TypeId, value: F) {
+        let index = type_id as usize;
+        if self.queues.len() <= index {
+            self.queues.resize_with(index + 1, VecDeque::new);
+        }
+        self.queues[index].push_back(value);
+    }
+
+    fn pop_front(&mut self, type_id: TypeId) -> Option<F> {
+        self.queues.get_mut(type_id as usize)?.pop_front()
+    }
+
+    /// Removes up to `count` values from the front of the queue of the type 0, and returns them
+    /// as new pools.
+    fn split_front(&mut self, count: usize) -> TypedQueues<F> {
+        let mut front = TypedQueues::default();
+        if let Some(queue) = self.queues.get_mut(0) {
+            let back = queue.split_off(std::cmp::min(queue.len(), count));
+            front.queues.push(std::mem::replace(queue, back));
+        }
+        front
+    }
+}
+
 /// This structure is the core of IR evaluation. It is instantiated using a ZKBackend,
 /// and will read the IR circuit, parses it, and calls the corresponding function from the
 /// ZKBackend to evaluate each single operation.
@@ -156,17 +226,21 @@ struct FunctionDeclaration {
 /// let _ = simulator.ingest_relation(&relation, &mut zkbackend);
 /// ```
 pub struct Evaluator<B: ZKBackend> {
-    values: HashMap<WireId, B::Wire>,
+    values: WireScope<B::Wire>,
     modulus: BigUint,
-    instance_queue: VecDeque<B::FieldElement>,
-    witness_queue: VecDeque<B::FieldElement>,
+    instance_queue: TypedQueues<B::FieldElement>,
+    witness_queue: TypedQueues<B::FieldElement>,
     is_boolean: bool,
 
     // name => (instance_nbr, witness_nbr, subcircuit)
     known_functions: HashMap<String, FunctionDeclaration>,
+    // The calls between the known functions, updated with the functions of each message.
+    call_graph: CallGraph,
 
     verified_at_least_one_gate: bool,
     found_error: Option<String>,
+
+    budget: Budget,
 }
 
 impl<B: ZKBackend> Default for Evaluator<B> {
@@ -178,8 +252,10 @@ impl<B: ZKBackend> Default for Evaluator<B> {
             witness_queue: Default::default(),
             is_boolean: false,
             known_functions: Default::default(),
+            call_graph: Default::default(),
             verified_at_least_one_gate: false,
             found_error: None,
+            budget: Default::default(),
         }
     }
 }
@@ -194,6 +270,12 @@ impl<B: ZKBackend> Evaluator<B> {
         evaluator
     }
 
+    /// Set the limits on the resources used to evaluate the circuit. It resets the resources
+    /// already counted.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Budget::new(limits);
+    }
+
     /// Returns the list of violations detected when evaluating the IR circuit.
     /// It consumes `self`.
     pub fn get_violations(self) -> Vec<String> {
@@ -229,6 +311,22 @@ impl<B: ZKBackend> Evaluator<B> {
         }
     }
 
+    /// Like `ingest_message`, with the relation read in place.
+    pub fn ingest_message_view(&mut self, msg: &MessageView, backend: &mut B) {
+        if self.found_error.is_some() {
+            return;
+        }
+
+        let result = match msg {
+            MessageView::Instance(i) => self.ingest_instance(&i),
+            MessageView::Witness(w) => self.ingest_witness(&w),
+            MessageView::Relation(r) => self.ingest_relation_view(&r, backend),
+        };
+        if let Err(err) = result {
+            self.found_error = Some(err.to_string());
+        }
+    }
+
     fn ingest_header(&mut self, header: &Header) -> Result<()> {
         self.modulus = BigUint::from_bytes_le(&header.field_characteristic);
         Ok(())
@@ -240,7 +338,9 @@ impl<B: ZKBackend> Evaluator<B> {
         self.ingest_header(&instance.header)?;
 
         for value in &instance.common_inputs {
-            self.instance_queue.push_back(B::from_bytes_le(value)?);
+            self.budget.limits.check_value(value)?;
+            self.instance_queue
+                .push_back(instance.type_id, B::from_bytes_le(value)?);
         }
         Ok(())
     }
@@ -251,57 +351,120 @@ impl<B: ZKBackend> Evaluator<B> {
         self.ingest_header(&witness.header)?;
 
         for value in &witness.short_witness {
-            self.witness_queue.push_back(B::from_bytes_le(value)?);
+            self.budget.limits.check_value(value)?;
+            self.witness_queue
+                .push_back(witness.type_id, B::from_bytes_le(value)?);
         }
         Ok(())
     }
 
     /// Ingest a `Relation` message
     pub fn ingest_relation(&mut self, relation: &Relation, backend: &mut B) -> Result<()> {
-        self.ingest_header(&relation.header)?;
-        self.is_boolean = contains_feature(relation.gate_mask, BOOL);
-        backend.set_field(
-            &relation.header.field_characteristic,
-            relation.header.field_degree,
-            self.is_boolean,
-        )?;
+        self.ingest_relation_header(&relation.header, relation.gate_mask, backend)?;
 
         if relation.gates.len() > 0 {
             self.verified_at_least_one_gate = true;
         }
 
         for f in relation.functions.iter() {
-            self.known_functions.insert(
-                f.name.clone(),
-                FunctionDeclaration {
-                    subcircuit: f.body.clone(),
-                    instance_nbr: f.instance_count,
-                    witness_nbr: f.witness_count,
-                    output_count: f.output_count,
-                    input_count: f.input_count,
-                },
-            );
+            self.define_function(f.clone());
         }
+        self.ensure_not_recursive(relation.functions.iter().map(|f| &f.name))?;
 
         let mut known_iterators = HashMap::new();
 
         for gate in &relation.gates {
-            Self::ingest_gate(
-                gate,
-                backend,
-                &mut self.values,
-                &self.known_functions,
-                &mut known_iterators,
-                &self.modulus,
-                self.is_boolean,
-                &mut self.instance_queue,
-                &mut self.witness_queue,
-                None,
-            )?;
+            self.ingest_relation_gate(gate, backend, &mut known_iterators)?;
         }
         Ok(())
     }
 
+    /// Like `ingest_relation`, reading the relation in place. The body of each function is
+    /// decoded once into its declaration, and each gate is decoded, evaluated and dropped in turn.
+    pub fn ingest_relation_view(&mut self, relation: &RelationView, backend: &mut B) -> Result<()> {
+        self.ingest_relation_header(&relation.header()?, relation.gate_mask()?, backend)?;
+
+        let gates = relation.gates()?;
+        if gates.len() > 0 {
+            self.verified_at_least_one_gate = true;
+        }
+
+        let mut names = vec![];
+        for f in relation.functions() {
+            let f = f.to_function()?;
+            names.push(f.name.clone());
+            self.define_function(f);
+        }
+        self.ensure_not_recursive(&names)?;
+
+        let mut known_iterators = HashMap::new();
+
+        for gate in gates {
+            self.ingest_relation_gate(&gate?, backend, &mut known_iterators)?;
+        }
+        Ok(())
+    }
+
+    fn ingest_relation_header(
+        &mut self,
+        header: &Header,
+        gate_mask: u16,
+        backend: &mut B,
+    ) -> Result<()> {
+        self.ingest_header(header)?;
+        self.is_boolean = contains_feature(gate_mask, BOOL);
+        backend.set_field(
+            &header.field_characteristic,
+            header.field_degree,
+            self.is_boolean,
+        )?;
+        backend.set_types(&header.types)
+    }
+
+    fn define_function(&mut self, f: Function) {
+        self.call_graph.add_function(&f.name, &f.body);
+        self.known_functions.insert(
+            f.name,
+            FunctionDeclaration {
+                subcircuit: f.body,
+                instance_nbr: f.instance_count,
+                witness_nbr: f.witness_count,
+                output_count: f.output_count,
+                input_count: f.input_count,
+            },
+        );
+    }
+
+    /// Refuse recursive functions, which would never finish to be evaluated. The functions known
+    /// before are not recursive, so only the cycles through the new functions are searched.
+    fn ensure_not_recursive<'a>(
+        &self,
+        new_functions: impl IntoIterator<Item = &'a String>,
+    ) -> Result<()> {
+        self.call_graph.check_recursion_from(new_functions)
+    }
+
+    fn ingest_relation_gate(
+        &mut self,
+        gate: &Gate,
+        backend: &mut B,
+        known_iterators: &mut HashMap<String, u64>,
+    ) -> Result<()> {
+        Self::ingest_gate(
+            gate,
+            backend,
+            &mut self.values,
+            &self.known_functions,
+            known_iterators,
+            &self.modulus,
+            self.is_boolean,
+            &mut self.instance_queue,
+            &mut self.witness_queue,
+            None,
+            &mut self.budget,
+        )
+    }
+
     /// This function ingests one gate at a time (but can call itself recursively)
     /// If the current gate is in a branch of a switch, then it has to be weighted.
     /// The weight is used in `AssertZero` gates by multiplying the tested wire by the weight. It
@@ -315,20 +478,26 @@ impl<B: ZKBackend> Evaluator<B> {
     /// - `modulus` and `is_boolean` are used mainly is switches to compute the weight of each branch.
     /// - `instances` and `witnesses` are the instances and witnesses pools, implemented as Queues.
     ///    They will be consumed whenever necessary.
+    /// - `budget` counts the resources used, and returns an Err when a limit is exceeded.
     fn ingest_gate(
         gate: &Gate,
         backend: &mut B,
-        scope: &mut HashMap<WireId, B::Wire>,
+        scope: &mut WireScope<B::Wire>,
         known_functions: &HashMap<String, FunctionDeclaration>,
         known_iterators: &mut HashMap<String, u64>,
         modulus: &BigUint,
         is_boolean: bool,
-        instances: &mut VecDeque<B::FieldElement>,
-        witnesses: &mut VecDeque<B::FieldElement>,
+        instances: &mut TypedQueues<B::FieldElement>,
+        witnesses: &mut TypedQueues<B::FieldElement>,
         weight: Option<&B::Wire>,
+        budget: &mut Budget,
     ) -> Result<()> {
         use Gate::*;
 
+        if !matches!(gate, Typed(..)) {
+            budget.spend_gates(1)?;
+        }
+
         macro_rules! get {
             ($wire_id:expr) => {{
                 get::<B>(scope, $wire_id)
@@ -343,6 +512,7 @@ impl<B: ZKBackend> Evaluator<B> {
 
         match gate {
             Constant(out, value) => {
+                budget.limits.check_value(value)?;
                 let wire = backend.constant(B::from_bytes_le(value)?)?;
                 set::<B>(scope, *out, wire)?;
             }
@@ -385,6 +555,7 @@ impl<B: ZKBackend> Evaluator<B> {
 
             AddConstant(out, inp, constant) => {
                 let l = get!(*inp)?;
+                budget.limits.check_value(constant)?;
                 let r = B::from_bytes_le(constant)?;
                 let sum = backend.add_constant(l, r)?;
                 set!(*out, sum)?;
@@ -392,6 +563,7 @@ impl<B: ZKBackend> Evaluator<B> {
 
             MulConstant(out, inp, constant) => {
                 let l = get!(*inp)?;
+                budget.limits.check_value(constant)?;
                 let r = B::from_bytes_le(constant)?;
                 let prod = backend.mul_constant(l, r)?;
                 set!(*out, prod)?;
@@ -418,7 +590,7 @@ impl<B: ZKBackend> Evaluator<B> {
             }
 
             Instance(out) => {
-                let val = if let Some(inner) = instances.pop_front() {
+                let val = if let Some(inner) = instances.pop_front(0) {
                     inner
                 } else {
                     return Err("Not enough instance to consume".into());
@@ -427,12 +599,86 @@ impl<B: ZKBackend> Evaluator<B> {
             }
 
             Witness(out) => {
-                let val = witnesses.pop_front();
+                let val = witnesses.pop_front(0);
                 set_witness(backend, scope, *out, val)?;
             }
 
+            // The backend works on the wires of the type while the gate is evaluated. Switches
+            // are multiplexed in the type 0 only, so typed gates cannot be weighted.
+            Typed(type_id, inner) => {
+                if weight.is_some() && *type_id != 0 {
+                    return Err(format!(
+                        "Gates of type {} are not supported in the branches of a switch",
+                        type_id
+                    )
+                    .into());
+                }
+                backend.set_type(*type_id)?;
+                let result =
+                    match inner.as_ref() {
+                        Instance(out) => budget.spend_gates(1).and_then(|_| {
+                            match instances.pop_front(*type_id) {
+                                Some(val) => set_instance(backend, scope, *out, val),
+                                None => Err(format!(
+                                    "Not enough instance of type {} to consume",
+                                    type_id
+                                )
+                                .into()),
+                            }
+                        }),
+                        Witness(out) => budget.spend_gates(1).and_then(|_| {
+                            let val = witnesses.pop_front(*type_id);
+                            set_witness(backend, scope, *out, val)
+                        }),
+                        inner => Self::ingest_gate(
+                            inner,
+                            backend,
+                            scope,
+                            known_functions,
+                            known_iterators,
+                            modulus,
+                            is_boolean,
+                            instances,
+                            witnesses,
+                            weight,
+                            budget,
+                        ),
+                    };
+                backend.set_type(0)?;
+                result?;
+            }
+
+            Convert(out_type, output_wires, in_type, input_wires) => {
+                if weight.is_some() {
+                    return Err(
+                        "Conversion gates are not supported in the branches of a switch".into(),
+                    );
+                }
+                let output_wires = budget.limits.wirelist_view(output_wires)?;
+                let input_wires = budget.limits.wirelist_view(input_wires)?;
+                let outputs = {
+                    let inputs = input_wires
+                        .ids()
+                        .map(|wire| get!(wire))
+                        .collect::<Result<Vec<_>>>()?;
+                    backend.convert(output_wires.len(), *out_type, *in_type, &inputs)?
+                };
+                if outputs.len() != output_wires.len() {
+                    return Err(format!(
+                        "The conversion returned {} wires instead of {}",
+                        outputs.len(),
+                        output_wires.len()
+                    )
+                    .into());
+                }
+                for (wire, value) in output_wires.ids().zip(outputs) {
+                    set!(wire, value)?;
+                }
+            }
+
             Free(first, last) => {
                 let last_value = last.unwrap_or(*first);
+                budget.limits.check_wire_range(*first, last_value)?;
                 for current in *first..=last_value {
                     remove::<B>(scope, current)?;
                 }
@@ -442,23 +688,23 @@ impl<B: ZKBackend> Evaluator<B> {
                 let function = known_functions
                     .get(name)
                     .ok_or_else(|| "Unknown function")?;
-                let expanded_output = expand_wirelist(output_wires)?;
-                let expanded_input = expand_wirelist(input_wires)?;
+                let output_wires = budget.limits.wirelist_view(output_wires)?;
+                let input_wires = budget.limits.wirelist_view(input_wires)?;
 
                 // simple checks.
-                if expanded_output.len() != function.output_count {
-                    return Err(format!("Wrong number of output variables in call to function {} (Expected {} / Got {}).", name, function.output_count, expanded_output.len()).into());
+                if output_wires.len() != function.output_count {
+                    return Err(format!("Wrong number of output variables in call to function {} (Expected {} / Got {}).", name, function.output_count, output_wires.len()).into());
                 }
-                if expanded_input.len() != function.input_count {
-                    return Err(format!("Wrong number of input variables in call to function {} (Expected {} / Got {}).", name, function.input_count, expanded_input.len()).into());
+                if input_wires.len() != function.input_count {
+                    return Err(format!("Wrong number of input variables in call to function {} (Expected {} / Got {}).", name, function.input_count, input_wires.len()).into());
                 }
 
                 // in the case of an named call, iterators *ARE NOT* forwarded into inner bodies.
                 Self::ingest_subcircuit(
                     &function.subcircuit,
                     backend,
-                    &expanded_output,
-                    &expanded_input,
+                    output_wires,
+                    input_wires,
                     scope,
                     known_functions,
                     &mut HashMap::new(),
@@ -467,18 +713,19 @@ impl<B: ZKBackend> Evaluator<B> {
                     instances,
                     witnesses,
                     weight,
+                    budget,
                 )?;
             }
 
             AnonCall(output_wires, input_wires, _, _, subcircuit) => {
-                let expanded_output = expand_wirelist(output_wires)?;
-                let expanded_input = expand_wirelist(input_wires)?;
+                let output_wires = budget.limits.wirelist_view(output_wires)?;
+                let input_wires = budget.limits.wirelist_view(input_wires)?;
                 // in the case of an anoncall, iterators *ARE* forwarded into inner bodies.
                 Self::ingest_subcircuit(
                     subcircuit,
                     backend,
-                    &expanded_output,
-                    &expanded_input,
+                    output_wires,
+                    input_wires,
                     scope,
                     known_functions,
                     known_iterators,
@@ -487,12 +734,16 @@ impl<B: ZKBackend> Evaluator<B> {
                     instances,
                     witnesses,
                     weight,
+                    budget,
                 )?;
             }
 
             // For loops are unrolled. The body is called as many times (NB: the loop bounds are
             // inclusive), and iterator expressions are evaluated for each.
             For(iterator_name, start_val, end_val, _, body) => {
+                budget
+                    .limits
+                    .check_loop(iterator_name, *start_val, *end_val)?;
                 for i in *start_val..=*end_val {
                     known_iterators.insert(iterator_name.clone(), i);
 
@@ -501,22 +752,28 @@ impl<B: ZKBackend> Evaluator<B> {
                             let function = known_functions
                                 .get(name)
                                 .ok_or_else(|| "Unknown function")?;
-                            let expanded_output = evaluate_iterexpr_list(outputs, known_iterators);
-                            let expanded_input = evaluate_iterexpr_list(inputs, known_iterators);
+                            // The ranges are collected, since the iterators are borrowed by
+                            // the views.
+                            let outputs =
+                                budget.limits.iterexpr_list_view(outputs, known_iterators)?;
+                            let inputs =
+                                budget.limits.iterexpr_list_view(inputs, known_iterators)?;
 
                             // simple checks.
-                            if expanded_output.len() != function.output_count {
-                                return Err(format!("Wrong number of output variables in call to function {} (Expected {} / Got {}).", name, function.output_count, expanded_output.len()).into());
+                            if outputs.len() != function.output_count {
+                                return Err(format!("Wrong number of output variables in call to function {} (Expected {} / Got {}).", name, function.output_count, outputs.len()).into());
                             }
-                            if expanded_input.len() != function.input_count {
-                                return Err(format!("Wrong number of input variables in call to function {} (Expected {} / Got {}).", name, function.input_count, expanded_input.len()).into());
+                            if inputs.len() != function.input_count {
+                                return Err(format!("Wrong number of input variables in call to function {} (Expected {} / Got {}).", name, function.input_count, inputs.len()).into());
                             }
+                            let output_ranges: Vec<_> = outputs.ranges().collect();
+                            let input_ranges: Vec<_> = inputs.ranges().collect();
 
                             Self::ingest_subcircuit(
                                 &function.subcircuit,
                                 backend,
-                                &expanded_output,
-                                &expanded_input,
+                                &output_ranges[..],
+                                &input_ranges[..],
                                 scope,
                                 known_functions,
                                 &mut HashMap::new(),
@@ -525,6 +782,7 @@ impl<B: ZKBackend> Evaluator<B> {
                                 instances,
                                 witnesses,
                                 weight,
+                                budget,
                             )?;
                         }
                         ForLoopBody::IterExprAnonCall(
@@ -534,15 +792,21 @@ impl<B: ZKBackend> Evaluator<B> {
                             _,
                             subcircuit,
                         ) => {
-                            let expanded_output =
-                                evaluate_iterexpr_list(output_wires, known_iterators);
-                            let expanded_input =
-                                evaluate_iterexpr_list(input_wires, known_iterators);
+                            let output_ranges: Vec<_> = budget
+                                .limits
+                                .iterexpr_list_view(output_wires, known_iterators)?
+                                .ranges()
+                                .collect();
+                            let input_ranges: Vec<_> = budget
+                                .limits
+                                .iterexpr_list_view(input_wires, known_iterators)?
+                                .ranges()
+                                .collect();
                             Self::ingest_subcircuit(
                                 subcircuit,
                                 backend,
-                                &expanded_output,
-                                &expanded_input,
+                                &output_ranges[..],
+                                &input_ranges[..],
                                 scope,
                                 known_functions,
                                 known_iterators,
@@ -551,6 +815,7 @@ impl<B: ZKBackend> Evaluator<B> {
                                 instances,
                                 witnesses,
                                 weight,
+                                budget,
                             )?;
                         }
                     }
@@ -583,21 +848,18 @@ impl<B: ZKBackend> Evaluator<B> {
                 // 'consumes' max_instances and max_witnesses values from the corresponding pools
                 // by removing them from the instances/witnesses variables, and storing them into
                 // new queues. The new queues (a clone of them) will be used in each branch.
-                let mut new_instances: VecDeque<B::FieldElement> =
-                    instances.split_off(std::cmp::min(instances.len(), max_instance_count));
-                std::mem::swap(instances, &mut new_instances);
-                let mut new_witnesses: VecDeque<B::FieldElement> =
-                    witnesses.split_off(std::cmp::min(witnesses.len(), max_witness_count));
-                std::mem::swap(witnesses, &mut new_witnesses);
+                let new_instances = instances.split_front(max_instance_count);
+                let new_witnesses = witnesses.split_front(max_witness_count);
 
                 // This will handle the input/output wires for each branches. Output wires will then
                 // be combined using their respective weight.
                 let mut branches_scope = Vec::new();
 
-                let expanded_output = expand_wirelist(output_wires)?;
+                let output_wires = budget.limits.wirelist_view(output_wires)?;
                 let mut weights = Vec::new();
 
                 for (case, branch) in cases.iter().zip(branches.iter()) {
+                    budget.limits.check_value(case)?;
                     // Compute (1 - ('case' - 'condition') ^ (self.modulus - 1))
                     let branch_weight =
                         compute_weight(backend, case, get!(*condition)?, modulus, is_boolean)?;
@@ -607,31 +869,31 @@ impl<B: ZKBackend> Evaluator<B> {
                         branch_weight
                     };
 
-                    let mut branch_scope = HashMap::new();
+                    let mut branch_scope = WireScope::new();
                     match branch {
                         CaseInvoke::AbstractGateCall(name, input_wires) => {
                             let function = known_functions
                                 .get(name)
                                 .ok_or_else(|| format!("Unknown function: {}", name))?;
-                            let expanded_input = expand_wirelist(input_wires)?;
+                            let input_wires = budget.limits.wirelist_view(input_wires)?;
 
                             // simple checks.
-                            if expanded_output.len() != function.output_count {
-                                return Err(format!("Wrong number of output variables in call to function {} (Expected {} / Got {}).", name, function.output_count, expanded_output.len()).into());
+                            if output_wires.len() != function.output_count {
+                                return Err(format!("Wrong number of output variables in call to function {} (Expected {} / Got {}).", name, function.output_count, output_wires.len()).into());
                             }
-                            if expanded_input.len() != function.input_count {
-                                return Err(format!("Wrong number of input variables in call to function {} (Expected {} / Got {}).", name, function.input_count, expanded_input.len()).into());
+                            if input_wires.len() != function.input_count {
+                                return Err(format!("Wrong number of input variables in call to function {} (Expected {} / Got {}).", name, function.input_count, input_wires.len()).into());
                             }
 
-                            for wire in expanded_input.iter() {
-                                let w = get!(*wire)?;
-                                branch_scope.insert(*wire, backend.copy(w)?);
+                            for wire in input_wires.ids() {
+                                let w = get!(wire)?;
+                                branch_scope.insert(wire, backend.copy(w)?);
                             }
                             Self::ingest_subcircuit(
                                 &function.subcircuit,
                                 backend,
-                                &expanded_output,
-                                &expanded_input,
+                                output_wires,
+                                input_wires,
                                 &mut branch_scope,
                                 known_functions,
                                 &mut HashMap::new(),
@@ -640,19 +902,20 @@ impl<B: ZKBackend> Evaluator<B> {
                                 &mut new_instances.clone(),
                                 &mut new_witnesses.clone(),
                                 Some(&weighted_branch_weight),
+                                budget,
                             )?;
                         }
                         CaseInvoke::AbstractAnonCall(input_wires, _, _, subcircuit) => {
-                            let expanded_input = expand_wirelist(input_wires)?;
-                            for wire in expanded_input.iter() {
-                                let w = get!(*wire)?;
-                                branch_scope.insert(*wire, backend.copy(w)?);
+                            let input_wires = budget.limits.wirelist_view(input_wires)?;
+                            for wire in input_wires.ids() {
+                                let w = get!(wire)?;
+                                branch_scope.insert(wire, backend.copy(w)?);
                             }
                             Self::ingest_subcircuit(
                                 subcircuit,
                                 backend,
-                                &expanded_output,
-                                &expanded_input,
+                                output_wires,
+                                input_wires,
                                 &mut branch_scope,
                                 known_functions,
                                 known_iterators,
@@ -661,6 +924,7 @@ impl<B: ZKBackend> Evaluator<B> {
                                 &mut new_instances.clone(),
                                 &mut new_witnesses.clone(),
                                 Some(&weighted_branch_weight),
+                                budget,
                             )?;
                         }
                     }
@@ -670,23 +934,24 @@ impl<B: ZKBackend> Evaluator<B> {
                 }
 
                 // Compute the weighted sum for all output wire.
-                for output_wire in expanded_output.iter() {
+                for output_wire in output_wires.ids() {
                     let weighted_output = branches_scope.iter().zip(weights.iter()).fold(
                         backend.constant(backend.zero()?),
                         |accu, (branch_scope, branch_weight)| {
                             let weighted_wire = as_mul(
                                 backend,
-                                get::<B>(branch_scope, *output_wire)?,
+                                get::<B>(branch_scope, output_wire)?,
                                 branch_weight,
                                 is_boolean,
                             )?;
                             as_add(backend, &accu?, &weighted_wire, is_boolean)
                         },
                     )?;
-                    set!(*output_wire, weighted_output)?;
+                    set!(output_wire, weighted_output)?;
                 }
             }
         }
+        budget.limits.check_live_wires(scope.len())?;
         Ok(())
     }
 
@@ -698,25 +963,29 @@ impl<B: ZKBackend> Evaluator<B> {
     fn ingest_subcircuit(
         subcircuit: &[Gate],
         backend: &mut B,
-        output_list: &[WireId],
-        input_list: &[WireId],
-        scope: &mut HashMap<WireId, B::Wire>,
+        output_list: impl WireRanges,
+        input_list: impl WireRanges,
+        scope: &mut WireScope<B::Wire>,
         known_functions: &HashMap<String, FunctionDeclaration>,
         known_iterators: &mut HashMap<String, u64>,
         modulus: &BigUint,
         is_boolean: bool,
-        instances: &mut VecDeque<B::FieldElement>,
-        witnesses: &mut VecDeque<B::FieldElement>,
+        instances: &mut TypedQueues<B::FieldElement>,
+        witnesses: &mut TypedQueues<B::FieldElement>,
         weight: Option<&B::Wire>,
+        budget: &mut Budget,
     ) -> Result<()> {
-        let mut new_scope: HashMap<WireId, B::Wire> = HashMap::new();
+        budget.enter()?;
+        // A new frame: the wires of the subcircuit are numbered from 0.
+        let mut new_scope: WireScope<B::Wire> = WireScope::new();
 
         // copy the inputs required by this function into the new scope, at the proper index
-        for (idx, input) in input_list.iter().enumerate() {
-            let i = get::<B>(scope, *input)?;
+        let output_count = output_list.len();
+        for (idx, input) in input_list.ids().enumerate() {
+            let i = get::<B>(scope, input)?;
             set::<B>(
                 &mut new_scope,
-                (idx + output_list.len()) as u64,
+                (idx + output_count) as u64,
                 backend.copy(i)?,
             )?;
         }
@@ -733,15 +1002,17 @@ impl<B: ZKBackend> Evaluator<B> {
                 instances,
                 witnesses,
                 weight,
+                budget,
             )?;
         }
         // copy the outputs produced from 'new_scope', into 'scope'
 
-        for (idx, output) in output_list.iter().enumerate() {
+        for (idx, output) in output_list.ids().enumerate() {
             let w = get::<B>(&new_scope, idx as u64)?;
-            set::<B>(scope, *output, backend.copy(w)?)?
+            set::<B>(scope, output, backend.copy(w)?)?
         }
 
+        budget.exit();
         Ok(())
     }
 
@@ -754,7 +1025,7 @@ impl<B: ZKBackend> Evaluator<B> {
 
 fn set_instance<I: ZKBackend>(
     backend: &mut I,
-    scope: &mut HashMap<WireId, I::Wire>,
+    scope: &mut WireScope<I::Wire>,
     id: WireId,
     value: I::FieldElement,
 ) -> Result<()> {
@@ -764,7 +1035,7 @@ fn set_instance<I: ZKBackend>(
 
 fn set_witness<I: ZKBackend>(
     backend: &mut I,
-    scope: &mut HashMap<WireId, I::Wire>,
+    scope: &mut WireScope<I::Wire>,
     id: WireId,
     value: Option<I::FieldElement>,
 ) -> Result<()> {
@@ -772,11 +1043,7 @@ fn set_witness<I: ZKBackend>(
     set::<I>(scope, id, wire)
 }
 
-fn set<I: ZKBackend>(
-    scope: &mut HashMap<WireId, I::Wire>,
-    id: WireId,
-    wire: I::Wire,
-) -> Result<()> {
+fn set<I: ZKBackend>(scope: &mut WireScope<I::Wire>, id: WireId, wire: I::Wire) -> Result<()> {
     if scope.insert(id, wire).is_some() {
         Err(format!("Wire_{} already has a value in this scope.", id).into())
     } else {
@@ -784,15 +1051,15 @@ fn set<I: ZKBackend>(
     }
 }
 
-pub fn get<I: ZKBackend>(scope: &HashMap<WireId, I::Wire>, id: WireId) -> Result<&I::Wire> {
+pub fn get<I: ZKBackend>(scope: &WireScope<I::Wire>, id: WireId) -> Result<&I::Wire> {
     scope
-        .get(&id)
+        .get(id)
         .ok_or_else(|| format!("No value given for wire_{}", id).into())
 }
 
-fn remove<I: ZKBackend>(scope: &mut HashMap<WireId, I::Wire>, id: WireId) -> Result<I::Wire> {
+fn remove<I: ZKBackend>(scope: &mut WireScope<I::Wire>, id: WireId) -> Result<I::Wire> {
     scope
-        .remove(&id)
+        .remove(id)
         .ok_or_else(|| format!("No value given for wire_{}", id).into())
 }
 
@@ -845,13 +1112,27 @@ fn compute_weight<B: ZKBackend>(
 /// can even be slower than a secure backend if the evaluated circuit contains a lot of such
 /// operations.
 /// Currently, this backend does not support 'verifier' mode, and requires witnesses to be provided.
+/// It supports the multiple types of IR version 2.0.0, and conversions between them.
 pub struct PlaintextBackend {
+    /// The modulus of the current type.
     pub m: BigUint,
+    // The moduli of all types, starting with the type 0.
+    moduli: Vec<BigUint>,
+}
+
+impl PlaintextBackend {
+    /// The moduli of all types, starting with the type 0.
+    pub fn moduli(&self) -> &[BigUint] {
+        &self.moduli
+    }
 }
 
 impl Default for PlaintextBackend {
     fn default() -> Self {
-        PlaintextBackend { m: BigUint::zero() }
+        PlaintextBackend {
+            m: BigUint::zero(),
+            moduli: vec![],
+        }
     }
 }
 
@@ -865,6 +1146,7 @@ impl ZKBackend for PlaintextBackend {
 
     fn set_field(&mut self, modulus: &[u8], degree: u32, _is_boolean: bool) -> Result<()> {
         self.m = BigUint::from_bytes_le(modulus);
+        self.moduli = vec![self.m.clone()];
         if self.m.is_zero() {
             Err("Modulus cannot be zero.".into())
         } else if degree != 1 {
@@ -874,6 +1156,26 @@ impl ZKBackend for PlaintextBackend {
         }
     }
 
+    fn set_types(&mut self, types: &[Value]) -> Result<()> {
+        for modulus in types {
+            let modulus = BigUint::from_bytes_le(modulus);
+            if modulus.is_zero() {
+                return Err("Modulus cannot be zero.".into());
+            }
+            self.moduli.push(modulus);
+        }
+        Ok(())
+    }
+
+    fn set_type(&mut self, type_id: TypeId) -> Result<()> {
+        self.m = self
+            .moduli
+            .get(type_id as usize)
+            .ok_or_else(|| format!("Unknown type {}", type_id))?
+            .clone();
+        Ok(())
+    }
+
     fn one(&self) -> Result<Self::FieldElement> {
         Ok(BigUint::one())
     }
@@ -944,6 +1246,40 @@ impl ZKBackend for PlaintextBackend {
     fn witness(&mut self, val: Option<Self::FieldElement>) -> Result<Self::Wire> {
         self.constant(val.unwrap_or_else(|| panic!("Missing witness value for PlaintextBackend")))
     }
+
+    fn convert(
+        &mut self,
+        output_count: usize,
+        out_type: TypeId,
+        in_type: TypeId,
+        inputs: &[&Self::Wire],
+    ) -> Result<Vec<Self::Wire>> {
+        let modulus = |type_id: TypeId| {
+            self.moduli
+                .get(type_id as usize)
+                .ok_or_else(|| format!("Unknown type {}", type_id))
+        };
+        let in_modulus = modulus(in_type)?;
+        let out_modulus = modulus(out_type)?;
+
+        let mut number = BigUint::zero();
+        for input in inputs {
+            number = number * in_modulus + *input;
+        }
+        let mut outputs = vec![BigUint::zero(); output_count];
+        for output in outputs.iter_mut().rev() {
+            *output = &number % out_modulus;
+            number /= out_modulus;
+        }
+        if !number.is_zero() {
+            return Err(format!(
+                "The conversion does not fit in {} wires of type {}",
+                output_count, out_type
+            )
+            .into());
+        }
+        Ok(outputs)
+    }
 }
 
 #[test]
@@ -1102,3 +1438,196 @@ fn test_evaluator_wrong_result() -> crate::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_evaluator_types() -> crate::Result<()> {
+    use crate::consumers::evaluator::Evaluator;
+    use crate::producers::examples::*;
+    use crate::structs::wire::WireListElement;
+    use crate::wirelist;
+
+    let relation = example_typed_relation();
+    let instance = example_typed_instance();
+    let witness = example_typed_witness();
+
+    let mut zkbackend = PlaintextBackend::default();
+    let mut simulator = Evaluator::default();
+    simulator.ingest_instance(&instance)?;
+    simulator.ingest_witness(&witness)?;
+    simulator.ingest_relation(&relation, &mut zkbackend)?;
+    assert_eq!(simulator.get_violations().len(), 0);
+    assert_eq!(zkbackend.m, BigUint::from(101u32));
+
+    // The bits of 4 do not pass the check in the type 1.
+    let mut wrong_witness = witness.clone();
+    wrong_witness.short_witness[2] = vec![0];
+    let mut simulator = Evaluator::default();
+    simulator.ingest_instance(&instance)?;
+    simulator.ingest_witness(&wrong_witness)?;
+    let should_be_err = simulator.ingest_relation(&relation, &mut PlaintextBackend::default());
+    assert_eq!(
+        "Wire_4 (may be weighted) should be 0, while it is not",
+        should_be_err.err().unwrap().to_string()
+    );
+
+    // Values of the type 1 are not consumed by gates of the type 0.
+    let mut wrong_instance = instance.clone();
+    wrong_instance.type_id = 1;
+    let mut simulator = Evaluator::default();
+    simulator.ingest_instance(&wrong_instance)?;
+    simulator.ingest_witness(&witness)?;
+    let should_be_err = simulator.ingest_relation(&relation, &mut PlaintextBackend::default());
+    assert_eq!(
+        "Not enough instance to consume",
+        should_be_err.err().unwrap().to_string()
+    );
+
+    // 5 does not fit in 2 bits.
+    let mut backend = PlaintextBackend::default();
+    backend.set_field(&[101], 1, false)?;
+    backend.set_types(&[vec![2]])?;
+    assert_eq!(
+        backend.moduli(),
+        &[BigUint::from(101u32), BigUint::from(2u32)]
+    );
+    let (one, zero) = (BigUint::one(), BigUint::zero());
+    assert_eq!(
+        backend.convert(3, 1, 0, &[&BigUint::from(5u32)])?,
+        vec![one.clone(), zero.clone(), one.clone()]
+    );
+    assert_eq!(
+        backend.convert(2, 0, 1, &[&one, &zero, &one])?,
+        vec![zero.clone(), BigUint::from(5u32)]
+    );
+    assert!(backend.convert(2, 1, 0, &[&BigUint::from(5u32)]).is_err());
+    assert!(backend.convert(1, 2, 0, &[&one]).is_err());
+
+    // The types must be declared in the header.
+    let mut relation = example_relation();
+    relation.gates = vec![
+        Gate::Constant(0, vec![1]),
+        Gate::Convert(1, wirelist![1], 0, wirelist![0]),
+    ];
+    let mut simulator = Evaluator::default();
+    let should_be_err = simulator.ingest_relation(&relation, &mut PlaintextBackend::default());
+    assert_eq!(should_be_err.err().unwrap().to_string(), "Unknown type 1");
+
+    Ok(())
+}
+
+#[test]
+fn test_evaluator_limits() -> crate::Result<()> {
+    use crate::consumers::evaluator::Evaluator;
+    use crate::consumers::limits::Limits;
+    use crate::producers::examples::*;
+    use crate::structs::iterators::{IterExprListElement::*, IterExprWireNumber::*};
+    use crate::structs::wire::WireListElement::*;
+
+    let instance = Message::Instance(example_instance());
+    let witness = Message::Witness(example_witness());
+
+    // A loop with too many iterations.
+    let mut relation = example_relation();
+    relation.gates.push(Gate::For(
+        "i".into(),
+        0,
+        1 << 40,
+        vec![WireRange(100, 100 + (1 << 40))],
+        ForLoopBody::IterExprAnonCall(
+            vec![Single(IterExprAdd(
+                Box::new(IterExprName("i".into())),
+                Box::new(IterExprConst(100)),
+            ))],
+            vec![],
+            0,
+            0,
+            vec![Gate::Constant(0, vec![1])],
+        ),
+    ));
+
+    let mut zkbackend = PlaintextBackend::default();
+    let mut evaluator = Evaluator::default();
+    evaluator.set_limits(Limits::conservative());
+    evaluator.ingest_message(&instance, &mut zkbackend);
+    evaluator.ingest_message(&witness, &mut zkbackend);
+    evaluator.ingest_message(&Message::Relation(relation), &mut zkbackend);
+    assert_eq!(
+        evaluator.get_violations(),
+        vec!["The loop over i has 1099511627777 iterations, more than the limit of 4294967296 (max_loop_iterations)."]
+    );
+
+    // Too many gates once loops are unrolled.
+    let mut zkbackend = PlaintextBackend::default();
+    let mut evaluator = Evaluator::default();
+    evaluator.set_limits(Limits {
+        max_unrolled_gates: 50,
+        ..Limits::default()
+    });
+    evaluator.ingest_message(&instance, &mut zkbackend);
+    evaluator.ingest_message(&witness, &mut zkbackend);
+    evaluator.ingest_message(&Message::Relation(example_relation()), &mut zkbackend);
+    assert_eq!(
+        evaluator.get_violations(),
+        vec!["More than 50 gates are processed once functions are inlined and loops unrolled (max_unrolled_gates)."]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_evaluator_recursive_functions() -> crate::Result<()> {
+    use crate::consumers::evaluator::Evaluator;
+    use crate::producers::examples::*;
+    use crate::structs::function::Function;
+    use crate::structs::wire::WireListElement;
+    use crate::wirelist;
+
+    let mut relation = example_relation();
+    relation.functions.push(Function::new(
+        "rec::f".to_string(),
+        1,
+        1,
+        0,
+        0,
+        vec![Gate::Call("rec::f".to_string(), wirelist![0], wirelist![1])],
+    ));
+
+    let mut zkbackend = PlaintextBackend::default();
+    let mut simulator = Evaluator::default();
+    simulator.ingest_instance(&example_instance())?;
+    simulator.ingest_witness(&example_witness())?;
+    let should_be_err = simulator.ingest_relation(&relation, &mut zkbackend);
+
+    assert_eq!(
+        "The function rec::f is recursive: rec::f -> rec::f.",
+        should_be_err.err().unwrap().to_string()
+    );
+
+    // A cycle closed by the functions of a later message.
+    let function = |name: &str, callee: &str| {
+        Function::new(
+            name.to_string(),
+            1,
+            1,
+            0,
+            0,
+            vec![Gate::Call(callee.to_string(), wirelist![0], wirelist![1])],
+        )
+    };
+    let mut first_relation = example_relation();
+    first_relation.functions = vec![function("rec::g", "rec::h")];
+    first_relation.gates = vec![];
+    let mut second_relation = first_relation.clone();
+    second_relation.functions = vec![function("rec::h", "rec::g")];
+
+    let mut zkbackend = PlaintextBackend::default();
+    let mut simulator = Evaluator::default();
+    simulator.ingest_relation(&first_relation, &mut zkbackend)?;
+    let should_be_err = simulator.ingest_relation(&second_relation, &mut zkbackend);
+    assert_eq!(
+        "The function rec::h is recursive: rec::h -> rec::g -> rec::h.",
+        should_be_err.err().unwrap().to_string()
+    );
+
+    Ok(())
+}
@@ -53,6 +53,7 @@ impl<S: Sink> ZKBackend for IRFlattener<S> {
                 version: IR_VERSION.parse().unwrap(),
                 field_characteristic: Value::from(modulus),
                 field_degree: degree,
+                types: vec![],
             };
             self.modulus = BigUint::from_bytes_le(modulus);
             self.b = Some(GateBuilder::new(
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use crate::structs::iterators::{
+    evaluate_iterexpr, evaluate_iterexpr_list, IterExprList, IterExprListElement, IterExprListView,
+};
+use crate::structs::wire::{expand_wirelist, WireList, WireListElement, WireListView};
+use crate::{Result, WireId};
+
+/// Limits on the resources used by the consumers. A small statement may describe a huge circuit,
+/// e.g. with nested loops over large ranges, or wire ranges of 2^64 wires. The consumers stop with
+/// an error instead of running out of memory or running forever.
+///
+/// By default, there are no limits. Untrusted statements should be consumed with
+/// `Limits::conservative()`, as the CLI does.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Limits {
+    /// The maximum number of gates processed, counting function bodies once per call and loop
+    /// bodies once per iteration.
+    pub max_unrolled_gates: u64,
+    /// The maximum number of iterations of a loop.
+    pub max_loop_iterations: u64,
+    /// The maximum nesting depth of calls, loops and switches.
+    pub max_depth: usize,
+    /// The maximum number of wires in a wire list or a range of wires, once expanded.
+    pub max_wire_list_size: u64,
+    /// The maximum number of wires alive at the same time in a scope, i.e. in the relation or in
+    /// the body of a call.
+    pub max_live_wires: u64,
+    /// The maximum length of a value, in bytes.
+    pub max_value_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::unlimited()
+    }
+}
+
+impl Limits {
+    /// Generous limits for untrusted statements. Wire lists are not limited, since ranges of wires
+    /// are not expanded, and the wires they allocate count as live wires.
+    pub fn conservative() -> Self {
+        Limits {
+            max_unrolled_gates: 1 << 32,
+            max_loop_iterations: 1 << 32,
+            max_depth: 256,
+            max_wire_list_size: u64::MAX,
+            max_live_wires: 1 << 28,
+            max_value_bytes: 1024,
+        }
+    }
+
+    /// No limits at all, for trusted statements. This is the default.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_unrolled_gates: u64::MAX,
+            max_loop_iterations: u64::MAX,
+            max_depth: usize::MAX,
+            max_wire_list_size: u64::MAX,
+            max_live_wires: u64::MAX,
+            max_value_bytes: usize::MAX,
+        }
+    }
+
+    pub fn check_loop(&self, iterator_name: &str, start: u64, end: u64) -> Result<()> {
+        let iterations = range_len(start, end);
+        if iterations > self.max_loop_iterations as u128 {
+            return Err(format!(
+                "The loop over {} has {} iterations, more than the limit of {} (max_loop_iterations).",
+                iterator_name, iterations, self.max_loop_iterations
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn check_depth(&self, depth: usize) -> Result<()> {
+        if depth > self.max_depth {
+            return Err(format!(
+                "Calls, loops and switches are nested more than {} times (max_depth).",
+                self.max_depth
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Check the number of wires from `first` to `last` included, e.g. in a Free gate.
+    pub fn check_wire_range(&self, first: WireId, last: WireId) -> Result<()> {
+        self.check_wire_list_size(range_len(first, last))
+    }
+
+    /// Check the size of a wire list, without expanding it.
+    pub fn check_wire_list(&self, wirelist: &WireList) -> Result<()> {
+        let size = wirelist
+            .iter()
+            .map(|element| match element {
+                WireListElement::Wire(_) => 1,
+                WireListElement::WireRange(first, last) => range_len(*first, *last),
+            })
+            .sum();
+        self.check_wire_list_size(size)
+    }
+
+    /// Check the size of an iterator expression list, without expanding it.
+    pub fn check_iterexpr_list(
+        &self,
+        list: &IterExprList,
+        known_iterators: &HashMap<String, u64>,
+    ) -> Result<()> {
+        let mut size = 0;
+        for element in list {
+            size += match element {
+                IterExprListElement::Single(_) => 1,
+                IterExprListElement::Range(first, last) => range_len(
+                    evaluate_iterexpr(first, known_iterators)?,
+                    evaluate_iterexpr(last, known_iterators)?,
+                ),
+            };
+        }
+        self.check_wire_list_size(size)
+    }
+
+    /// Expand a wire list if it is within the limits.
+    pub fn expand_wirelist(&self, wirelist: &WireList) -> Result<Vec<WireId>> {
+        self.check_wire_list(wirelist)?;
+        expand_wirelist(wirelist)
+    }
+
+    /// Evaluate an iterator expression list if it is within the limits.
+    pub fn evaluate_iterexpr_list(
+        &self,
+        list: &IterExprList,
+        known_iterators: &HashMap<String, u64>,
+    ) -> Result<Vec<WireId>> {
+        self.check_iterexpr_list(list, known_iterators)?;
+        Ok(evaluate_iterexpr_list(list, known_iterators))
+    }
+
+    /// A view of a wire list, to iterate over its wires without expanding it, if it is within the
+    /// limits.
+    pub fn wirelist_view<'a>(&self, wirelist: &'a WireList) -> Result<WireListView<'a>> {
+        self.check_wire_list(wirelist)?;
+        WireListView::new(wirelist)
+    }
+
+    /// A view of an iterator expression list, to evaluate its wires on demand, if it is within
+    /// the limits.
+    pub fn iterexpr_list_view<'a>(
+        &self,
+        list: &'a IterExprList,
+        known_iterators: &'a HashMap<String, u64>,
+    ) -> Result<IterExprListView<'a>> {
+        self.check_iterexpr_list(list, known_iterators)?;
+        IterExprListView::new(list, known_iterators)
+    }
+
+    pub fn check_live_wires(&self, live_wires: usize) -> Result<()> {
+        if live_wires as u64 > self.max_live_wires {
+            return Err(format!(
+                "More than {} wires are alive at the same time (max_live_wires).",
+                self.max_live_wires
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn check_value(&self, value: &[u8]) -> Result<()> {
+        if value.len() > self.max_value_bytes {
+            return Err(format!(
+                "A value has {} bytes, more than the limit of {} (max_value_bytes).",
+                value.len(),
+                self.max_value_bytes
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn check_wire_list_size(&self, size: u128) -> Result<()> {
+        if size > self.max_wire_list_size as u128 {
+            return Err(format!(
+                "A list of wires has {} wires, more than the limit of {} (max_wire_list_size).",
+                size, self.max_wire_list_size
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// The number of integers from `first` to `last` included.
+fn range_len(first: u64, last: u64) -> u128 {
+    if last < first {
+        0
+    } else {
+        (last - first) as u128 + 1
+    }
+}
+
+/// Budget counts the gates processed and the current nesting depth, against `Limits`.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    pub limits: Limits,
+    unrolled_gates: u64,
+    depth: usize,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        Budget {
+            limits,
+            ..Budget::default()
+        }
+    }
+
+    /// Count gates about to be processed.
+    pub fn spend_gates(&mut self, count: u64) -> Result<()> {
+        self.unrolled_gates = self.unrolled_gates.saturating_add(count);
+        if self.is_exhausted() {
+            return Err(format!(
+                "More than {} gates are processed once functions are inlined and loops unrolled (max_unrolled_gates).",
+                self.limits.max_unrolled_gates
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Whether more gates than allowed were processed.
+    pub fn is_exhausted(&self) -> bool {
+        self.unrolled_gates > self.limits.max_unrolled_gates
+    }
+
+    /// Enter the body of a call, loop or switch.
+    pub fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        self.limits.check_depth(self.depth)
+    }
+
+    /// Leave the body entered last.
+    pub fn exit(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// The number of bodies entered, 0 for the gates of a relation.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+}
+
+#[test]
+fn test_limits() {
+    use crate::structs::iterators::IterExprWireNumber::*;
+    use WireListElement::*;
+
+    let limits = Limits {
+        max_wire_list_size: 10,
+        max_loop_iterations: 10,
+        ..Limits::default()
+    };
+    assert!(limits
+        .check_wire_list(&vec![WireRange(0, 8), Wire(20)])
+        .is_ok());
+    assert!(limits
+        .check_wire_list(&vec![WireRange(0, 9), Wire(20)])
+        .is_err());
+    assert!(limits.check_wire_range(0, u64::MAX).is_err());
+    assert!(limits.check_loop("i", 1, 10).is_ok());
+    assert!(limits.check_loop("i", 0, 10).is_err());
+
+    let mut known_iterators = HashMap::new();
+    known_iterators.insert("i".to_string(), 5);
+    let list = vec![IterExprListElement::Range(
+        IterExprName("i".into()),
+        IterExprConst(1_000),
+    )];
+    assert!(limits.check_iterexpr_list(&list, &known_iterators).is_err());
+
+    let mut budget = Budget::new(Limits {
+        max_unrolled_gates: 2,
+        max_depth: 1,
+        ..Limits::default()
+    });
+    assert_eq!(Limits::default(), Limits::unlimited());
+    assert!(Budget::default().spend_gates(u64::MAX).is_ok());
+    assert!(Limits::conservative().check_wire_range(0, u64::MAX).is_ok());
+    assert!(Limits::conservative().check_loop("i", 0, 1 << 32).is_err());
+    assert!(budget.spend_gates(2).is_ok());
+    assert!(budget.spend_gates(1).is_err());
+    assert!(budget.is_exhausted());
+    assert!(budget.enter().is_ok());
+    assert!(budget.enter().is_err());
+    budget.exit();
+    budget.exit();
+    assert!(budget.enter().is_ok());
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Result;
+
+/// A constant value defined by more Constant gates than this in the same body is reported.
+pub const DUPLICATED_CONSTANT_THRESHOLD: usize = 3;
+
+/// Lints are style and efficiency checks of the Validator. Unlike violations, they do not make a
+/// statement invalid, unless their severity is raised to `Severity::Deny`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Lint {
+    /// A wire is computed but never used as an input.
+    UnusedWire,
+    /// The same constant value is defined by many Constant gates in the same body.
+    DuplicatedConstant,
+    /// A Copy gate copies a wire which is itself a copy.
+    CopyChain,
+    /// A function is defined but never called.
+    UncalledFunction,
+    /// A Free gate covers wires that are not allocated in the scope.
+    FreeUnallocated,
+    /// A switch has a single case.
+    SingleCaseSwitch,
+    /// A loop has zero or one iteration.
+    TrivialLoop,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 7] = [
+        Lint::UnusedWire,
+        Lint::DuplicatedConstant,
+        Lint::CopyChain,
+        Lint::UncalledFunction,
+        Lint::FreeUnallocated,
+        Lint::SingleCaseSwitch,
+        Lint::TrivialLoop,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedWire => "unused-wire",
+            Lint::DuplicatedConstant => "duplicated-constant",
+            Lint::CopyChain => "copy-chain",
+            Lint::UncalledFunction => "uncalled-function",
+            Lint::FreeUnallocated => "free-unallocated",
+            Lint::SingleCaseSwitch => "single-case-switch",
+            Lint::TrivialLoop => "trivial-loop",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Lint {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Lint::ALL
+            .iter()
+            .find(|lint| lint.name() == s)
+            .cloned()
+            .ok_or_else(|| format!("Unknown lint {}.", s).into())
+    }
+}
+
+/// What to do when a lint is triggered.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Severity {
+    /// Ignore it.
+    Allow,
+    /// Report it as a warning.
+    Warn,
+    /// Report it as a violation.
+    Deny,
+}
+
+/// The severity of each lint. By default, all lints are warnings.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LintLevels {
+    levels: HashMap<Lint, Severity>,
+}
+
+impl LintLevels {
+    pub fn get(&self, lint: Lint) -> Severity {
+        self.levels.get(&lint).cloned().unwrap_or(Severity::Warn)
+    }
+
+    pub fn set(&mut self, lint: Lint, severity: Severity) {
+        self.levels.insert(lint, severity);
+    }
+
+    pub fn set_all(&mut self, severity: Severity) {
+        for lint in Lint::ALL.iter() {
+            self.set(*lint, severity);
+        }
+    }
+
+    /// Set the severity of a lint given by name, or of all lints with the name "all".
+    pub fn set_by_name(&mut self, name: &str, severity: Severity) -> Result<()> {
+        if name == "all" {
+            self.set_all(severity);
+        } else {
+            self.set(name.parse()?, severity);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_lint_levels() -> Result<()> {
+    let mut levels = LintLevels::default();
+    assert_eq!(levels.get(Lint::CopyChain), Severity::Warn);
+
+    levels.set_by_name("all", Severity::Allow)?;
+    levels.set_by_name("unused-wire", Severity::Deny)?;
+    assert_eq!(levels.get(Lint::CopyChain), Severity::Allow);
+    assert_eq!(levels.get(Lint::UnusedWire), Severity::Deny);
+
+    assert!(levels.set_by_name("no-such-lint", Severity::Deny).is_err());
+    for lint in Lint::ALL.iter() {
+        assert_eq!(lint.name().parse::<Lint>()?, *lint);
+    }
+    Ok(())
+}
@@ -4,18 +4,42 @@ pub mod source;
 /// The Validator checks the format and semantics of a statement.
 pub mod validator;
 
+/// Lints are the style and efficiency checks of the Validator, with configurable severities.
+pub mod lints;
+
+/// Limits protect the consumers against statements that expand to huge circuits.
+pub mod limits;
+
+/// WireSet stores the live wires of the Validator as intervals.
+pub mod wire_set;
+
 /// The Evaluator determines whether a statement is true by evaluating the circuit using the short witness.
 pub mod evaluator;
 
+/// WireScope stores the wires of the Evaluator in pages indexed by wire id.
+pub mod wire_scope;
+
+/// The Unroller goes through a circuit as if functions were inlined and loops unrolled.
+pub mod unroller;
+
 /// Stats aggregates statistics about a circuit.
 pub mod stats;
 
+/// The ConstraintAnalyzer finds witnesses and wires that are not constrained by any assertion.
+pub mod constraint_analysis;
+
+/// The ConsumptionCounter computes how many instance and witness values the relations consume.
+pub mod consumption;
+
 /// Helper functions to read buffers.
 pub mod utils;
 
 // Flattening SIEVE IR.
 pub mod flattening;
 
+/// Repartitioner rebuilds the messages of a statement to a target size.
+pub mod repartition;
+
 // Expand definable gates in SIEVE IR.
 pub mod exp_definable;
 
@@ -23,3 +47,12 @@ pub const TEMPORARY_WIRES_START: u64 = 1u64 << 63;
 
 // ir to r1cs converter
 pub mod to_r1cs;
+
+// ir to circom .r1cs/.wtns writer
+pub mod to_circom;
+
+// ir to plonkish table converter
+pub mod to_plonk;
+
+/// Render relations as Graphviz graphs.
+pub mod to_dot;
@@ -0,0 +1,377 @@
+use crate::structs::call_graph::called_functions;
+use crate::structs::function::Function;
+use crate::structs::message::MessageView;
+use crate::structs::relation_view::RelationView;
+use crate::{Gate, Header, Instance, Message, Relation, Result, Sink, Value, Witness};
+use std::collections::BTreeSet;
+
+/// Repartitioner rebuilds the messages of a statement into messages of a target size: small
+/// consecutive messages with the same header, and the same type of values, are merged, and large
+/// messages are split.
+///
+/// The size of an Instance or Witness message is its number of values, and the size of a
+/// Relation message is its number of gates plus the number of gates in the bodies of its
+/// functions, as for `MessageBuilder::max_len`. Messages may go over the target size by one
+/// gate and the functions that it calls.
+///
+/// A function definition is held back until its first use, and then written in the same
+/// message as the gate, or the function, that first calls it. Functions that are never called
+/// are written at the end. The order of the gates, the values and the function definitions
+/// that they depend on is preserved, so that the statement validates and evaluates the same.
+///
+/// # Example
+/// ```
+/// use zki_sieve::consumers::repartition::Repartitioner;
+/// use zki_sieve::producers::examples::*;
+/// use zki_sieve::producers::sink::MemorySink;
+/// use zki_sieve::Source;
+///
+/// let mut repartitioner = Repartitioner::new(MemorySink::default(), 2);
+/// repartitioner.ingest_instance(&example_instance()).unwrap();
+/// repartitioner.ingest_witness(&example_witness()).unwrap();
+/// repartitioner.ingest_relation(&example_relation()).unwrap();
+/// let source: Source = repartitioner.finish().unwrap().into();
+///
+/// let messages = source.read_all_messages().unwrap();
+/// assert!(messages.relations.len() > 1);
+/// ```
+pub struct Repartitioner<S: Sink> {
+    sink: S,
+
+    /// The target number of gates or values per message.
+    pub max_len: usize,
+
+    instance: Option<Instance>,
+    instance_messages: usize,
+    witness: Option<Witness>,
+    witness_messages: usize,
+
+    relation: Option<Relation>,
+    relation_size: usize,
+    relation_messages: usize,
+    /// Functions that are not called yet, in order of definition.
+    pending_functions: Vec<Function>,
+}
+
+impl<S: Sink> Repartitioner<S> {
+    pub fn new(sink: S, max_len: usize) -> Self {
+        Repartitioner {
+            sink,
+            max_len: max_len.max(1),
+            instance: None,
+            instance_messages: 0,
+            witness: None,
+            witness_messages: 0,
+            relation: None,
+            relation_size: 0,
+            relation_messages: 0,
+            pending_functions: vec![],
+        }
+    }
+
+    pub fn ingest_message(&mut self, msg: &Message) -> Result<()> {
+        match msg {
+            Message::Instance(i) => self.ingest_instance(i),
+            Message::Witness(w) => self.ingest_witness(w),
+            Message::Relation(r) => self.ingest_relation(r),
+        }
+    }
+
+    /// Like `ingest_message`, with relations read in place. Only the gates of the current
+    /// message are held in memory, however large the relations are.
+    pub fn ingest_message_view(&mut self, msg: &MessageView) -> Result<()> {
+        match msg {
+            MessageView::Instance(i) => self.ingest_instance(i),
+            MessageView::Witness(w) => self.ingest_witness(w),
+            MessageView::Relation(r) => self.ingest_relation_view(r),
+        }
+    }
+
+    pub fn ingest_instance(&mut self, instance: &Instance) -> Result<()> {
+        let stream = |i: &Instance| (i.header.clone(), i.type_id);
+        if self.instance.as_ref().map(stream) != Some(stream(instance)) {
+            self.flush_instance()?;
+            self.instance = Some(Instance {
+                header: instance.header.clone(),
+                common_inputs: vec![],
+                type_id: instance.type_id,
+            });
+        }
+        for value in &instance.common_inputs {
+            self.push_instance_value(value)?;
+        }
+        Ok(())
+    }
+
+    pub fn ingest_witness(&mut self, witness: &Witness) -> Result<()> {
+        let stream = |w: &Witness| (w.header.clone(), w.type_id);
+        if self.witness.as_ref().map(stream) != Some(stream(witness)) {
+            self.flush_witness()?;
+            self.witness = Some(Witness {
+                header: witness.header.clone(),
+                short_witness: vec![],
+                type_id: witness.type_id,
+            });
+        }
+        for value in &witness.short_witness {
+            self.push_witness_value(value)?;
+        }
+        Ok(())
+    }
+
+    pub fn ingest_relation(&mut self, relation: &Relation) -> Result<()> {
+        self.ingest_relation_header(&relation.header, relation.gate_mask, relation.feat_mask)?;
+        for function in &relation.functions {
+            self.pending_functions.push(function.clone());
+        }
+        for gate in &relation.gates {
+            self.push_gate(gate.clone())?;
+        }
+        Ok(())
+    }
+
+    pub fn ingest_relation_view(&mut self, relation: &RelationView) -> Result<()> {
+        self.ingest_relation_header(
+            &relation.header()?,
+            relation.gate_mask()?,
+            relation.feat_mask()?,
+        )?;
+        for function in relation.functions() {
+            self.pending_functions.push(function.to_function()?);
+        }
+        for gate in relation.gates()? {
+            self.push_gate(gate?)?;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining messages, and return the sink.
+    pub fn finish(mut self) -> Result<S> {
+        self.flush_instance()?;
+        self.flush_witness()?;
+        self.flush_pending_functions()?;
+        Ok(self.sink)
+    }
+
+    fn push_instance_value(&mut self, value: &Value) -> Result<()> {
+        let instance = self.instance.as_mut().unwrap();
+        instance.common_inputs.push(value.clone());
+        if instance.common_inputs.len() >= self.max_len {
+            self.sink.push_instance_message(instance)?;
+            instance.common_inputs.clear();
+            self.instance_messages += 1;
+        }
+        Ok(())
+    }
+
+    fn push_witness_value(&mut self, value: &Value) -> Result<()> {
+        let witness = self.witness.as_mut().unwrap();
+        witness.short_witness.push(value.clone());
+        if witness.short_witness.len() >= self.max_len {
+            self.sink.push_witness_message(witness)?;
+            witness.short_witness.clear();
+            self.witness_messages += 1;
+        }
+        Ok(())
+    }
+
+    /// Write the current instance values, or an empty message if no message was written under
+    /// this header, so that no header is lost.
+    fn flush_instance(&mut self) -> Result<()> {
+        if let Some(instance) = self.instance.take() {
+            if !instance.common_inputs.is_empty() || self.instance_messages == 0 {
+                self.sink.push_instance_message(&instance)?;
+            }
+        }
+        self.instance_messages = 0;
+        Ok(())
+    }
+
+    fn flush_witness(&mut self) -> Result<()> {
+        if let Some(witness) = self.witness.take() {
+            if !witness.short_witness.is_empty() || self.witness_messages == 0 {
+                self.sink.push_witness_message(&witness)?;
+            }
+        }
+        self.witness_messages = 0;
+        Ok(())
+    }
+
+    fn ingest_relation_header(
+        &mut self,
+        header: &Header,
+        gate_mask: u16,
+        feat_mask: u16,
+    ) -> Result<()> {
+        let compatible = self.relation.as_ref().map_or(false, |r| {
+            r.header == *header && r.gate_mask == gate_mask && r.feat_mask == feat_mask
+        });
+        if !compatible {
+            // Functions are written under the header that they were defined with.
+            self.flush_pending_functions()?;
+            self.relation = Some(Relation {
+                header: header.clone(),
+                gate_mask,
+                feat_mask,
+                functions: vec![],
+                gates: vec![],
+            });
+        }
+        Ok(())
+    }
+
+    fn push_gate(&mut self, gate: Gate) -> Result<()> {
+        let called = called_functions(std::slice::from_ref(&gate));
+        self.define_called_functions(called);
+
+        let relation = self.relation.as_mut().unwrap();
+        relation.gates.push(gate);
+        self.relation_size += 1;
+        if self.relation_size >= self.max_len {
+            self.flush_relation()?;
+        }
+        Ok(())
+    }
+
+    /// Move the pending functions called by a gate into the current message, with the pending
+    /// functions that they call, in order of definition.
+    fn define_called_functions(&mut self, mut called: BTreeSet<String>) {
+        if self.pending_functions.is_empty() {
+            return;
+        }
+        // Functions are defined after the functions that they call.
+        for function in self.pending_functions.iter().rev() {
+            if called.contains(&function.name) {
+                called.extend(called_functions(&function.body));
+            }
+        }
+        let (defined, pending) = self
+            .pending_functions
+            .drain(..)
+            .partition::<Vec<_>, _>(|function| called.contains(&function.name));
+        self.pending_functions = pending;
+
+        let relation = self.relation.as_mut().unwrap();
+        for function in defined {
+            self.relation_size += function.body.len();
+            relation.functions.push(function);
+        }
+    }
+
+    fn flush_relation(&mut self) -> Result<()> {
+        if let Some(relation) = self.relation.as_mut() {
+            if !relation.gates.is_empty() || !relation.functions.is_empty() {
+                self.sink.push_relation_message(relation)?;
+                relation.gates.clear();
+                relation.functions.clear();
+                self.relation_size = 0;
+                self.relation_messages += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the functions that were never called and the current relation, or an empty message
+    /// if no message was written under its header.
+    fn flush_pending_functions(&mut self) -> Result<()> {
+        if let Some(relation) = self.relation.as_mut() {
+            relation.functions.extend(self.pending_functions.drain(..));
+        }
+        match &self.relation {
+            Some(relation) if self.relation_messages == 0 => {
+                self.sink.push_relation_message(relation)?
+            }
+            _ => self.flush_relation()?,
+        }
+        self.relation = None;
+        self.relation_size = 0;
+        self.relation_messages = 0;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_repartition() -> Result<()> {
+    use crate::consumers::evaluator::{Evaluator, PlaintextBackend};
+    use crate::consumers::validator::Validator;
+    use crate::producers::examples::*;
+    use crate::producers::sink::MemorySink;
+    use crate::{Messages, Source};
+
+    let all_messages = |messages: &Messages| {
+        let instances = messages.instances.iter().cloned().map(Message::Instance);
+        let witnesses = messages.witnesses.iter().cloned().map(Message::Witness);
+        let relations = messages.relations.iter().cloned().map(Message::Relation);
+        instances
+            .chain(witnesses)
+            .chain(relations)
+            .collect::<Vec<_>>()
+    };
+    let check = |messages: &Messages| {
+        let mut validator = Validator::new_as_prover();
+        let mut evaluator = Evaluator::default();
+        let mut backend = PlaintextBackend::default();
+        for msg in all_messages(messages) {
+            validator.ingest_message(&msg);
+            evaluator.ingest_message(&msg, &mut backend);
+        }
+        (validator.get_violations(), evaluator.get_violations())
+    };
+
+    let instance = example_instance();
+    let witness = example_witness();
+    let relation = example_relation();
+    let mut original = Messages::default();
+    original.push_message(&Message::Instance(instance.clone()));
+    original.push_message(&Message::Witness(witness.clone()));
+    original.push_message(&Message::Relation(relation.clone()));
+    let expected = check(&original);
+    assert_eq!(expected, (vec![], vec![]));
+
+    // Split into small messages.
+    let mut repartitioner = Repartitioner::new(MemorySink::default(), 1);
+    for msg in all_messages(&original) {
+        repartitioner.ingest_message(&msg)?;
+    }
+    let source: Source = repartitioner.finish()?.into();
+    let split = source.read_all_messages()?;
+    assert_eq!(split.instances.len(), instance.common_inputs.len());
+    assert_eq!(split.witnesses.len(), witness.short_witness.len());
+    assert!(split.relations.len() > 1);
+    assert_eq!(check(&split), expected);
+
+    // Each function is defined in the message of its first use.
+    for relation in &split.relations {
+        let mut called = called_functions(&relation.gates);
+        for function in &relation.functions {
+            called.extend(called_functions(&function.body));
+        }
+        for function in &relation.functions {
+            assert!(called.contains(&function.name));
+        }
+    }
+    let gates = split
+        .relations
+        .iter()
+        .flat_map(|r| r.gates.iter().cloned())
+        .collect::<Vec<_>>();
+    assert_eq!(gates, relation.gates);
+
+    // Merge them back.
+    let mut repartitioner = Repartitioner::new(MemorySink::default(), 1000);
+    for msg in all_messages(&split) {
+        repartitioner.ingest_message(&msg)?;
+    }
+    let source: Source = repartitioner.finish()?.into();
+    assert_eq!(source.read_all_messages()?, original);
+
+    // Headers that differ are not merged.
+    let mut other = relation.clone();
+    other.feat_mask = 0;
+    let mut repartitioner = Repartitioner::new(MemorySink::default(), 1000);
+    repartitioner.ingest_relation(&relation)?;
+    repartitioner.ingest_relation(&other)?;
+    let source: Source = repartitioner.finish()?.into();
+    assert_eq!(source.read_all_messages()?.relations, vec![relation, other]);
+    Ok(())
+}
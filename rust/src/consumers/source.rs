@@ -1,9 +1,11 @@
-use crate::consumers::utils::read_buffer;
+use crate::consumers::utils::{read_buffer, split_messages};
+use crate::structs::message::MessageView;
 use crate::{Message, Messages, Result, FILE_EXTENSION};
+use flate2::read::MultiGzDecoder;
+use memmap2::Mmap;
 use std::convert::TryFrom;
-use std::ffi::OsStr;
 use std::fs::{read_dir, File};
-use std::io::{stdin, Read};
+use std::io::{stdin, BufReader, Read};
 use std::iter;
 use std::path::{Path, PathBuf};
 
@@ -11,6 +13,7 @@ use std::path::{Path, PathBuf};
 /// It supports reading messages one-by-one from large files or from many files.
 /// It supports reading from stdin using dash (-) as a special filename.
 /// It supports reading from given buffers.
+/// It supports reading files compressed with gzip (.sieve.gz) or Zstandard (.sieve.zst).
 ///
 /// # Example
 /// ```
@@ -115,6 +118,147 @@ impl Source {
         }
         Ok(messages)
     }
+
+    /// Whether `map_files` can be used, i.e. whether this source does not read stdin nor
+    /// compressed files.
+    pub fn can_map_files(&self) -> bool {
+        match &self.buffer_source {
+            BufferSource::Stdin => false,
+            BufferSource::Files(paths) => paths
+                .iter()
+                .all(|path| Compression::from_path(path) == Some(Compression::None)),
+            BufferSource::Memory(_) => true,
+        }
+    }
+
+    /// Map the files of this source into memory, to read their messages without copying them.
+    /// Fails when reading from stdin or from compressed files, which cannot be mapped.
+    pub fn map_files(&self) -> Result<MappedSource> {
+        let buffers = match &self.buffer_source {
+            BufferSource::Stdin => return Err("Cannot map stdin into memory.".into()),
+            BufferSource::Files(paths) => {
+                let mut buffers = vec![];
+                for path in paths {
+                    buffers.push((Some(path.clone()), MappedBuffer::map_file(path)?));
+                }
+                buffers
+            }
+            BufferSource::Memory(buffers) => buffers
+                .iter()
+                .map(|buffer| (None, MappedBuffer::Memory(buffer.clone())))
+                .collect(),
+        };
+        Ok(MappedSource {
+            print_filenames: self.print_filenames,
+            buffers,
+        })
+    }
+}
+
+/// MappedSource reads IR messages from memory-mapped files. Messages are handed out as slices of
+/// the files, so that they can be decoded in place, e.g. with `MessageView`, without reading the
+/// files into buffers first. See `Source::map_files`.
+///
+/// The files must not be modified while they are mapped.
+///
+/// # Example
+/// ```
+/// use zki_sieve::{Source, FilesSink, Sink};
+/// use zki_sieve::producers::examples::*;
+/// use zki_sieve::structs::message::MessageView;
+/// use std::path::PathBuf;
+///
+/// let dir = PathBuf::from("local/test_mapped_source");
+/// let mut sink = FilesSink::new_clean(&dir).unwrap();
+/// sink.push_instance_message(&example_instance());
+/// sink.push_relation_message(&example_relation());
+///
+/// let mapped = Source::from_directory(&dir).unwrap().map_files().unwrap();
+/// let mut got = vec![];
+/// for msg in mapped.iter_message_views() {
+///     match msg.unwrap() {
+///         MessageView::Instance(_) => got.push("INSTANCE"),
+///         MessageView::Witness(_) => got.push("WITNESS"),
+///         MessageView::Relation(_) => got.push("RELATION"),
+///     }
+/// }
+/// assert_eq!(got, vec!["INSTANCE", "RELATION"]);
+/// ```
+#[derive(Debug)]
+pub struct MappedSource {
+    /// Set to true to print the paths of files as they are read.
+    pub print_filenames: bool,
+
+    buffers: Vec<(Option<PathBuf>, MappedBuffer)>,
+}
+
+#[derive(Debug)]
+enum MappedBuffer {
+    File(Mmap),
+    // Empty files cannot be mapped on all platforms.
+    Empty,
+    Memory(Vec<u8>),
+}
+
+impl MappedBuffer {
+    fn map_file(path: &Path) -> Result<Self> {
+        if Compression::from_path(path) != Some(Compression::None) {
+            return Err(
+                format!("Cannot map compressed file {} into memory.", path.display()).into(),
+            );
+        }
+        let file = File::open(path)
+            .map_err(|err| format!("Failed to open file {}: {}", path.display(), err))?;
+        if file.metadata()?.len() == 0 {
+            return Ok(MappedBuffer::Empty);
+        }
+        // Safety: the map is only read, and the file is expected to stay unchanged while the
+        // statement is processed, as for any reader.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedBuffer::File(map))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            MappedBuffer::File(map) => &map[..],
+            MappedBuffer::Empty => &[],
+            MappedBuffer::Memory(buffer) => &buffer[..],
+        }
+    }
+}
+
+impl MappedSource {
+    /// The size-prefixed messages of all the files, in order. A truncated file gives an error
+    /// instead of its messages.
+    pub fn iter_buffers(&self) -> impl Iterator<Item = Result<&[u8]>> + '_ {
+        let print = self.print_filenames;
+        self.buffers.iter().flat_map(move |(path, buffer)| {
+            match path {
+                Some(path) if print => eprintln!("Reading {}", path.display()),
+                _ => {}
+            }
+            let messages: Vec<Result<&[u8]>> = match split_messages(buffer.bytes()) {
+                Ok(messages) => messages.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(match path {
+                    Some(path) => format!("{}: {}", path.display(), err).into(),
+                    None => err,
+                })],
+            };
+            messages
+        })
+    }
+
+    /// The messages, with relations read in place.
+    pub fn iter_message_views(&self) -> impl Iterator<Item = Result<MessageView<'_>>> + '_ {
+        self.iter_buffers()
+            .map(|buffer| buffer.and_then(MessageView::try_from))
+    }
+
+    /// The messages, decoded into owned structures.
+    pub fn iter_messages(&self) -> impl Iterator<Item = Result<Message>> + '_ {
+        self.iter_buffers()
+            .map(|buffer| buffer.and_then(Message::try_from))
+    }
 }
 
 pub fn iterate_files<'w>(paths: &'w [PathBuf], print: bool) -> impl Iterator<Item = Vec<u8>> + 'w {
@@ -127,7 +271,7 @@ pub fn iterate_files<'w>(paths: &'w [PathBuf], print: bool) -> impl Iterator<Ite
 }
 
 pub fn iterate_file(path: &Path) -> Box<dyn Iterator<Item = Vec<u8>>> {
-    match File::open(path) {
+    match open_file(path) {
         Err(err) => {
             eprintln!("Warning: failed to open file {}: {}", path.display(), err);
             Box::new(iter::empty())
@@ -136,6 +280,14 @@ pub fn iterate_file(path: &Path) -> Box<dyn Iterator<Item = Vec<u8>>> {
     }
 }
 
+/// Open a file of messages, decompressing it according to its extension.
+pub fn open_file(path: &Path) -> Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    Compression::from_path(path)
+        .unwrap_or(Compression::None)
+        .decompress(file)
+}
+
 pub fn iterate_buffers<'w>(buffers: &'w [Vec<u8>]) -> impl Iterator<Item = Vec<u8>> + 'w {
     buffers
         .iter()
@@ -158,8 +310,78 @@ pub fn iterate_stream<'s>(mut stream: impl Read + 's) -> impl Iterator<Item = Ve
     })
 }
 
+/// Read messages written as JSON Lines, e.g. by `zki_sieve to-json --lines`, one at a time.
+/// Any whitespace may separate the messages.
+pub fn iterate_json_lines<'r>(
+    reader: impl Read + 'r,
+) -> impl Iterator<Item = Result<Message>> + 'r {
+    serde_json::Deserializer::from_reader(reader)
+        .into_iter::<Message>()
+        .map(|msg| msg.map_err(|err| err.into()))
+}
+
+/// The compression of files of messages, given by their extension: `.sieve` files are not
+/// compressed, `.sieve.gz` files are compressed with gzip and `.sieve.zst` files with Zstandard.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub const ALL: [Compression; 3] = [Compression::None, Compression::Gzip, Compression::Zstd];
+
+    /// The compression of a file of messages, or None if it does not have a known extension.
+    pub fn from_path(path: &Path) -> Option<Compression> {
+        let name = path.file_name()?.to_str()?;
+        Compression::ALL
+            .iter()
+            .find(|compression| {
+                name.strip_suffix(&compression.extension())
+                    .map_or(false, |stem| stem.ends_with('.'))
+            })
+            .copied()
+    }
+
+    /// The extension of files with this compression, without the leading dot.
+    pub fn extension(&self) -> String {
+        match self {
+            Compression::None => FILE_EXTENSION.to_string(),
+            Compression::Gzip => format!("{}.gz", FILE_EXTENSION),
+            Compression::Zstd => format!("{}.zst", FILE_EXTENSION),
+        }
+    }
+
+    /// Wrap a compressed stream into a stream of its decompressed content.
+    pub fn decompress<'r>(&self, stream: impl Read + 'r) -> Result<Box<dyn Read + 'r>> {
+        Ok(match self {
+            Compression::None => Box::new(BufReader::new(stream)),
+            // Accept concatenated gzip members, as produced by appending to a file.
+            Compression::Gzip => Box::new(MultiGzDecoder::new(stream)),
+            Compression::Zstd => Box::new(zstd::Decoder::new(stream)?),
+        })
+    }
+}
+
+impl std::str::FromStr for Compression {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, String> {
+        match name {
+            "none" => Ok(Compression::None),
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(format!(
+                "Unknown compression {} (expected none, gzip or zstd)",
+                name
+            )),
+        }
+    }
+}
+
 pub fn has_sieve_extension(path: &Path) -> bool {
-    path.extension() == Some(OsStr::new(FILE_EXTENSION))
+    Compression::from_path(path).is_some()
 }
 
 pub fn list_workspace_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
@@ -191,3 +413,98 @@ pub fn list_workspace_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
     }
     Ok(all_paths)
 }
+
+#[test]
+fn test_mapped_source() -> Result<()> {
+    use crate::producers::examples::*;
+    use crate::{FilesSink, Sink};
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    let dir = PathBuf::from("local/test_mapped_source_truncated");
+    let mut sink = FilesSink::new_clean(&dir)?;
+    sink.push_instance_message(&example_instance())?;
+    sink.push_witness_message(&example_witness())?;
+    sink.push_relation_message(&example_relation())?;
+
+    let source = Source::from_directory(&dir)?;
+    let mapped = source.map_files()?;
+    let expected = source.read_all_messages()?;
+    let mut messages = Messages::default();
+    for msg in mapped.iter_messages() {
+        messages.push_message(&msg?);
+    }
+    assert_eq!(messages, expected);
+    assert_eq!(mapped.iter_message_views().count(), 3);
+    drop(mapped);
+
+    // A truncated message at the end of a file is an error.
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(FilesSink::witness_path(&dir))?;
+    file.write_all(&[100, 0, 0, 0, 1, 2])?;
+    drop(file);
+    let mapped = source.map_files()?;
+    let buffers: Vec<Result<&[u8]>> = mapped.iter_buffers().collect();
+    assert_eq!(buffers.len(), 3);
+    assert!(buffers[0].is_ok());
+    assert!(buffers[1]
+        .as_ref()
+        .unwrap_err()
+        .to_string()
+        .ends_with("The last message is truncated to 6 of its 104 bytes."));
+    assert!(mapped.iter_messages().any(|msg| msg.is_err()));
+    drop(mapped);
+
+    // So is a truncated size.
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(FilesSink::relation_path(&dir))?;
+    file.write_all(&[100, 0])?;
+    drop(file);
+    assert!(split_messages(&std::fs::read(FilesSink::relation_path(&dir))?).is_err());
+
+    assert!(Source::from_filenames(vec![PathBuf::from("-")])
+        .map_files()
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_compression_from_path() {
+    let from_path = |name: &str| Compression::from_path(Path::new(name));
+    assert_eq!(from_path("dir/000_instance.sieve"), Some(Compression::None));
+    assert_eq!(from_path("002_relation.sieve.gz"), Some(Compression::Gzip));
+    assert_eq!(from_path("002_relation.sieve.zst"), Some(Compression::Zstd));
+    assert_eq!(from_path("002_relation.gz"), None);
+    assert_eq!(from_path("notsieve"), None);
+    assert_eq!(from_path("dir.sieve/file"), None);
+    assert!(has_sieve_extension(Path::new("001_witness.sieve.zst")));
+    assert_eq!("zstd".parse(), Ok(Compression::Zstd));
+    assert!("lz4".parse::<Compression>().is_err());
+}
+
+#[test]
+fn test_json_lines() -> Result<()> {
+    use crate::producers::examples::*;
+
+    let messages = vec![
+        Message::Instance(example_instance()),
+        Message::Witness(example_witness()),
+        Message::Relation(example_relation()),
+    ];
+    let mut buffer = vec![];
+    for msg in &messages {
+        msg.write_json_line(&mut buffer)?;
+    }
+    assert_eq!(buffer.iter().filter(|&&b| b == b'\n').count(), 3);
+
+    let read = iterate_json_lines(&buffer[..]).collect::<Result<Vec<_>>>()?;
+    assert_eq!(read, messages);
+
+    let truncated = &buffer[..buffer.len() - 10];
+    let read = iterate_json_lines(truncated).collect::<Vec<_>>();
+    assert_eq!(read.len(), 3);
+    assert!(read[2].is_err());
+    Ok(())
+}
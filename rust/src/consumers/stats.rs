@@ -1,12 +1,23 @@
 extern crate serde;
 extern crate serde_json;
 
-use std::collections::HashMap;
+use std::cmp::max;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::rc::Rc;
 
+use num_bigint::BigUint;
+use num_traits::One;
 use serde::{Deserialize, Serialize};
 
-use crate::structs::function::{CaseInvoke, ForLoopBody};
-use crate::{Gate, Header, Instance, Message, Relation, Result, Witness};
+use crate::consumers::limits::Limits;
+use crate::consumers::unroller::{self, Body, GateVisitor, Unroller};
+use crate::structs::function::{CaseInvoke, ForLoopBody, Function};
+use crate::structs::message::MessageView;
+use crate::structs::relation::{contains_feature, BOOL};
+use crate::structs::relation_view::RelationView;
+use crate::structs::wire::{WireListView, WireRanges};
+use crate::{Gate, Header, Instance, Message, Relation, Result, WireId, Witness};
 
 #[derive(Clone, Default, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct GateStats {
@@ -34,25 +45,130 @@ pub struct GateStats {
 
     pub for_loops: usize,
 
+    // Gates of a type other than 0, and conversions between types (IR version 2.0.0).
+    #[serde(default)]
+    pub typed_gates: usize,
+    #[serde(default)]
+    pub convert_gates: usize,
+
     // The number of messages into which the statement was split.
     pub instance_messages: usize,
     pub witness_messages: usize,
     pub relation_messages: usize,
 }
 
-#[derive(Default, Debug, Eq, PartialEq, Deserialize, Serialize)]
+/// WireStats describes the dataflow of the circuit, as if functions were inlined, loops unrolled,
+/// and switches multiplexed.
+#[derive(Clone, Default, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct WireStats {
+    // The largest number of multiplications (or AND gates) on a path from the inputs to a wire.
+    // Multiplications by a constant are not counted.
+    pub multiplicative_depth: usize,
+    // The largest number of gates on a path from the inputs to a wire. Copy gates are not counted.
+    pub depth: usize,
+    // The largest number of times a wire is used as a gate input.
+    pub max_fan_out: usize,
+    // The largest number of wires allocated and not yet freed at any point of the circuit.
+    pub peak_live_wires: usize,
+}
+
+/// EffectiveStats counts the gates that a prover actually executes: function bodies are counted
+/// once per call, loop bodies once per iteration, and switches include all their branches plus the
+/// gates that the Evaluator adds to multiplex them (branch weights, weighted assertions, and the
+/// selection of outputs). The copies made by the Evaluator are not counted.
+#[derive(Clone, Default, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct EffectiveStats {
+    // The total of the relation, including the multiplexing overhead.
+    pub gate_stats: GateStats,
+    // The part of the total added to multiplex switches.
+    pub multiplexing: GateStats,
+    // Function name => profile
+    pub functions: BTreeMap<String, FunctionProfile>,
+}
+
+/// FunctionProfile is the share of a function in the effective cost, like in a profiler.
+#[derive(Clone, Default, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub struct FunctionProfile {
+    pub calls: usize,
+    // The gates of the body itself, over all calls.
+    pub self_stats: GateStats,
+    // The gates of the body and of the functions it calls, over all calls.
+    pub total_stats: GateStats,
+}
+
+#[derive(Default, Debug, Deserialize, Serialize)]
 pub struct Stats {
     // Header.
     pub field_characteristic: Vec<u8>,
     pub field_degree: u32,
+    // The name of the field, if it is a well-known one, see `FieldPreset`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_name: Option<String>,
+    // The fields of the types after the type 0, see `Header::types`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<Vec<u8>>,
 
     pub gate_stats: GateStats,
 
     // Function definitions => stats / instance_count / witness_count
     pub functions: HashMap<String, (GateStats, usize, usize)>,
+
+    pub wire_stats: WireStats,
+
+    // The cost after inlining and unrolling, only computed by `Stats::effective()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective: Option<EffectiveStats>,
+
+    // The unrolled dataflow and the state of the wires, kept between messages.
+    #[serde(skip)]
+    pub(crate) unroller: Unroller<(usize, bool)>,
+    #[serde(skip)]
+    pub(crate) wire_tracker: WireTracker,
+
+    // The first limit exceeded, after which messages are ignored.
+    #[serde(skip)]
+    found_error: Option<String>,
 }
 
+// The unroller and the wire tracker are an intermediate state and are not compared.
+impl PartialEq for Stats {
+    fn eq(&self, other: &Self) -> bool {
+        self.field_characteristic == other.field_characteristic
+            && self.field_degree == other.field_degree
+            && self.field_name == other.field_name
+            && self.types == other.types
+            && self.gate_stats == other.gate_stats
+            && self.functions == other.functions
+            && self.wire_stats == other.wire_stats
+            && self.effective == other.effective
+    }
+}
+
+impl Eq for Stats {}
+
 impl Stats {
+    /// Create a Stats that also computes `EffectiveStats`.
+    pub fn effective() -> Self {
+        Stats {
+            effective: Some(EffectiveStats::default()),
+            ..Stats::default()
+        }
+    }
+
+    /// Set the limits on the resources used to go through the circuit.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.unroller.set_limits(limits);
+    }
+
+    /// Return an error if a limit was exceeded or a function is recursive, in which case the
+    /// statistics are incomplete.
+    pub fn check_limits(&self) -> Result<()> {
+        match &self.found_error {
+            Some(err) => Err(err.clone().into()),
+            None => Ok(()),
+        }
+    }
+
     pub fn from_messages(messages: impl Iterator<Item = Result<Message>>) -> Self {
         let mut stats = Stats::default();
         messages.for_each(|msg| stats.ingest_message(&msg.unwrap()));
@@ -67,56 +183,153 @@ impl Stats {
         }
     }
 
+    /// Like `ingest_message`, with the relation read in place.
+    pub fn ingest_message_view(&mut self, msg: &MessageView) {
+        match msg {
+            MessageView::Instance(i) => self.ingest_instance(&i),
+            MessageView::Witness(w) => self.ingest_witness(&w),
+            MessageView::Relation(r) => self.ingest_relation_view(&r),
+        }
+    }
+
     pub fn ingest_instance(&mut self, instance: &Instance) {
         self.ingest_header(&instance.header);
         self.gate_stats.instance_messages += 1;
+        self.check_values(&instance.common_inputs);
     }
 
     pub fn ingest_witness(&mut self, witness: &Witness) {
         self.ingest_header(&witness.header);
         self.gate_stats.witness_messages += 1;
+        self.check_values(&witness.short_witness);
     }
 
     pub fn ingest_relation(&mut self, relation: &Relation) {
+        if self.found_error.is_some() {
+            return;
+        }
+        if let Err(err) = self.ingest_relation_(relation) {
+            self.found_error = Some(err.to_string());
+        }
+    }
+
+    fn ingest_relation_(&mut self, relation: &Relation) -> Result<()> {
         self.ingest_header(&relation.header);
         self.gate_stats.relation_messages += 1;
 
         for f in relation.functions.iter() {
-            let (name, _, _, instance_count, witness_count, subcircuit) = (
-                f.name.clone(),
-                f.output_count,
-                f.input_count,
-                f.instance_count,
-                f.witness_count,
-                f.body.clone(),
-            );
-            // Just record the signature.
-            self.gate_stats.functions_defined += 1;
-            let func_stats = ingest_subcircuit(&subcircuit, &self.functions);
-            self.functions
-                .insert(name.clone(), (func_stats, instance_count, witness_count));
+            self.define_function(f.clone())?;
         }
+        self.unroller
+            .check_recursion(relation.functions.iter().map(|f| &f.name))?;
 
+        let is_boolean = contains_feature(relation.gate_mask, BOOL);
         for gate in &relation.gates {
-            self.gate_stats.ingest_gate(gate, &self.functions);
+            self.ingest_relation_gate(gate, is_boolean)?;
         }
+        Ok(())
+    }
+
+    /// Like `ingest_relation`, reading the relation in place. Each gate is decoded, counted and
+    /// dropped in turn.
+    pub fn ingest_relation_view(&mut self, relation: &RelationView) {
+        if self.found_error.is_some() {
+            return;
+        }
+        if let Err(err) = self.ingest_relation_view_(relation) {
+            self.found_error = Some(err.to_string());
+        }
+    }
+
+    fn ingest_relation_view_(&mut self, relation: &RelationView) -> Result<()> {
+        self.ingest_header(&relation.header()?);
+        self.gate_stats.relation_messages += 1;
+
+        let mut names = vec![];
+        for f in relation.functions() {
+            let f = f.to_function()?;
+            names.push(f.name.clone());
+            self.define_function(f)?;
+        }
+        self.unroller.check_recursion(&names)?;
+
+        let is_boolean = contains_feature(relation.gate_mask()?, BOOL);
+        for gate in relation.gates()? {
+            self.ingest_relation_gate(&gate?, is_boolean)?;
+        }
+        Ok(())
+    }
+
+    fn define_function(&mut self, f: Function) -> Result<()> {
+        // Just record the signature.
+        self.gate_stats.functions_defined += 1;
+        let func_stats = ingest_subcircuit(&f.body, &self.functions)?;
+        self.functions.insert(
+            f.name.clone(),
+            (func_stats, f.instance_count, f.witness_count),
+        );
+        self.unroller
+            .define_function(f.name, f.output_count, f.body);
+        Ok(())
+    }
+
+    /// Count a gate of a relation, once its functions are defined.
+    fn ingest_relation_gate(&mut self, gate: &Gate, is_boolean: bool) -> Result<()> {
+        // The wire tracker goes through the unrolled gate first, to enforce the limits.
+        self.unroller.ingest_relation_gates(
+            &mut self.wire_tracker,
+            std::slice::from_ref(gate),
+            0,
+            &(),
+        );
+        self.wire_stats = self.wire_tracker.stats.clone();
+        if let Some(err) = self.unroller.error.take() {
+            return Err(err.into());
+        }
+
+        self.gate_stats.ingest_gate(gate, &self.functions)?;
+
+        if let Some(effective) = &mut self.effective {
+            let mut counter = EffectiveCounter {
+                functions: &self.unroller.functions,
+                is_boolean,
+                exponent_muls: exponent_muls(&self.field_characteristic),
+                profile: &mut effective.functions,
+                multiplexing: &mut effective.multiplexing,
+            };
+            let (_, total) = counter.count_gates(std::slice::from_ref(gate), false, 1)?;
+            effective.gate_stats.add_scaled(&total, 1)?;
+        }
+        Ok(())
     }
 
     fn ingest_header(&mut self, header: &Header) {
         self.field_characteristic = header.field_characteristic.clone();
         self.field_degree = header.field_degree;
+        self.field_name = header.field_preset().map(|preset| preset.name.to_string());
+        self.types = header.types.clone();
+    }
+
+    fn check_values(&mut self, values: &[Vec<u8>]) {
+        let limits = self.unroller.budget.limits;
+        if let Some(err) = values
+            .iter()
+            .find_map(|value| limits.check_value(value).err())
+        {
+            self.found_error.get_or_insert(err.to_string());
+        }
     }
 }
 
-fn ingest_subcircuit(
+pub(crate) fn ingest_subcircuit(
     subcircuit: &[Gate],
     known_functions: &HashMap<String, (GateStats, usize, usize)>,
-) -> GateStats {
+) -> Result<GateStats> {
     let mut local_stats = GateStats::default();
     for gate in subcircuit {
-        local_stats.ingest_gate(gate, known_functions);
+        local_stats.ingest_gate(gate, known_functions)?;
     }
-    local_stats
+    Ok(local_stats)
 }
 
 impl GateStats {
@@ -124,7 +337,7 @@ impl GateStats {
         &mut self,
         gate: &Gate,
         known_functions: &HashMap<String, (GateStats, usize, usize)>,
-    ) {
+    ) -> Result<()> {
         use Gate::*;
 
         match gate {
@@ -177,25 +390,25 @@ impl GateStats {
             }
 
             Free(first, last) => {
-                let last_one = last.unwrap_or(*first);
-                self.variables_freed += (last_one - *first + 1) as usize;
+                let freed = range_len(*first, last.unwrap_or(*first))?;
+                add_count(&mut self.variables_freed, freed)?;
             }
 
             Call(name, _, _) => {
                 self.functions_called += 1;
                 if let Some(stats_ins_wit) = known_functions.get(name).cloned() {
-                    self.ingest_call_stats(&stats_ins_wit.0);
-                    self.instance_variables += stats_ins_wit.1;
-                    self.witness_variables += stats_ins_wit.2;
+                    self.ingest_call_stats(&stats_ins_wit.0)?;
+                    add_count(&mut self.instance_variables, stats_ins_wit.1)?;
+                    add_count(&mut self.witness_variables, stats_ins_wit.2)?;
                 } else {
                     eprintln!("WARNING Stats: function not defined \"{}\"", name);
                 }
             }
 
             AnonCall(_, _, instance_count, witness_count, subcircuit) => {
-                self.ingest_call_stats(&ingest_subcircuit(subcircuit, known_functions));
-                self.instance_variables += instance_count;
-                self.witness_variables += witness_count;
+                self.ingest_call_stats(&ingest_subcircuit(subcircuit, known_functions)?)?;
+                add_count(&mut self.instance_variables, *instance_count)?;
+                add_count(&mut self.witness_variables, *witness_count)?;
             }
 
             Switch(_, _, _, branches) => {
@@ -208,7 +421,7 @@ impl GateStats {
                         CaseInvoke::AbstractGateCall(name, _) => {
                             self.functions_called += 1;
                             if let Some(stats_ins_wit) = known_functions.get(name).cloned() {
-                                self.ingest_call_stats(&stats_ins_wit.0);
+                                self.ingest_call_stats(&stats_ins_wit.0)?;
                                 (stats_ins_wit.1, stats_ins_wit.2)
                             } else {
                                 eprintln!("WARNING Stats: function not defined \"{}\"", name);
@@ -221,7 +434,10 @@ impl GateStats {
                             witness_count,
                             subcircuit,
                         ) => {
-                            self.ingest_call_stats(&ingest_subcircuit(subcircuit, known_functions));
+                            self.ingest_call_stats(&ingest_subcircuit(
+                                subcircuit,
+                                known_functions,
+                            )?)?;
                             (*instance_count, *witness_count)
                         }
                     };
@@ -230,59 +446,547 @@ impl GateStats {
                     max_witness_count = std::cmp::max(max_witness_count, witness_count);
                 }
 
-                self.instance_variables += max_instance_count;
-                self.witness_variables += max_witness_count;
+                add_count(&mut self.instance_variables, max_instance_count)?;
+                add_count(&mut self.witness_variables, max_witness_count)?;
+            }
+
+            Typed(type_id, gate) => {
+                if *type_id != 0 {
+                    self.typed_gates += 1;
+                }
+                self.ingest_gate(gate, known_functions)?;
+            }
+
+            Convert(..) => {
+                self.convert_gates += 1;
             }
 
             For(_, start_val, end_val, _, body) => {
                 self.for_loops += 1;
-                for _ in *start_val..=*end_val {
+                // Count one iteration, and repeat it.
+                let mut iteration = GateStats::default();
+                match body {
+                    ForLoopBody::IterExprCall(name, _, _) => {
+                        iteration.functions_called += 1;
+                        if let Some(stats_ins_wit) = known_functions.get(name).cloned() {
+                            iteration.ingest_call_stats(&stats_ins_wit.0)?;
+                            iteration.instance_variables = stats_ins_wit.1;
+                            iteration.witness_variables = stats_ins_wit.2;
+                        } else {
+                            eprintln!("WARNING Stats: function not defined \"{}\"", name);
+                        }
+                    }
+                    ForLoopBody::IterExprAnonCall(
+                        _,
+                        _,
+                        instance_count,
+                        witness_count,
+                        subcircuit,
+                    ) => {
+                        iteration
+                            .ingest_call_stats(&ingest_subcircuit(subcircuit, known_functions)?)?;
+                        iteration.instance_variables = *instance_count;
+                        iteration.witness_variables = *witness_count;
+                    }
+                }
+                let iterations = checked(usize::try_from(loop_len(*start_val, *end_val)?).ok())?;
+                self.add_scaled(&iteration, iterations)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ingest_call_stats(&mut self, other: &GateStats) -> Result<()> {
+        // The inputs are counted by the caller.
+        let gates = GateStats {
+            instance_variables: 0,
+            witness_variables: 0,
+            ..other.clone()
+        };
+        self.add_scaled(&gates, 1)
+    }
+
+    /// Add the gates and inputs of `other`, as if it was repeated `times` times.
+    /// Fail if a count overflows.
+    fn add_scaled(&mut self, other: &GateStats, times: usize) -> Result<()> {
+        macro_rules! add_scaled {
+            ($($field:ident),*) => {
+                $(add_count(&mut self.$field, checked(other.$field.checked_mul(times))?)?;)*
+            };
+        }
+        add_scaled!(
+            instance_variables,
+            witness_variables,
+            constants_gates,
+            assert_zero_gates,
+            copy_gates,
+            add_gates,
+            mul_gates,
+            add_constant_gates,
+            mul_constant_gates,
+            and_gates,
+            xor_gates,
+            not_gates,
+            variables_freed,
+            switches,
+            branches,
+            for_loops,
+            functions_called,
+            typed_gates,
+            convert_gates
+        );
+        Ok(())
+    }
+}
+
+fn checked(count: Option<usize>) -> Result<usize> {
+    count.ok_or_else(|| "The statistics count more than 2^64 gates or wires.".into())
+}
+
+/// Add `n` to a count, failing instead of overflowing.
+fn add_count(count: &mut usize, n: usize) -> Result<()> {
+    *count = checked(count.checked_add(n))?;
+    Ok(())
+}
+
+/// The number of iterations of a loop, which must end at or after its start and fit in a u64.
+pub(crate) fn loop_len(start: u64, end: u64) -> Result<u64> {
+    end.checked_sub(start)
+        .and_then(|n| n.checked_add(1))
+        .ok_or_else(|| format!("Invalid loop range from {} to {}.", start, end).into())
+}
+
+/// The number of wires or iterations from `first` to `last` included.
+fn range_len(first: u64, last: u64) -> Result<usize> {
+    if last < first {
+        return Err(format!("Invalid range from {} to {}.", first, last).into());
+    }
+    checked(
+        usize::try_from(last - first)
+            .ok()
+            .and_then(|n| n.checked_add(1)),
+    )
+}
+
+/// EffectiveCounter goes through the gates in the order of the Evaluator to compute
+/// `EffectiveStats`, without evaluating anything. A body repeated by a loop is only visited once.
+struct EffectiveCounter<'a> {
+    functions: &'a HashMap<String, Rc<(usize, Vec<Gate>)>>,
+    is_boolean: bool,
+    // The number of multiplications to raise a value to the power p-1.
+    exponent_muls: usize,
+    profile: &'a mut BTreeMap<String, FunctionProfile>,
+    multiplexing: &'a mut GateStats,
+}
+
+impl<'a> EffectiveCounter<'a> {
+    /// Count the gates of a subcircuit executed `times` times, inside a switch if `weighted`.
+    /// Return the gates of the subcircuit itself, and the total including the functions it calls.
+    fn count_gates(
+        &mut self,
+        gates: &[Gate],
+        weighted: bool,
+        times: usize,
+    ) -> Result<(GateStats, GateStats)> {
+        use Gate::*;
+
+        let mut own = GateStats::default();
+        let mut total = GateStats::default();
+
+        for gate in gates {
+            match gate {
+                Call(name, _, _) => {
+                    add_count(&mut own.functions_called, times)?;
+                    add_count(&mut total.functions_called, times)?;
+                    total.add_scaled(&self.count_call(name, weighted, times)?, 1)?;
+                }
+
+                AnonCall(_, _, _, _, subcircuit) => {
+                    let (sub_own, sub_total) = self.count_gates(subcircuit, weighted, times)?;
+                    own.add_scaled(&sub_own, 1)?;
+                    total.add_scaled(&sub_total, 1)?;
+                }
+
+                Switch(_, output_wires, cases, branches) => {
+                    add_count(&mut own.switches, times)?;
+                    add_count(&mut total.switches, times)?;
+                    let branch_count = checked(branches.len().checked_mul(times))?;
+                    add_count(&mut own.branches, branch_count)?;
+                    add_count(&mut total.branches, branch_count)?;
+
+                    for branch in branches {
+                        match branch {
+                            CaseInvoke::AbstractGateCall(name, _) => {
+                                add_count(&mut own.functions_called, times)?;
+                                add_count(&mut total.functions_called, times)?;
+                                total.add_scaled(&self.count_call(name, true, times)?, 1)?;
+                            }
+                            CaseInvoke::AbstractAnonCall(_, _, _, subcircuit) => {
+                                let (sub_own, sub_total) =
+                                    self.count_gates(subcircuit, true, times)?;
+                                own.add_scaled(&sub_own, 1)?;
+                                total.add_scaled(&sub_total, 1)?;
+                            }
+                        }
+                    }
+
+                    let output_count = WireListView::new(output_wires).map_or(0, |o| o.len());
+                    let overhead = self.switch_overhead(cases.len(), output_count, weighted)?;
+                    own.add_scaled(&overhead, times)?;
+                    total.add_scaled(&overhead, times)?;
+                    self.multiplexing.add_scaled(&overhead, times)?;
+                }
+
+                For(_, start_val, end_val, _, body) => {
+                    add_count(&mut own.for_loops, times)?;
+                    add_count(&mut total.for_loops, times)?;
+                    let iterations =
+                        checked(usize::try_from(loop_len(*start_val, *end_val)?).ok())?;
+                    let times = checked(iterations.checked_mul(times))?;
+
                     match body {
                         ForLoopBody::IterExprCall(name, _, _) => {
-                            self.functions_called += 1;
-                            if let Some(stats_ins_wit) = known_functions.get(name).cloned() {
-                                self.ingest_call_stats(&stats_ins_wit.0);
-                                self.instance_variables += stats_ins_wit.1;
-                                self.witness_variables += stats_ins_wit.2;
-                            } else {
-                                eprintln!("WARNING Stats: function not defined \"{}\"", name);
-                            }
+                            add_count(&mut own.functions_called, times)?;
+                            add_count(&mut total.functions_called, times)?;
+                            let sub_total = self.count_call(name, weighted, times)?;
+                            total.add_scaled(&sub_total, 1)?;
                         }
-                        ForLoopBody::IterExprAnonCall(
-                            _,
-                            _,
-                            instance_count,
-                            witness_count,
-                            subcircuit,
-                        ) => {
-                            self.ingest_call_stats(&ingest_subcircuit(subcircuit, known_functions));
-                            self.instance_variables += *instance_count;
-                            self.witness_variables += *witness_count;
+                        ForLoopBody::IterExprAnonCall(_, _, _, _, subcircuit) => {
+                            let (sub_own, sub_total) =
+                                self.count_gates(subcircuit, weighted, times)?;
+                            own.add_scaled(&sub_own, 1)?;
+                            total.add_scaled(&sub_total, 1)?;
+                        }
+                    }
+                }
+
+                _ => {
+                    let mut gate_stats = GateStats::default();
+                    gate_stats.ingest_gate(gate, &HashMap::new())?;
+                    // In a switch, assertions are multiplied by the weight of the branch.
+                    if weighted && matches!(gate, AssertZero(_)) {
+                        let mut overhead = GateStats::default();
+                        self.count_mul(&mut overhead, 1)?;
+                        gate_stats.add_scaled(&overhead, 1)?;
+                        self.multiplexing.add_scaled(&overhead, times)?;
+                    }
+                    own.add_scaled(&gate_stats, times)?;
+                    total.add_scaled(&gate_stats, times)?;
+                }
+            }
+        }
+        Ok((own, total))
+    }
+
+    /// Count the calls of a function and record them in its profile. Return the total cost.
+    fn count_call(&mut self, name: &str, weighted: bool, times: usize) -> Result<GateStats> {
+        let functions = self.functions;
+        let function = match functions.get(name) {
+            Some(function) => function,
+            None => {
+                eprintln!("WARNING Stats: function not defined \"{}\"", name);
+                return Ok(GateStats::default());
+            }
+        };
+
+        let (own, total) = self.count_gates(&function.1, weighted, times)?;
+        let profile = self.profile.entry(name.to_string()).or_default();
+        add_count(&mut profile.calls, times)?;
+        profile.self_stats.add_scaled(&own, 1)?;
+        profile.total_stats.add_scaled(&total, 1)?;
+        Ok(total)
+    }
+
+    /// The gates added by the Evaluator to multiplex one switch.
+    fn switch_overhead(
+        &self,
+        case_count: usize,
+        output_count: usize,
+        weighted: bool,
+    ) -> Result<GateStats> {
+        let mut overhead = GateStats::default();
+
+        // For each case, the weight `1 - (case - condition)^(p-1)`, multiplied by the weight of
+        // the enclosing branch if any.
+        add_count(&mut overhead.constants_gates, case_count)?;
+        if self.is_boolean {
+            add_count(&mut overhead.xor_gates, case_count)?;
+            add_count(&mut overhead.not_gates, case_count)?;
+        } else {
+            add_count(
+                &mut overhead.mul_constant_gates,
+                checked(case_count.checked_mul(2))?,
+            )?;
+            add_count(&mut overhead.add_gates, case_count)?;
+            add_count(&mut overhead.add_constant_gates, case_count)?;
+        }
+        let weight_muls = checked(self.exponent_muls.checked_add(usize::from(weighted)))?;
+        self.count_mul(&mut overhead, checked(weight_muls.checked_mul(case_count))?)?;
+
+        // For each output, the sum of the outputs of the branches multiplied by their weight.
+        add_count(&mut overhead.constants_gates, output_count)?;
+        let products = checked(case_count.checked_mul(output_count))?;
+        self.count_mul(&mut overhead, products)?;
+        if self.is_boolean {
+            add_count(&mut overhead.xor_gates, products)?;
+        } else {
+            add_count(&mut overhead.add_gates, products)?;
+        }
+        Ok(overhead)
+    }
+
+    fn count_mul(&self, stats: &mut GateStats, count: usize) -> Result<()> {
+        if self.is_boolean {
+            add_count(&mut stats.and_gates, count)
+        } else {
+            add_count(&mut stats.mul_gates, count)
+        }
+    }
+}
+
+/// The number of multiplications done by the Evaluator to raise a value to the power p-1:
+/// one squaring per bit after the first one, and one multiplication per other set bit.
+fn exponent_muls(field_characteristic: &[u8]) -> usize {
+    let characteristic = BigUint::from_bytes_le(field_characteristic);
+    if characteristic <= BigUint::one() {
+        return 0;
+    }
+    let exponent = characteristic - BigUint::one();
+    let set_bits: u32 = exponent.to_bytes_le().iter().map(|b| b.count_ones()).sum();
+    (exponent.bits() as usize - 1) + (set_bits as usize - 1)
+}
+
+/// The depth of a wire, and the number of times it was used.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct WireInfo {
+    depth: usize,
+    mul_depth: usize,
+    fan_out: usize,
+}
+
+/// Map the wires of a scope (the relation, or the body of a call) to a slot of the tracker, and
+/// whether the scope owns the slot. The inputs of a call point to the slots of the caller.
+type Scope = unroller::Scope<(usize, bool)>;
+
+/// WireTracker computes `WireStats` from the gates given by an `Unroller`, including the bodies of
+/// functions, loops and switches, while keeping track of the depth of live wires.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct WireTracker {
+    slots: Vec<WireInfo>,
+    free_slots: Vec<usize>,
+    live_wires: usize,
+    stats: WireStats,
+}
+
+impl GateVisitor for WireTracker {
+    type Entry = (usize, bool);
+    type Context = ();
+
+    const NAME: &'static str = "Stats";
+
+    fn visit_gate(
+        &mut self,
+        unroller: &mut Unroller<(usize, bool)>,
+        scope: &mut Scope,
+        gate: &Gate,
+        gate_index: usize,
+        _: &(),
+    ) {
+        use Gate::*;
+
+        match gate {
+            Constant(out, value) => {
+                if let Err(err) = unroller.budget.limits.check_value(value) {
+                    unroller.fail(err);
+                    return;
+                }
+                self.allocate(unroller, scope, *out, 0, 0);
+            }
+
+            Instance(out) | Witness(out) => {
+                self.allocate(unroller, scope, *out, 0, 0);
+            }
+
+            AssertZero(inp) => {
+                self.read(scope, *inp);
+            }
+
+            Copy(out, inp) => {
+                let inp = self.read(scope, *inp);
+                self.allocate(unroller, scope, *out, inp.depth, inp.mul_depth);
+            }
+
+            Add(out, left, right) | Xor(out, left, right) => {
+                let (left, right) = (self.read(scope, *left), self.read(scope, *right));
+                let depth = max(left.depth, right.depth) + 1;
+                let mul_depth = max(left.mul_depth, right.mul_depth);
+                self.allocate(unroller, scope, *out, depth, mul_depth);
+            }
+
+            Mul(out, left, right) | And(out, left, right) => {
+                let (left, right) = (self.read(scope, *left), self.read(scope, *right));
+                let depth = max(left.depth, right.depth) + 1;
+                let mul_depth = max(left.mul_depth, right.mul_depth) + 1;
+                self.allocate(unroller, scope, *out, depth, mul_depth);
+            }
+
+            AddConstant(out, inp, _) | MulConstant(out, inp, _) | Not(out, inp) => {
+                let inp = self.read(scope, *inp);
+                self.allocate(unroller, scope, *out, inp.depth + 1, inp.mul_depth);
+            }
+
+            Convert(_, output_wires, _, input_wires) => {
+                let (outputs, inputs) = match unroller.wire_views(output_wires, input_wires) {
+                    Some(wires) => wires,
+                    None => return,
+                };
+                let mut info = WireInfo::default();
+                for input in inputs.ids() {
+                    let input = self.read(scope, input);
+                    info.depth = max(info.depth, input.depth);
+                    info.mul_depth = max(info.mul_depth, input.mul_depth);
+                }
+                for output in outputs.ids() {
+                    self.allocate(unroller, scope, output, info.depth + 1, info.mul_depth);
+                }
+            }
+
+            Switch(condition, output_wires, _, branches) => {
+                let condition = self.read(scope, *condition);
+                let outputs = match unroller.wirelist_view(output_wires) {
+                    Some(outputs) => outputs,
+                    None => return,
+                };
+
+                // Branches are evaluated one after the other, then each output is selected by
+                // multiplying the branch outputs with weights computed from the condition.
+                let mut merged = vec![WireInfo::default(); outputs.len()];
+                for branch in branches.iter().enumerate() {
+                    let results = match unroller.call_branch(
+                        self,
+                        scope,
+                        branch,
+                        outputs.len(),
+                        gate_index,
+                        &(),
+                    ) {
+                        Some(results) => results,
+                        None => continue,
+                    };
+                    for (merged, result) in merged.iter_mut().zip(results) {
+                        if let Some((slot, owned)) = result {
+                            let info = self.slots[slot];
+                            merged.depth = max(merged.depth, info.depth);
+                            merged.mul_depth = max(merged.mul_depth, info.mul_depth);
+                            self.release((slot, owned));
                         }
                     }
                 }
+
+                for (wire, info) in outputs.ids().zip(merged.iter()) {
+                    let depth = max(info.depth, condition.depth) + 1;
+                    let mul_depth = max(info.mul_depth, condition.mul_depth) + 1;
+                    self.allocate(unroller, scope, wire, depth, mul_depth);
+                }
             }
+
+            // The other gates are handled by the Unroller.
+            _ => unreachable!(),
         }
     }
 
-    fn ingest_call_stats(&mut self, other: &GateStats) {
-        // Gates.
-        self.constants_gates += other.constants_gates;
-        self.assert_zero_gates += other.assert_zero_gates;
-        self.copy_gates += other.copy_gates;
-        self.add_gates += other.add_gates;
-        self.mul_gates += other.mul_gates;
-        self.add_constant_gates += other.add_constant_gates;
-        self.mul_constant_gates += other.mul_constant_gates;
-        self.and_gates += other.and_gates;
-        self.xor_gates += other.xor_gates;
-        self.not_gates += other.not_gates;
-        self.variables_freed += other.variables_freed;
-
-        self.switches += other.switches;
-        self.branches += other.branches;
-        self.for_loops += other.for_loops;
-        self.functions_called += other.functions_called;
+    fn body_context(&mut self, _: &(), _: usize, _: Body) {}
+
+    /// Give the output slots of a call to the wires of the caller.
+    fn assign_outputs(
+        &mut self,
+        unroller: &mut Unroller<(usize, bool)>,
+        scope: &mut Scope,
+        outputs: impl WireRanges,
+        results: Vec<Option<(usize, bool)>>,
+        _: &(),
+    ) {
+        let mut results = results.into_iter();
+        for wire in outputs.ids() {
+            match results.next().flatten() {
+                Some((slot, true)) => {
+                    if let Some(old) = scope.insert(wire, (slot, true)) {
+                        self.release(old);
+                    }
+                }
+                // The output was not assigned, or is an input of the call.
+                other => {
+                    let info = match other {
+                        Some((slot, _)) => self.slots[slot],
+                        None => WireInfo::default(),
+                    };
+                    self.allocate(unroller, scope, wire, info.depth, info.mul_depth);
+                }
+            }
+        }
+    }
+
+    fn input_entry(&self, (slot, _): (usize, bool)) -> (usize, bool) {
+        (slot, false)
+    }
+
+    fn release(&mut self, (slot, owned): (usize, bool)) {
+        if owned {
+            self.free_slots.push(slot);
+            self.live_wires -= 1;
+        }
+    }
+}
+
+impl WireTracker {
+    fn allocate(
+        &mut self,
+        unroller: &mut Unroller<(usize, bool)>,
+        scope: &mut Scope,
+        wire: WireId,
+        depth: usize,
+        mul_depth: usize,
+    ) {
+        let info = WireInfo {
+            depth,
+            mul_depth,
+            fan_out: 0,
+        };
+        let slot = match self.free_slots.pop() {
+            Some(slot) => {
+                self.slots[slot] = info;
+                slot
+            }
+            None => {
+                self.slots.push(info);
+                self.slots.len() - 1
+            }
+        };
+        if let Some(old) = scope.insert(wire, (slot, true)) {
+            self.release(old);
+        }
+        if let Err(err) = unroller.budget.limits.check_live_wires(scope.len()) {
+            unroller.fail(err);
+        }
+
+        self.live_wires += 1;
+        self.stats.peak_live_wires = max(self.stats.peak_live_wires, self.live_wires);
+        self.stats.depth = max(self.stats.depth, depth);
+        self.stats.multiplicative_depth = max(self.stats.multiplicative_depth, mul_depth);
+    }
+
+    /// Return the information of a wire, and count one more use of it.
+    /// Unknown wires are considered inputs of the circuit.
+    fn read(&mut self, scope: &Scope, wire: WireId) -> WireInfo {
+        match scope.get(&wire) {
+            Some((slot, _)) => {
+                let info = &mut self.slots[*slot];
+                info.fan_out += 1;
+                self.stats.max_fan_out = max(self.stats.max_fan_out, info.fan_out);
+                *info
+            }
+            None => WireInfo::default(),
+        }
     }
 }
 
@@ -321,11 +1025,20 @@ fn test_stats() -> crate::Result<()> {
             switches: 1,
             branches: 2,
             for_loops: 2,
+            typed_gates: 0,
+            convert_gates: 0,
             instance_messages: 1,
             witness_messages: 1,
             relation_messages: 1,
         },
         functions: HashMap::new(),
+        wire_stats: WireStats {
+            multiplicative_depth: 17,
+            depth: 40,
+            max_fan_out: 4,
+            peak_live_wires: 43,
+        },
+        ..Stats::default()
     };
     expected_stats.functions.insert(
         "com.example::mul".to_string(),
@@ -341,5 +1054,300 @@ fn test_stats() -> crate::Result<()> {
 
     assert_eq!(expected_stats, stats);
 
+    // Well-known fields are named.
+    let header = Header::from_preset("goldilocks")?;
+    let mut stats = Stats::default();
+    stats.ingest_instance(&example_instance_h(&header));
+    assert_eq!(stats.field_name, Some("goldilocks".to_string()));
+    assert!(serde_json::to_string(&stats)?.contains(r#""field_name":"goldilocks""#));
+
+    // Gates of all types are counted.
+    let mut stats = Stats::default();
+    stats.ingest_instance(&example_typed_instance());
+    stats.ingest_witness(&example_typed_witness());
+    stats.ingest_relation(&example_typed_relation());
+    assert_eq!(stats.types, vec![literal(2u32)]);
+    assert_eq!(stats.gate_stats.typed_gates, 6);
+    assert_eq!(stats.gate_stats.convert_gates, 1);
+    assert_eq!(stats.gate_stats.witness_variables, 3);
+    assert_eq!(stats.gate_stats.mul_gates, 1);
+    assert_eq!(stats.wire_stats.depth, 2);
+
     Ok(())
 }
+
+#[test]
+fn test_effective_stats() -> crate::Result<()> {
+    use crate::producers::examples::*;
+
+    let mut stats = Stats::effective();
+    stats.ingest_instance(&example_instance());
+    stats.ingest_witness(&example_witness());
+    stats.ingest_relation(&example_relation());
+
+    // Multiplexing the switch: 2 weights, each with 8 multiplications to raise to the power 100,
+    // 4 weighted assertions, and 8 outputs selected from 2 branches.
+    let multiplexing = GateStats {
+        constants_gates: 10,
+        add_gates: 18,
+        mul_gates: 36,
+        add_constant_gates: 2,
+        mul_constant_gates: 4,
+        ..GateStats::default()
+    };
+    let mut expected = EffectiveStats {
+        gate_stats: GateStats {
+            instance_variables: 6,
+            witness_variables: 6,
+            constants_gates: 11,
+            assert_zero_gates: 6,
+            add_gates: 43,
+            mul_gates: 57,
+            add_constant_gates: 2,
+            mul_constant_gates: 5,
+            variables_freed: 51,
+            functions_called: 20,
+            switches: 1,
+            branches: 2,
+            for_loops: 2,
+            ..GateStats::default()
+        },
+        multiplexing,
+        functions: BTreeMap::new(),
+    };
+    let mul_stats = GateStats {
+        mul_gates: 20,
+        ..GateStats::default()
+    };
+    expected.functions.insert(
+        "com.example::mul".to_string(),
+        FunctionProfile {
+            calls: 20,
+            self_stats: mul_stats.clone(),
+            total_stats: mul_stats,
+        },
+    );
+
+    assert_eq!(stats.effective, Some(expected));
+
+    // The structural stats are not changed.
+    let mut structural = Stats::default();
+    structural.ingest_instance(&example_instance());
+    structural.ingest_witness(&example_witness());
+    structural.ingest_relation(&example_relation());
+    assert_eq!(structural.gate_stats, stats.gate_stats);
+
+    Ok(())
+}
+
+#[test]
+fn test_stats_limits() {
+    use crate::producers::examples::*;
+    use crate::Gate::*;
+
+    // Nested loops of 2^40 iterations in total.
+    let inner_loop = For(
+        "j".into(),
+        0,
+        1 << 20,
+        vec![],
+        ForLoopBody::IterExprAnonCall(vec![], vec![], 0, 0, vec![Constant(0, vec![1])]),
+    );
+    let mut relation = example_relation();
+    relation.gates.push(For(
+        "i".into(),
+        0,
+        1 << 20,
+        vec![],
+        ForLoopBody::IterExprAnonCall(vec![], vec![], 0, 0, vec![inner_loop]),
+    ));
+
+    let mut stats = Stats::default();
+    stats.set_limits(Limits {
+        max_unrolled_gates: 1000,
+        ..Limits::default()
+    });
+    stats.ingest_relation(&relation);
+    assert_eq!(
+        stats.check_limits().unwrap_err().to_string(),
+        "More than 1000 gates are processed once functions are inlined and loops unrolled (max_unrolled_gates)."
+    );
+}
+
+#[test]
+fn test_stats_recursive_functions() {
+    use crate::producers::examples::*;
+    use crate::structs::function::Function;
+
+    let mut relation = example_relation();
+    relation.functions.push(Function::new(
+        "rec::f".to_string(),
+        0,
+        0,
+        0,
+        0,
+        vec![Gate::For(
+            "i".into(),
+            0,
+            1,
+            vec![],
+            ForLoopBody::IterExprCall("rec::f".into(), vec![], vec![]),
+        )],
+    ));
+
+    let mut stats = Stats::default();
+    stats.ingest_relation(&relation);
+    assert_eq!(
+        stats.check_limits().unwrap_err().to_string(),
+        "The function rec::f is recursive: rec::f -> rec::f."
+    );
+}
+
+#[test]
+fn test_stats_overflow() {
+    use crate::producers::examples::*;
+    use crate::structs::function::Function;
+    use crate::Gate::*;
+
+    // A function that is not called, whose nested loops have 2^64 iterations in total.
+    let loop_ = |name: &str, body| {
+        For(
+            name.into(),
+            0,
+            (1 << 32) - 1,
+            vec![],
+            ForLoopBody::IterExprAnonCall(vec![], vec![], 0, 0, body),
+        )
+    };
+    let mut relation = example_relation();
+    relation.functions.push(Function::new(
+        "big::f".to_string(),
+        0,
+        0,
+        0,
+        0,
+        vec![loop_("i", vec![loop_("j", vec![Constant(0, vec![1])])])],
+    ));
+
+    let mut stats = Stats::default();
+    stats.ingest_relation(&relation);
+    assert_eq!(
+        stats.check_limits().unwrap_err().to_string(),
+        "The statistics count more than 2^64 gates or wires."
+    );
+
+    // A loop over all the u64 values.
+    let mut relation = example_relation();
+    relation.functions.push(Function::new(
+        "big::g".to_string(),
+        0,
+        0,
+        0,
+        0,
+        vec![For(
+            "i".into(),
+            0,
+            u64::MAX,
+            vec![],
+            ForLoopBody::IterExprAnonCall(vec![], vec![], 0, 0, vec![]),
+        )],
+    ));
+    let mut stats = Stats::default();
+    stats.ingest_relation(&relation);
+    assert!(stats.check_limits().is_err());
+
+    // A loop that ends before its start, rejected like in the graph drawer.
+    let mut relation = example_relation();
+    relation.functions.push(Function::new(
+        "big::h".to_string(),
+        0,
+        0,
+        0,
+        0,
+        vec![For(
+            "i".into(),
+            5,
+            4,
+            vec![],
+            ForLoopBody::IterExprAnonCall(vec![], vec![], 0, 0, vec![]),
+        )],
+    ));
+    let mut stats = Stats::default();
+    stats.ingest_relation(&relation);
+    assert_eq!(
+        stats.check_limits().unwrap_err().to_string(),
+        "Invalid loop range from 5 to 4."
+    );
+}
+
+#[test]
+fn test_wire_tracker_peak_live_wires() {
+    use crate::producers::examples::*;
+    use crate::structs::function::Function;
+    use crate::structs::iterators::{IterExprListElement::*, IterExprWireNumber::*};
+    use crate::structs::wire::WireListElement::*;
+    use crate::Gate::*;
+
+    // $0 <- $1 ^ 4, with two temporary wires.
+    let square_twice = vec![Mul(2, 1, 1), Mul(3, 2, 2), Copy(0, 3)];
+    let gates = vec![
+        // The gate, then the number of live wires and the peak after it.
+        (Instance(0), 1, 1),
+        (Witness(1), 2, 2),
+        (Add(2, 0, 1), 3, 3),
+        (Free(0, Some(1)), 1, 3),
+        // The input and the 3 wires of the body are live at the end of the call.
+        (Call("sq::f".into(), vec![Wire(3)], vec![Wire(2)]), 2, 4),
+        // Each iteration sets one more wire, with one temporary wire.
+        (
+            For(
+                "i".into(),
+                4,
+                6,
+                vec![WireRange(4, 6)],
+                ForLoopBody::IterExprAnonCall(
+                    vec![Single(IterExprName("i".into()))],
+                    vec![Single(IterExprSub(
+                        Box::new(IterExprName("i".into())),
+                        Box::new(IterExprConst(1)),
+                    ))],
+                    0,
+                    0,
+                    vec![Add(2, 1, 1), Copy(0, 2)],
+                ),
+            ),
+            5,
+            6,
+        ),
+        (Free(2, Some(6)), 0, 6),
+        (Instance(7), 1, 6),
+        // A large range only frees the live wires.
+        (Free(0, Some(1 << 40)), 0, 6),
+    ];
+
+    let mut unroller = Unroller::default();
+    let mut tracker = WireTracker::default();
+    unroller.define_function("sq::f".into(), 1, square_twice.clone());
+    for (i, (gate, live_wires, peak_live_wires)) in gates.iter().enumerate() {
+        unroller.ingest_relation_gates(&mut tracker, std::slice::from_ref(gate), i, &());
+        assert_eq!(
+            (tracker.live_wires, tracker.stats.peak_live_wires),
+            (*live_wires, *peak_live_wires),
+            "after {:?}",
+            gate
+        );
+    }
+    assert_eq!(unroller.error, None);
+    assert!(unroller.scope.is_empty());
+
+    // The same relation through Stats.
+    let mut relation = example_relation();
+    relation.functions = vec![Function::new("sq::f".into(), 1, 1, 0, 0, square_twice)];
+    relation.gates = gates.into_iter().map(|(gate, _, _)| gate).collect();
+    let mut stats = Stats::default();
+    stats.ingest_relation(&relation);
+    assert!(stats.check_limits().is_ok());
+    assert_eq!(stats.wire_stats.peak_live_wires, 6);
+    // The addition, the two multiplications of the call and the three iterations form a chain.
+    assert_eq!(stats.wire_stats.depth, 6);
+}
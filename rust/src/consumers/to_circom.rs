@@ -0,0 +1,290 @@
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::producers::from_circom::{
+    encode_field_element, R1CS_CONSTRAINTS_SECTION, R1CS_HEADER_SECTION, R1CS_MAGIC,
+    R1CS_WIRE2LABEL_SECTION, WTNS_DATA_SECTION, WTNS_HEADER_SECTION, WTNS_MAGIC,
+};
+use crate::Result;
+
+use zkinterface::{
+    BilinearConstraint, CircuitHeader as zkiCircuitHeader, ConstraintSystem as zkiConstraintSystem,
+    Sink, Variables as zkiVariables, Witness as zkiWitness,
+};
+
+/// CircomSink collects the messages produced by a zkInterface producer (e.g. `ToR1CSConverter`)
+/// and writes them in the iden3 `.r1cs` and `.wtns` binary formats used by circom and snarkjs.
+///
+/// Since circom requires public wires to come first, variables are renumbered: the constant one
+/// stays at 0, instance variables follow, then all the other variables. The original zkInterface
+/// ids are kept as labels in the `.r1cs` file.
+#[derive(Default)]
+pub struct CircomSink {
+    header: Option<zkiCircuitHeader>,
+    constraints: Vec<BilinearConstraint>,
+    witness_values: BTreeMap<u64, Vec<u8>>,
+}
+
+impl Sink for CircomSink {
+    fn push_header(&mut self, header: zkiCircuitHeader) -> zkinterface::Result<()> {
+        if self.header.is_some() {
+            return Err("CircomSink supports a single circuit header.".into());
+        }
+        self.header = Some(header);
+        Ok(())
+    }
+
+    fn push_constraints(&mut self, cs: zkiConstraintSystem) -> zkinterface::Result<()> {
+        self.constraints.extend(cs.constraints);
+        Ok(())
+    }
+
+    fn push_witness(&mut self, witness: zkiWitness) -> zkinterface::Result<()> {
+        if witness.assigned_variables.variable_ids.is_empty() {
+            return Ok(());
+        }
+        for var in witness.assigned_variables.get_variables() {
+            self.witness_values.insert(var.id, var.value.to_vec());
+        }
+        Ok(())
+    }
+}
+
+impl CircomSink {
+    fn header(&self) -> Result<&zkiCircuitHeader> {
+        self.header
+            .as_ref()
+            .ok_or_else(|| "No circuit header was received.".into())
+    }
+
+    fn prime(&self) -> Result<BigUint> {
+        match &self.header()?.field_maximum {
+            Some(field_maximum) => Ok(BigUint::from_bytes_le(field_maximum) + BigUint::one()),
+            None => Err("field_maximum must be provided".into()),
+        }
+    }
+
+    /// Size in bytes of an encoded field element, rounded up to a multiple of 8 as circom does.
+    fn field_size(&self) -> Result<usize> {
+        let bits = (self.prime()? - BigUint::one()).bits().max(1) as usize;
+        Ok(((bits - 1) / 64 + 1) * 8)
+    }
+
+    /// Map each zkInterface variable id to its circom wire. Also return the number of public wires.
+    fn wire_mapping(&self) -> Result<(HashMap<u64, u32>, u32)> {
+        let header = self.header()?;
+        let mut mapping = HashMap::new();
+        mapping.insert(0, 0);
+
+        for id in header.instance_variables.variable_ids.iter() {
+            let next = mapping.len() as u32;
+            mapping.entry(*id).or_insert(next);
+        }
+        let n_public = mapping.len() as u32 - 1;
+
+        for id in 1..header.free_variable_id {
+            let next = mapping.len() as u32;
+            mapping.entry(id).or_insert(next);
+        }
+        Ok((mapping, n_public))
+    }
+
+    /// Write the constraint system as a `.r1cs` file (version 1).
+    pub fn write_r1cs(&self, writer: &mut impl Write) -> Result<()> {
+        let prime = self.prime()?;
+        let n8 = self.field_size()?;
+        let (mapping, n_public) = self.wire_mapping()?;
+
+        let mut header = vec![];
+        header.extend_from_slice(&(n8 as u32).to_le_bytes());
+        header.append(&mut encode_field_element(&prime, n8));
+        header.extend_from_slice(&(mapping.len() as u32).to_le_bytes());
+        header.extend_from_slice(&0u32.to_le_bytes()); // nPubOut
+        header.extend_from_slice(&n_public.to_le_bytes()); // nPubIn
+        header.extend_from_slice(&0u32.to_le_bytes()); // nPrvIn
+        header.extend_from_slice(&(mapping.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(self.constraints.len() as u32).to_le_bytes());
+
+        let mut constraints = vec![];
+        for constraint in self.constraints.iter() {
+            for lc in [
+                &constraint.linear_combination_a,
+                &constraint.linear_combination_b,
+                &constraint.linear_combination_c,
+            ] {
+                write_linear_combination(&mut constraints, lc, &mapping, &prime, n8)?;
+            }
+        }
+
+        let mut labels = vec![0u64; mapping.len()];
+        for (id, wire) in mapping.iter() {
+            labels[*wire as usize] = *id;
+        }
+        let wire2label: Vec<u8> = labels.iter().flat_map(|l| l.to_le_bytes()).collect();
+
+        write_sections(
+            writer,
+            R1CS_MAGIC,
+            1,
+            &[
+                (R1CS_HEADER_SECTION, header),
+                (R1CS_CONSTRAINTS_SECTION, constraints),
+                (R1CS_WIRE2LABEL_SECTION, wire2label),
+            ],
+        )
+    }
+
+    /// Write the assignment of all wires as a `.wtns` file (version 2).
+    /// This requires the witness messages to have been produced.
+    pub fn write_wtns(&self, writer: &mut impl Write) -> Result<()> {
+        let prime = self.prime()?;
+        let n8 = self.field_size()?;
+        let (mapping, _) = self.wire_mapping()?;
+
+        let mut values = vec![None; mapping.len()];
+        values[0] = Some(BigUint::one());
+        let instance_variables = &self.header()?.instance_variables;
+        if !instance_variables.variable_ids.is_empty() {
+            for var in instance_variables.get_variables() {
+                values[mapping[&var.id] as usize] = Some(BigUint::from_bytes_le(var.value));
+            }
+        }
+        for (id, value) in self.witness_values.iter() {
+            let wire = mapping
+                .get(id)
+                .ok_or_else(|| format!("The witness variable {} was not allocated.", id))?;
+            values[*wire as usize] = Some(BigUint::from_bytes_le(value));
+        }
+
+        let mut header = vec![];
+        header.extend_from_slice(&(n8 as u32).to_le_bytes());
+        header.append(&mut encode_field_element(&prime, n8));
+        header.extend_from_slice(&(values.len() as u32).to_le_bytes());
+
+        let mut data = Vec::with_capacity(values.len() * n8);
+        for (wire, value) in values.iter().enumerate() {
+            let value = value
+                .as_ref()
+                .ok_or_else(|| format!("No value for the circom wire {}.", wire))?;
+            data.append(&mut encode_field_element(&(value % &prime), n8));
+        }
+
+        write_sections(
+            writer,
+            WTNS_MAGIC,
+            2,
+            &[(WTNS_HEADER_SECTION, header), (WTNS_DATA_SECTION, data)],
+        )
+    }
+
+    /// Write `circuit.r1cs` into the given directory, and `witness.wtns` if a witness was received.
+    /// Return the paths of the written files.
+    pub fn write_into_dir(&self, dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        create_dir_all(dir)?;
+
+        let r1cs_path = dir.join("circuit.r1cs");
+        let mut file = BufWriter::new(File::create(&r1cs_path)?);
+        self.write_r1cs(&mut file)?;
+        file.flush()?;
+        let mut paths = vec![r1cs_path];
+
+        if !self.witness_values.is_empty() {
+            let wtns_path = dir.join("witness.wtns");
+            let mut file = BufWriter::new(File::create(&wtns_path)?);
+            self.write_wtns(&mut file)?;
+            file.flush()?;
+            paths.push(wtns_path);
+        }
+        Ok(paths)
+    }
+}
+
+fn write_linear_combination(
+    out: &mut Vec<u8>,
+    lc: &zkiVariables,
+    mapping: &HashMap<u64, u32>,
+    prime: &BigUint,
+    n8: usize,
+) -> Result<()> {
+    let mut terms = vec![];
+    for var in lc.get_variables() {
+        let coeff = BigUint::from_bytes_le(var.value) % prime;
+        if coeff.is_zero() {
+            continue;
+        }
+        let wire = mapping
+            .get(&var.id)
+            .ok_or_else(|| format!("The variable {} was not allocated.", var.id))?;
+        terms.push((*wire, coeff));
+    }
+
+    out.extend_from_slice(&(terms.len() as u32).to_le_bytes());
+    for (wire, coeff) in terms {
+        out.extend_from_slice(&wire.to_le_bytes());
+        out.append(&mut encode_field_element(&coeff, n8));
+    }
+    Ok(())
+}
+
+fn write_sections(
+    writer: &mut impl Write,
+    magic: &[u8; 4],
+    version: u32,
+    sections: &[(u32, Vec<u8>)],
+) -> Result<()> {
+    writer.write_all(magic)?;
+    writer.write_all(&version.to_le_bytes())?;
+    writer.write_all(&(sections.len() as u32).to_le_bytes())?;
+    for (section_type, content) in sections {
+        writer.write_all(&section_type.to_le_bytes())?;
+        writer.write_all(&(content.len() as u64).to_le_bytes())?;
+        writer.write_all(content)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_circom_round_trip() -> Result<()> {
+    use crate::consumers::evaluator::{Evaluator, PlaintextBackend};
+    use crate::consumers::to_r1cs::ToR1CSConverter;
+    use crate::producers::examples::*;
+    use crate::producers::from_circom::{circom_to_ir, CircomR1CS, CircomWitness};
+    use crate::producers::sink::MemorySink;
+    use crate::{Message, Source};
+
+    let messages = vec![
+        Ok(Message::Instance(example_instance())),
+        Ok(Message::Witness(example_witness())),
+        Ok(Message::Relation(example_relation())),
+    ];
+
+    let mut to_r1cs = ToR1CSConverter::new(CircomSink::default(), true, false);
+    let evaluator = Evaluator::from_messages(messages.into_iter(), &mut to_r1cs);
+    assert_eq!(evaluator.get_violations(), Vec::<String>::new());
+    let circom_sink = to_r1cs.finish()?;
+
+    let mut r1cs_bytes = vec![];
+    circom_sink.write_r1cs(&mut r1cs_bytes)?;
+    let mut wtns_bytes = vec![];
+    circom_sink.write_wtns(&mut wtns_bytes)?;
+
+    let r1cs = CircomR1CS::read_from(&mut &r1cs_bytes[..])?;
+    let witness = CircomWitness::read_from(&mut &wtns_bytes[..])?;
+    assert_eq!(r1cs.prime, BigUint::from(EXAMPLE_MODULUS));
+    assert_eq!(r1cs.field_size, 8);
+    assert_eq!(r1cs.constraints.len(), circom_sink.constraints.len());
+    assert_eq!(witness.values.len(), r1cs.n_wires as usize);
+
+    // Back to IR, the statement must still be true.
+    let source: Source = circom_to_ir(MemorySink::default(), &r1cs, Some(&witness))?.into();
+    let mut backend = PlaintextBackend::default();
+    let evaluator = Evaluator::from_messages(source.iter_messages(), &mut backend);
+    assert_eq!(evaluator.get_violations(), Vec::<String>::new());
+
+    Ok(())
+}
@@ -0,0 +1,719 @@
+use num_bigint::BigUint;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+
+use crate::consumers::limits::Limits;
+use crate::consumers::stats::{ingest_subcircuit, loop_len, GateStats, Stats};
+use crate::structs::function::{CaseInvoke, ForLoopBody};
+use crate::structs::iterators::{evaluate_iterexpr_listelement, IterExprList};
+use crate::structs::wire::expand_wirelist;
+use crate::{Gate, Relation, Result, WireId};
+
+/// Options to render a relation in the Graphviz DOT language.
+#[derive(Clone, Debug, Default)]
+pub struct DotOptions {
+    /// In a dataflow graph, only draw the gates at this depth or less (gates without inputs are at
+    /// depth 0). In a summary graph, only draw loops, switches and anonymous calls nested at most
+    /// this deep in a function or in the main relation.
+    pub max_depth: Option<usize>,
+    /// Highlight the fan-in cone of this wire, i.e. all the top-level gates it depends on.
+    pub highlight: Option<WireId>,
+    /// The limits on the loops expanded to find the inputs of gates, and on the resources used to
+    /// compute the statistics of a summary graph.
+    pub limits: Limits,
+}
+
+const HIGHLIGHT_STYLE: &str = ", style=filled, fillcolor=lightcoral";
+
+/// A top-level gate in the dataflow graph.
+struct DataflowNode {
+    /// Index of the gate in the relation.
+    gate_index: usize,
+    label: String,
+    /// The input wires, with the node which produced them, if any.
+    inputs: Vec<(WireId, Option<usize>)>,
+    depth: usize,
+}
+
+/// Write a gate-level dataflow graph of the relation: each top-level gate is a node, and each edge
+/// is a wire going from the gate which produced it to a gate which uses it.
+/// Functions, loops and switches are drawn as a single node. This is meant for small flat circuits.
+pub fn write_dataflow_dot(
+    relation: &Relation,
+    options: &DotOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let (nodes, producers) = build_dataflow(relation, &options.limits)?;
+    let cone = match options.highlight {
+        Some(wire) => fan_in_cone(&nodes, &producers, wire)?,
+        None => BTreeSet::new(),
+    };
+    let visible = |node: usize| options.max_depth.map_or(true, |d| nodes[node].depth <= d);
+
+    writeln!(writer, "digraph dataflow {{")?;
+    writeln!(writer, "    node [shape=box, fontname=\"monospace\"];")?;
+    let mut undefined_wires = BTreeSet::new();
+    for (i, node) in nodes.iter().enumerate() {
+        if !visible(i) {
+            continue;
+        }
+        let style = if cone.contains(&i) {
+            HIGHLIGHT_STYLE
+        } else {
+            ""
+        };
+        writeln!(
+            writer,
+            "    g{} [label=\"{}\"{}];",
+            i,
+            escape(&node.label),
+            style
+        )?;
+        for (wire, producer) in node.inputs.iter() {
+            match producer {
+                Some(producer) => {
+                    if visible(*producer) {
+                        writeln!(writer, "    g{} -> g{} [label=\"${}\"];", producer, i, wire)?;
+                    }
+                }
+                None => {
+                    if undefined_wires.insert(*wire) {
+                        writeln!(
+                            writer,
+                            "    w{} [label=\"${}\", shape=plaintext];",
+                            wire, wire
+                        )?;
+                    }
+                    writeln!(writer, "    w{} -> g{};", wire, i)?;
+                }
+            }
+        }
+    }
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+/// Write a summary graph of the relation: the main relation and each function are nodes annotated
+/// with their `GateStats`, linked to the functions they call, and to the loops, switches and
+/// anonymous calls they contain.
+pub fn write_summary_dot(
+    relation: &Relation,
+    options: &DotOptions,
+    writer: &mut impl Write,
+) -> Result<()> {
+    // Invalid loops are reported before they are unrolled by the statistics.
+    for function in relation.functions.iter() {
+        check_loop_ranges(&function.body)?;
+    }
+    check_loop_ranges(&relation.gates)?;
+    let mut stats = Stats::default();
+    stats.set_limits(options.limits);
+    stats.ingest_relation(relation);
+    stats.check_limits()?;
+
+    // The highlighted cone is only known for top-level gates.
+    let cone = match options.highlight {
+        Some(wire) => {
+            let (nodes, producers) = build_dataflow(relation, &options.limits)?;
+            fan_in_cone(&nodes, &producers, wire)?
+                .into_iter()
+                .map(|i| nodes[i].gate_index)
+                .collect()
+        }
+        None => BTreeSet::new(),
+    };
+
+    writeln!(writer, "digraph summary {{")?;
+    writeln!(writer, "    node [shape=box, fontname=\"monospace\"];")?;
+
+    let mut summary = SummaryWriter {
+        writer,
+        functions: &stats.functions,
+        function_ids: HashMap::new(),
+        max_depth: options.max_depth,
+        next_id: 0,
+    };
+
+    for (i, function) in relation.functions.iter().enumerate() {
+        let id = format!("f{}", i);
+        let mut lines = vec![format!("@function {}", function.name)];
+        lines.push(format!(
+            "outputs: {}, inputs: {}, instance: {}, witness: {}",
+            function.output_count,
+            function.input_count,
+            function.instance_count,
+            function.witness_count
+        ));
+        if let Some((func_stats, _, _)) = stats.functions.get(&function.name) {
+            lines.extend(stats_lines(func_stats)?);
+        }
+        summary.node(&id, &lines, ", shape=component")?;
+        summary.function_ids.insert(function.name.clone(), id);
+    }
+
+    let mut lines = vec!["relation".to_string()];
+    lines.extend(stats_lines(&stats.gate_stats)?);
+    summary.node("main", &lines, ", shape=doubleoctagon")?;
+
+    for function in relation.functions.iter() {
+        let id = summary.function_ids[&function.name].clone();
+        summary.walk_body(&id, &function.body, 0, &BTreeSet::new())?;
+    }
+    summary.walk_body("main", &relation.gates, 0, &cone)?;
+
+    writeln!(summary.writer, "}}")?;
+    Ok(())
+}
+
+struct SummaryWriter<'a, W: Write> {
+    writer: &'a mut W,
+    functions: &'a HashMap<String, (GateStats, usize, usize)>,
+    /// Node id of each function.
+    function_ids: HashMap<String, String>,
+    max_depth: Option<usize>,
+    next_id: usize,
+}
+
+impl<'a, W: Write> SummaryWriter<'a, W> {
+    fn new_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    fn node(&mut self, id: &str, lines: &[String], attributes: &str) -> Result<()> {
+        let label: Vec<String> = lines.iter().map(|l| escape(l)).collect();
+        writeln!(
+            self.writer,
+            "    {} [label=\"{}\"{}];",
+            id,
+            label.join("\\n"),
+            attributes
+        )?;
+        Ok(())
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: &str, highlight: bool) -> Result<()> {
+        let style = if highlight { ", color=red" } else { "" };
+        writeln!(
+            self.writer,
+            "    {} -> {} [label=\"{}\"{}];",
+            from,
+            to,
+            escape(label),
+            style
+        )?;
+        Ok(())
+    }
+
+    /// Return the node id of a function, creating a placeholder if it is not defined.
+    fn function_id(&mut self, name: &str) -> Result<String> {
+        if let Some(id) = self.function_ids.get(name) {
+            return Ok(id.clone());
+        }
+        let id = self.new_id("u");
+        self.node(
+            &id,
+            &[format!("@function {}", name), "undefined".to_string()],
+            ", shape=component, style=dashed",
+        )?;
+        self.function_ids.insert(name.to_string(), id.clone());
+        Ok(id)
+    }
+
+    fn anon_node(
+        &mut self,
+        subcircuit: &[Gate],
+        instance_count: usize,
+        witness_count: usize,
+        highlight: bool,
+    ) -> Result<String> {
+        let id = self.new_id("a");
+        let mut lines = vec![
+            "@anon_call".to_string(),
+            format!("instance: {}, witness: {}", instance_count, witness_count),
+        ];
+        lines.extend(stats_lines(&ingest_subcircuit(
+            subcircuit,
+            self.functions,
+        )?)?);
+        let style = if highlight { HIGHLIGHT_STYLE } else { "" };
+        self.node(&id, &lines, style)?;
+        Ok(id)
+    }
+
+    fn visible(&self, depth: usize) -> bool {
+        self.max_depth.map_or(true, |max| depth <= max)
+    }
+
+    /// Draw the calls, loops, switches and anonymous calls found in `gates`, linked to the node
+    /// `parent` which is at the given nesting depth. `cone` contains the indices of highlighted gates.
+    fn walk_body(
+        &mut self,
+        parent: &str,
+        gates: &[Gate],
+        depth: usize,
+        cone: &BTreeSet<usize>,
+    ) -> Result<()> {
+        // Calls to the same function are merged into a single edge: name => (count, highlight).
+        let mut calls: BTreeMap<String, (usize, bool)> = BTreeMap::new();
+
+        for (i, gate) in gates.iter().enumerate() {
+            let highlight = cone.contains(&i);
+            match gate {
+                Gate::Call(name, _, _) => {
+                    let entry = calls.entry(name.clone()).or_insert((0, false));
+                    entry.0 += 1;
+                    entry.1 |= highlight;
+                }
+
+                Gate::AnonCall(_, _, instance_count, witness_count, subcircuit) => {
+                    if self.visible(depth + 1) {
+                        let id =
+                            self.anon_node(subcircuit, *instance_count, *witness_count, highlight)?;
+                        self.edge(parent, &id, "", highlight)?;
+                        self.walk_body(&id, subcircuit, depth + 1, &BTreeSet::new())?;
+                    }
+                }
+
+                Gate::Switch(condition, _, cases, branches) => {
+                    if !self.visible(depth + 1) {
+                        continue;
+                    }
+                    let id = self.new_id("s");
+                    let style = if highlight { HIGHLIGHT_STYLE } else { "" };
+                    self.node(
+                        &id,
+                        &[format!("@switch ${}", condition)],
+                        &format!(", shape=diamond{}", style),
+                    )?;
+                    self.edge(parent, &id, "", highlight)?;
+
+                    for (case, branch) in cases.iter().zip(branches.iter()) {
+                        let label = format!("case {}", BigUint::from_bytes_le(case));
+                        match branch {
+                            CaseInvoke::AbstractGateCall(name, _) => {
+                                let function_id = self.function_id(name)?;
+                                self.edge(&id, &function_id, &label, false)?;
+                            }
+                            CaseInvoke::AbstractAnonCall(
+                                _,
+                                instance_count,
+                                witness_count,
+                                subcircuit,
+                            ) => {
+                                if self.visible(depth + 2) {
+                                    let anon_id = self.anon_node(
+                                        subcircuit,
+                                        *instance_count,
+                                        *witness_count,
+                                        false,
+                                    )?;
+                                    self.edge(&id, &anon_id, &label, false)?;
+                                    self.walk_body(
+                                        &anon_id,
+                                        subcircuit,
+                                        depth + 2,
+                                        &BTreeSet::new(),
+                                    )?;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                Gate::For(iterator, start, end, _, body) => {
+                    if !self.visible(depth + 1) {
+                        continue;
+                    }
+                    let id = self.new_id("l");
+                    let style = if highlight { HIGHLIGHT_STYLE } else { "" };
+                    self.node(
+                        &id,
+                        &[format!("@for {} @from {} @to {}", iterator, start, end)],
+                        &format!(", shape=ellipse{}", style),
+                    )?;
+                    self.edge(parent, &id, "", highlight)?;
+
+                    let iterations = loop_len(*start, *end)?;
+                    let label = format!("x{}", iterations);
+                    match body {
+                        ForLoopBody::IterExprCall(name, _, _) => {
+                            let function_id = self.function_id(name)?;
+                            self.edge(&id, &function_id, &format!("call {}", label), false)?;
+                        }
+                        ForLoopBody::IterExprAnonCall(
+                            _,
+                            _,
+                            instance_count,
+                            witness_count,
+                            subcircuit,
+                        ) => {
+                            if self.visible(depth + 2) {
+                                let anon_id = self.anon_node(
+                                    subcircuit,
+                                    *instance_count,
+                                    *witness_count,
+                                    false,
+                                )?;
+                                self.edge(&id, &anon_id, &label, false)?;
+                                self.walk_body(&anon_id, subcircuit, depth + 2, &BTreeSet::new())?;
+                            }
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        for (name, (count, highlight)) in calls.iter() {
+            let function_id = self.function_id(name)?;
+            self.edge(
+                parent,
+                &function_id,
+                &format!("call x{}", count),
+                *highlight,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Build the nodes of the dataflow graph, and return the node which produced each wire last.
+fn build_dataflow(
+    relation: &Relation,
+    limits: &Limits,
+) -> Result<(Vec<DataflowNode>, HashMap<WireId, usize>)> {
+    let mut nodes: Vec<DataflowNode> = vec![];
+    let mut producers: HashMap<WireId, usize> = HashMap::new();
+
+    for (gate_index, gate) in relation.gates.iter().enumerate() {
+        if let Gate::Free(_, _) = gate {
+            continue;
+        }
+        let (label, outputs, inputs) = gate_io(gate, limits)?;
+
+        // Loops may read the outputs of their previous iterations, these are not drawn.
+        let inputs: Vec<(WireId, Option<usize>)> = inputs
+            .into_iter()
+            .filter(|wire| !outputs.contains(wire))
+            .map(|wire| (wire, producers.get(&wire).cloned()))
+            .collect();
+        let depth = inputs
+            .iter()
+            .filter_map(|(_, producer)| producer.map(|p| nodes[p].depth + 1))
+            .max()
+            .unwrap_or(0);
+
+        let node = nodes.len();
+        for wire in outputs {
+            producers.insert(wire, node);
+        }
+        nodes.push(DataflowNode {
+            gate_index,
+            label,
+            inputs,
+            depth,
+        });
+    }
+    Ok((nodes, producers))
+}
+
+/// Return the nodes on which the given wire depends, including the node which produced it.
+fn fan_in_cone(
+    nodes: &[DataflowNode],
+    producers: &HashMap<WireId, usize>,
+    wire: WireId,
+) -> Result<BTreeSet<usize>> {
+    let start = *producers
+        .get(&wire)
+        .ok_or_else(|| format!("The wire {} is not defined in the relation.", wire))?;
+
+    let mut cone = BTreeSet::new();
+    let mut to_visit = vec![start];
+    while let Some(node) = to_visit.pop() {
+        if cone.insert(node) {
+            to_visit.extend(
+                nodes[node]
+                    .inputs
+                    .iter()
+                    .filter_map(|(_, producer)| *producer),
+            );
+        }
+    }
+    Ok(cone)
+}
+
+/// Return a short description of a gate, its output wires, and its input wires.
+fn gate_io(gate: &Gate, limits: &Limits) -> Result<(String, Vec<WireId>, Vec<WireId>)> {
+    use Gate::*;
+
+    let binary = |name: &str, out: &WireId, left: &WireId, right: &WireId| {
+        (
+            format!("${} <- @{}(${}, ${})", out, name, left, right),
+            vec![*out],
+            vec![*left, *right],
+        )
+    };
+    let with_constant = |name: &str, out: &WireId, inp: &WireId, constant: &[u8]| {
+        (
+            format!(
+                "${} <- @{}(${}, <{}>)",
+                out,
+                name,
+                inp,
+                BigUint::from_bytes_le(constant)
+            ),
+            vec![*out],
+            vec![*inp],
+        )
+    };
+
+    Ok(match gate {
+        Constant(out, value) => (
+            format!("${} <- <{}>", out, BigUint::from_bytes_le(value)),
+            vec![*out],
+            vec![],
+        ),
+        AssertZero(inp) => (format!("@assert_zero(${})", inp), vec![], vec![*inp]),
+        Copy(out, inp) => (format!("${} <- ${}", out, inp), vec![*out], vec![*inp]),
+        Add(out, left, right) => binary("add", out, left, right),
+        Mul(out, left, right) => binary("mul", out, left, right),
+        And(out, left, right) => binary("and", out, left, right),
+        Xor(out, left, right) => binary("xor", out, left, right),
+        AddConstant(out, inp, constant) => with_constant("addc", out, inp, constant),
+        MulConstant(out, inp, constant) => with_constant("mulc", out, inp, constant),
+        Not(out, inp) => (
+            format!("${} <- @not(${})", out, inp),
+            vec![*out],
+            vec![*inp],
+        ),
+        Instance(out) => (format!("${} <- @instance", out), vec![*out], vec![]),
+        Witness(out) => (format!("${} <- @short_witness", out), vec![*out], vec![]),
+        Typed(type_id, gate) => {
+            let (label, outputs, inputs) = gate_io(gate, limits)?;
+            (format!("{} : {}", label, type_id), outputs, inputs)
+        }
+        Convert(out_type, outputs, in_type, inputs) => (
+            format!("@convert({} <- {})", out_type, in_type),
+            expand_wirelist(outputs)?,
+            expand_wirelist(inputs)?,
+        ),
+        Free(first, last) => (
+            match last {
+                Some(last) => format!("@free(${}..${})", first, last),
+                None => format!("@free(${})", first),
+            },
+            vec![],
+            vec![],
+        ),
+        AnonCall(outputs, inputs, _, _, _) => (
+            "@anon_call".to_string(),
+            expand_wirelist(outputs)?,
+            expand_wirelist(inputs)?,
+        ),
+        Call(name, outputs, inputs) => (
+            format!("@call({})", name),
+            expand_wirelist(outputs)?,
+            expand_wirelist(inputs)?,
+        ),
+        Switch(condition, outputs, _, branches) => {
+            let mut inputs = vec![*condition];
+            for branch in branches {
+                match branch {
+                    CaseInvoke::AbstractGateCall(_, branch_inputs) => {
+                        inputs.extend(expand_wirelist(branch_inputs)?)
+                    }
+                    CaseInvoke::AbstractAnonCall(branch_inputs, _, _, _) => {
+                        inputs.extend(expand_wirelist(branch_inputs)?)
+                    }
+                }
+            }
+            inputs.sort_unstable();
+            inputs.dedup();
+            (
+                format!("@switch(${})", condition),
+                expand_wirelist(outputs)?,
+                inputs,
+            )
+        }
+        For(iterator, start, end, outputs, body) => {
+            let body_inputs = match body {
+                ForLoopBody::IterExprCall(_, _, inputs) => inputs,
+                ForLoopBody::IterExprAnonCall(_, inputs, _, _, _) => inputs,
+            };
+            loop_len(*start, *end)?;
+            limits.check_loop(iterator, *start, *end)?;
+            let mut inputs = vec![];
+            for i in *start..=*end {
+                inputs.extend(loop_inputs(iterator, i, body_inputs)?);
+            }
+            inputs.sort_unstable();
+            inputs.dedup();
+            (
+                format!("@for {} @from {} @to {}", iterator, start, end),
+                expand_wirelist(outputs)?,
+                inputs,
+            )
+        }
+    })
+}
+
+/// Check the ranges of the loops in some gates, including in nested bodies.
+fn check_loop_ranges(gates: &[Gate]) -> Result<()> {
+    for gate in gates {
+        match gate {
+            Gate::For(_, start, end, _, body) => {
+                loop_len(*start, *end)?;
+                if let ForLoopBody::IterExprAnonCall(_, _, _, _, subcircuit) = body {
+                    check_loop_ranges(subcircuit)?;
+                }
+            }
+            Gate::AnonCall(_, _, _, _, subcircuit) => check_loop_ranges(subcircuit)?,
+            Gate::Switch(_, _, _, branches) => {
+                for branch in branches {
+                    if let CaseInvoke::AbstractAnonCall(_, _, _, subcircuit) = branch {
+                        check_loop_ranges(subcircuit)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn loop_inputs(iterator: &str, value: u64, inputs: &IterExprList) -> Result<Vec<WireId>> {
+    let mut known_iterators = HashMap::new();
+    known_iterators.insert(iterator.to_string(), value);
+    let mut wires = vec![];
+    for element in inputs {
+        wires.extend(evaluate_iterexpr_listelement(element, &known_iterators)?);
+    }
+    Ok(wires)
+}
+
+/// Return a line `name: count` for each non-zero counter of the stats.
+fn stats_lines(stats: &GateStats) -> Result<Vec<String>> {
+    let mut lines = vec![];
+    if let serde_json::Value::Object(fields) = serde_json::to_value(stats)? {
+        for (name, count) in fields {
+            if count.as_u64().unwrap_or(0) != 0 {
+                lines.push(format!("{}: {}", name, count));
+            }
+        }
+    }
+    Ok(lines)
+}
+
+/// Escape a string to be used in a quoted DOT label.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[test]
+fn test_to_dot() -> Result<()> {
+    use crate::producers::examples::*;
+
+    let relation = example_relation();
+
+    let mut dataflow = vec![];
+    write_dataflow_dot(&relation, &DotOptions::default(), &mut dataflow)?;
+    let dataflow = String::from_utf8(dataflow)?;
+    assert!(dataflow.starts_with("digraph dataflow {"));
+    assert!(dataflow.contains("$34 <- @add($9, $33)"));
+    assert!(!dataflow.contains("lightcoral"));
+
+    // The fan-in cone of the final AssertZero input contains the switch, but not the last loop.
+    let options = DotOptions {
+        max_depth: None,
+        highlight: Some(34),
+        ..DotOptions::default()
+    };
+    let mut dataflow = vec![];
+    write_dataflow_dot(&relation, &options, &mut dataflow)?;
+    let dataflow = String::from_utf8(dataflow)?;
+    let highlighted: Vec<&str> = dataflow
+        .lines()
+        .filter(|l| l.contains("lightcoral"))
+        .collect();
+    assert!(highlighted.iter().any(|l| l.contains("@switch($1)")));
+    assert!(!highlighted.iter().any(|l| l.contains("@for i @from 35")));
+
+    // Depth 0 only keeps the gates without inputs.
+    let options = DotOptions {
+        max_depth: Some(0),
+        highlight: None,
+        ..DotOptions::default()
+    };
+    let mut dataflow = vec![];
+    write_dataflow_dot(&relation, &options, &mut dataflow)?;
+    let dataflow = String::from_utf8(dataflow)?;
+    assert!(dataflow.contains("$1 <- @short_witness"));
+    assert!(!dataflow.contains("@add"));
+
+    let mut summary = vec![];
+    write_summary_dot(&relation, &DotOptions::default(), &mut summary)?;
+    let summary = String::from_utf8(summary)?;
+    assert!(summary.starts_with("digraph summary {"));
+    assert!(summary.contains("@function com.example::mul"));
+    assert!(summary.contains("@for i @from 35 @to 50"));
+    assert!(summary.contains("case 3"));
+    assert!(summary.contains("call x16"));
+
+    let options = DotOptions {
+        max_depth: Some(0),
+        highlight: None,
+        ..DotOptions::default()
+    };
+    let mut summary = vec![];
+    write_summary_dot(&relation, &options, &mut summary)?;
+    let summary = String::from_utf8(summary)?;
+    assert!(!summary.contains("@switch"));
+    assert!(summary.contains("call x1"));
+
+    // Empty or overflowing loop ranges are reported.
+    for &(start, end) in &[(2, 1), (0, u64::MAX)] {
+        let mut relation = example_relation();
+        relation.gates.push(Gate::For(
+            "k".into(),
+            start,
+            end,
+            vec![],
+            ForLoopBody::IterExprAnonCall(vec![], vec![], 0, 0, vec![]),
+        ));
+        let err =
+            write_summary_dot(&relation, &DotOptions::default(), &mut std::io::sink()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Invalid loop range from {} to {}.", start, end)
+        );
+    }
+
+    // Long loops are stopped by the limits, in both graphs.
+    let mut relation = example_relation();
+    relation.gates.push(Gate::For(
+        "k".into(),
+        0,
+        1 << 40,
+        vec![],
+        ForLoopBody::IterExprAnonCall(vec![], vec![], 0, 0, vec![]),
+    ));
+    let options = DotOptions {
+        limits: Limits {
+            max_loop_iterations: 1000,
+            ..Limits::default()
+        },
+        ..DotOptions::default()
+    };
+    let expected = format!(
+        "The loop over k has {} iterations, more than the limit of 1000 (max_loop_iterations).",
+        (1u64 << 40) + 1
+    );
+    let err = write_summary_dot(&relation, &options, &mut std::io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+    let err = write_dataflow_dot(&relation, &options, &mut std::io::sink()).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+
+    Ok(())
+}
@@ -0,0 +1,637 @@
+use crate::consumers::evaluator::ZKBackend;
+use crate::Result;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+/// Index of a variable in a `PlonkTable`. A variable may be placed in several cells, which are
+/// then linked by the copy-constraint permutation.
+pub type PlonkVariable = u64;
+
+/// Names of the three advice columns, as used in the file format.
+pub const PLONK_COLUMNS: [&str; 3] = ["a", "b", "c"];
+
+/// A row of the table enforces the custom gate
+///     qL·a + qR·b + qO·c + qM·a·b + qC = 0
+/// A cell which is `None` is not used by the row; its value is 0 and it is not part of any copy
+/// constraint.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlonkRow {
+    pub q_l: BigUint,
+    pub q_r: BigUint,
+    pub q_o: BigUint,
+    pub q_m: BigUint,
+    pub q_c: BigUint,
+    pub cells: [Option<PlonkVariable>; 3],
+}
+
+/// A cell of the table, identified by its row and column (0 for a, 1 for b, 2 for c).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct PlonkCell {
+    pub row: usize,
+    pub column: usize,
+}
+
+/// PlonkTable is the circuit part of a Plonkish constraint system: the selectors of each row, and
+/// the assignment of variables to cells from which the copy-constraint permutation is derived.
+///
+/// # File format
+/// The table is written as text, one item per line, numbers in decimal:
+/// ```text
+/// plonk-table 1
+/// field <modulus>
+/// rows <number of rows>
+/// row <qL> <qR> <qO> <qM> <qC>
+/// ...
+/// copy <row>.<column> <row>.<column> ...
+/// ...
+/// ```
+/// Selectors are field elements in `[0, modulus)`. Each `copy` line is a cycle of the permutation,
+/// i.e. a list of at least 2 cells which must hold the same value. Columns are named `a`, `b`, `c`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlonkTable {
+    pub modulus: BigUint,
+    pub rows: Vec<PlonkRow>,
+    pub copies: Vec<Vec<PlonkCell>>,
+}
+
+/// PlonkWitness assigns a value to each cell of a `PlonkTable`.
+///
+/// # File format
+/// ```text
+/// plonk-witness 1
+/// field <modulus>
+/// rows <number of rows>
+/// <a> <b> <c>
+/// ...
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlonkWitness {
+    pub modulus: BigUint,
+    pub rows: Vec<[BigUint; 3]>,
+}
+
+impl fmt::Display for PlonkCell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.row, PLONK_COLUMNS[self.column])
+    }
+}
+
+impl FromStr for PlonkCell {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (row, column) = s
+            .split_once('.')
+            .ok_or_else(|| format!("Invalid cell {}.", s))?;
+        let column = PLONK_COLUMNS
+            .iter()
+            .position(|c| *c == column)
+            .ok_or_else(|| format!("Invalid column in cell {}.", s))?;
+        Ok(PlonkCell {
+            row: row.parse()?,
+            column,
+        })
+    }
+}
+
+impl PlonkTable {
+    pub fn write_into(&self, writer: &mut impl Write) -> Result<()> {
+        writeln!(writer, "plonk-table 1")?;
+        writeln!(writer, "field {}", self.modulus)?;
+        writeln!(writer, "rows {}", self.rows.len())?;
+        for row in self.rows.iter() {
+            writeln!(
+                writer,
+                "row {} {} {} {} {}",
+                row.q_l, row.q_r, row.q_o, row.q_m, row.q_c
+            )?;
+        }
+        for cycle in self.copies.iter() {
+            let cells: Vec<String> = cycle.iter().map(|cell| cell.to_string()).collect();
+            writeln!(writer, "copy {}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+
+    /// Read a table written by `write_into`. The variables of the cells are rebuilt from the copy
+    /// constraints.
+    pub fn read_from(reader: &mut impl BufRead) -> Result<PlonkTable> {
+        let mut lines = TextLines::new(reader, "plonk-table")?;
+        let modulus = lines.expect_field()?;
+        let n_rows = lines.expect_rows()?;
+
+        let mut table = PlonkTable {
+            modulus,
+            ..PlonkTable::default()
+        };
+        while let Some(tokens) = lines.next_tokens()? {
+            match tokens[0].as_str() {
+                "row" => {
+                    if tokens.len() != 6 {
+                        return Err(format!("Invalid row line: {}", tokens.join(" ")).into());
+                    }
+                    let q: Vec<BigUint> = tokens[1..]
+                        .iter()
+                        .map(|t| BigUint::from_str(t))
+                        .collect::<std::result::Result<_, _>>()?;
+                    table.rows.push(PlonkRow {
+                        q_l: q[0].clone(),
+                        q_r: q[1].clone(),
+                        q_o: q[2].clone(),
+                        q_m: q[3].clone(),
+                        q_c: q[4].clone(),
+                        cells: [None, None, None],
+                    });
+                }
+                "copy" => {
+                    let cycle = tokens[1..]
+                        .iter()
+                        .map(|t| PlonkCell::from_str(t))
+                        .collect::<Result<Vec<_>>>()?;
+                    table.copies.push(cycle);
+                }
+                other => return Err(format!("Unknown line type {}.", other).into()),
+            }
+        }
+        if table.rows.len() != n_rows {
+            return Err(format!("Expected {} rows but found {}.", n_rows, table.rows.len()).into());
+        }
+
+        let copies = table.copies.clone();
+        for (var, cycle) in copies.iter().enumerate() {
+            for cell in cycle {
+                let row = table
+                    .rows
+                    .get_mut(cell.row)
+                    .ok_or_else(|| format!("The cell {} is out of the table.", cell))?;
+                row.cells[cell.column] = Some(var as PlonkVariable);
+            }
+        }
+        Ok(table)
+    }
+
+    /// Compute the cycles of the copy-constraint permutation from the variables in the cells.
+    /// Variables used in a single cell do not create a cycle.
+    pub fn compute_copies(&mut self) {
+        let mut cells_per_var: BTreeMap<PlonkVariable, Vec<PlonkCell>> = BTreeMap::new();
+        for (row_id, row) in self.rows.iter().enumerate() {
+            for (column, var) in row.cells.iter().enumerate() {
+                if let Some(var) = var {
+                    cells_per_var.entry(*var).or_default().push(PlonkCell {
+                        row: row_id,
+                        column,
+                    });
+                }
+            }
+        }
+        self.copies = cells_per_var
+            .into_values()
+            .filter(|cells| cells.len() > 1)
+            .collect();
+    }
+
+    /// Check that the witness satisfies all the rows and the copy constraints.
+    /// Return the list of violations, empty if the witness is valid.
+    pub fn check(&self, witness: &PlonkWitness) -> Vec<String> {
+        let mut violations = vec![];
+        if self.modulus != witness.modulus {
+            violations.push(format!(
+                "The table is defined over the field {} but the witness over {}.",
+                self.modulus, witness.modulus
+            ));
+            return violations;
+        }
+        if self.rows.len() != witness.rows.len() {
+            violations.push(format!(
+                "The table has {} rows but the witness has {}.",
+                self.rows.len(),
+                witness.rows.len()
+            ));
+            return violations;
+        }
+
+        let m = &self.modulus;
+        for (row_id, (row, values)) in self.rows.iter().zip(witness.rows.iter()).enumerate() {
+            let [a, b, c] = values;
+            let sum = &row.q_l * a + &row.q_r * b + &row.q_o * c + &row.q_m * a * b + &row.q_c;
+            if !(sum % m).is_zero() {
+                violations.push(format!("The row {} is not satisfied.", row_id));
+            }
+        }
+
+        for cycle in self.copies.iter() {
+            let mut values = vec![];
+            for cell in cycle.iter() {
+                match witness.rows.get(cell.row) {
+                    Some(row) => values.push(&row[cell.column] % m),
+                    None => violations.push(format!("The cell {} is out of the table.", cell)),
+                }
+            }
+            if values.windows(2).any(|w| w[0] != w[1]) {
+                let cells: Vec<String> = cycle.iter().map(|cell| cell.to_string()).collect();
+                violations.push(format!(
+                    "The copy constraint between {} is not satisfied.",
+                    cells.join(", ")
+                ));
+            }
+        }
+        violations
+    }
+}
+
+impl PlonkWitness {
+    pub fn write_into(&self, writer: &mut impl Write) -> Result<()> {
+        writeln!(writer, "plonk-witness 1")?;
+        writeln!(writer, "field {}", self.modulus)?;
+        writeln!(writer, "rows {}", self.rows.len())?;
+        for [a, b, c] in self.rows.iter() {
+            writeln!(writer, "{} {} {}", a, b, c)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl BufRead) -> Result<PlonkWitness> {
+        let mut lines = TextLines::new(reader, "plonk-witness")?;
+        let modulus = lines.expect_field()?;
+        let n_rows = lines.expect_rows()?;
+
+        let mut witness = PlonkWitness {
+            modulus,
+            rows: vec![],
+        };
+        while let Some(tokens) = lines.next_tokens()? {
+            if tokens.len() != 3 {
+                return Err(format!("Invalid witness line: {}", tokens.join(" ")).into());
+            }
+            witness.rows.push([
+                BigUint::from_str(&tokens[0])?,
+                BigUint::from_str(&tokens[1])?,
+                BigUint::from_str(&tokens[2])?,
+            ]);
+        }
+        if witness.rows.len() != n_rows {
+            return Err(
+                format!("Expected {} rows but found {}.", n_rows, witness.rows.len()).into(),
+            );
+        }
+        Ok(witness)
+    }
+}
+
+/// Helper to parse the line-oriented text formats above.
+struct TextLines<'a, R: BufRead> {
+    reader: &'a mut R,
+}
+
+impl<'a, R: BufRead> TextLines<'a, R> {
+    fn new(reader: &'a mut R, magic: &str) -> Result<Self> {
+        let mut lines = TextLines { reader };
+        match lines.next_tokens()? {
+            Some(tokens) if tokens.len() == 2 && tokens[0] == magic && tokens[1] == "1" => {
+                Ok(lines)
+            }
+            _ => Err(format!("Expected a '{} 1' file.", magic).into()),
+        }
+    }
+
+    /// Return the tokens of the next non-empty line.
+    fn next_tokens(&mut self) -> Result<Option<Vec<String>>> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let tokens: Vec<String> = line.split_whitespace().map(String::from).collect();
+            if !tokens.is_empty() {
+                return Ok(Some(tokens));
+            }
+        }
+    }
+
+    fn expect_value(&mut self, key: &str) -> Result<String> {
+        match self.next_tokens()? {
+            Some(tokens) if tokens.len() == 2 && tokens[0] == key => Ok(tokens[1].clone()),
+            _ => Err(format!("Expected a '{}' line.", key).into()),
+        }
+    }
+
+    fn expect_field(&mut self) -> Result<BigUint> {
+        Ok(BigUint::from_str(&self.expect_value("field")?)?)
+    }
+
+    fn expect_rows(&mut self) -> Result<usize> {
+        Ok(self.expect_value("rows")?.parse()?)
+    }
+}
+
+/// ToPlonkConverter is a `ZKBackend` lowering a circuit into a `PlonkTable`. Since the `Evaluator`
+/// unrolls functions, loops and switches, the table corresponds to the flattened circuit.
+///
+/// Each Add, Mul, Constant, AssertZero and Instance gate becomes one row. AddConstant and
+/// MulConstant are packed into the selectors of their row (qC and qL respectively). Copy, and
+/// the boolean gates mapped on their arithmetic counterparts, do not create any additional row.
+/// Witness values are tracked if `use_witness` is true, so that a `PlonkWitness` can be produced.
+pub struct ToPlonkConverter {
+    table: PlonkTable,
+    use_witness: bool,
+    /// Value of each variable, only used when `use_witness` is true.
+    values: Vec<BigUint>,
+    variable_count: PlonkVariable,
+}
+
+impl ToPlonkConverter {
+    pub fn new(use_witness: bool) -> Self {
+        ToPlonkConverter {
+            table: PlonkTable::default(),
+            use_witness,
+            values: vec![],
+            variable_count: 0,
+        }
+    }
+
+    /// Return the table, with its copy constraints, and the witness if it was tracked.
+    pub fn finish(mut self) -> (PlonkTable, Option<PlonkWitness>) {
+        self.table.compute_copies();
+
+        let witness = if self.use_witness {
+            let values = &self.values;
+            let value_of = |cell: &Option<PlonkVariable>| match cell {
+                Some(var) => values[*var as usize].clone(),
+                None => BigUint::zero(),
+            };
+            Some(PlonkWitness {
+                modulus: self.table.modulus.clone(),
+                rows: self
+                    .table
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        [
+                            value_of(&row.cells[0]),
+                            value_of(&row.cells[1]),
+                            value_of(&row.cells[2]),
+                        ]
+                    })
+                    .collect(),
+            })
+        } else {
+            None
+        };
+        (self.table, witness)
+    }
+
+    fn new_variable(&mut self, value: Option<BigUint>) -> Result<PlonkVariable> {
+        if self.use_witness {
+            let value = value.ok_or("The value should have been given.")?;
+            self.values.push(value % &self.table.modulus);
+        }
+        let var = self.variable_count;
+        self.variable_count += 1;
+        Ok(var)
+    }
+
+    /// Return the value of the variable, if witness values are tracked.
+    fn value(&self, var: &PlonkVariable) -> Option<&BigUint> {
+        self.values.get(*var as usize)
+    }
+
+    fn neg(&self, val: &BigUint) -> BigUint {
+        let m = &self.table.modulus;
+        (m - (val % m)) % m
+    }
+
+    fn push_row(
+        &mut self,
+        [q_l, q_r, q_o, q_m, q_c]: [BigUint; 5],
+        cells: [Option<PlonkVariable>; 3],
+    ) {
+        self.table.rows.push(PlonkRow {
+            q_l,
+            q_r,
+            q_o,
+            q_m,
+            q_c,
+            cells,
+        });
+    }
+}
+
+impl ZKBackend for ToPlonkConverter {
+    type Wire = PlonkVariable;
+    type FieldElement = BigUint;
+
+    fn from_bytes_le(val: &[u8]) -> Result<Self::FieldElement> {
+        Ok(BigUint::from_bytes_le(val))
+    }
+
+    fn set_field(&mut self, modulus: &[u8], degree: u32, _is_boolean: bool) -> Result<()> {
+        self.table.modulus = BigUint::from_bytes_le(modulus);
+        if self.table.modulus.is_zero() {
+            Err("Modulus cannot be zero.".into())
+        } else if degree != 1 {
+            Err("Degree higher than 1 is not supported".into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn one(&self) -> Result<Self::FieldElement> {
+        Ok(BigUint::one())
+    }
+
+    fn minus_one(&self) -> Result<Self::FieldElement> {
+        if self.table.modulus.is_zero() {
+            return Err("Modulus is not initiated, used `set_field()` before calling.".into());
+        }
+        Ok(&self.table.modulus - self.one()?)
+    }
+
+    fn zero(&self) -> Result<Self::FieldElement> {
+        Ok(BigUint::zero())
+    }
+
+    fn copy(&mut self, wire: &Self::Wire) -> Result<Self::Wire> {
+        Ok(*wire)
+    }
+
+    fn constant(&mut self, val: Self::FieldElement) -> Result<Self::Wire> {
+        // a - val = 0
+        let out = self.new_variable(Some(val.clone()))?;
+        let neg_val = self.neg(&val);
+        self.push_row(
+            [one(), zero(), zero(), zero(), neg_val],
+            [Some(out), None, None],
+        );
+        Ok(out)
+    }
+
+    fn assert_zero(&mut self, wire: &Self::Wire) -> Result<()> {
+        self.push_row(
+            [one(), zero(), zero(), zero(), zero()],
+            [Some(*wire), None, None],
+        );
+        match self.value(wire) {
+            Some(val) if !val.is_zero() => Err("AssertZero failed".into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn add(&mut self, a: &Self::Wire, b: &Self::Wire) -> Result<Self::Wire> {
+        // a + b - c = 0
+        let val = match (self.value(a), self.value(b)) {
+            (Some(a), Some(b)) => Some(a + b),
+            _ => None,
+        };
+        let out = self.new_variable(val)?;
+        let minus_one = self.minus_one()?;
+        self.push_row(
+            [one(), one(), minus_one, zero(), zero()],
+            [Some(*a), Some(*b), Some(out)],
+        );
+        Ok(out)
+    }
+
+    fn multiply(&mut self, a: &Self::Wire, b: &Self::Wire) -> Result<Self::Wire> {
+        // a·b - c = 0
+        let val = match (self.value(a), self.value(b)) {
+            (Some(a), Some(b)) => Some(a * b),
+            _ => None,
+        };
+        let out = self.new_variable(val)?;
+        let minus_one = self.minus_one()?;
+        self.push_row(
+            [zero(), zero(), minus_one, one(), zero()],
+            [Some(*a), Some(*b), Some(out)],
+        );
+        Ok(out)
+    }
+
+    fn add_constant(&mut self, a: &Self::Wire, b: Self::FieldElement) -> Result<Self::Wire> {
+        // a - c + b = 0
+        let val = self.value(a).map(|a| a + &b);
+        let out = self.new_variable(val)?;
+        let minus_one = self.minus_one()?;
+        let b = b % &self.table.modulus;
+        self.push_row(
+            [one(), zero(), minus_one, zero(), b],
+            [Some(*a), None, Some(out)],
+        );
+        Ok(out)
+    }
+
+    fn mul_constant(&mut self, a: &Self::Wire, b: Self::FieldElement) -> Result<Self::Wire> {
+        // b·a - c = 0
+        let val = self.value(a).map(|a| a * &b);
+        let out = self.new_variable(val)?;
+        let minus_one = self.minus_one()?;
+        let b = b % &self.table.modulus;
+        self.push_row(
+            [b, zero(), minus_one, zero(), zero()],
+            [Some(*a), None, Some(out)],
+        );
+        Ok(out)
+    }
+
+    fn and(&mut self, a: &Self::Wire, b: &Self::Wire) -> Result<Self::Wire> {
+        self.multiply(a, b)
+    }
+
+    fn xor(&mut self, a: &Self::Wire, b: &Self::Wire) -> Result<Self::Wire> {
+        self.add(a, b)
+    }
+
+    fn not(&mut self, a: &Self::Wire) -> Result<Self::Wire> {
+        self.add_constant(a, self.one()?)
+    }
+
+    fn instance(&mut self, val: Self::FieldElement) -> Result<Self::Wire> {
+        // Instance values are known to the verifier, so they are fixed like constants.
+        self.constant(val)
+    }
+
+    fn witness(&mut self, val: Option<Self::FieldElement>) -> Result<Self::Wire> {
+        if self.use_witness == val.is_none() {
+            return Err("Inconsistency.".into());
+        }
+        self.new_variable(val)
+    }
+}
+
+fn one() -> BigUint {
+    BigUint::one()
+}
+
+fn zero() -> BigUint {
+    BigUint::zero()
+}
+
+#[test]
+fn test_to_plonk() -> Result<()> {
+    use crate::consumers::evaluator::Evaluator;
+    use crate::producers::examples::*;
+    use crate::Message;
+
+    let messages = vec![
+        Ok(Message::Instance(example_instance())),
+        Ok(Message::Witness(example_witness())),
+        Ok(Message::Relation(example_relation())),
+    ];
+    let mut to_plonk = ToPlonkConverter::new(true);
+    let evaluator = Evaluator::from_messages(messages.into_iter(), &mut to_plonk);
+    assert_eq!(evaluator.get_violations(), Vec::<String>::new());
+
+    let (table, witness) = to_plonk.finish();
+    let witness = witness.unwrap();
+    assert_eq!(table.modulus, BigUint::from(EXAMPLE_MODULUS));
+    assert!(!table.copies.is_empty());
+    assert_eq!(table.check(&witness), Vec::<String>::new());
+
+    // Round-trip through the file formats.
+    let mut buf = vec![];
+    table.write_into(&mut buf)?;
+    let table2 = PlonkTable::read_from(&mut &buf[..])?;
+    assert_eq!(table2.rows.len(), table.rows.len());
+    assert_eq!(table2.copies, table.copies);
+    let mut buf = vec![];
+    witness.write_into(&mut buf)?;
+    let witness2 = PlonkWitness::read_from(&mut &buf[..])?;
+    assert_eq!(witness2, witness);
+    assert_eq!(table2.check(&witness2), Vec::<String>::new());
+
+    // The number of rows announced by a file is not trusted.
+    let forged = "plonk-witness 1\nfield 101\nrows 1000000000000000\n1 2 3\n";
+    assert_eq!(
+        PlonkWitness::read_from(&mut forged.as_bytes())
+            .unwrap_err()
+            .to_string(),
+        "Expected 1000000000000000 rows but found 1."
+    );
+
+    // Break a copy constraint.
+    let mut bad_witness = witness;
+    let cell = table.copies[0][0];
+    bad_witness.rows[cell.row][cell.column] += 1u32;
+    assert!(!table.check(&bad_witness).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_to_plonk_incorrect_witness() {
+    use crate::consumers::evaluator::Evaluator;
+    use crate::producers::examples::*;
+    use crate::Message;
+
+    let messages = vec![
+        Ok(Message::Instance(example_instance())),
+        Ok(Message::Witness(example_witness_incorrect())),
+        Ok(Message::Relation(example_relation())),
+    ];
+    let mut to_plonk = ToPlonkConverter::new(true);
+    let evaluator = Evaluator::from_messages(messages.into_iter(), &mut to_plonk);
+    assert_eq!(evaluator.get_violations().len(), 1);
+}
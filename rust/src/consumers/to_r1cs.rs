@@ -80,13 +80,14 @@ impl<S: Sink> ToR1CSConverter<S> {
         Ok(())
     }
 
-    pub fn finish(mut self) -> Result<()> {
+    /// Flush the remaining messages and return the sink.
+    pub fn finish(mut self) -> Result<S> {
         self.builder.finish_header()?;
         self.builder.push_constraints(self.constraints)?;
         if self.use_witness {
             self.builder.push_witness(self.witnesses.to_owned())?;
         }
-        Ok(())
+        Ok(self.builder.sink)
     }
 }
 
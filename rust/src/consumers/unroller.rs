@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::consumers::limits::{Budget, Limits};
+use crate::structs::call_graph::CallGraph;
+use crate::structs::function::{CaseInvoke, ForLoopBody};
+use crate::structs::wire::{WireList, WireListView, WireRanges};
+use crate::{Gate, Result, WireId};
+
+/// Map the wires of a body (the relation, or the body of a call) to what a `GateVisitor` knows
+/// about them.
+pub(crate) type Scope<E> = HashMap<WireId, E>;
+
+/// A body called by a gate, see `GateVisitor::body_context`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Body<'a> {
+    Function(&'a str),
+    AnonCall,
+    SwitchBranch(usize),
+    Loop(&'a str),
+}
+
+/// A GateVisitor processes the gates given by an `Unroller`, and decides what to remember about
+/// each wire.
+pub(crate) trait GateVisitor {
+    /// What is known about a wire.
+    type Entry: Copy;
+    /// What is known about the body being visited, e.g. where it is in the circuit.
+    type Context;
+
+    /// The name of the visitor in warnings.
+    const NAME: &'static str;
+
+    /// Process a gate other than `Typed`, `Free`, `AnonCall`, `Call` and `For`, which the
+    /// Unroller handles. The branches of a switch go through `Unroller::call_branch`.
+    fn visit_gate(
+        &mut self,
+        unroller: &mut Unroller<Self::Entry>,
+        scope: &mut Scope<Self::Entry>,
+        gate: &Gate,
+        gate_index: usize,
+        context: &Self::Context,
+    );
+
+    /// The context of a body called by the gate `gate_index` of the body of `context`.
+    fn body_context(
+        &mut self,
+        context: &Self::Context,
+        gate_index: usize,
+        body: Body,
+    ) -> Self::Context;
+
+    /// Give the outputs of a call to the wires of the caller.
+    fn assign_outputs(
+        &mut self,
+        unroller: &mut Unroller<Self::Entry>,
+        scope: &mut Scope<Self::Entry>,
+        outputs: impl WireRanges,
+        results: Vec<Option<Self::Entry>>,
+        context: &Self::Context,
+    );
+
+    /// The entry of an input of a call, in the scope of the body.
+    fn input_entry(&self, entry: Self::Entry) -> Self::Entry {
+        entry
+    }
+
+    /// Called when a wire is freed, or dies at the end of a body.
+    fn release(&mut self, _entry: Self::Entry) {}
+}
+
+/// Unroller goes through the gates as if functions were inlined and loops unrolled, with a scope
+/// of wires for each body, and gives the other gates to a `GateVisitor`.
+///
+/// It stops at the first exceeded limit or recursive function, see `check_limits`.
+#[derive(Clone, Debug)]
+pub(crate) struct Unroller<E> {
+    // The scope of the relation, kept between messages.
+    pub(crate) scope: Scope<E>,
+    // Function name => output_count / body
+    pub(crate) functions: HashMap<String, Rc<(usize, Vec<Gate>)>>,
+    // The calls between the functions, updated with the functions of each message.
+    call_graph: CallGraph,
+    pub(crate) budget: Budget,
+    // The first limit exceeded, after which gates are ignored.
+    pub(crate) error: Option<String>,
+}
+
+impl<E> Default for Unroller<E> {
+    fn default() -> Self {
+        Unroller {
+            scope: Scope::new(),
+            functions: HashMap::new(),
+            call_graph: CallGraph::default(),
+            budget: Budget::default(),
+            error: None,
+        }
+    }
+}
+
+impl<E: Copy> Unroller<E> {
+    /// Set the limits on the resources used to go through the circuit.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Budget::new(limits);
+    }
+
+    /// Return an error if a limit was exceeded or a function is recursive.
+    pub fn check_limits(&self) -> Result<()> {
+        match &self.error {
+            Some(err) => Err(err.clone().into()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn define_function(&mut self, name: String, output_count: usize, body: Vec<Gate>) {
+        self.call_graph.add_function(&name, &body);
+        self.functions.insert(name, Rc::new((output_count, body)));
+    }
+
+    /// Recursive functions cannot be unrolled. The functions defined before are not recursive, so
+    /// only the cycles through the new functions are searched.
+    pub fn check_recursion<'a>(
+        &self,
+        new_functions: impl IntoIterator<Item = &'a String>,
+    ) -> Result<()> {
+        self.call_graph.check_recursion_from(new_functions)
+    }
+
+    /// Go through gates of the relation, the first of which is the gate `first_index`.
+    pub fn ingest_relation_gates<V: GateVisitor<Entry = E>>(
+        &mut self,
+        visitor: &mut V,
+        gates: &[Gate],
+        first_index: usize,
+        context: &V::Context,
+    ) {
+        let mut scope = std::mem::take(&mut self.scope);
+        for (i, gate) in gates.iter().enumerate() {
+            self.ingest_gate(visitor, &mut scope, gate, first_index + i, context);
+        }
+        self.scope = scope;
+    }
+
+    fn ingest_gate<V: GateVisitor<Entry = E>>(
+        &mut self,
+        visitor: &mut V,
+        scope: &mut Scope<E>,
+        gate: &Gate,
+        gate_index: usize,
+        context: &V::Context,
+    ) {
+        use Gate::*;
+
+        if self.error.is_some() {
+            return;
+        }
+        // The wires of all types are processed together.
+        if let Typed(_, gate) = gate {
+            self.ingest_gate(visitor, scope, gate, gate_index, context);
+            return;
+        }
+        if let Err(err) = self.budget.spend_gates(1) {
+            self.fail(err);
+            return;
+        }
+
+        match gate {
+            Free(first, last) => {
+                let last = last.unwrap_or(*first);
+                if let Err(err) = self.budget.limits.check_wire_range(*first, last) {
+                    self.fail(err);
+                    return;
+                }
+                // Large ranges cost the size of the scope, not the number of wires.
+                let freed: Vec<WireId> = if last.saturating_sub(*first) >= scope.len() as u64 {
+                    scope
+                        .keys()
+                        .filter(|wire| (*first..=last).contains(*wire))
+                        .copied()
+                        .collect()
+                } else {
+                    (*first..=last).collect()
+                };
+                for wire in freed {
+                    if let Some(entry) = scope.remove(&wire) {
+                        visitor.release(entry);
+                    }
+                }
+            }
+
+            AnonCall(output_wires, input_wires, _, _, subcircuit) => {
+                let (outputs, inputs) = match self.wire_views(output_wires, input_wires) {
+                    Some(wires) => wires,
+                    None => return,
+                };
+                let body_context = visitor.body_context(context, gate_index, Body::AnonCall);
+                let results = self.call(
+                    visitor,
+                    scope,
+                    outputs.len(),
+                    inputs,
+                    subcircuit,
+                    &body_context,
+                );
+                visitor.assign_outputs(self, scope, outputs, results, context);
+            }
+
+            Call(name, output_wires, input_wires) => {
+                let (outputs, inputs) = match self.wire_views(output_wires, input_wires) {
+                    Some(wires) => wires,
+                    None => return,
+                };
+                let results = self.call_function(visitor, scope, name, inputs, gate_index, context);
+                visitor.assign_outputs(self, scope, outputs, results, context);
+            }
+
+            For(iterator_name, start_val, end_val, _, body) => {
+                if let Err(err) = self
+                    .budget
+                    .limits
+                    .check_loop(iterator_name, *start_val, *end_val)
+                {
+                    self.fail(err);
+                    return;
+                }
+                let (output_wires, input_wires) = match body {
+                    ForLoopBody::IterExprCall(_, output_wires, input_wires) => {
+                        (output_wires, input_wires)
+                    }
+                    ForLoopBody::IterExprAnonCall(output_wires, input_wires, _, _, _) => {
+                        (output_wires, input_wires)
+                    }
+                };
+
+                let mut body_context = None;
+                let mut known_iterators = HashMap::new();
+                for i in *start_val..=*end_val {
+                    if self.error.is_some() {
+                        break;
+                    }
+                    known_iterators.insert(iterator_name.clone(), i);
+                    let limits = self.budget.limits;
+                    let views = limits
+                        .iterexpr_list_view(output_wires, &known_iterators)
+                        .and_then(|outputs| {
+                            let inputs =
+                                limits.iterexpr_list_view(input_wires, &known_iterators)?;
+                            Ok((outputs, inputs))
+                        });
+                    let (outputs, inputs) = match views {
+                        Ok(views) => views,
+                        Err(err) => {
+                            self.fail(err);
+                            break;
+                        }
+                    };
+
+                    let results = match body {
+                        ForLoopBody::IterExprCall(name, _, _) => {
+                            self.call_function(visitor, scope, name, inputs, gate_index, context)
+                        }
+                        ForLoopBody::IterExprAnonCall(_, _, _, _, subcircuit) => {
+                            let body_context = body_context.get_or_insert_with(|| {
+                                visitor.body_context(context, gate_index, Body::Loop(iterator_name))
+                            });
+                            self.call(
+                                visitor,
+                                scope,
+                                outputs.len(),
+                                inputs,
+                                subcircuit,
+                                body_context,
+                            )
+                        }
+                    };
+                    visitor.assign_outputs(self, scope, outputs, results, context);
+                }
+            }
+
+            _ => visitor.visit_gate(self, scope, gate, gate_index, context),
+        }
+    }
+
+    /// Go through a branch of a switch of the gate `gate_index`, given with its index. Return the
+    /// outputs of the branch, or None if its inputs are invalid.
+    pub fn call_branch<V: GateVisitor<Entry = E>>(
+        &mut self,
+        visitor: &mut V,
+        scope: &Scope<E>,
+        (branch_index, branch): (usize, &CaseInvoke),
+        output_count: usize,
+        gate_index: usize,
+        context: &V::Context,
+    ) -> Option<Vec<Option<E>>> {
+        match branch {
+            CaseInvoke::AbstractGateCall(name, input_wires) => {
+                let inputs = self.wirelist_view(input_wires)?;
+                Some(self.call_function(visitor, scope, name, inputs, gate_index, context))
+            }
+            CaseInvoke::AbstractAnonCall(input_wires, _, _, subcircuit) => {
+                let inputs = self.wirelist_view(input_wires)?;
+                let body_context =
+                    visitor.body_context(context, gate_index, Body::SwitchBranch(branch_index));
+                Some(self.call(
+                    visitor,
+                    scope,
+                    output_count,
+                    inputs,
+                    subcircuit,
+                    &body_context,
+                ))
+            }
+        }
+    }
+
+    fn call_function<V: GateVisitor<Entry = E>>(
+        &mut self,
+        visitor: &mut V,
+        caller: &Scope<E>,
+        name: &str,
+        inputs: impl WireRanges,
+        gate_index: usize,
+        context: &V::Context,
+    ) -> Vec<Option<E>> {
+        match self.functions.get(name).cloned() {
+            Some(function) => {
+                let (output_count, subcircuit) = &*function;
+                let body_context = visitor.body_context(context, gate_index, Body::Function(name));
+                self.call(
+                    visitor,
+                    caller,
+                    *output_count,
+                    inputs,
+                    subcircuit,
+                    &body_context,
+                )
+            }
+            None => {
+                eprintln!("WARNING {}: function not defined \"{}\"", V::NAME, name);
+                vec![]
+            }
+        }
+    }
+
+    /// Go through a subcircuit whose inputs are the given wires of the caller.
+    /// Return the entries of its outputs.
+    fn call<V: GateVisitor<Entry = E>>(
+        &mut self,
+        visitor: &mut V,
+        caller: &Scope<E>,
+        output_count: usize,
+        inputs: impl WireRanges,
+        subcircuit: &[Gate],
+        context: &V::Context,
+    ) -> Vec<Option<E>> {
+        if let Err(err) = self.budget.enter() {
+            self.budget.exit();
+            self.fail(err);
+            return vec![];
+        }
+
+        let mut scope = Scope::new();
+        for (i, input) in inputs.ids().enumerate() {
+            if let Some(entry) = caller.get(&input) {
+                scope.insert((output_count + i) as WireId, visitor.input_entry(*entry));
+            }
+        }
+
+        for (gate_index, gate) in subcircuit.iter().enumerate() {
+            self.ingest_gate(visitor, &mut scope, gate, gate_index, context);
+        }
+
+        let outputs = (0..output_count)
+            .map(|i| scope.remove(&(i as WireId)))
+            .collect();
+        // All the other wires of the subcircuit die here.
+        for (_, entry) in scope.drain() {
+            visitor.release(entry);
+        }
+        self.budget.exit();
+        outputs
+    }
+
+    pub fn wirelist_view<'a>(&mut self, wirelist: &'a WireList) -> Option<WireListView<'a>> {
+        match self.budget.limits.wirelist_view(wirelist) {
+            Ok(wires) => Some(wires),
+            Err(err) => {
+                self.fail(err);
+                None
+            }
+        }
+    }
+
+    pub fn wire_views<'a>(
+        &mut self,
+        output_wires: &'a WireList,
+        input_wires: &'a WireList,
+    ) -> Option<(WireListView<'a>, WireListView<'a>)> {
+        let outputs = self.wirelist_view(output_wires)?;
+        let inputs = self.wirelist_view(input_wires)?;
+        Some((outputs, inputs))
+    }
+
+    pub fn fail(&mut self, err: Box<dyn std::error::Error>) {
+        self.error.get_or_insert(err.to_string());
+    }
+}
@@ -11,17 +11,34 @@ pub fn read_size_prefix(buf: &[u8]) -> usize {
     SIZE_UOFFSET + size
 }
 
-pub fn split_messages(mut buf: &[u8]) -> Vec<&[u8]> {
+/// Split a buffer into its size-prefixed messages, up to its end or an explicit size 0. A
+/// truncated message at the end is an error.
+pub fn split_messages(mut buf: &[u8]) -> Result<Vec<&[u8]>> {
     let mut bufs = vec![];
     loop {
+        if !buf.is_empty() && buf.len() < SIZE_UOFFSET {
+            return Err(format!(
+                "The size of the last message is truncated to {} bytes.",
+                buf.len()
+            )
+            .into());
+        }
         let size = read_size_prefix(buf);
         if size <= SIZE_UOFFSET {
             break;
         }
+        if size > buf.len() {
+            return Err(format!(
+                "The last message is truncated to {} of its {} bytes.",
+                buf.len(),
+                size
+            )
+            .into());
+        }
         bufs.push(&buf[..size]);
         buf = &buf[size..];
     }
-    bufs
+    Ok(bufs)
 }
 
 pub fn read_buffer(stream: &mut impl Read) -> Result<Vec<u8>> {
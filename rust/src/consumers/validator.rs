@@ -1,17 +1,27 @@
-use crate::{Gate, Header, Instance, Message, Relation, Result, WireId, Witness};
+use crate::consumers::limits::{Budget, Limits};
+use crate::consumers::lints::{Lint, LintLevels, Severity, DUPLICATED_CONSTANT_THRESHOLD};
+use crate::consumers::wire_set::WireSet;
+use crate::{Gate, Header, Instance, Message, Relation, Result, TypeId, WireId, Witness};
 use num_bigint::{BigUint, ToBigUint};
 use num_traits::identities::One;
-use std::collections::{BTreeSet, HashMap, HashSet};
-
-use crate::structs::function::{CaseInvoke, ForLoopBody};
-use crate::structs::iterators::evaluate_iterexpr_list;
-use crate::structs::relation::{contains_feature, FOR, FUNCTION, SWITCH};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::structs::call_graph::{recursion_error, CallGraph};
+use crate::structs::field_presets::describe_characteristic;
+use crate::structs::function::{CaseInvoke, ForLoopBody, Function};
+use crate::structs::iterators::{
+    affine_iterexpr_list, iterexpr_list_uses_iterator, AffineExpr, IterExprList,
+};
+use crate::structs::message::MessageView;
+use crate::structs::relation::{contains_feature, CONVERT, FOR, FUNCTION, SWITCH};
 use crate::structs::relation::{ADD, ADDC, AND, ARITH, BOOL, MUL, MULC, NOT, XOR};
+use crate::structs::relation_view::RelationView;
 use crate::structs::value::is_probably_prime;
-use crate::structs::wire::expand_wirelist;
+use crate::structs::wire::{WireList, WireListView, WireRanges};
 use regex::Regex;
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::rc::Rc;
 
 type Field = BigUint;
@@ -23,6 +33,13 @@ type Field = BigUint;
 const VERSION_REGEX: &str = r"^\d+.\d+.\d+$";
 /// Used to check the validity of names of functions / iterators
 const NAMES_REGEX: &str = r"^[a-zA-Z_][\w]*(?:(?:\.|:{2})[a-zA-Z_][\w]*)*$";
+/// Loops with more wires than this per iteration are unrolled instead of validated symbolically.
+const SYMBOLIC_LOOP_MAX_WIRES: usize = 1024;
+/// Loops are also unrolled, under the limits, when the symbolic validation would check more wires
+/// than this one by one, because their numbers grow by 2 or more per iteration.
+const SYMBOLIC_LOOP_MAX_STRIDED_WIRES: i128 = 1 << 16;
+/// Ranges of more wires than this are reported in a single message instead of one per wire.
+const WIRES_REPORTED_ONE_BY_ONE: u64 = 16;
 const IMPLEMENTED_CHECKS: &str = r"
 Here is the list of implemented semantic/syntactic checks:
 
@@ -34,6 +51,8 @@ Header Validation
  - Ensure header messages are coherent.
      - Versions should be identical.
      - Field characteristic and field degree should be the same.
+     - Types should be the same.
+ - Ensure that types are only declared from version 2.0.0, and that their characteristics are primes.
 
 Relation Validation
  - Ensure that the defined gateset is either 'arithmetic' (or a subset) or 'boolean' (or a subset).
@@ -45,6 +64,7 @@ Inputs Validation (Instances / Witnesses)
  - Ensure that all Instance and Witness are consumed at the end of the circuit
  - Ensure that the value they are set to is indeed encoding an element lying in the underlying field.
    For degree 1 fields, it can be achieved by ensuring that the encoded value is strictly smaller than the field characteristic.
+ - Ensure that the values of each type are consumed by the Instance and Witness gates of this type.
 
 Gates Validation
  - Ensure that gates used are coherent with the profile.
@@ -54,20 +74,47 @@ Gates Validation
  - Ensure input wires of gates map to an already set variable.
  - Enforce Single Static Assignment by checking that the same wire is used only once as an output wire.
  - Ensure that @function/@for/@switch are indeed allowed if they are encountered in the circuit.
+ - Ensure that functions do not call themselves, directly or through other functions, switches or loops.
+ - Ensure that the case values of a switch are distinct field elements.
+ - Ensure that each branch of a switch assigns each of its outputs exactly once, and reads only its inputs
+   and the wires it assigned before, for the verifier too.
  - Ensure that for Free gates of the format @free(first, last), we have (last > first).
+ - Ensure that typed gates are basic gates of a declared type, and that their input wires have this type.
+ - Ensure that typed Instance and Witness gates are not in functions, loops or switches.
+ - Ensure that @convert is allowed, and that the inputs of conversions have the input type.
+ - Ensure that typed gates and conversions are not in the branches of a switch.
  - Ensure that start (first) and stop (last) conditions in loop verify that (last > first).
+ - Ensure that the wires set in a loop are in its output list.
+ - Loops whose wires are affine functions of the iterator are checked symbolically, without unrolling.
 
 WireRange Validation
  - Ensure that for WireRange(first, last) that (last > first).
+
+Lints (reported as warnings by default, or as violations if denied)
+ - unused-wire: a wire is computed but never used.
+ - duplicated-constant: the same constant is defined by many Constant gates in the same scope.
+ - copy-chain: a Copy gate copies a wire which is itself a copy.
+ - uncalled-function: a function is defined but never called.
+ - free-unallocated: a Free gate covers wires that are not allocated in the scope.
+ - single-case-switch: a switch has a single case.
+ - trivial-loop: a loop has zero or one iteration.
 ";
 
+/// The branch of a switch checked by a sub-validator, to report its wires clearly.
+#[derive(Clone, Debug)]
+struct Branch {
+    // e.g. "The branch 1 of the switch on the wire 5".
+    name: String,
+    output_count: usize,
+}
+
 #[derive(Clone)]
 pub struct Validator {
     as_prover: bool,
 
     instance_queue_len: usize,
     witness_queue_len: usize,
-    live_wires: BTreeSet<WireId>,
+    live_wires: WireSet,
 
     got_header: bool,
     gate_set: u16,
@@ -76,12 +123,41 @@ pub struct Validator {
 
     field_characteristic: Field,
     field_degree: usize,
+    // The characteristics of the types 1, 2, etc.
+    types: Vec<Field>,
+
+    // The type of the gate being validated, 0 outside of typed gates.
+    current_type: TypeId,
+    // type => wires of this scope of this type, for the types 1, 2, etc.
+    wire_types: BTreeMap<TypeId, WireSet>,
+    // type => (instance_count, witness_count), the values of the types 1, 2, etc. to consume.
+    typed_queue_lens: HashMap<TypeId, (usize, usize)>,
+    // Set when validating a switch, where all the gates are of the type 0.
+    in_switch: bool,
 
     // name => (output_count, input_count, instance_count, witness_count, subcircuit)
     known_functions: Rc<RefCell<HashMap<String, (usize, usize, usize, usize)>>>,
     known_iterators: Rc<RefCell<HashMap<String, u64>>>,
+    called_functions: Rc<RefCell<HashSet<String>>>,
+    // The calls between the functions of the relation, updated with each message.
+    call_graph: CallGraph,
+
+    // Wires set by a gate of this scope, and not used since.
+    unused_wires: WireSet,
+    // Wires set by a Copy gate of this scope.
+    copied_wires: HashSet<WireId>,
+    // Constant value => number of Constant gates in this scope.
+    constants: HashMap<Field, usize>,
 
     violations: Vec<String>,
+    // Lints are reported as warnings or violations according to `lint_levels` at the end.
+    lints: Vec<(Lint, String)>,
+    lint_levels: LintLevels,
+
+    // The resources used, shared with the validators of subcircuits.
+    budget: Rc<RefCell<Budget>>,
+    // Set when validating the branch of a switch.
+    branch: Option<Branch>,
 }
 
 impl Default for Validator {
@@ -97,9 +173,23 @@ impl Default for Validator {
             header_version: Default::default(),
             field_characteristic: Default::default(),
             field_degree: Default::default(),
+            types: Default::default(),
+            current_type: 0,
+            wire_types: Default::default(),
+            typed_queue_lens: Default::default(),
+            in_switch: false,
             known_functions: Rc::new(RefCell::new(HashMap::default())),
             known_iterators: Rc::new(RefCell::new(HashMap::default())),
+            called_functions: Rc::new(RefCell::new(HashSet::default())),
+            call_graph: Default::default(),
+            unused_wires: Default::default(),
+            copied_wires: Default::default(),
+            constants: Default::default(),
             violations: Default::default(),
+            lints: Default::default(),
+            lint_levels: Default::default(),
+            budget: Default::default(),
+            branch: None,
         }
     }
 }
@@ -132,13 +222,51 @@ impl Validator {
         println!("{}", IMPLEMENTED_CHECKS);
     }
 
-    pub fn get_violations(mut self) -> Vec<String> {
+    /// Choose which lints are ignored, reported as warnings, or reported as violations.
+    pub fn set_lint_levels(&mut self, lint_levels: LintLevels) {
+        self.lint_levels = lint_levels;
+    }
+
+    /// Set the limits on the resources used to validate the statement. Exceeding them is a
+    /// violation.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Rc::new(RefCell::new(Budget::new(limits)));
+    }
+
+    fn limits(&self) -> Limits {
+        self.budget.borrow().limits
+    }
+
+    pub fn get_violations(self) -> Vec<String> {
+        self.get_violations_and_warnings().0
+    }
+
+    /// Return the violations, including the denied lints, and the warnings.
+    pub fn get_violations_and_warnings(mut self) -> (Vec<String>, Vec<String>) {
         self.ensure_all_instance_values_consumed();
         self.ensure_all_witness_values_consumed();
-        if self.live_wires.len() != 0 {
+        self.ensure_all_typed_values_consumed();
+        if !self.live_wires.is_empty() {
             println!("WARNING: few variables were not freed.");
         }
-        self.violations
+        self.lint_scope();
+        self.lint_uncalled_functions();
+
+        let mut warnings = vec![];
+        let mut reported = HashSet::new();
+        for (lint, msg) in std::mem::take(&mut self.lints) {
+            let msg = format!("{} [{}]", msg, lint);
+            // Lints in loops and functions are found many times.
+            if !reported.insert(msg.clone()) {
+                continue;
+            }
+            match self.lint_levels.get(lint) {
+                Severity::Allow => {}
+                Severity::Warn => warnings.push(msg),
+                Severity::Deny => self.violations.push(msg),
+            }
+        }
+        (self.violations, warnings)
     }
 
     pub fn get_strict_violations(&self) -> &Vec<String> {
@@ -157,6 +285,15 @@ impl Validator {
         }
     }
 
+    /// Like `ingest_message`, with the relation read in place.
+    pub fn ingest_message_view(&mut self, msg: &MessageView) {
+        match msg {
+            MessageView::Instance(i) => self.ingest_instance(&i),
+            MessageView::Witness(w) => self.ingest_witness(&w),
+            MessageView::Relation(r) => self.ingest_relation_view(&r),
+        }
+    }
+
     fn ingest_header(&mut self, header: &Header) {
         if self.got_header {
             // in this case, ensure that headers are compatible
@@ -170,6 +307,14 @@ impl Validator {
             if self.header_version != header.version {
                 self.violate("The profile version is not consistent across headers.");
             }
+            let types: Vec<Field> = header
+                .types
+                .iter()
+                .map(|value| Field::from_bytes_le(value))
+                .collect();
+            if self.types != types {
+                self.violate("The types are not consistent across headers.");
+            }
         } else {
             self.got_header = true;
 
@@ -194,6 +339,22 @@ impl Validator {
                 self.violate("The profile version should match the following format <major>.<minor>.<patch>.");
             }
             self.header_version = header.version.clone();
+
+            // check types
+            if !header.types.is_empty() && !header.supports_types() {
+                self.violate("Types are only supported from the version 2.0.0.");
+            }
+            for (i, value) in header.types.iter().enumerate() {
+                let characteristic = Field::from_bytes_le(value);
+                if characteristic.cmp(&One::one()) != Ordering::Greater || !is_probably_prime(value)
+                {
+                    self.violate(format!(
+                        "The characteristic of the type {} should be a prime.",
+                        i + 1
+                    ));
+                }
+                self.types.push(characteristic);
+            }
         }
     }
 
@@ -201,11 +362,19 @@ impl Validator {
         self.ingest_header(&instance.header);
 
         // Check values.
+        let type_id = instance.type_id;
+        if !self.ensure_declared_type(type_id) {
+            return;
+        }
         for value in instance.common_inputs.iter() {
-            self.ensure_value_in_field(value, || format!("instance value {:?}", value));
+            self.ensure_value_in_type(type_id, value, || format!("instance value {:?}", value));
         }
         // Provide values on the queue available for Instance gates.
-        self.instance_queue_len += instance.common_inputs.len();
+        if type_id == 0 {
+            self.instance_queue_len += instance.common_inputs.len();
+        } else {
+            self.typed_queue_lens.entry(type_id).or_default().0 += instance.common_inputs.len();
+        }
     }
 
     pub fn ingest_witness(&mut self, witness: &Witness) {
@@ -215,17 +384,92 @@ impl Validator {
         self.ingest_header(&witness.header);
 
         // Check values.
+        let type_id = witness.type_id;
+        if !self.ensure_declared_type(type_id) {
+            return;
+        }
         for value in witness.short_witness.iter() {
-            self.ensure_value_in_field(value, || format!("witness value {:?}", value));
+            self.ensure_value_in_type(type_id, value, || format!("witness value {:?}", value));
         }
         // Provide values on the queue available for Witness gates.
-        self.witness_queue_len += witness.short_witness.len();
+        if type_id == 0 {
+            self.witness_queue_len += witness.short_witness.len();
+        } else {
+            self.typed_queue_lens.entry(type_id).or_default().1 += witness.short_witness.len();
+        }
     }
 
     pub fn ingest_relation(&mut self, relation: &Relation) {
-        self.ingest_header(&relation.header);
+        self.ingest_relation_header(&relation.header, relation.gate_mask, relation.feat_mask);
+        for f in relation.functions.iter() {
+            self.call_graph.add_function(&f.name, &f.body);
+        }
+        self.ensure_not_recursive(relation.functions.iter().map(|f| &f.name));
+
+        for f in relation.functions.iter() {
+            self.ingest_function(f);
+        }
+
+        for gate in &relation.gates {
+            self.ingest_gate(gate);
+        }
+    }
+
+    /// Like `ingest_relation`, reading the relation in place. Each function and each gate is
+    /// decoded, validated and dropped in turn. Recursive functions are reported once all the
+    /// functions are known.
+    pub fn ingest_relation_view(&mut self, relation: &RelationView) {
+        let parts = relation
+            .header()
+            .and_then(|header| Ok((header, relation.gate_mask()?, relation.feat_mask()?)));
+        match parts {
+            Ok((header, gate_mask, feat_mask)) => {
+                self.ingest_relation_header(&header, gate_mask, feat_mask)
+            }
+            Err(err) => {
+                self.violate(err.to_string());
+                return;
+            }
+        }
+
+        let mut names = vec![];
+        for f in relation.functions() {
+            match f.to_function() {
+                Ok(f) => {
+                    self.call_graph.add_function(&f.name, &f.body);
+                    names.push(f.name.clone());
+                    self.ingest_function(&f);
+                }
+                Err(err) => {
+                    self.violate(err.to_string());
+                    return;
+                }
+            }
+        }
+        self.ensure_not_recursive(&names);
+
+        let gates = match relation.gates() {
+            Ok(gates) => gates,
+            Err(err) => {
+                self.violate(err.to_string());
+                return;
+            }
+        };
+        for gate in gates {
+            match gate {
+                Ok(gate) => self.ingest_gate(&gate),
+                Err(err) => {
+                    self.violate(err.to_string());
+                    return;
+                }
+            }
+        }
+    }
 
-        self.gate_set = relation.gate_mask;
+    fn ingest_relation_header(&mut self, header: &Header, gate_mask: u16, feat_mask: u16) {
+        self.ingest_header(header);
+
+        self.gate_set = gate_mask;
         if contains_feature(self.gate_set, BOOL) && contains_feature(self.gate_set, ARITH) {
             self.violate("Cannot mix arithmetic and boolean gates");
         }
@@ -236,63 +480,88 @@ impl Validator {
             }
         }
 
-        self.features = relation.feat_mask;
+        self.features = feat_mask;
+    }
 
-        for f in relation.functions.iter() {
-            self.ensure_allowed_feature("@function", FUNCTION);
-
-            let (name, output_count, input_count, instance_count, witness_count) = (
-                f.name.clone(),
-                f.output_count,
-                f.input_count,
-                f.instance_count,
-                f.witness_count,
-            );
+    /// Recursive functions would never finish to be evaluated. The cycles through the functions
+    /// of previous messages were reported with them, so only the new functions are searched.
+    fn ensure_not_recursive<'a>(&mut self, new_functions: impl IntoIterator<Item = &'a String>) {
+        for cycle in self.call_graph.find_cycles_from(new_functions) {
+            self.violate(recursion_error(&cycle));
+        }
+    }
 
-            // Check that the name follows the proper REGEX
-            let re = Regex::new(NAMES_REGEX).unwrap();
-            if !re.is_match(name.trim()) {
-                self.violate(format!(
-                    "The function name ({}) should match the proper format ({}).",
-                    name, NAMES_REGEX
-                ));
-            }
+    fn ingest_function(&mut self, f: &Function) {
+        self.ensure_allowed_feature("@function", FUNCTION);
 
-            // Just record the signature first.
-            if self.known_functions.borrow().contains_key(&name) {
-                self.violate(format!(
-                    "A function with the name '{}' already exists",
-                    name
-                ));
-                continue;
-            } else {
-                self.known_functions.borrow_mut().insert(
-                    name.clone(),
-                    (output_count, input_count, instance_count, witness_count),
-                );
-            }
-            // Now validate the subcircuit.
-            self.ingest_subcircuit(
-                &f.body,
-                output_count,
-                input_count,
-                instance_count,
-                witness_count,
-                false,
-            );
+        let (name, output_count, input_count, instance_count, witness_count) = (
+            f.name.clone(),
+            f.output_count,
+            f.input_count,
+            f.instance_count,
+            f.witness_count,
+        );
+
+        // Check that the name follows the proper REGEX
+        let re = Regex::new(NAMES_REGEX).unwrap();
+        if !re.is_match(name.trim()) {
+            self.violate(format!(
+                "The function name ({}) should match the proper format ({}).",
+                name, NAMES_REGEX
+            ));
         }
 
-        for gate in &relation.gates {
-            self.ingest_gate(gate);
+        // Just record the signature first.
+        if self.known_functions.borrow().contains_key(&name) {
+            self.violate(format!(
+                "A function with the name '{}' already exists",
+                name
+            ));
+            return;
+        } else {
+            self.known_functions.borrow_mut().insert(
+                name.clone(),
+                (output_count, input_count, instance_count, witness_count),
+            );
         }
+        // Now validate the subcircuit.
+        self.ingest_subcircuit(
+            &f.body,
+            output_count,
+            input_count,
+            instance_count,
+            witness_count,
+            false,
+            None,
+        );
     }
 
     fn ingest_gate(&mut self, gate: &Gate) {
         use Gate::*;
 
+        // The inner gate is counted.
+        if let Typed(type_id, inner) = gate {
+            self.ingest_typed_gate(*type_id, inner);
+            return;
+        }
+
+        // Stop validating once the limit is reported.
+        if self.budget.borrow().is_exhausted() {
+            return;
+        }
+        let spent = self.budget.borrow_mut().spend_gates(1);
+        if let Err(err) = spent {
+            self.violate(err.to_string());
+            return;
+        }
+
         match gate {
             Constant(out, value) => {
                 self.ensure_value_in_field(value, || "Gate::Constant constant".to_string());
+                *self
+                    .constants
+                    .entry(Field::from_bytes_le(value))
+                    .or_insert(0) += 1;
                 self.ensure_undefined_and_set(*out);
             }
 
@@ -303,6 +572,16 @@ impl Validator {
             Copy(out, inp) => {
                 self.ensure_defined_and_set(*inp);
                 self.ensure_undefined_and_set(*out);
+                if self.copied_wires.contains(inp) {
+                    self.lint(
+                        Lint::CopyChain,
+                        format!(
+                            "The wire {} is a copy of the wire {}, which is itself a copy.",
+                            out, inp
+                        ),
+                    );
+                }
+                self.copied_wires.insert(*out);
             }
 
             Add(out, left, right) => {
@@ -362,15 +641,25 @@ impl Validator {
             Instance(out) => {
                 self.declare(*out);
                 // Consume value.
-                self.consume_instance(1);
+                if self.current_type == 0 {
+                    self.consume_instance(1);
+                } else {
+                    self.consume_typed_value(true);
+                }
             }
 
             Witness(out) => {
                 self.declare(*out);
                 // Consume value.
-                self.consume_witness(1);
+                if self.current_type == 0 {
+                    self.consume_witness(1);
+                } else {
+                    self.consume_typed_value(false);
+                }
             }
 
+            Typed(..) => unreachable!(),
+
             Free(first, last) => {
                 // first < last
                 if let Some(last_id) = last {
@@ -381,44 +670,81 @@ impl Validator {
                         ));
                     }
                 }
+                let last = last.unwrap_or(*first);
+                if let Err(err) = self.limits().check_wire_range(*first, last) {
+                    self.violate(err.to_string());
+                    return;
+                }
+                if last < *first {
+                    return;
+                }
                 // all wires between first and last INCLUSIVE
-                for wire_id in *first..=last.unwrap_or(*first) {
-                    self.ensure_defined_and_set(wire_id);
-                    self.remove(wire_id);
+                let unallocated: u128 = self
+                    .live_wires
+                    .gaps(*first, last)
+                    .into_iter()
+                    .map(|(gap_first, gap_last)| (gap_last - gap_first) as u128 + 1)
+                    .sum();
+                self.lint_unused_wires(*first, last);
+                self.ensure_defined_range(*first, last);
+                self.live_wires.remove_range(*first, last);
+                for wires in self.wire_types.values_mut() {
+                    wires.remove_range(*first, last);
+                }
+                if unallocated > 0 {
+                    self.lint(
+                        Lint::FreeUnallocated,
+                        format!(
+                            "The gate Free({}, {}) covers {} wires which are not allocated in this scope.",
+                            first, last, unallocated
+                        ),
+                    );
                 }
             }
 
+            Convert(out_type, output_wires, in_type, input_wires) => {
+                self.ensure_allowed_feature("@convert", CONVERT);
+                if !self.ensure_declared_type(*out_type) || !self.ensure_declared_type(*in_type) {
+                    return;
+                }
+                if self.in_switch {
+                    self.violate("Conversion gates are not allowed in the branches of a switch.");
+                }
+                let output_wires = self.wirelist_view(output_wires);
+                let input_wires = self.wirelist_view(input_wires);
+                if output_wires.len() == 0 || input_wires.len() == 0 {
+                    self.violate("Conversion gates must have input and output wires.");
+                }
+
+                self.current_type = *in_type;
+                self.ensure_defined_and_set_ranges(input_wires);
+                self.current_type = *out_type;
+                self.ensure_undefined_and_set_ranges(output_wires);
+                self.current_type = 0;
+            }
+
             AnonCall(output_wires, input_wires, instance_count, witness_count, subcircuit) => {
                 self.ensure_allowed_feature("@anoncall", FUNCTION);
-                let expanded_outputs = expand_wirelist(output_wires).unwrap_or_else(|err| {
-                    self.violate(err.to_string());
-                    vec![]
-                });
-                let expanded_inputs = expand_wirelist(input_wires).unwrap_or_else(|err| {
-                    self.violate(err.to_string());
-                    vec![]
-                });
+                let output_wires = self.wirelist_view(output_wires);
+                let input_wires = self.wirelist_view(input_wires);
 
-                expanded_inputs
-                    .iter()
-                    .for_each(|id| self.ensure_defined_and_set(*id));
+                self.ensure_defined_and_set_ranges(input_wires);
                 // ingest it and validate it.
                 self.ingest_subcircuit(
                     subcircuit,
-                    expanded_outputs.len(),
-                    expanded_inputs.len(),
+                    output_wires.len(),
+                    input_wires.len(),
                     *instance_count,
                     *witness_count,
                     true,
+                    None,
                 );
 
                 // Now, consume instances and witnesses from self.
                 self.consume_instance(*instance_count);
                 self.consume_witness(*witness_count);
                 // set the output wires as defined, since we checked they were in each branch.
-                expanded_outputs
-                    .iter()
-                    .for_each(|id| self.ensure_undefined_and_set(*id));
+                self.ensure_undefined_and_set_ranges(output_wires);
             }
 
             Call(name, output_wires, input_wires) => {
@@ -427,30 +753,20 @@ impl Validator {
                 // - Outputs and inputs match function signature
                 // - define outputs, check inputs
                 // - consume witness.
-                let expanded_outputs = expand_wirelist(output_wires).unwrap_or_else(|err| {
-                    self.violate(err.to_string());
-                    vec![]
-                });
-                let expanded_inputs = expand_wirelist(input_wires).unwrap_or_else(|err| {
-                    self.violate(err.to_string());
-                    vec![]
-                });
+                let output_wires = self.wirelist_view(output_wires);
+                let input_wires = self.wirelist_view(input_wires);
 
-                expanded_inputs
-                    .iter()
-                    .for_each(|id| self.ensure_defined_and_set(*id));
+                self.ensure_defined_and_set_ranges(input_wires);
 
                 let (instance_count, witness_count) = self
-                    .ingest_call(name, &expanded_outputs, &expanded_inputs)
+                    .ingest_call(name, output_wires.len(), input_wires.len())
                     .unwrap_or((0, 0));
 
                 // Now, consume instances and witnesses from self.
                 self.consume_instance(instance_count);
                 self.consume_witness(witness_count);
                 // set the output wires as defined, since we checked they were in each branch.
-                expanded_outputs
-                    .iter()
-                    .for_each(|id| self.ensure_undefined_and_set(*id));
+                self.ensure_undefined_and_set_ranges(output_wires);
             }
 
             Switch(condition, output_wires, cases, branches) => {
@@ -471,39 +787,39 @@ impl Validator {
                     return;
                 }
 
-                // Ensure each value of cases are in the proper field.
+                if cases.len() == 1 {
+                    self.lint(
+                        Lint::SingleCaseSwitch,
+                        format!("The switch on the wire {} has a single case.", condition),
+                    );
+                }
+
+                // Ensure each value of cases are in the proper field, and that there is no duplicate.
                 let mut cases_set = HashSet::new();
                 for case in cases {
                     self.ensure_value_in_field(case, || {
                         format!("Gate::Switch case value: {}", Field::from_bytes_le(case))
                     });
-                    cases_set.insert(Field::from_bytes_le(case));
-                }
-
-                // ensure that there is no duplicate in cases.
-                if cases_set.len() != cases.len() {
-                    self.violate("Gate::Switch: The cases values contain duplicates.");
+                    if !cases_set.insert(Field::from_bytes_le(case)) {
+                        self.violate(format!(
+                            "Gate::Switch: The case value {} appears more than once.",
+                            Field::from_bytes_le(case)
+                        ));
+                    }
                 }
 
                 let (mut max_instance_count, mut max_witness_count) = (0usize, 0usize);
 
-                let expanded_outputs = expand_wirelist(output_wires).unwrap_or_else(|err| {
-                    self.violate(err.to_string());
-                    vec![]
-                });
+                let output_wires = self.wirelist_view(output_wires);
+                let output_count = output_wires.len();
 
                 // 'Validate' each branch of the switch independently, and perform checks
-                for branch in branches {
+                for (i, branch) in branches.iter().enumerate() {
                     let (instance_count, witness_count) = match branch {
                         CaseInvoke::AbstractGateCall(name, inputs) => {
-                            let expanded_inputs = expand_wirelist(inputs).unwrap_or_else(|err| {
-                                self.violate(err.to_string());
-                                vec![]
-                            });
-                            expanded_inputs
-                                .iter()
-                                .for_each(|id| self.ensure_defined_and_set(*id));
-                            self.ingest_call(name, &expanded_outputs, &expanded_inputs)
+                            let inputs = self.wirelist_view(inputs);
+                            self.ensure_defined_and_set_ranges(inputs);
+                            self.ingest_call(name, output_count, inputs.len())
                                 .unwrap_or((0, 0))
                         }
                         CaseInvoke::AbstractAnonCall(
@@ -512,20 +828,22 @@ impl Validator {
                             witness_count,
                             subcircuit,
                         ) => {
-                            let expanded_inputs = expand_wirelist(inputs).unwrap_or_else(|err| {
-                                self.violate(err.to_string());
-                                vec![]
-                            });
-                            expanded_inputs
-                                .iter()
-                                .for_each(|id| self.ensure_defined_and_set(*id));
+                            let inputs = self.wirelist_view(inputs);
+                            self.ensure_defined_and_set_ranges(inputs);
                             self.ingest_subcircuit(
                                 subcircuit,
-                                expanded_outputs.len(),
-                                expanded_inputs.len(),
+                                output_count,
+                                inputs.len(),
                                 *instance_count,
                                 *witness_count,
                                 true,
+                                Some(Branch {
+                                    name: format!(
+                                        "The branch {} of the switch on the wire {}",
+                                        i, condition
+                                    ),
+                                    output_count,
+                                }),
                             );
                             (*instance_count, *witness_count)
                         }
@@ -539,19 +857,37 @@ impl Validator {
                 self.consume_instance(max_instance_count);
                 self.consume_witness(max_witness_count);
                 // set the output wires as defined, since we checked they were in each branch.
-                expanded_outputs
-                    .iter()
-                    .for_each(|id| self.ensure_undefined_and_set(*id));
+                self.ensure_undefined_and_set_ranges(output_wires);
             }
 
             For(iterator_name, start_val, end_val, global_output_list, body) => {
                 self.ensure_allowed_feature("@for", FOR);
 
+                if *end_val <= *start_val {
+                    let iterations = if end_val == start_val {
+                        "one iteration"
+                    } else {
+                        "no iteration"
+                    };
+                    self.lint(
+                        Lint::TrivialLoop,
+                        format!("The loop over {} has {}.", iterator_name, iterations),
+                    );
+                }
+
                 if *end_val < *start_val {
                     self.violate(format!("In a For loop, the end value ({}) must be strictly greater than the start value ({}).", *end_val, *start_val));
                     return;
                 }
 
+                if let Err(err) = self
+                    .limits()
+                    .check_loop(iterator_name, *start_val, *end_val)
+                {
+                    self.violate(err.to_string());
+                    return;
+                }
+
                 if self.known_iterators.borrow().contains_key(iterator_name) {
                     self.violate("Iterator already used in this context.");
                     return;
@@ -565,28 +901,58 @@ impl Validator {
                     ));
                 }
 
-                for i in *start_val..=*end_val {
+                let global_output_list = self.wirelist_view(global_output_list);
+                let global_outputs = WireSet::from_ranges(global_output_list);
+
+                self.known_iterators
+                    .borrow_mut()
+                    .insert(iterator_name.clone(), *start_val);
+                let validated = self.ingest_for_symbolically(
+                    iterator_name,
+                    *start_val,
+                    *end_val,
+                    &global_outputs,
+                    body,
+                );
+
+                // Otherwise, unroll the loop.
+                let unrolled_iterations = if validated {
+                    1..=0
+                } else {
+                    *start_val..=*end_val
+                };
+                for i in unrolled_iterations {
+                    // Stop unrolling once the limit is reported.
+                    if self.budget.borrow().is_exhausted() {
+                        break;
+                    }
                     self.known_iterators
                         .borrow_mut()
                         .insert(iterator_name.clone(), i);
 
                     match body {
                         ForLoopBody::IterExprCall(name, outputs, inputs) => {
-                            let expanded_outputs =
-                                evaluate_iterexpr_list(outputs, &*self.known_iterators.borrow());
-                            let expanded_inputs =
-                                evaluate_iterexpr_list(inputs, &*self.known_iterators.borrow());
-                            expanded_inputs
-                                .iter()
-                                .for_each(|id| self.ensure_defined_and_set(*id));
+                            let output_ranges = self.iterexpr_list_ranges(outputs);
+                            let input_ranges = self.iterexpr_list_ranges(inputs);
+                            self.ensure_defined_and_set_ranges(&input_ranges[..]);
                             let (instance_count, witness_count) = self
-                                .ingest_call(name, &expanded_outputs, &expanded_inputs)
+                                .ingest_call(
+                                    name,
+                                    count_wires(&output_ranges),
+                                    count_wires(&input_ranges),
+                                )
                                 .unwrap_or((0, 0));
 
                             // Now, consume instances and witnesses from self, and set the output wires
-                            expanded_outputs
-                                .iter()
-                                .for_each(|id| self.ensure_undefined_and_set(*id));
+                            for (first, last) in output_ranges {
+                                self.ensure_undefined_and_set_range(first, last);
+                                self.ensure_in_loop_outputs(
+                                    first,
+                                    last,
+                                    &global_outputs,
+                                    iterator_name,
+                                );
+                            }
                             self.consume_instance(instance_count);
                             self.consume_witness(witness_count);
                         }
@@ -597,30 +963,29 @@ impl Validator {
                             witness_count,
                             subcircuit,
                         ) => {
-                            let expanded_outputs = evaluate_iterexpr_list(
-                                output_wires,
-                                &*self.known_iterators.borrow(),
-                            );
-                            let expanded_inputs = evaluate_iterexpr_list(
-                                input_wires,
-                                &*self.known_iterators.borrow(),
-                            );
-                            expanded_inputs
-                                .iter()
-                                .for_each(|id| self.ensure_defined_and_set(*id));
+                            let output_ranges = self.iterexpr_list_ranges(output_wires);
+                            let input_ranges = self.iterexpr_list_ranges(input_wires);
+                            self.ensure_defined_and_set_ranges(&input_ranges[..]);
                             self.ingest_subcircuit(
                                 subcircuit,
-                                expanded_outputs.len(),
-                                expanded_inputs.len(),
+                                count_wires(&output_ranges),
+                                count_wires(&input_ranges),
                                 *instance_count,
                                 *witness_count,
                                 true,
+                                None,
                             );
 
                             // Now, consume instances and witnesses from self, and set the output wires
-                            expanded_outputs
-                                .iter()
-                                .for_each(|id| self.ensure_undefined_and_set(*id));
+                            for (first, last) in output_ranges {
+                                self.ensure_undefined_and_set_range(first, last);
+                                self.ensure_in_loop_outputs(
+                                    first,
+                                    last,
+                                    &global_outputs,
+                                    iterator_name,
+                                );
+                            }
                             self.consume_instance(*instance_count);
                             self.consume_witness(*witness_count);
                         }
@@ -629,19 +994,394 @@ impl Validator {
                 self.known_iterators.borrow_mut().remove(iterator_name);
 
                 // Ensure that each global output wire has been set in one of the loops.
-                let expanded_global_outputs =
-                    expand_wirelist(global_output_list).unwrap_or_else(|err| {
-                        self.violate(err.to_string());
-                        vec![]
-                    });
-                expanded_global_outputs
-                    .iter()
-                    .for_each(|id| self.ensure_defined_and_set(*id));
+                if !self.budget.borrow().is_exhausted() {
+                    for (first, last) in global_output_list.ranges() {
+                        self.ensure_defined_range(first, last);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Validate a loop without unrolling it, when the wires of its body are affine functions of
+    /// the iterator. It proves that the outputs of all iterations are distinct, that the inputs
+    /// only read wires set before the loop or by previous iterations, and that the outputs are in
+    /// the output list of the loop. The body itself is validated once.
+    /// It returns false if the loop must be unrolled instead, in which case nothing was checked.
+    fn ingest_for_symbolically(
+        &mut self,
+        iterator_name: &str,
+        start_val: u64,
+        end_val: u64,
+        global_outputs: &WireSet,
+        body: &ForLoopBody,
+    ) -> bool {
+        let (output_list, input_list) = match body {
+            ForLoopBody::IterExprCall(_, outputs, inputs) => (outputs, inputs),
+            ForLoopBody::IterExprAnonCall(outputs, inputs, _, _, subcircuit) => {
+                // The body must be the same in all iterations.
+                if gates_use_iterator(subcircuit, iterator_name) {
+                    return false;
+                }
+                (outputs, inputs)
+            }
+        };
+        let (outputs, inputs) = {
+            let known_iterators = self.known_iterators.borrow();
+            let affine = |list| {
+                affine_iterexpr_list(
+                    list,
+                    iterator_name,
+                    &known_iterators,
+                    SYMBOLIC_LOOP_MAX_WIRES,
+                )
+            };
+            match (affine(output_list), affine(input_list)) {
+                (Some(outputs), Some(inputs)) => (outputs, inputs),
+                _ => return false,
+            }
+        };
+
+        // Iterations are numbered from `start` to `start + last`.
+        let (start, last) = (start_val as i128, (end_val - start_val) as i128);
+        let span = |wire: &AffineExpr| -> Option<(i128, i128)> {
+            let (first, last) = (wire.evaluate(start)?, wire.evaluate(start + last)?);
+            Some((first.min(last), first.max(last)))
+        };
+        let mut spans = vec![];
+        for wire in outputs.iter().chain(inputs.iter()) {
+            match span(wire) {
+                Some((low, high)) if low >= 0 && high <= WireId::MAX as i128 => {
+                    spans.push((low, high))
+                }
+                // Let the unrolled evaluation report it.
+                _ => return false,
+            }
+        }
+        let output_spans = &spans[..outputs.len()];
+
+        // Wires with different slopes may meet in the middle of the loop.
+        for (output, output_span) in outputs.iter().zip(output_spans) {
+            for (other, other_span) in outputs.iter().chain(inputs.iter()).zip(spans.iter()) {
+                if other.slope != output.slope
+                    && other_span.0 <= output_span.1
+                    && output_span.0 <= other_span.1
+                {
+                    return false;
+                }
+            }
+        }
+
+        let at =
+            |wire: &AffineExpr, iteration: i128| wire.evaluate(iteration).unwrap_or(0) as WireId;
+
+        // The outputs with a slope of 2 or more, by slope. A group covers a single range when each
+        // iteration sets a block of consecutive wires, e.g. `$(2 * i)` and `$(2 * i + 1)`.
+        let mut groups: BTreeMap<i128, Vec<(&AffineExpr, &(i128, i128))>> = BTreeMap::new();
+        for (output, span) in outputs.iter().zip(output_spans) {
+            if output.slope.abs() > 1 {
+                groups.entry(output.slope).or_default().push((output, span));
+            }
+        }
+        let strided: Vec<(bool, Vec<(&AffineExpr, &(i128, i128))>)> = groups
+            .into_iter()
+            .map(|(slope, group)| {
+                let mut firsts: Vec<WireId> =
+                    group.iter().map(|&(output, _)| at(output, start)).collect();
+                firsts.sort_unstable();
+                firsts.dedup();
+                let block_len = firsts.len() as u64;
+                let is_block = block_len as i128 >= slope.abs()
+                    && firsts[firsts.len() - 1] - firsts[0] == block_len - 1;
+                (is_block, group)
+            })
+            .collect();
+
+        // The other wires with a slope of 2 or more are checked one by one, for each iteration.
+        let mut strided_wires = 0;
+        for input in inputs.iter().filter(|input| input.slope.abs() > 1) {
+            let reads = 1 + outputs.iter().filter(|o| o.slope == input.slope).count() as i128;
+            strided_wires += reads * (last + 1);
+        }
+        for (_, group) in strided.iter().filter(|(is_block, _)| !is_block) {
+            strided_wires += group.len() as i128 * (last + 1);
+        }
+        if strided_wires > SYMBOLIC_LOOP_MAX_STRIDED_WIRES {
+            return false;
+        }
+
+        // From now on, the loop is validated symbolically.
+
+        // The wires of an expression from the iteration `start + from` to `start + to`, as a
+        // single range when they are consecutive.
+        let wire_ranges = |wire: &AffineExpr, from: i128, to: i128| -> Vec<(WireId, WireId)> {
+            if wire.slope.abs() <= 1 {
+                let (first, last) = (at(wire, start + from), at(wire, start + to));
+                vec![(first.min(last), first.max(last))]
+            } else {
+                (from..=to)
+                    .map(|i| {
+                        let id = at(wire, start + i);
+                        (id, id)
+                    })
+                    .collect()
+            }
+        };
+
+        // Inputs must be set before the loop, or by a previous iteration.
+        let mut read_from_loop = vec![];
+        for input in inputs.iter() {
+            // The number of first iterations that read a wire set before the loop.
+            let mut read_before_loop = last + 1;
+            for output in outputs.iter().filter(|output| output.slope == input.slope) {
+                // The input of iteration i is the output of iteration i - distance.
+                let distance = match previous_iteration_distance(input, output) {
+                    Some(distance) => distance,
+                    None => continue,
+                };
+                if distance >= 1 {
+                    read_before_loop = read_before_loop.min(distance);
+                    read_from_loop.push((*output, distance));
+                } else if -distance <= last {
+                    self.violate(format!(
+                        "The wire {} is used in the loop over {} before it is set.",
+                        at(input, start),
+                        iterator_name
+                    ));
+                }
+            }
+            if input.slope == 0 {
+                read_before_loop = read_before_loop.min(1);
+            }
+            for (low, high) in wire_ranges(input, 0, read_before_loop - 1) {
+                self.ensure_defined_and_set_range(low, high);
+            }
+        }
+
+        // Validate the body once, and consume the instances and witnesses of all iterations.
+        let iterations = usize::try_from(last + 1).unwrap_or(usize::MAX);
+        let (instance_count, witness_count) = match body {
+            ForLoopBody::IterExprCall(name, _, _) => self
+                .ingest_call(name, outputs.len(), inputs.len())
+                .unwrap_or((0, 0)),
+            ForLoopBody::IterExprAnonCall(_, _, instance_count, witness_count, subcircuit) => {
+                self.ingest_subcircuit(
+                    subcircuit,
+                    outputs.len(),
+                    inputs.len(),
+                    *instance_count,
+                    *witness_count,
+                    true,
+                    None,
+                );
+                (*instance_count, *witness_count)
+            }
+        };
+        self.consume_instance(instance_count.saturating_mul(iterations));
+        self.consume_witness(witness_count.saturating_mul(iterations));
+
+        // Outputs must be distinct across outputs and iterations.
+        for (i, output) in outputs.iter().enumerate() {
+            for (j, other) in outputs.iter().enumerate().skip(i) {
+                if let Some(wire) = first_collision(output, other, start, last, i == j) {
+                    self.violate(format!(
+                        "The wire {} is set several times in the loop over {}. This violates the SSA property.",
+                        wire, iterator_name
+                    ));
+                }
+            }
+        }
+
+        // The wires set by all the iterations.
+        let mut output_ranges = vec![];
+        for output in outputs.iter().filter(|output| output.slope.abs() <= 1) {
+            output_ranges.extend(wire_ranges(output, 0, last));
+        }
+        for (is_block, group) in strided {
+            if is_block {
+                let low = group.iter().map(|(_, span)| span.0).min().unwrap_or(0);
+                let high = group.iter().map(|(_, span)| span.1).max().unwrap_or(0);
+                output_ranges.push((low as WireId, high as WireId));
+            } else {
+                for (output, _) in group {
+                    output_ranges.extend(wire_ranges(output, 0, last));
+                }
+            }
+        }
+
+        // Outputs must not be set before the loop, and must be in the output list of the loop.
+        for &(low, high) in output_ranges.iter() {
+            self.ensure_undefined_range(low, high);
+            self.ensure_in_loop_outputs(low, high, global_outputs, iterator_name);
+        }
+
+        for (low, high) in output_ranges {
+            self.declare_range(low, high);
+            self.unused_wires.insert_range(low, high);
+        }
+        for (output, distance) in read_from_loop {
+            if distance > last {
+                continue;
+            }
+            for (low, high) in wire_ranges(&output, 0, last - distance) {
+                self.unused_wires.remove_range(low, high);
+            }
+        }
+        true
+    }
+
+    /// Ensure that the wires set in a loop are in the output list of the loop.
+    fn ensure_in_loop_outputs(
+        &mut self,
+        first: WireId,
+        last: WireId,
+        global_outputs: &WireSet,
+        iterator_name: &str,
+    ) {
+        for (gap_first, gap_last) in global_outputs.gaps(first, last) {
+            let messages = describe_wires(
+                gap_first,
+                gap_last,
+                |id| {
+                    format!(
+                        "The wire {} is set in the loop over {} but is not in its output list.",
+                        id, iterator_name
+                    )
+                },
+                |gap_first, gap_last| {
+                    format!(
+                        "The wires {} to {} are set in the loop over {} but are not in its output list.",
+                        gap_first, gap_last, iterator_name
+                    )
+                },
+            );
+            for msg in messages {
+                self.violate(msg);
             }
         }
     }
 
-    /// Ingest an equivalent of the AbstractGateCall, along with the expanded outputs list.
+    /// Validate a gate of the given type. Only basic gates are typed, and the wires they use and
+    /// set are of this type.
+    fn ingest_typed_gate(&mut self, type_id: TypeId, gate: &Gate) {
+        use Gate::*;
+
+        if !self.ensure_declared_type(type_id) {
+            return;
+        }
+        if !gate.is_basic() {
+            self.violate(format!("Only basic gates can be of the type {}.", type_id));
+            return;
+        }
+        if matches!(gate, Instance(..) | Witness(..))
+            && type_id != 0
+            && self.budget.borrow().depth() > 0
+        {
+            self.violate(format!(
+                "Instance and Witness gates of the type {} are not allowed in functions, loops or switches.",
+                type_id
+            ));
+            return;
+        }
+        if type_id != 0 && self.in_switch {
+            self.violate(format!(
+                "Gates of the type {} are not allowed in the branches of a switch.",
+                type_id
+            ));
+        }
+        self.current_type = type_id;
+        self.ingest_gate(gate);
+        self.current_type = 0;
+    }
+
+    /// Ensure that the type is declared in the header, and return whether it is.
+    fn ensure_declared_type(&mut self, type_id: TypeId) -> bool {
+        if type_id == 0 {
+            return true;
+        }
+        if (type_id as usize) > self.types.len() {
+            self.violate(format!(
+                "The type {} is not declared in the header.",
+                type_id
+            ));
+            return false;
+        }
+        true
+    }
+
+    /// The characteristic of the field of a declared type.
+    fn type_characteristic(&self, type_id: TypeId) -> &Field {
+        match type_id {
+            0 => &self.field_characteristic,
+            _ => &self.types[type_id as usize - 1],
+        }
+    }
+
+    /// Consume an instance or a witness value of the current type, which is not 0.
+    fn consume_typed_value(&mut self, is_instance: bool) {
+        let type_id = self.current_type;
+        if !is_instance && !self.as_prover {
+            return;
+        }
+        let (instances, witnesses) = self.typed_queue_lens.entry(type_id).or_default();
+        let (len, kind) = if is_instance {
+            (instances, "Instance")
+        } else {
+            (witnesses, "Witness")
+        };
+        if *len > 0 {
+            *len -= 1;
+        } else {
+            self.violate(format!(
+                "Not enough {} value of the type {} to consume.",
+                kind, type_id
+            ));
+        }
+    }
+
+    /// Ensure that the wires from `first` to `last` have the type of the current gate.
+    fn ensure_wire_types(&mut self, first: WireId, last: WireId) {
+        let current_type = self.current_type;
+        // The wires of the type 0 are those which are in no set of `wire_types`.
+        let mut untyped = WireSet::new();
+        untyped.insert_range(first, last);
+        let mut mismatches: Vec<(WireId, WireId, TypeId)> = vec![];
+        for (type_id, wires) in self.wire_types.iter() {
+            for (overlap_first, overlap_last) in wires.overlaps(first, last) {
+                untyped.remove_range(overlap_first, overlap_last);
+                if *type_id != current_type {
+                    mismatches.push((overlap_first, overlap_last, *type_id));
+                }
+            }
+        }
+        if current_type != 0 {
+            mismatches.extend(untyped.ranges().map(|(first, last)| (first, last, 0)));
+        }
+        mismatches.sort_unstable();
+
+        for (mismatch_first, mismatch_last, type_id) in mismatches {
+            for message in describe_wires(
+                mismatch_first,
+                mismatch_last,
+                |id| {
+                    format!(
+                        "The wire {} of the type {} is used by a gate of the type {}.",
+                        id, type_id, current_type
+                    )
+                },
+                |first, last| {
+                    format!(
+                        "The wires {} to {} of the type {} are used by a gate of the type {}.",
+                        first, last, type_id, current_type
+                    )
+                },
+            ) {
+                self.violate(message);
+            }
+        }
+    }
+
+    /// Ingest an equivalent of the AbstractGateCall, along with the number of outputs and inputs.
     /// It will not set the output_wires as defined in the current validator, as well as it will not
     /// consume instances and witnesses of the current validator. It's up to the caller
     /// to do so whenever necessary.
@@ -649,8 +1389,8 @@ impl Validator {
     fn ingest_call(
         &mut self,
         name: &str,
-        output_wires: &[WireId],
-        input_wires: &[WireId],
+        output_wire_count: usize,
+        input_wire_count: usize,
     ) -> Result<(usize, usize)> {
         if !self.known_functions.borrow().contains_key(name) {
             self.violate(format!("Unknown Function gate {}", name));
@@ -659,12 +1399,13 @@ impl Validator {
 
         let (output_count, input_count, instance_count, witness_count) =
             self.known_functions.borrow().get(name).cloned().unwrap();
+        self.called_functions.borrow_mut().insert(name.to_string());
 
-        if output_count != output_wires.len() {
+        if output_count != output_wire_count {
             self.violate("Call: number of output wires mismatch.");
         }
 
-        if input_count != input_wires.len() {
+        if input_count != input_wire_count {
             self.violate("Call: number of input wires mismatch.");
         }
 
@@ -688,7 +1429,15 @@ impl Validator {
         instance_count: usize,
         witness_count: usize,
         use_same_scope: bool,
+        branch: Option<Branch>,
     ) {
+        let entered = self.budget.borrow_mut().enter();
+        if let Err(err) = entered {
+            self.budget.borrow_mut().exit();
+            self.violate(err.to_string());
+            return;
+        }
+
         let mut current_validator = Validator {
             as_prover: self.as_prover,
             instance_queue_len: instance_count,
@@ -700,20 +1449,38 @@ impl Validator {
             header_version: self.header_version.clone(),
             field_characteristic: self.field_characteristic.clone(),
             field_degree: self.field_degree,
+            types: self.types.clone(),
+            current_type: 0,
+            wire_types: Default::default(),
+            typed_queue_lens: Default::default(),
+            in_switch: self.in_switch || branch.is_some(),
             known_functions: self.known_functions.clone(),
             known_iterators: if use_same_scope {
                 self.known_iterators.clone()
             } else {
                 Default::default()
             },
+            called_functions: self.called_functions.clone(),
+            call_graph: Default::default(),
+            unused_wires: Default::default(),
+            copied_wires: Default::default(),
+            constants: Default::default(),
             violations: vec![],
+            lints: vec![],
+            lint_levels: self.lint_levels.clone(),
+            budget: self.budget.clone(),
+            branch,
         };
 
         // input wires should be already defined, and they are numbered from
         // output_wire, so we will artificially define them in the inner
         // validator.
-        for wire in output_count..(output_count + input_count) {
-            current_validator.live_wires.insert(wire as u64);
+        if input_count > 0 {
+            let first_input = output_count as u64;
+            current_validator.live_wires.insert_range(
+                first_input,
+                first_input.saturating_add(input_count as u64 - 1),
+            );
         }
 
         for x in subcircuit.iter() {
@@ -721,9 +1488,33 @@ impl Validator {
         }
 
         // ensure that all output wires are set.
-        (0..output_count).for_each(|id| current_validator.ensure_defined_and_set(id as u64));
+        if output_count > 0 {
+            let last_output = output_count as u64 - 1;
+            if let Some(branch) = current_validator.branch.clone() {
+                for (first, last) in current_validator.live_wires.gaps(0, last_output) {
+                    let messages = describe_wires(
+                        first,
+                        last,
+                        |id| format!("{} does not assign its output {}.", branch.name, id),
+                        |first, last| {
+                            format!(
+                                "{} does not assign its outputs {} to {}.",
+                                branch.name, first, last
+                            )
+                        },
+                    );
+                    for msg in messages {
+                        current_validator.violate(msg);
+                    }
+                    current_validator.declare_range(first, last);
+                }
+            }
+            current_validator.ensure_defined_and_set_range(0, last_output);
+        }
+        current_validator.lint_scope();
 
         self.violations.append(&mut current_validator.violations);
+        self.lints.append(&mut current_validator.lints);
         if current_validator.instance_queue_len != 0 {
             self.violate(
                 "The subcircuit has not consumed all the instance variables it should have.",
@@ -734,19 +1525,54 @@ impl Validator {
                 "The subcircuit has not consumed all the witness variables it should have.",
             )
         }
+        self.budget.borrow_mut().exit();
+    }
+
+    /// A view of the wires of a wire list, or report a violation and return no wire.
+    fn wirelist_view<'a>(&mut self, wirelist: &'a WireList) -> WireListView<'a> {
+        self.limits().wirelist_view(wirelist).unwrap_or_else(|err| {
+            self.violate(err.to_string());
+            WireListView::empty()
+        })
     }
 
-    fn is_defined(&self, id: WireId) -> bool {
-        self.live_wires.contains(&id)
+    /// The ranges of wires of a list of iterator expressions, or report a violation and return no
+    /// range. They are collected because the known iterators cannot stay borrowed while nested
+    /// loops are ingested.
+    fn iterexpr_list_ranges(&mut self, list: &IterExprList) -> Vec<(WireId, WireId)> {
+        let known_iterators = self.known_iterators.clone();
+        let known_iterators = known_iterators.borrow();
+        let result = self
+            .limits()
+            .iterexpr_list_view(list, &known_iterators)
+            .map(|view| view.ranges().collect());
+        result.unwrap_or_else(|err| {
+            self.violate(err.to_string());
+            vec![]
+        })
     }
 
     fn declare(&mut self, id: WireId) {
-        self.live_wires.insert(id);
+        self.declare_range(id, id);
     }
 
-    fn remove(&mut self, id: WireId) {
-        if !self.live_wires.remove(&id) {
-            self.violate(format!("The variable {} is being freed, but was not defined previously, or has been already freed", id));
+    fn declare_range(&mut self, first: WireId, last: WireId) {
+        let max_live_wires = self.limits().max_live_wires as u128;
+        let live_before = self.live_wires.len();
+        self.live_wires.insert_range(first, last);
+        if self.current_type != 0 {
+            self.wire_types
+                .entry(self.current_type)
+                .or_default()
+                .insert_range(first, last);
+        }
+        // Report the limit once, when it is crossed.
+        let live_after = self.live_wires.len();
+        if live_before <= max_live_wires && live_after > max_live_wires {
+            let live_after = usize::try_from(live_after).unwrap_or(usize::MAX);
+            if let Err(err) = self.limits().check_live_wires(live_after) {
+                self.violate(err.to_string());
+            }
         }
     }
 
@@ -770,50 +1596,175 @@ impl Validator {
         }
     }
 
+    /// Ensure that the wire is defined, and count it as used.
     fn ensure_defined_and_set(&mut self, id: WireId) {
-        if !self.is_defined(id) {
-            if self.as_prover {
+        self.ensure_defined_and_set_range(id, id);
+    }
+
+    fn ensure_defined_and_set_range(&mut self, first: WireId, last: WireId) {
+        self.ensure_defined_range(first, last);
+        self.ensure_wire_types(first, last);
+        self.unused_wires.remove_range(first, last);
+    }
+
+    fn ensure_defined_and_set_ranges(&mut self, wires: impl WireRanges) {
+        for (first, last) in wires.ranges() {
+            self.ensure_defined_and_set_range(first, last);
+        }
+    }
+
+    fn ensure_defined_range(&mut self, first: WireId, last: WireId) {
+        for (gap_first, gap_last) in self.live_wires.gaps(first, last) {
+            let messages = if let Some(branch) = &self.branch {
+                // A branch may only read its inputs, even for the verifier.
+                describe_wires(
+                    gap_first,
+                    gap_last,
+                    |id| {
+                        format!(
+                            "{} reads the wire {}, which is neither an input nor assigned before.",
+                            branch.name, id
+                        )
+                    },
+                    |first, last| {
+                        format!(
+                            "{} reads the wires {} to {}, which are neither inputs nor assigned before.",
+                            branch.name, first, last
+                        )
+                    },
+                )
+            } else if self.as_prover {
                 // in this case, this is a violation, since all variables must have been defined
                 // previously
-                self.violate(format!(
-                    "The wire {} is used but was not assigned a value, or has been freed already.",
-                    id
-                ));
+                describe_wires(
+                    gap_first,
+                    gap_last,
+                    |id| {
+                        format!(
+                            "The wire {} is used but was not assigned a value, or has been freed already.",
+                            id
+                        )
+                    },
+                    |first, last| {
+                        format!(
+                            "The wires {} to {} are used but were not assigned a value, or have been freed already.",
+                            first, last
+                        )
+                    },
+                )
+            } else {
+                vec![]
+            };
+            for msg in messages {
+                self.violate(msg);
             }
             // this line is useful to avoid having many times the same message if the validator already
-            // detected that this wire was not previously initialized.
-            self.declare(id);
+            // detected that these wires were not previously initialized.
+            self.declare_range(gap_first, gap_last);
         }
     }
 
-    fn ensure_undefined(&mut self, id: WireId) {
-        if self.is_defined(id) {
-            self.violate(format!(
-                "The wire {} has already been initialized before. This violates the SSA property.",
-                id
-            ));
+    fn ensure_undefined_range(&mut self, first: WireId, last: WireId) {
+        for (set_first, set_last) in self.live_wires.overlaps(first, last) {
+            for msg in self.already_set_messages(set_first, set_last) {
+                self.violate(msg);
+            }
+        }
+    }
+
+    /// Describe the wires from `first` to `last`, which are set again.
+    fn already_set_messages(&self, first: WireId, last: WireId) -> Vec<String> {
+        let ssa = |first: WireId, last: WireId| {
+            describe_wires(
+                first,
+                last,
+                |id| {
+                    format!(
+                        "The wire {} has already been initialized before. This violates the SSA property.",
+                        id
+                    )
+                },
+                |first, last| {
+                    format!(
+                        "The wires {} to {} have already been initialized before. This violates the SSA property.",
+                        first, last
+                    )
+                },
+            )
+        };
+        match &self.branch {
+            Some(branch) if first < branch.output_count as u64 => {
+                let last_output = last.min(branch.output_count as u64 - 1);
+                let mut messages = describe_wires(
+                    first,
+                    last_output,
+                    |id| format!("{} assigns its output {} more than once.", branch.name, id),
+                    |first, last| {
+                        format!(
+                            "{} assigns its outputs {} to {} more than once.",
+                            branch.name, first, last
+                        )
+                    },
+                );
+                if last_output < last {
+                    messages.extend(ssa(last_output + 1, last));
+                }
+                messages
+            }
+            _ => ssa(first, last),
         }
     }
 
     fn ensure_undefined_and_set(&mut self, id: WireId) {
-        self.ensure_undefined(id);
-        // define it.
-        self.declare(id);
+        self.ensure_undefined_and_set_range(id, id);
+    }
+
+    fn ensure_undefined_and_set_range(&mut self, first: WireId, last: WireId) {
+        self.ensure_undefined_range(first, last);
+        // define them.
+        self.declare_range(first, last);
+        self.unused_wires.insert_range(first, last);
+    }
+
+    fn ensure_undefined_and_set_ranges(&mut self, wires: impl WireRanges) {
+        for (first, last) in wires.ranges() {
+            self.ensure_undefined_and_set_range(first, last);
+        }
     }
 
+    /// Ensure that the value is in the field of the current type.
     fn ensure_value_in_field(&mut self, value: &[u8], name: impl Fn() -> String) {
+        self.ensure_value_in_type(self.current_type, value, name);
+    }
+
+    fn ensure_value_in_type(&mut self, type_id: TypeId, value: &[u8], name: impl Fn() -> String) {
+        if let Err(err) = self.limits().check_value(value) {
+            self.violate(err.to_string());
+            return;
+        }
         if value.len() == 0 {
             self.violate(format!("The {} is empty.", name()));
         }
 
         let int = &Field::from_bytes_le(value);
-        if int >= &self.field_characteristic {
-            let msg = format!(
-                "The {} cannot be represented in the field specified in Header ({} >= {}).",
-                name(),
-                int,
-                self.field_characteristic
-            );
+        let characteristic = self.type_characteristic(type_id);
+        if int >= characteristic {
+            let msg = if type_id == 0 {
+                format!(
+                    "The {} cannot be represented in the field specified in Header ({} >= {}).",
+                    name(),
+                    int,
+                    describe_characteristic(characteristic)
+                )
+            } else {
+                format!(
+                    "The {} cannot be represented in the field of the type {} ({} >= {}).",
+                    name(),
+                    type_id,
+                    int,
+                    describe_characteristic(characteristic)
+                )
+            };
             self.violate(msg);
         }
     }
@@ -854,10 +1805,193 @@ impl Validator {
         }
     }
 
+    fn ensure_all_typed_values_consumed(&mut self) {
+        let mut queue_lens: Vec<_> = self.typed_queue_lens.drain().collect();
+        queue_lens.sort_unstable();
+        for (type_id, (instances, witnesses)) in queue_lens {
+            if instances > 0 {
+                self.violate(format!(
+                    "Too many Instance values of the type {} ({} not consumed)",
+                    type_id, instances
+                ));
+            }
+            if self.as_prover && witnesses > 0 {
+                self.violate(format!(
+                    "Too many Witness values of the type {} ({} not consumed)",
+                    type_id, witnesses
+                ));
+            }
+        }
+    }
+
     fn violate(&mut self, msg: impl Into<String>) {
         self.violations.push(msg.into());
         // println!("{}", msg.into());
     }
+
+    fn lint(&mut self, lint: Lint, msg: impl Into<String>) {
+        self.lints.push((lint, msg.into()));
+    }
+
+    /// Report the wires from `first` to `last` which are computed but never used.
+    fn lint_unused_wires(&mut self, first: WireId, last: WireId) {
+        for (unused_first, unused_last) in self.unused_wires.overlaps(first, last) {
+            for msg in describe_unused_wires(unused_first, unused_last) {
+                self.lint(Lint::UnusedWire, msg);
+            }
+        }
+        self.unused_wires.remove_range(first, last);
+    }
+
+    /// Report the lints about the whole current scope, at its end.
+    fn lint_scope(&mut self) {
+        let unused_wires = std::mem::take(&mut self.unused_wires);
+        for (first, last) in unused_wires.ranges() {
+            for msg in describe_unused_wires(first, last) {
+                self.lint(Lint::UnusedWire, msg);
+            }
+        }
+
+        let mut duplicated: Vec<(Field, usize)> = self
+            .constants
+            .drain()
+            .filter(|(_, count)| *count > DUPLICATED_CONSTANT_THRESHOLD)
+            .collect();
+        duplicated.sort();
+        for (value, count) in duplicated {
+            self.lint(
+                Lint::DuplicatedConstant,
+                format!(
+                    "The constant {} is defined by {} Constant gates in the same scope.",
+                    value, count
+                ),
+            );
+        }
+    }
+
+    fn lint_uncalled_functions(&mut self) {
+        let mut uncalled: Vec<String> = self
+            .known_functions
+            .borrow()
+            .keys()
+            .filter(|name| !self.called_functions.borrow().contains(*name))
+            .cloned()
+            .collect();
+        uncalled.sort();
+        for name in uncalled {
+            self.lint(
+                Lint::UncalledFunction,
+                format!("The function {} is defined but never called.", name),
+            );
+        }
+    }
+}
+
+/// The number of wires in a list of ranges.
+fn count_wires(ranges: &[(WireId, WireId)]) -> usize {
+    // Not the number of ranges, as `<[_]>::len` would give.
+    WireRanges::len(&ranges)
+}
+
+/// Describe the wires from `first` to `last` included, with one message per wire if there are
+/// few of them, or a single message for the whole range.
+fn describe_wires(
+    first: WireId,
+    last: WireId,
+    one: impl Fn(WireId) -> String,
+    many: impl Fn(WireId, WireId) -> String,
+) -> Vec<String> {
+    if last - first < WIRES_REPORTED_ONE_BY_ONE {
+        (first..=last).map(one).collect()
+    } else {
+        vec![many(first, last)]
+    }
+}
+
+fn describe_unused_wires(first: WireId, last: WireId) -> Vec<String> {
+    describe_wires(
+        first,
+        last,
+        |id| format!("The wire {} is computed but never used.", id),
+        |first, last| {
+            format!(
+                "The wires {} to {} are computed but never used.",
+                first, last
+            )
+        },
+    )
+}
+
+/// Return whether a loop iterator is used by loops nested in the given gates.
+fn gates_use_iterator(gates: &[Gate], iterator_name: &str) -> bool {
+    gates.iter().any(|gate| match gate {
+        Gate::AnonCall(_, _, _, _, subcircuit) => gates_use_iterator(subcircuit, iterator_name),
+        Gate::Switch(_, _, _, branches) => branches.iter().any(|branch| match branch {
+            CaseInvoke::AbstractGateCall(_, _) => false,
+            CaseInvoke::AbstractAnonCall(_, _, _, subcircuit) => {
+                gates_use_iterator(subcircuit, iterator_name)
+            }
+        }),
+        Gate::For(_, _, _, _, body) => match body {
+            ForLoopBody::IterExprCall(_, outputs, inputs) => {
+                iterexpr_list_uses_iterator(outputs, iterator_name)
+                    || iterexpr_list_uses_iterator(inputs, iterator_name)
+            }
+            ForLoopBody::IterExprAnonCall(outputs, inputs, _, _, subcircuit) => {
+                iterexpr_list_uses_iterator(outputs, iterator_name)
+                    || iterexpr_list_uses_iterator(inputs, iterator_name)
+                    || gates_use_iterator(subcircuit, iterator_name)
+            }
+        },
+        _ => false,
+    })
+}
+
+/// Return `distance` such that the input at iteration `i` is the output at iteration
+/// `i - distance`, if any.
+fn previous_iteration_distance(input: &AffineExpr, output: &AffineExpr) -> Option<i128> {
+    // slope * i + input.offset == slope * (i - distance) + output.offset
+    let difference = output.offset - input.offset;
+    if input.slope == 0 {
+        return if difference == 0 { Some(0) } else { None };
+    }
+    if difference % input.slope != 0 {
+        return None;
+    }
+    Some(difference / input.slope)
+}
+
+/// Return a wire set by two outputs of a loop (or twice by the same output), if any.
+/// Iterations are numbered from `start` to `start + last`.
+fn first_collision(
+    first: &AffineExpr,
+    second: &AffineExpr,
+    start: i128,
+    last: i128,
+    same_output: bool,
+) -> Option<WireId> {
+    if first.slope != second.slope || (same_output && first.slope != 0) {
+        return None;
+    }
+    // first at iteration i == second at iteration j <=> slope * (i - j) == difference
+    let difference = second.offset - first.offset;
+    let distance = if first.slope == 0 {
+        match (difference, same_output) {
+            (0, true) => 1,
+            (0, false) => 0,
+            _ => return None,
+        }
+    } else if difference % first.slope == 0 {
+        difference / first.slope
+    } else {
+        return None;
+    };
+    if distance.abs() > last {
+        return None;
+    }
+    first
+        .evaluate(start + distance.max(0))
+        .map(|wire| wire as WireId)
 }
 
 #[test]
@@ -927,6 +2061,117 @@ fn test_validator_violations() -> crate::Result<()> {
         "Not enough Witness value to consume.",
     ]);
 
+    // Well-known fields are named.
+    let mut instance = example_instance_h(&Header::from_preset("gf2")?);
+    instance.common_inputs = vec![vec![2]];
+    let mut validator = Validator::new_as_prover();
+    validator.ingest_instance(&instance);
+    assert!(validator.get_violations().contains(
+        &"The instance value [2] cannot be represented in the field specified in Header (2 >= 2 (gf2))."
+            .to_string()
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_validator_types() -> crate::Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::wire::WireListElement;
+    use crate::wirelist;
+
+    let mut validator = Validator::new_as_prover();
+    validator.ingest_instance(&example_typed_instance());
+    validator.ingest_witness(&example_typed_witness());
+    validator.ingest_relation(&example_typed_relation());
+    assert_eq!(validator.get_violations(), Vec::<String>::new());
+
+    let mut validator = Validator::new_as_verifier();
+    validator.ingest_instance(&example_typed_instance());
+    validator.ingest_relation(&example_typed_relation());
+    assert_eq!(validator.get_violations(), Vec::<String>::new());
+
+    let mut witness = example_typed_witness();
+    witness.short_witness[0] = vec![2];
+    witness.short_witness.push(vec![1]);
+    let mut relation = example_typed_relation();
+    relation.feat_mask = 0;
+    relation.gates[9] = Gate::Add(8, 5, 0);
+    relation
+        .gates
+        .push(Gate::Typed(2, Box::new(Gate::Constant(9, vec![1]))));
+    relation
+        .gates
+        .push(Gate::Typed(1, Box::new(Gate::Free(0, None))));
+
+    let mut validator = Validator::new_as_prover();
+    validator.ingest_instance(&example_typed_instance());
+    validator.ingest_witness(&witness);
+    validator.ingest_relation(&relation);
+    assert_eq!(
+        validator.get_violations(),
+        vec![
+            "The witness value [2] cannot be represented in the field of the type 1 (2 >= 2 (gf2)).",
+            "The feature @convert is not allowed in this circuit.",
+            "The wire 0 of the type 1 is used by a gate of the type 0.",
+            "The type 2 is not declared in the header.",
+            "Only basic gates can be of the type 1.",
+            "Too many Witness values of the type 1 (1 not consumed)",
+        ]
+    );
+
+    // The types of ranges of wires are checked without expanding them.
+    let mut relation = example_typed_relation();
+    relation.gates.extend(vec![
+        Gate::Convert(
+            1,
+            vec![WireListElement::WireRange(100, 1 << 40)],
+            0,
+            wirelist![5],
+        ),
+        Gate::Add(9, 100, 5),
+        Gate::Typed(1, Box::new(Gate::Add(10, 100, 6))),
+        Gate::Free(100, Some(1 << 40)),
+    ]);
+    let mut validator = Validator::new_as_prover();
+    validator.set_limits(Limits::unlimited());
+    validator.ingest_instance(&example_typed_instance());
+    validator.ingest_witness(&example_typed_witness());
+    validator.ingest_relation(&relation);
+    assert_eq!(
+        validator.get_violations(),
+        vec![
+            "The wire 100 of the type 1 is used by a gate of the type 0.",
+            "The wire 6 of the type 0 is used by a gate of the type 1.",
+        ]
+    );
+
+    // Types need the version 2.0.0.
+    let mut header = example_typed_header();
+    header.version = "1.0.0".to_string();
+    let mut validator = Validator::new_as_prover();
+    validator.ingest_instance(&example_instance_h(&header));
+    assert!(validator
+        .get_violations()
+        .contains(&"Types are only supported from the version 2.0.0.".to_string()));
+
+    // Typed values are consumed by the gates of the relation only.
+    let mut relation = example_typed_relation();
+    relation.feat_mask |= FUNCTION;
+    relation.gates = vec![Gate::AnonCall(
+        wirelist![0],
+        wirelist![],
+        0,
+        0,
+        vec![Gate::Typed(1, Box::new(Gate::Witness(0)))],
+    )];
+    let mut validator = Validator::new_as_prover();
+    validator.ingest_relation(&relation);
+    assert!(validator.get_violations().contains(
+        &"Instance and Witness gates of the type 1 are not allowed in functions, loops or switches."
+            .to_string()
+    ));
+
     Ok(())
 }
 
@@ -958,3 +2203,449 @@ fn test_validator_free_violations() -> crate::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_validator_switch_branches() -> crate::Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::function::CaseInvoke::AbstractAnonCall;
+    use crate::structs::wire::WireListElement;
+    use crate::wirelist;
+    use crate::Gate::*;
+
+    let instance = example_instance();
+    let mut relation = example_relation();
+
+    relation.gates.push(Switch(
+        1,
+        wirelist![200, 201],
+        vec![vec![3], vec![3], vec![101]],
+        vec![
+            // The output 1 is not assigned.
+            AbstractAnonCall(wirelist![1], 0, 0, vec![Copy(0, 2)]),
+            // The output 1 is assigned twice.
+            AbstractAnonCall(wirelist![1], 0, 0, vec![Copy(0, 2), Copy(1, 2), Copy(1, 2)]),
+            // The wire 3 is neither an input nor assigned.
+            AbstractAnonCall(wirelist![1], 0, 0, vec![Copy(0, 2), Copy(1, 3)]),
+        ],
+    ));
+
+    // The verifier reports them too.
+    let mut validator = Validator::new_as_verifier();
+    validator.ingest_instance(&instance);
+    validator.ingest_relation(&relation);
+
+    let violations = validator.get_violations();
+    assert_eq!(
+        violations,
+        vec![
+            "Gate::Switch: The case value 3 appears more than once.",
+            "The Gate::Switch case value: 101 cannot be represented in the field specified in Header (101 >= 101).",
+            "The branch 0 of the switch on the wire 1 does not assign its output 1.",
+            "The branch 1 of the switch on the wire 1 assigns its output 1 more than once.",
+            "The branch 2 of the switch on the wire 1 reads the wire 3, which is neither an input nor assigned before.",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_validator_recursive_functions() -> crate::Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::function::Function;
+    use crate::structs::wire::WireListElement;
+    use crate::wirelist;
+
+    let instance = example_instance();
+    let witness = example_witness();
+    let mut relation = example_relation();
+
+    let call = |name: &str| vec![Gate::Call(name.to_string(), wirelist![0], wirelist![1])];
+    relation.functions.extend(vec![
+        Function::new("rec::f".to_string(), 1, 1, 0, 0, call("rec::f")),
+        Function::new("rec::g".to_string(), 1, 1, 0, 0, call("rec::h")),
+        Function::new("rec::h".to_string(), 1, 1, 0, 0, call("rec::g")),
+    ]);
+
+    let mut validator = Validator::new_as_prover();
+    validator.ingest_instance(&instance);
+    validator.ingest_witness(&witness);
+    validator.ingest_relation(&relation);
+
+    let violations = validator.get_violations();
+    assert_eq!(
+        violations,
+        vec![
+            "The function rec::f is recursive: rec::f -> rec::f.",
+            "The function rec::g is recursive: rec::g -> rec::h -> rec::g.",
+            "Unknown Function gate rec::h",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_validator_lints() -> crate::Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::function::{CaseInvoke::*, Function};
+    use crate::structs::iterators::{IterExprListElement::*, IterExprWireNumber::*};
+    use crate::structs::wire::WireListElement;
+    use crate::wirelist;
+    use Gate::*;
+
+    let instance = example_instance();
+    let mut relation = example_relation();
+    relation.functions.push(Function::new(
+        "com.example::unused".to_string(),
+        1,
+        1,
+        0,
+        0,
+        vec![Copy(0, 1)],
+    ));
+    relation.gates.extend(vec![
+        Constant(60, vec![1]),
+        Constant(61, vec![1]),
+        Constant(62, vec![1]),
+        Constant(63, vec![1]),
+        Copy(64, 60),
+        Copy(65, 64),
+        Switch(
+            61,
+            wirelist![66],
+            vec![vec![1]],
+            vec![AbstractAnonCall(wirelist![62], 0, 0, vec![Copy(0, 1)])],
+        ),
+        For(
+            "j".into(),
+            70,
+            70,
+            wirelist![70],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(IterExprName("j".into()))],
+                vec![Single(IterExprConst(63))],
+                0,
+                0,
+                vec![Copy(0, 1)],
+            ),
+        ),
+        Free(60, Some(70)),
+    ]);
+
+    let mut validator = Validator::new_as_verifier();
+    validator.ingest_instance(&instance);
+    validator.ingest_relation(&relation);
+
+    let (violations, warnings) = validator.clone().get_violations_and_warnings();
+    assert_eq!(violations, Vec::<String>::new());
+    assert_eq!(
+        warnings,
+        vec![
+            "The wire 2 is computed but never used. [unused-wire]",
+            "The wire 4 is computed but never used. [unused-wire]",
+            "The wire 5 is computed but never used. [unused-wire]",
+            "The wire 50 is computed but never used. [unused-wire]",
+            "The wire 65 is a copy of the wire 64, which is itself a copy. [copy-chain]",
+            "The switch on the wire 61 has a single case. [single-case-switch]",
+            "The loop over j has one iteration. [trivial-loop]",
+            "The wire 65 is computed but never used. [unused-wire]",
+            "The wire 66 is computed but never used. [unused-wire]",
+            "The wire 70 is computed but never used. [unused-wire]",
+            "The gate Free(60, 70) covers 3 wires which are not allocated in this scope. [free-unallocated]",
+            "The constant 1 is defined by 4 Constant gates in the same scope. [duplicated-constant]",
+            "The function com.example::unused is defined but never called. [uncalled-function]",
+        ]
+    );
+
+    // Deny some lints, and ignore the others.
+    let mut lint_levels = LintLevels::default();
+    lint_levels.set_all(Severity::Allow);
+    lint_levels.set(Lint::UnusedWire, Severity::Deny);
+    validator.set_lint_levels(lint_levels);
+
+    let (violations, warnings) = validator.get_violations_and_warnings();
+    assert_eq!(violations.len(), 7);
+    assert_eq!(warnings, Vec::<String>::new());
+
+    Ok(())
+}
+
+#[test]
+fn test_validator_symbolic_loops() -> crate::Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::iterators::{
+        IterExprListElement::*, IterExprWireNumber, IterExprWireNumber::*,
+    };
+    use crate::structs::wire::WireListElement::*;
+    use Gate::*;
+
+    fn plus(name: &str, offset: u64) -> IterExprWireNumber {
+        IterExprAdd(
+            Box::new(IterExprName(name.into())),
+            Box::new(IterExprConst(offset)),
+        )
+    }
+
+    let instance = example_instance();
+    let mut relation = example_relation();
+    relation.gates.extend(vec![
+        Constant(1000, vec![1]),
+        // A long chain of copies, validated without unrolling.
+        For(
+            "i".into(),
+            1,
+            1_000_000,
+            vec![WireRange(1001, 1_001_000)],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(plus("i", 1000))],
+                vec![Single(plus("i", 999))],
+                0,
+                0,
+                vec![Copy(0, 1)],
+            ),
+        ),
+        // The second output of an iteration is the first output of the next one.
+        For(
+            "i".into(),
+            0,
+            9,
+            vec![WireRange(2000, 2010)],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(plus("i", 2000)), Single(plus("i", 2001))],
+                vec![Single(IterExprConst(1000))],
+                0,
+                0,
+                vec![Copy(0, 2), Copy(1, 2)],
+            ),
+        ),
+        // The last iterations set wires outside of the output list.
+        For(
+            "j".into(),
+            0,
+            9,
+            vec![WireRange(3000, 3005)],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(plus("j", 3000))],
+                vec![Single(IterExprConst(1000))],
+                0,
+                0,
+                vec![Copy(0, 1)],
+            ),
+        ),
+        // A non-affine loop is unrolled.
+        For(
+            "k".into(),
+            0,
+            2,
+            vec![Wire(4000), Wire(4001), Wire(4004)],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(IterExprAdd(
+                    Box::new(IterExprMul(
+                        Box::new(IterExprName("k".into())),
+                        Box::new(IterExprName("k".into())),
+                    )),
+                    Box::new(IterExprConst(4000)),
+                ))],
+                vec![Single(IterExprConst(1000))],
+                0,
+                0,
+                vec![Copy(0, 1)],
+            ),
+        ),
+        Free(1000, Some(1_001_000)),
+    ]);
+
+    let mut validator = Validator::new_as_verifier();
+    validator.ingest_instance(&instance);
+    validator.ingest_relation(&relation);
+
+    assert_eq!(
+        validator.get_violations(),
+        vec![
+            "The wire 2001 is set several times in the loop over i. This violates the SSA property.",
+            "The wire 3006 is set in the loop over j but is not in its output list.",
+            "The wire 3007 is set in the loop over j but is not in its output list.",
+            "The wire 3008 is set in the loop over j but is not in its output list.",
+            "The wire 3009 is set in the loop over j but is not in its output list.",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_validator_huge_ranges() -> crate::Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::iterators::{IterExprListElement::*, IterExprWireNumber::*};
+    use crate::structs::wire::WireListElement::*;
+    use Gate::*;
+
+    // Ranges of 2^40 wires are validated as fast as single wires.
+    let n: u64 = 1 << 40;
+    let two_k_plus = |offset| {
+        IterExprAdd(
+            Box::new(IterExprMul(
+                Box::new(IterExprConst(2)),
+                Box::new(IterExprName("k".into())),
+            )),
+            Box::new(IterExprConst(offset)),
+        )
+    };
+    let instance = example_instance();
+    let witness = example_witness();
+    let mut relation = example_relation();
+    relation.gates.extend(vec![
+        Constant(n - 1, vec![1]),
+        // Copy the constant into the wires n to 2n - 1.
+        For(
+            "i".into(),
+            n,
+            2 * n - 1,
+            vec![WireRange(n, 2 * n - 1)],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(IterExprName("i".into()))],
+                vec![Single(IterExprConst(n - 1))],
+                0,
+                0,
+                vec![Copy(0, 1)],
+            ),
+        ),
+        // Copy them again into the wires 2n to 3n - 1.
+        AnonCall(
+            vec![WireRange(2 * n, 3 * n - 1)],
+            vec![WireRange(n, 2 * n - 1)],
+            0,
+            0,
+            vec![For(
+                "j".into(),
+                0,
+                n - 1,
+                vec![WireRange(0, n - 1)],
+                ForLoopBody::IterExprAnonCall(
+                    vec![Single(IterExprName("j".into()))],
+                    vec![Single(IterExprAdd(
+                        Box::new(IterExprName("j".into())),
+                        Box::new(IterExprConst(n)),
+                    ))],
+                    0,
+                    0,
+                    vec![Copy(0, 1)],
+                ),
+            )],
+        ),
+        Free(n, Some(2 * n - 1)),
+        // Read freed wires.
+        AnonCall(
+            vec![Wire(4 * n)],
+            vec![WireRange(n + 1, n + 100)],
+            0,
+            0,
+            vec![Copy(0, 1)],
+        ),
+        Free(2 * n, Some(3 * n - 1)),
+        Free(4 * n, None),
+        // Set the wires 5n to 7n - 1, two wires per iteration.
+        For(
+            "k".into(),
+            0,
+            n - 1,
+            vec![WireRange(5 * n, 7 * n - 1)],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(two_k_plus(5 * n)), Single(two_k_plus(5 * n + 1))],
+                vec![Single(IterExprConst(n - 1))],
+                0,
+                0,
+                vec![Copy(0, 2), Copy(1, 2)],
+            ),
+        ),
+        Free(5 * n, Some(7 * n - 1)),
+    ]);
+
+    let mut validator = Validator::new_as_prover();
+    validator.set_limits(Limits::unlimited());
+    validator.ingest_instance(&instance);
+    validator.ingest_witness(&witness);
+    validator.ingest_relation(&relation);
+
+    assert_eq!(
+        validator.get_violations(),
+        vec![format!(
+            "The wires {} to {} are used but were not assigned a value, or have been freed already.",
+            n + 1,
+            n + 100
+        )]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_validator_limits() -> crate::Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::iterators::{IterExprListElement::*, IterExprWireNumber::*};
+    use crate::structs::wire::WireListElement::*;
+
+    let instance = example_instance();
+    let witness = example_witness();
+    let mut relation = example_relation();
+    relation.gates.push(Gate::Free(100, Some(u64::MAX)));
+
+    let mut validator = Validator::new_as_prover();
+    validator.set_limits(Limits {
+        max_wire_list_size: 1 << 28,
+        ..Limits::default()
+    });
+    validator.ingest_instance(&instance);
+    validator.ingest_witness(&witness);
+    validator.ingest_relation(&relation);
+
+    let violations = validator.get_violations();
+    assert_eq!(
+        violations,
+        vec!["A list of wires has 18446744073709551516 wires, more than the limit of 268435456 (max_wire_list_size)."]
+    );
+
+    // A loop whose outputs are too sparse to be validated symbolically is unrolled under the
+    // limits, instead of checking its 2^30 outputs one by one.
+    let n: u64 = 1 << 30;
+    let mut relation = example_relation();
+    relation.gates.extend(vec![
+        Gate::Constant(999, vec![1]),
+        Gate::For(
+            "i".into(),
+            0,
+            n - 1,
+            vec![WireRange(1000, 1000 + 2 * n)],
+            ForLoopBody::IterExprAnonCall(
+                vec![Single(IterExprAdd(
+                    Box::new(IterExprMul(
+                        Box::new(IterExprConst(2)),
+                        Box::new(IterExprName("i".into())),
+                    )),
+                    Box::new(IterExprConst(1000)),
+                ))],
+                vec![Single(IterExprConst(999))],
+                0,
+                0,
+                vec![Gate::Copy(0, 1)],
+            ),
+        ),
+    ]);
+
+    let mut validator = Validator::new_as_prover();
+    validator.set_limits(Limits {
+        max_unrolled_gates: 1000,
+        ..Limits::default()
+    });
+    validator.ingest_instance(&instance);
+    validator.ingest_witness(&witness);
+    validator.ingest_relation(&relation);
+
+    assert!(validator.get_violations().contains(
+        &"More than 1000 gates are processed once functions are inlined and loops unrolled (max_unrolled_gates)."
+            .to_string()
+    ));
+
+    Ok(())
+}
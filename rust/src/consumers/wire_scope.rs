@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use crate::WireId;
+
+/// Wires are stored in pages of this many consecutive ids.
+const PAGE_BITS: u32 = 10;
+const PAGE_SIZE: u64 = 1 << PAGE_BITS;
+/// Pages that can always be allocated, whatever the number of wires.
+const MIN_DENSE_PAGES: u64 = 16;
+/// Pages are allocated while at least one slot out of this many holds a wire. Wires with sparser
+/// ids are kept in a map.
+const MIN_DENSITY: u64 = 8;
+
+#[derive(Clone, Debug)]
+struct Page<W> {
+    // Grows up to PAGE_SIZE slots as needed, so that small scopes stay small.
+    slots: Vec<Option<W>>,
+    live: usize,
+}
+
+/// WireScope maps wire ids to wires, like a `HashMap<WireId, W>` without hashing.
+///
+/// Ids are usually small and dense in a scope, because the body of a function or a loop numbers
+/// its wires from 0. They are stored in pages of a vector indexed by id. Ids too large or too
+/// sparse to be worth a page, such as temporary wires, fall back to a map.
+///
+/// A new scope does not allocate until a wire is inserted, so it is cheap to create one for each
+/// call of a function.
+#[derive(Clone, Debug)]
+pub struct WireScope<W> {
+    pages: Vec<Page<W>>,
+    allocated_pages: u64,
+    sparse: HashMap<WireId, W>,
+    len: usize,
+}
+
+impl<W> Default for WireScope<W> {
+    fn default() -> Self {
+        WireScope {
+            pages: vec![],
+            allocated_pages: 0,
+            sparse: HashMap::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<W> WireScope<W> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of wires in the scope.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: WireId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: WireId) -> Option<&W> {
+        match self.slot(id) {
+            Some(Some(wire)) => Some(wire),
+            _ if self.sparse.is_empty() => None,
+            _ => self.sparse.get(&id),
+        }
+    }
+
+    /// Insert a wire, and return the wire which had the same id, if any.
+    pub fn insert(&mut self, id: WireId, wire: W) -> Option<W> {
+        if !self.sparse.is_empty() {
+            if let Some(previous) = self.sparse.get_mut(&id) {
+                return Some(std::mem::replace(previous, wire));
+            }
+        }
+
+        let (page_index, offset) = split(id);
+        if !self.can_use_page(page_index) {
+            self.len += 1;
+            return self.sparse.insert(id, wire);
+        }
+
+        let page_index = page_index as usize;
+        if page_index >= self.pages.len() {
+            self.pages.resize_with(page_index + 1, || Page {
+                slots: vec![],
+                live: 0,
+            });
+        }
+        let page = &mut self.pages[page_index];
+        if page.live == 0 {
+            self.allocated_pages += 1;
+        }
+        if offset >= page.slots.len() {
+            page.slots.resize_with(offset + 1, || None);
+        }
+        let previous = page.slots[offset].replace(wire);
+        if previous.is_none() {
+            page.live += 1;
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Remove a wire, and return it if it was in the scope.
+    pub fn remove(&mut self, id: WireId) -> Option<W> {
+        let (page_index, offset) = split(id);
+        let page = match usize::try_from(page_index) {
+            Ok(page_index) => self.pages.get_mut(page_index),
+            Err(_) => None,
+        };
+        if let Some(page) = page {
+            if let Some(wire) = page.slots.get_mut(offset).and_then(|slot| slot.take()) {
+                page.live -= 1;
+                self.len -= 1;
+                // Release the memory of pages without wires.
+                if page.live == 0 {
+                    page.slots = vec![];
+                    self.allocated_pages -= 1;
+                }
+                return Some(wire);
+            }
+        }
+        let wire = self.sparse.remove(&id);
+        if wire.is_some() {
+            self.len -= 1;
+        }
+        wire
+    }
+
+    fn slot(&self, id: WireId) -> Option<&Option<W>> {
+        let (page_index, offset) = split(id);
+        self.page(page_index)?.slots.get(offset)
+    }
+
+    fn page(&self, page_index: u64) -> Option<&Page<W>> {
+        self.pages.get(usize::try_from(page_index).ok()?)
+    }
+
+    /// Whether a page may hold wires, without using much more memory than the wires themselves.
+    fn can_use_page(&self, page_index: u64) -> bool {
+        if let Some(page) = self.page(page_index) {
+            if page.live > 0 {
+                return true;
+            }
+        }
+        let live = self.len as u64;
+        page_index < MIN_DENSE_PAGES + live
+            && (self.allocated_pages < MIN_DENSE_PAGES
+                || self.allocated_pages * PAGE_SIZE < MIN_DENSITY * live)
+    }
+}
+
+fn split(id: WireId) -> (u64, usize) {
+    (id >> PAGE_BITS, (id & (PAGE_SIZE - 1)) as usize)
+}
+
+#[test]
+fn test_wire_scope() {
+    let mut scope = WireScope::new();
+    assert!(scope.is_empty());
+
+    // Dense ids.
+    for id in 0..5000 {
+        assert_eq!(scope.insert(id, id * 10), None);
+    }
+    assert_eq!(scope.insert(42, 0), Some(420));
+    assert_eq!(scope.get(42), Some(&0));
+    assert_eq!(scope.get(4999), Some(&49990));
+    assert_eq!(scope.get(5000), None);
+    assert_eq!(scope.len(), 5000);
+    assert!(scope.sparse.is_empty());
+
+    // Sparse ids, such as temporary wires.
+    let temporary = 1u64 << 63;
+    assert_eq!(scope.insert(temporary, 1), None);
+    assert_eq!(scope.insert(u64::MAX, 2), None);
+    assert_eq!(scope.insert(temporary, 3), Some(1));
+    assert_eq!(scope.get(temporary), Some(&3));
+    assert_eq!(scope.get(u64::MAX), Some(&2));
+    assert_eq!(scope.len(), 5002);
+    assert_eq!(scope.sparse.len(), 2);
+
+    // Removing all the wires of a page releases it.
+    for id in 0..1024 {
+        assert_eq!(scope.remove(id), Some(if id == 42 { 0 } else { id * 10 }));
+    }
+    assert_eq!(scope.remove(0), None);
+    assert!(scope.pages[0].slots.is_empty());
+    assert_eq!(scope.remove(u64::MAX), Some(2));
+    assert_eq!(scope.len(), 5000 - 1024 + 1);
+    assert!(!scope.contains(u64::MAX));
+    assert!(scope.contains(1024));
+}
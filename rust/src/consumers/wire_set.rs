@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+
+use crate::structs::wire::WireRanges;
+use crate::WireId;
+
+/// WireSet is a set of wire ids stored as disjoint intervals, like a `BTreeSet<WireId>` whose
+/// operations on ranges of wires cost the number of intervals they touch, not the number of wires.
+///
+/// Ranges are given as (first, last) with `first <= last`, both included.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WireSet {
+    // first => last, for intervals which neither overlap nor touch each other.
+    intervals: BTreeMap<WireId, WireId>,
+    // Up to 2^64 wires.
+    len: u128,
+}
+
+impl WireSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_ranges(wires: impl WireRanges) -> Self {
+        let mut set = WireSet::default();
+        for (first, last) in wires.ranges() {
+            set.insert_range(first, last);
+        }
+        set
+    }
+
+    /// The number of wires in the set.
+    pub fn len(&self) -> u128 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: WireId) -> bool {
+        match self.intervals.range(..=id).next_back() {
+            Some((_, last)) => id <= *last,
+            None => false,
+        }
+    }
+
+    /// The intervals of the set, in increasing order.
+    pub fn ranges(&self) -> impl Iterator<Item = (WireId, WireId)> + '_ {
+        self.intervals.iter().map(|(first, last)| (*first, *last))
+    }
+
+    /// Insert a wire, and return whether it was not in the set.
+    pub fn insert(&mut self, id: WireId) -> bool {
+        if self.contains(id) {
+            return false;
+        }
+        self.insert_range(id, id);
+        true
+    }
+
+    pub fn insert_range(&mut self, first: WireId, last: WireId) {
+        let (mut first, mut last) = (first, last);
+
+        // Merge with the interval before, if it overlaps or touches the range.
+        let previous = self.intervals.range(..=first).next_back();
+        if let Some((&previous_first, &previous_last)) = previous {
+            if previous_last.saturating_add(1) >= first {
+                self.remove_interval(previous_first, previous_last);
+                first = previous_first;
+                last = last.max(previous_last);
+            }
+        }
+        // Merge with the intervals after, while they overlap or touch the range.
+        loop {
+            let next = self.intervals.range(first..).next();
+            match next {
+                Some((&next_first, &next_last)) if next_first <= last.saturating_add(1) => {
+                    self.remove_interval(next_first, next_last);
+                    last = last.max(next_last);
+                }
+                _ => break,
+            }
+        }
+
+        self.intervals.insert(first, last);
+        self.len += range_len(first, last);
+    }
+
+    /// Remove a wire, and return whether it was in the set.
+    pub fn remove(&mut self, id: WireId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.remove_range(id, id);
+        true
+    }
+
+    pub fn remove_range(&mut self, first: WireId, last: WireId) {
+        for (overlap_first, overlap_last) in self.touched(first, last) {
+            self.remove_interval(overlap_first, overlap_last);
+            // Keep the parts of the interval outside of the range.
+            if overlap_first < first {
+                self.intervals.insert(overlap_first, first - 1);
+                self.len += range_len(overlap_first, first - 1);
+            }
+            if overlap_last > last {
+                self.intervals.insert(last + 1, overlap_last);
+                self.len += range_len(last + 1, overlap_last);
+            }
+        }
+    }
+
+    /// The parts of the range which are in the set, in increasing order.
+    pub fn overlaps(&self, first: WireId, last: WireId) -> Vec<(WireId, WireId)> {
+        self.touched(first, last)
+            .into_iter()
+            .map(|(overlap_first, overlap_last)| (overlap_first.max(first), overlap_last.min(last)))
+            .collect()
+    }
+
+    /// The parts of the range which are not in the set, in increasing order.
+    pub fn gaps(&self, first: WireId, last: WireId) -> Vec<(WireId, WireId)> {
+        let mut gaps = vec![];
+        let mut next = first;
+        for (overlap_first, overlap_last) in self.overlaps(first, last) {
+            if overlap_first > next {
+                gaps.push((next, overlap_first - 1));
+            }
+            if overlap_last == last {
+                return gaps;
+            }
+            next = overlap_last + 1;
+        }
+        gaps.push((next, last));
+        gaps
+    }
+
+    /// The number of wires of the range which are in the set.
+    pub fn count_range(&self, first: WireId, last: WireId) -> u128 {
+        self.overlaps(first, last)
+            .into_iter()
+            .map(|(overlap_first, overlap_last)| range_len(overlap_first, overlap_last))
+            .sum()
+    }
+
+    /// The intervals which overlap the range, in increasing order.
+    fn touched(&self, first: WireId, last: WireId) -> Vec<(WireId, WireId)> {
+        let mut touched: Vec<(WireId, WireId)> = self
+            .intervals
+            .range(..=last)
+            .rev()
+            .take_while(|(_, interval_last)| **interval_last >= first)
+            .map(|(interval_first, interval_last)| (*interval_first, *interval_last))
+            .collect();
+        touched.reverse();
+        touched
+    }
+
+    fn remove_interval(&mut self, first: WireId, last: WireId) {
+        self.intervals.remove(&first);
+        self.len -= range_len(first, last);
+    }
+}
+
+/// The number of wires from `first` to `last` included.
+fn range_len(first: WireId, last: WireId) -> u128 {
+    (last - first) as u128 + 1
+}
+
+#[test]
+fn test_wire_set() {
+    let mut set = WireSet::new();
+    assert!(set.is_empty());
+
+    assert!(set.insert(5));
+    assert!(!set.insert(5));
+    set.insert_range(10, 19);
+    set.insert_range(7, 8);
+    assert_eq!(
+        set.ranges().collect::<Vec<_>>(),
+        vec![(5, 5), (7, 8), (10, 19)]
+    );
+    assert_eq!(set.len(), 13);
+
+    // Touching and overlapping ranges are merged.
+    set.insert_range(6, 6);
+    set.insert_range(9, 12);
+    assert_eq!(set.ranges().collect::<Vec<_>>(), vec![(5, 19)]);
+    assert_eq!(set.len(), 15);
+
+    // Huge ranges cost as much as small ones.
+    set.insert_range(1 << 40, u64::MAX);
+    assert_eq!(set.len(), 15 + (1u128 << 64) - (1u128 << 40));
+    assert!(set.contains(u64::MAX));
+    assert!(!set.contains(20));
+    assert_eq!(set.gaps(0, 1 << 41), vec![(0, 4), (20, (1 << 40) - 1)]);
+    assert_eq!(
+        set.overlaps(10, 1 << 41),
+        vec![(10, 19), (1 << 40, 1 << 41)]
+    );
+    assert_eq!(set.count_range(0, 1 << 40), 16);
+
+    // Removing a range splits the intervals it overlaps.
+    set.remove_range(8, (1 << 40) + 9);
+    assert_eq!(
+        set.ranges().collect::<Vec<_>>(),
+        vec![(5, 7), ((1 << 40) + 10, u64::MAX)]
+    );
+    assert!(set.remove(6));
+    assert!(!set.remove(6));
+    assert_eq!(set.gaps(5, 7), vec![(6, 6)]);
+    assert_eq!(set.gaps(5, 5), Vec::<(WireId, WireId)>::new());
+    set.remove_range(0, u64::MAX);
+    assert!(set.is_empty());
+    assert_eq!(set.gaps(0, u64::MAX), vec![(0, u64::MAX)]);
+}
@@ -40,7 +40,7 @@ pub use producers::sink::{clean_workspace, FilesSink, Sink};
 pub use sieve_ir_generated::sieve_ir::ROOT_EXTENSION as FILE_EXTENSION;
 pub use structs::{
     gates::Gate, header::Header, instance::Instance, message::Message, messages::Messages,
-    relation::Relation, value::Value, witness::Witness, WireId,
+    relation::Relation, value::Value, witness::Witness, TypeId, WireId,
 };
 
 /// Common definition of Result with generic errors.
@@ -38,6 +38,7 @@ pub fn example_instance_h(header: &Header) -> Instance {
             vec![0],
             vec![1],
         ],
+        type_id: 0,
     }
 }
 
@@ -51,6 +52,7 @@ pub fn example_witness_h(header: &Header) -> Witness {
             vec![0],
             vec![0],
         ],
+        type_id: 0,
     }
 }
 
@@ -64,6 +66,7 @@ pub fn example_witness_incorrect_h(header: &Header) -> Witness {
             vec![0],
             vec![0],
         ],
+        type_id: 0,
     }
 }
 
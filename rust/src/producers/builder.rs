@@ -8,7 +8,7 @@ pub use super::build_gates::{BuildComplexGate, BuildGate};
 use crate::producers::sink::MemorySink;
 use crate::structs::gates::replace_output_wires;
 use crate::structs::relation::{ARITH, SIMPLE};
-use crate::structs::wire::{expand_wirelist, wirelist_len, WireList, WireListElement};
+use crate::structs::wire::{WireList, WireListElement, WireListView, WireRanges};
 use crate::structs::{function::CaseInvoke, function::Function, value::Value};
 use crate::Result;
 use crate::{Gate, Header, Instance, Relation, Sink, WireId, Witness};
@@ -56,10 +56,12 @@ impl<S: Sink> MessageBuilder<S> {
             instance: Instance {
                 header: header.clone(),
                 common_inputs: vec![],
+                type_id: 0,
             },
             witness: Witness {
                 header: header.clone(),
                 short_witness: vec![],
+                type_id: 0,
             },
             relation: Relation {
                 header: header.clone(),
@@ -279,7 +281,7 @@ impl<S: Sink> GateBuilderT for GateBuilder<S> {
         let output_count = match gate {
             BuildComplexGate::Call(ref name, ref input_wires) => {
                 let function_params = known_function_params(&self.known_functions, name)?;
-                let input_count = expand_wirelist(input_wires)?.len();
+                let input_count = WireListView::new(input_wires)?.len();
                 function_params.check(
                     name,
                     Some(input_count),
@@ -462,7 +464,7 @@ impl FunctionBuilder<'_> {
             BuildComplexGate::Call(ref name, ref input_wires) => {
                 let function_params = known_function_params(&self.known_functions, name)?;
                 // Check inputs size
-                let input_count = expand_wirelist(input_wires)?.len();
+                let input_count = WireListView::new(input_wires)?.len();
                 if function_params.input_count != input_count {
                     return Err(format!(
                         "Function {} has {} inputs and is called with {} inputs.",
@@ -603,7 +605,7 @@ impl SwitchBuilder<'_> {
         // Check that the function exists
         let function_params = known_function_params(&self.known_functions, &name)?;
         // Check input_count
-        let input_count = wirelist_len(&inputs);
+        let input_count = WireListView::new(&inputs)?.len();
         function_params.check(&name, Some(input_count), None, None, None)?;
         Ok(BranchBuilder {
             branch: CaseInvoke::AbstractGateCall(name, inputs),
@@ -1,12 +1,13 @@
 use flatbuffers::{emplace_scalar, read_scalar, EndianScalar};
 use num_bigint::BigUint;
+use num_traits::Zero;
 use std::mem::size_of;
 
 use crate::structs::function::ForLoopBody;
-use crate::structs::relation::{ADD, FOR, FUNCTION, MUL, MULC, SWITCH};
+use crate::structs::relation::{ADD, ARITH, CONVERT, FOR, FUNCTION, MUL, MULC, SWITCH};
 use crate::structs::wire::WireListElement;
 use crate::wirelist;
-use crate::{Header, Instance, Relation, Witness};
+use crate::{Header, Instance, Relation, Result, Witness};
 
 pub fn example_header() -> Header {
     example_header_in_field(literal32(EXAMPLE_MODULUS))
@@ -17,7 +18,7 @@ pub fn example_instance() -> Instance {
 }
 
 pub fn example_witness() -> Witness {
-    example_witness_h(&example_header())
+    example_witness_h(&example_header()).expect("Invalid example header")
 }
 
 pub fn example_witness_incorrect() -> Witness {
@@ -40,21 +41,25 @@ pub fn example_instance_h(header: &Header) -> Instance {
     Instance {
         header: header.clone(),
         common_inputs: vec![literal32(25), literal32(0), literal32(1)],
+        type_id: 0,
     }
 }
 
-pub fn example_witness_h(header: &Header) -> Witness {
-    let modulus = BigUint::from_bytes_le(&header.field_characteristic);
-    let fibonacci_22 = BigUint::from(17711 as u64) % modulus;
-    Witness {
+/// The witness of the example in the field of the header, or an error if the header does not
+/// describe a supported field.
+pub fn example_witness_h(header: &Header) -> Result<Witness> {
+    let field = header.field()?;
+    let fibonacci_22 = field.from_u64(17711);
+    Ok(Witness {
         header: header.clone(),
         short_witness: vec![
             literal32(3),
             literal32(4),
             literal32(0),
-            fibonacci_22.to_bytes_le(),
+            fibonacci_22.to_value(),
         ],
-    }
+        type_id: 0,
+    })
 }
 
 pub fn example_witness_incorrect_h(header: &Header) -> Witness {
@@ -66,6 +71,7 @@ pub fn example_witness_incorrect_h(header: &Header) -> Witness {
             literal32(1),
             literal32(40), // incorrect
         ],
+        type_id: 0,
     }
 }
 
@@ -211,6 +217,57 @@ pub fn example_relation_h(header: &Header) -> Relation {
     }
 }
 
+/// A header of IR version 2.0.0 with two types: the type 0 is the field of 101 elements, and
+/// the type 1 is the field of 2 elements.
+pub fn example_typed_header() -> Header {
+    Header::new_with_types(literal32(EXAMPLE_MODULUS), vec![literal32(2)])
+}
+
+pub fn example_typed_instance() -> Instance {
+    Instance {
+        header: example_typed_header(),
+        common_inputs: vec![literal32(5)],
+        type_id: 0,
+    }
+}
+
+/// The bits of 5, most significant first, in the type 1.
+pub fn example_typed_witness() -> Witness {
+    Witness {
+        header: example_typed_header(),
+        short_witness: vec![literal32(1), literal32(0), literal32(1)],
+        type_id: 1,
+    }
+}
+
+/// Checks that the bits of the witness, converted from the type 1 to the type 0, are equal to
+/// the instance.
+pub fn example_typed_relation() -> Relation {
+    use crate::Gate::*;
+
+    let header = example_typed_header();
+    Relation {
+        header: header.clone(),
+        gate_mask: ARITH,
+        feat_mask: CONVERT,
+        functions: vec![],
+        gates: vec![
+            Typed(1, Box::new(Witness(0))),
+            Typed(1, Box::new(Witness(1))),
+            Typed(1, Box::new(Witness(2))),
+            // The most and least significant bits are set.
+            Typed(1, Box::new(Mul(3, 0, 2))),
+            Typed(1, Box::new(AddConstant(4, 3, literal32(1)))),
+            Typed(1, Box::new(AssertZero(4))),
+            Convert(0, wirelist![5], 1, wirelist![0, 1, 2]),
+            Instance(6),
+            MulConstant(7, 6, encode_negative_one(&header)),
+            Add(8, 5, 7),
+            AssertZero(8),
+        ],
+    }
+}
+
 pub const EXAMPLE_MODULUS: u32 = 101;
 
 pub fn literal<T: EndianScalar>(value: T) -> Vec<u8> {
@@ -233,10 +290,14 @@ pub fn read_literal<T: EndianScalar>(encoded: &[u8]) -> T {
     }
 }
 
+/// Encode -1 with as many bytes as the field characteristic of the header.
+///
+/// Panics if the field characteristic is zero.
 pub fn encode_negative_one(header: &Header) -> Vec<u8> {
-    let mut neg_one = header.field_characteristic.clone();
-    assert!(neg_one.len() > 0 && neg_one[0] > 0, "Invalid field order");
-    neg_one[0] -= 1;
+    let characteristic = BigUint::from_bytes_le(&header.field_characteristic);
+    assert!(!characteristic.is_zero(), "Invalid field order");
+    let mut neg_one = (characteristic - 1u32).to_bytes_le();
+    neg_one.resize(header.field_characteristic.len(), 0);
     neg_one
 }
 
@@ -256,4 +317,26 @@ fn test_examples() {
     assert_eq!(messages.relations, vec![example_relation()]);
     assert_eq!(messages.instances, vec![example_instance()]);
     assert_eq!(messages.witnesses, vec![example_witness()]);
+
+    // Extension fields are not supported by the witness, but do not panic.
+    let mut header = example_header();
+    header.field_degree = 2;
+    assert!(example_witness_h(&header).is_err());
+    assert_eq!(encode_negative_one(&header), vec![100, 0, 0, 0]);
+}
+
+#[test]
+fn test_typed_examples() {
+    use crate::Source;
+
+    let mut buf = Vec::<u8>::new();
+    example_typed_instance().write_into(&mut buf).unwrap();
+    example_typed_witness().write_into(&mut buf).unwrap();
+    example_typed_relation().write_into(&mut buf).unwrap();
+
+    let messages = Source::from_buffers(vec![buf]).read_all_messages().unwrap();
+    assert_eq!(messages.relations, vec![example_typed_relation()]);
+    assert_eq!(messages.instances, vec![example_typed_instance()]);
+    assert_eq!(messages.witnesses, vec![example_typed_witness()]);
+    assert_eq!(messages.witnesses[0].header.version, "2.0.0");
 }
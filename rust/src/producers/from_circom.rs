@@ -0,0 +1,580 @@
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use crate::producers::from_r1cs::FromR1CSConverter;
+use crate::{Result, Sink};
+
+use zkinterface::BilinearConstraint;
+use zkinterface::CircuitHeader as zkiCircuitHeader;
+use zkinterface::ConstraintSystem as zkiConstraintSystem;
+use zkinterface::Variables as zkiVariables;
+use zkinterface::Witness as zkiWitness;
+
+/// Magic bytes at the beginning of a circom `.r1cs` file.
+pub const R1CS_MAGIC: &[u8; 4] = b"r1cs";
+/// Magic bytes at the beginning of a circom `.wtns` file.
+pub const WTNS_MAGIC: &[u8; 4] = b"wtns";
+
+// Section types of the iden3 binary formats.
+pub const R1CS_HEADER_SECTION: u32 = 1;
+pub const R1CS_CONSTRAINTS_SECTION: u32 = 2;
+pub const R1CS_WIRE2LABEL_SECTION: u32 = 3;
+pub const WTNS_HEADER_SECTION: u32 = 1;
+pub const WTNS_DATA_SECTION: u32 = 2;
+
+/// A linear combination, as a list of (wire, coefficient).
+pub type CircomLinearCombination = Vec<(u32, BigUint)>;
+
+/// CircomR1CS holds the content of a circom `.r1cs` file (iden3 binary format, version 1).
+///
+/// Wire 0 is the constant one, wires `1..=n_pub_out + n_pub_in` are public, and the
+/// following wires are private.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CircomR1CS {
+    pub prime: BigUint,
+    /// Size in bytes of an encoded field element.
+    pub field_size: u32,
+    pub n_wires: u32,
+    pub n_pub_out: u32,
+    pub n_pub_in: u32,
+    pub n_prv_in: u32,
+    pub n_labels: u64,
+    /// Each constraint is A * B - C = 0.
+    pub constraints: Vec<(
+        CircomLinearCombination,
+        CircomLinearCombination,
+        CircomLinearCombination,
+    )>,
+}
+
+/// CircomWitness holds the content of a circom `.wtns` file: one value per wire.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CircomWitness {
+    pub prime: BigUint,
+    pub field_size: u32,
+    pub values: Vec<BigUint>,
+}
+
+impl CircomR1CS {
+    /// Read a `.r1cs` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<CircomR1CS> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Read a `.r1cs` file from a stream.
+    pub fn read_from(reader: &mut impl Read) -> Result<CircomR1CS> {
+        let sections = read_sections(reader, R1CS_MAGIC)?;
+
+        let mut r1cs = CircomR1CS::default();
+        let header = find_section(&sections, R1CS_HEADER_SECTION)?;
+        let mut buf = &header[..];
+        r1cs.field_size = read_field_size(&mut buf)?;
+        r1cs.prime = read_field_element(&mut buf, r1cs.field_size)?;
+        r1cs.n_wires = read_u32(&mut buf)?;
+        r1cs.n_pub_out = read_u32(&mut buf)?;
+        r1cs.n_pub_in = read_u32(&mut buf)?;
+        r1cs.n_prv_in = read_u32(&mut buf)?;
+        r1cs.n_labels = read_u64(&mut buf)?;
+        let n_constraints = read_u32(&mut buf)?;
+        r1cs.check_counts()?;
+
+        let data = find_section(&sections, R1CS_CONSTRAINTS_SECTION)?;
+        let mut buf = &data[..];
+        // Each constraint holds at least the number of terms of its 3 linear combinations.
+        check_count(buf, n_constraints, 3 * 4, "constraints")?;
+        for _ in 0..n_constraints {
+            let a = read_linear_combination(&mut buf, r1cs.field_size, r1cs.n_wires)?;
+            let b = read_linear_combination(&mut buf, r1cs.field_size, r1cs.n_wires)?;
+            let c = read_linear_combination(&mut buf, r1cs.field_size, r1cs.n_wires)?;
+            r1cs.constraints.push((a, b, c));
+        }
+        Ok(r1cs)
+    }
+
+    /// Number of public wires, excluding the constant one.
+    pub fn public_count(&self) -> u32 {
+        self.n_pub_out + self.n_pub_in
+    }
+
+    /// Check that the counts of the header are consistent. They come from the file and are
+    /// not trusted.
+    pub fn check_counts(&self) -> Result<()> {
+        // The wire 0 and the inputs are wires.
+        let n_inputs = (self.n_pub_out as u64) + (self.n_pub_in as u64) + (self.n_prv_in as u64);
+        if n_inputs >= self.n_wires as u64 {
+            return Err(format!(
+                "The .r1cs file declares {} inputs but only {} wires.",
+                n_inputs, self.n_wires
+            )
+            .into());
+        }
+        // Each wire is mapped to the label of a signal.
+        if self.n_labels < self.n_wires as u64 {
+            return Err(format!(
+                "The .r1cs file declares {} labels for {} wires.",
+                self.n_labels, self.n_wires
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Convert the header into a zkInterface CircuitHeader.
+    /// The values of public wires are taken from `witness`. Without it, they are set to zero
+    /// and the resulting Instance message should not be used for proving.
+    pub fn to_zki_header(&self, witness: Option<&CircomWitness>) -> Result<zkiCircuitHeader> {
+        if self.prime.is_zero() {
+            return Err("The .r1cs file declares a zero prime.".into());
+        }
+        self.check_counts()?;
+        if let Some(witness) = witness {
+            witness.check(self)?;
+        }
+        let n8 = self.field_size as usize;
+        let ids: Vec<u64> = (1..=self.public_count() as u64).collect();
+        let mut values = vec![];
+        for id in ids.iter() {
+            let value = match witness {
+                Some(witness) => witness.get(*id as u32)?.clone(),
+                None => BigUint::zero(),
+            };
+            values.append(&mut encode_field_element(&value, n8));
+        }
+
+        Ok(zkiCircuitHeader {
+            instance_variables: to_zki_values(ids, values),
+            free_variable_id: self.n_wires as u64,
+            field_maximum: Some((&self.prime - BigUint::one()).to_bytes_le()),
+            configuration: None,
+        })
+    }
+
+    /// Convert the constraints into a zkInterface ConstraintSystem.
+    pub fn to_zki_constraints(&self) -> zkiConstraintSystem {
+        let n8 = self.field_size as usize;
+        zkiConstraintSystem {
+            constraints: self
+                .constraints
+                .iter()
+                .map(|(a, b, c)| BilinearConstraint {
+                    linear_combination_a: to_zki_variables(a, n8),
+                    linear_combination_b: to_zki_variables(b, n8),
+                    linear_combination_c: to_zki_variables(c, n8),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl CircomWitness {
+    /// Read a `.wtns` file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<CircomWitness> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    /// Read a `.wtns` file from a stream.
+    pub fn read_from(reader: &mut impl Read) -> Result<CircomWitness> {
+        let sections = read_sections(reader, WTNS_MAGIC)?;
+
+        let mut witness = CircomWitness::default();
+        let header = find_section(&sections, WTNS_HEADER_SECTION)?;
+        let mut buf = &header[..];
+        witness.field_size = read_field_size(&mut buf)?;
+        witness.prime = read_field_element(&mut buf, witness.field_size)?;
+        let n_values = read_u32(&mut buf)?;
+
+        let data = find_section(&sections, WTNS_DATA_SECTION)?;
+        let mut buf = &data[..];
+        check_count(buf, n_values, witness.field_size as u64, "values")?;
+        for _ in 0..n_values {
+            witness
+                .values
+                .push(read_field_element(&mut buf, witness.field_size)?);
+        }
+        Ok(witness)
+    }
+
+    pub fn get(&self, wire: u32) -> Result<&BigUint> {
+        self.values
+            .get(wire as usize)
+            .ok_or_else(|| format!("The .wtns file has no value for wire {}.", wire).into())
+    }
+
+    /// Check that the witness gives a value below the prime to each wire of `r1cs`.
+    pub fn check(&self, r1cs: &CircomR1CS) -> Result<()> {
+        if self.prime != r1cs.prime {
+            return Err("The .wtns and .r1cs files are not defined over the same field.".into());
+        }
+        if self.values.len() as u64 != r1cs.n_wires as u64 {
+            return Err(format!(
+                "The .wtns file contains {} values while the .r1cs file has {} wires.",
+                self.values.len(),
+                r1cs.n_wires
+            )
+            .into());
+        }
+        if let Some(wire) = self.values.iter().position(|value| value >= &self.prime) {
+            return Err(format!(
+                "The value of wire {} in the .wtns file is not below the prime.",
+                wire
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Convert the values of the private wires into a zkInterface Witness.
+    pub fn to_zki_witness(&self, r1cs: &CircomR1CS) -> Result<zkiWitness> {
+        r1cs.check_counts()?;
+        self.check(r1cs)?;
+        let n8 = r1cs.field_size as usize;
+        let ids: Vec<u64> = (r1cs.public_count() as u64 + 1..r1cs.n_wires as u64).collect();
+        let mut values = vec![];
+        for id in ids.iter() {
+            values.append(&mut encode_field_element(self.get(*id as u32)?, n8));
+        }
+        Ok(zkiWitness {
+            assigned_variables: to_zki_values(ids, values),
+        })
+    }
+}
+
+/// Convert a circom statement into SIEVE IR using the `FromR1CSConverter`.
+/// Witness messages are produced only if `witness` is given.
+pub fn circom_to_ir<S: Sink>(
+    sink: S,
+    r1cs: &CircomR1CS,
+    witness: Option<&CircomWitness>,
+) -> Result<S> {
+    let zki_header = r1cs.to_zki_header(witness)?;
+    let mut converter = FromR1CSConverter::new(sink, &zki_header);
+    if let Some(witness) = witness {
+        converter.ingest_witness(&witness.to_zki_witness(r1cs)?)?;
+    }
+    converter.ingest_constraints(&r1cs.to_zki_constraints())?;
+    Ok(converter.finish())
+}
+
+fn to_zki_variables(lc: &CircomLinearCombination, n8: usize) -> zkiVariables {
+    let mut values = Vec::with_capacity(lc.len() * n8);
+    for (_, coeff) in lc.iter() {
+        values.append(&mut encode_field_element(coeff, n8));
+    }
+    to_zki_values(lc.iter().map(|(wire, _)| *wire as u64).collect(), values)
+}
+
+fn to_zki_values(variable_ids: Vec<u64>, values: Vec<u8>) -> zkiVariables {
+    // zkInterface cannot infer the size of values without variables, so leave them out.
+    let values = if variable_ids.is_empty() {
+        None
+    } else {
+        Some(values)
+    };
+    zkiVariables {
+        variable_ids,
+        values,
+    }
+}
+
+/// Encode a field element on exactly `n8` bytes, little-endian.
+pub fn encode_field_element(value: &BigUint, n8: usize) -> Vec<u8> {
+    let mut bytes = value.to_bytes_le();
+    bytes.resize(n8, 0);
+    bytes
+}
+
+/// Read the magic, the version and all the sections (type, content) of an iden3 binary file.
+fn read_sections(reader: &mut impl Read, magic: &[u8; 4]) -> Result<Vec<(u32, Vec<u8>)>> {
+    let mut got_magic = [0u8; 4];
+    reader.read_exact(&mut got_magic)?;
+    if &got_magic != magic {
+        return Err(format!(
+            "Invalid file type, expected magic '{}'.",
+            String::from_utf8_lossy(magic)
+        )
+        .into());
+    }
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version == 0 || version > 2 {
+        return Err(format!("Unsupported file version {}.", version).into());
+    }
+
+    let mut n_sections = [0u8; 4];
+    reader.read_exact(&mut n_sections)?;
+    let n_sections = u32::from_le_bytes(n_sections);
+
+    let mut sections = vec![];
+    for _ in 0..n_sections {
+        let mut section_type = [0u8; 4];
+        reader.read_exact(&mut section_type)?;
+        let mut section_size = [0u8; 8];
+        reader.read_exact(&mut section_size)?;
+        let section_size = u64::from_le_bytes(section_size);
+        // The size is not trusted: the content grows as it is read, up to the end of the file.
+        let mut content = vec![];
+        reader
+            .by_ref()
+            .take(section_size)
+            .read_to_end(&mut content)?;
+        if (content.len() as u64) < section_size {
+            return Err(format!(
+                "The file ends inside a section of {} bytes, after {} bytes.",
+                section_size,
+                content.len()
+            )
+            .into());
+        }
+        sections.push((u32::from_le_bytes(section_type), content));
+    }
+    Ok(sections)
+}
+
+fn find_section(sections: &[(u32, Vec<u8>)], section_type: u32) -> Result<&Vec<u8>> {
+    sections
+        .iter()
+        .find(|(t, _)| *t == section_type)
+        .map(|(_, content)| content)
+        .ok_or_else(|| format!("Missing section {}.", section_type).into())
+}
+
+fn read_bytes<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err("Unexpected end of section.".into());
+    }
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(bytes)
+}
+
+fn read_u32(buf: &mut &[u8]) -> Result<u32> {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(read_bytes(buf, 4)?);
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(buf: &mut &[u8]) -> Result<u64> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(read_bytes(buf, 8)?);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_field_size(buf: &mut &[u8]) -> Result<u32> {
+    let n8 = read_u32(buf)?;
+    if n8 == 0 {
+        return Err("The size of field elements should not be zero.".into());
+    }
+    Ok(n8)
+}
+
+/// Check that `count` items of at least `item_size` bytes each fit in the rest of the section,
+/// before reading them. The counts come from the file and are not trusted.
+fn check_count(buf: &[u8], count: u32, item_size: u64, what: &str) -> Result<()> {
+    let size = (count as u64).checked_mul(item_size);
+    if size.map_or(true, |size| size > buf.len() as u64) {
+        return Err(format!(
+            "The file declares {} {}, more than its {} remaining bytes can hold.",
+            count,
+            what,
+            buf.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn read_field_element(buf: &mut &[u8], n8: u32) -> Result<BigUint> {
+    Ok(BigUint::from_bytes_le(read_bytes(buf, n8 as usize)?))
+}
+
+fn read_linear_combination(
+    buf: &mut &[u8],
+    n8: u32,
+    n_wires: u32,
+) -> Result<CircomLinearCombination> {
+    let n_terms = read_u32(buf)?;
+    check_count(buf, n_terms, 4 + n8 as u64, "terms in a linear combination")?;
+    let mut lc = Vec::with_capacity(n_terms as usize);
+    for _ in 0..n_terms {
+        let wire = read_u32(buf)?;
+        if wire >= n_wires {
+            return Err(format!("The wire {} does not exist in the .r1cs file.", wire).into());
+        }
+        lc.push((wire, read_field_element(buf, n8)?));
+    }
+    Ok(lc)
+}
+
+#[test]
+fn test_circom_to_ir() -> Result<()> {
+    use crate::consumers::evaluator::{Evaluator, PlaintextBackend};
+    use crate::producers::sink::MemorySink;
+    use crate::Source;
+
+    // x * x = y, with y public and x private.
+    let r1cs = CircomR1CS {
+        prime: BigUint::from(101u32),
+        field_size: 8,
+        n_wires: 3,
+        n_pub_out: 1,
+        n_pub_in: 0,
+        n_prv_in: 1,
+        n_labels: 3,
+        constraints: vec![(
+            vec![(2, BigUint::one())],
+            vec![(2, BigUint::one())],
+            vec![(1, BigUint::one())],
+        )],
+    };
+    let witness = CircomWitness {
+        prime: BigUint::from(101u32),
+        field_size: 8,
+        values: vec![BigUint::one(), BigUint::from(16u32), BigUint::from(4u32)],
+    };
+
+    let source: Source = circom_to_ir(MemorySink::default(), &r1cs, Some(&witness))?.into();
+    let mut backend = PlaintextBackend::default();
+    let evaluator = Evaluator::from_messages(source.iter_messages(), &mut backend);
+    assert_eq!(evaluator.get_violations(), Vec::<String>::new());
+
+    let wrong_witness = CircomWitness {
+        values: vec![BigUint::one(), BigUint::from(16u32), BigUint::from(5u32)],
+        ..witness
+    };
+    let source: Source = circom_to_ir(MemorySink::default(), &r1cs, Some(&wrong_witness))?.into();
+    let mut backend = PlaintextBackend::default();
+    let evaluator = Evaluator::from_messages(source.iter_messages(), &mut backend);
+    assert_eq!(evaluator.get_violations().len(), 1);
+
+    // The counts and values are checked before the wires are enumerated.
+    let huge_r1cs = CircomR1CS {
+        n_pub_in: u32::MAX - 1,
+        ..r1cs.clone()
+    };
+    assert_eq!(
+        circom_to_ir(MemorySink::default(), &huge_r1cs, None)
+            .err()
+            .unwrap()
+            .to_string(),
+        "The .r1cs file declares 4294967296 inputs but only 3 wires."
+    );
+    let short_witness = CircomWitness {
+        values: vec![BigUint::one()],
+        ..wrong_witness.clone()
+    };
+    assert_eq!(
+        circom_to_ir(MemorySink::default(), &r1cs, Some(&short_witness))
+            .err()
+            .unwrap()
+            .to_string(),
+        "The .wtns file contains 1 values while the .r1cs file has 3 wires."
+    );
+    let unreduced_witness = CircomWitness {
+        values: vec![BigUint::one(), BigUint::from(117u32), BigUint::from(4u32)],
+        ..wrong_witness
+    };
+    assert_eq!(
+        circom_to_ir(MemorySink::default(), &r1cs, Some(&unreduced_witness))
+            .err()
+            .unwrap()
+            .to_string(),
+        "The value of wire 1 in the .wtns file is not below the prime."
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_read_circom_files() -> Result<()> {
+    let u32_le = |n: u32| n.to_le_bytes().to_vec();
+    let section = |section_type: u32, content: Vec<u8>| {
+        let mut bytes = u32_le(section_type);
+        bytes.extend((content.len() as u64).to_le_bytes().iter());
+        bytes.extend(content);
+        bytes
+    };
+    let file = |magic: &[u8; 4], sections: Vec<Vec<u8>>| {
+        let mut bytes = magic.to_vec();
+        bytes.extend(u32_le(1));
+        bytes.extend(u32_le(sections.len() as u32));
+        bytes.extend(sections.concat());
+        bytes
+    };
+    let prime = vec![101, 0, 0, 0, 0, 0, 0, 0];
+    // field_size, prime, n_wires, n_pub_out, n_pub_in, n_prv_in, n_labels, n_constraints.
+    let header = |n_constraints: u32| {
+        [
+            u32_le(8),
+            prime.clone(),
+            u32_le(3),
+            u32_le(1),
+            u32_le(0),
+            u32_le(1),
+            3u64.to_le_bytes().to_vec(),
+            u32_le(n_constraints),
+        ]
+        .concat()
+    };
+    // x * x = y: a single term in each linear combination.
+    let term = |wire: u32| [u32_le(1), u32_le(wire), vec![1, 0, 0, 0, 0, 0, 0, 0]].concat();
+    let constraints = [term(2), term(2), term(1)].concat();
+
+    let r1cs = CircomR1CS::read_from(
+        &mut &file(
+            R1CS_MAGIC,
+            vec![section(1, header(1)), section(2, constraints.clone())],
+        )[..],
+    )?;
+    assert_eq!(r1cs.n_wires, 3);
+    assert_eq!(r1cs.public_count(), 1);
+    assert_eq!(r1cs.constraints[0].2, vec![(1, BigUint::one())]);
+
+    // Counts larger than the file are errors, not huge allocations.
+    let too_many_constraints = file(
+        R1CS_MAGIC,
+        vec![
+            section(1, header(u32::MAX)),
+            section(2, constraints.clone()),
+        ],
+    );
+    assert!(CircomR1CS::read_from(&mut &too_many_constraints[..]).is_err());
+
+    let mut too_many_terms = constraints.clone();
+    too_many_terms[..4].copy_from_slice(&u32_le(u32::MAX));
+    let too_many_terms = file(
+        R1CS_MAGIC,
+        vec![section(1, header(1)), section(2, too_many_terms)],
+    );
+    assert!(CircomR1CS::read_from(&mut &too_many_terms[..]).is_err());
+
+    let mut truncated = file(R1CS_MAGIC, vec![section(1, header(1))]);
+    truncated[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(CircomR1CS::read_from(&mut &truncated[..]).is_err());
+
+    // n_labels is after the field size, the prime, and 4 counts.
+    let mut few_labels = header(1);
+    few_labels[28..36].copy_from_slice(&2u64.to_le_bytes());
+    let few_labels = file(
+        R1CS_MAGIC,
+        vec![section(1, few_labels), section(2, constraints.clone())],
+    );
+    assert_eq!(
+        CircomR1CS::read_from(&mut &few_labels[..])
+            .unwrap_err()
+            .to_string(),
+        "The .r1cs file declares 2 labels for 3 wires."
+    );
+
+    let wtns_header = [u32_le(8), prime.clone(), u32_le(u32::MAX)].concat();
+    let too_many_values = file(
+        WTNS_MAGIC,
+        vec![section(1, wtns_header), section(2, vec![0; 24])],
+    );
+    assert!(CircomWitness::read_from(&mut &too_many_values[..]).is_err());
+
+    Ok(())
+}
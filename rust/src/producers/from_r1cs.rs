@@ -233,7 +233,7 @@ fn assert_header(header: &Header) {
 
 #[test]
 fn test_r1cs_stats() -> Result<()> {
-    use crate::consumers::stats::GateStats;
+    use crate::consumers::stats::{GateStats, WireStats};
     use zkinterface::producers::examples::example_circuit_header_inputs as zki_example_header_inputs;
     use zkinterface::producers::examples::example_constraints as zki_example_constraints;
     use zkinterface::producers::examples::example_witness_inputs as zki_example_witness_inputs;
@@ -274,11 +274,20 @@ fn test_r1cs_stats() -> Result<()> {
             switches: 0,
             branches: 0,
             for_loops: 0,
+            typed_gates: 0,
+            convert_gates: 0,
             instance_messages: 1,
             witness_messages: 1,
             relation_messages: 1,
         },
         functions: Default::default(),
+        wire_stats: WireStats {
+            multiplicative_depth: 2,
+            depth: 4,
+            max_fan_out: 3,
+            peak_live_wires: 36,
+        },
+        ..Stats::default()
     };
 
     assert_eq!(expected_stats, stats);
@@ -2,11 +2,15 @@
 pub mod boolean_examples;
 /// Example statements generator.
 pub mod examples;
+/// RotatingFilesSink stores messages into numbered files of bounded size.
+pub mod rotating_sink;
 /// A Sink stores produced messages, e.g. in files.
 pub mod sink;
 
 /// A variant of gates for use with a GateBuilder.
 pub mod build_gates;
+/// circom .r1cs/.wtns to ir converter
+pub mod from_circom;
 /// r1cs to ir converter
 pub mod from_r1cs;
 
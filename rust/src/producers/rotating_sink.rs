@@ -0,0 +1,261 @@
+use crate::consumers::source::Compression;
+use crate::producers::sink::{clean_workspace, FileWriter, Sink};
+use crate::sieve_ir_generated::sieve_ir as g;
+use crate::{Instance, Relation, Result, Source, Witness};
+use std::fs::create_dir_all;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// RotatingFilesSink stores messages into files like `FilesSink`, but starts a new file once the
+/// current one reaches a number of bytes or of messages. The files of each kind of message are
+/// numbered in order: `002_relation.sieve`, `002_relation_00000000000000000001.sieve`,
+/// `002_relation_00000000000000000002.sieve`, etc., so that `Source::from_directory` reads them
+/// back in the same order.
+///
+/// Files are only rotated between messages, by the `push_*_message` methods; a file holds at
+/// least one message even if it is larger than the limit. Sizes are counted before compression.
+///
+/// # Example
+/// ```
+/// use zki_sieve::producers::examples::*;
+/// use zki_sieve::producers::rotating_sink::RotatingFilesSink;
+/// use zki_sieve::consumers::source::Compression;
+/// use zki_sieve::{Sink, Source};
+/// use std::path::PathBuf;
+///
+/// let dir = PathBuf::from("local/test_rotating_sink_example");
+/// let mut sink = RotatingFilesSink::new_clean(&dir, Compression::None).unwrap();
+/// sink.max_file_messages = Some(1);
+/// sink.push_instance_message(&example_instance()).unwrap();
+/// sink.push_witness_message(&example_witness()).unwrap();
+/// sink.push_relation_message(&example_relation()).unwrap();
+/// sink.push_relation_message(&example_relation()).unwrap();
+/// assert_eq!(sink.file_count(), 4);
+/// sink.finish().unwrap();
+///
+/// let messages = Source::from_directory(&dir).unwrap().read_all_messages().unwrap();
+/// assert_eq!(messages.relations.len(), 2);
+/// ```
+pub struct RotatingFilesSink {
+    pub workspace: PathBuf,
+    pub compression: Compression,
+
+    /// Start a new file before a message that would take a file over this number of bytes.
+    pub max_file_bytes: Option<u64>,
+    /// Start a new file once a file holds this number of messages.
+    pub max_file_messages: Option<u64>,
+    /// Set to true to print the paths of the files started when rotating.
+    pub print_filenames: bool,
+
+    instance_file: RotatingFile,
+    witness_file: RotatingFile,
+    relation_file: RotatingFile,
+}
+
+struct RotatingFile {
+    workspace: PathBuf,
+    name: &'static str,
+    compression: Compression,
+    part: usize,
+    bytes: u64,
+    messages: u64,
+    writer: FileWriter,
+}
+
+impl RotatingFile {
+    fn create(workspace: &Path, name: &'static str, compression: Compression) -> Result<Self> {
+        let path = workspace.join(format!("{}.{}", name, compression.extension()));
+        Ok(RotatingFile {
+            workspace: workspace.to_path_buf(),
+            name,
+            compression,
+            part: 0,
+            bytes: 0,
+            messages: 0,
+            writer: FileWriter::create(&path)?,
+        })
+    }
+
+    /// Complete the current file and start the next one. Part numbers are padded to the width of
+    /// the largest u64, so that the files sort in order by name.
+    fn rotate(&mut self, print_filenames: bool) -> Result<()> {
+        let path = self.workspace.join(format!(
+            "{}_{:020}.{}",
+            self.name,
+            self.part + 1,
+            self.compression.extension()
+        ));
+        if print_filenames {
+            eprintln!("Writing {}", path.display());
+        }
+        let writer = std::mem::replace(&mut self.writer, FileWriter::create(&path)?);
+        writer.finish()?;
+        self.part += 1;
+        self.bytes = 0;
+        self.messages = 0;
+        Ok(())
+    }
+}
+
+impl RotatingFilesSink {
+    /// Create the first file of each kind of message in a workspace, after removing other files
+    /// of messages from it.
+    pub fn new_clean(
+        workspace: &impl AsRef<Path>,
+        compression: Compression,
+    ) -> Result<RotatingFilesSink> {
+        let workspace = workspace.as_ref();
+        create_dir_all(workspace)?;
+        clean_workspace(workspace)?;
+
+        Ok(RotatingFilesSink {
+            workspace: workspace.to_path_buf(),
+            compression,
+            max_file_bytes: None,
+            max_file_messages: None,
+            print_filenames: false,
+
+            instance_file: RotatingFile::create(workspace, "000_instance", compression)?,
+            witness_file: RotatingFile::create(workspace, "001_witness", compression)?,
+            relation_file: RotatingFile::create(workspace, "002_relation", compression)?,
+        })
+    }
+
+    /// The number of files created so far, of all kinds of messages.
+    pub fn file_count(&self) -> usize {
+        self.instance_file.part + self.witness_file.part + self.relation_file.part + 3
+    }
+
+    /// Store a size-prefixed message that is already encoded, e.g. read from a `Source`.
+    pub fn push_message_buffer(&mut self, buffer: &[u8]) -> Result<()> {
+        let size = buffer.len() as u64;
+        let max_file_bytes = self.max_file_bytes;
+        let max_file_messages = self.max_file_messages;
+        let print_filenames = self.print_filenames;
+        let file = match g::get_size_prefixed_root_as_root(buffer).message_type() {
+            g::Message::Instance => &mut self.instance_file,
+            g::Message::Witness => &mut self.witness_file,
+            g::Message::Relation => &mut self.relation_file,
+            g::Message::NONE => return Err("Invalid message type".into()),
+        };
+
+        let too_many_bytes = max_file_bytes.map_or(false, |max| file.bytes + size > max);
+        let too_many_messages = max_file_messages.map_or(false, |max| file.messages >= max);
+        if file.messages > 0 && (too_many_bytes || too_many_messages) {
+            file.rotate(print_filenames)?;
+        }
+        file.writer.write_all(buffer)?;
+        file.bytes += size;
+        file.messages += 1;
+        Ok(())
+    }
+
+    /// Flush the files and finish their compressed streams, reporting errors.
+    pub fn finish(self) -> Result<()> {
+        self.instance_file.writer.finish()?;
+        self.witness_file.writer.finish()?;
+        self.relation_file.writer.finish()
+    }
+}
+
+impl Sink for RotatingFilesSink {
+    type Write = FileWriter;
+
+    /// The current instance file. Messages written directly into it do not rotate files.
+    fn get_instance_writer(&mut self) -> &mut FileWriter {
+        &mut self.instance_file.writer
+    }
+
+    fn get_witness_writer(&mut self) -> &mut FileWriter {
+        &mut self.witness_file.writer
+    }
+
+    fn get_relation_writer(&mut self) -> &mut FileWriter {
+        &mut self.relation_file.writer
+    }
+
+    fn push_instance_message(&mut self, instance: &Instance) -> Result<()> {
+        let mut buffer = vec![];
+        instance.write_into(&mut buffer)?;
+        self.push_message_buffer(&buffer)
+    }
+
+    fn push_witness_message(&mut self, witness: &Witness) -> Result<()> {
+        let mut buffer = vec![];
+        witness.write_into(&mut buffer)?;
+        self.push_message_buffer(&buffer)
+    }
+
+    fn push_relation_message(&mut self, relation: &Relation) -> Result<()> {
+        let mut buffer = vec![];
+        relation.write_into(&mut buffer)?;
+        self.push_message_buffer(&buffer)
+    }
+}
+
+impl Into<Source> for RotatingFilesSink {
+    fn into(self) -> Source {
+        let workspace = self.workspace.clone();
+        // Complete the files before reading them.
+        drop(self);
+        Source::from_directory(&workspace).unwrap()
+    }
+}
+
+#[test]
+fn test_rotating_sink() -> Result<()> {
+    use crate::producers::examples::*;
+    use crate::{Message, Messages};
+    use std::fs::read_dir;
+
+    let workspace = PathBuf::from("local/test_rotating_sink");
+    let mut relation_buffer = vec![];
+    example_relation().write_into(&mut relation_buffer)?;
+    let relation_size = relation_buffer.len() as u64;
+
+    let mut sink = RotatingFilesSink::new_clean(&workspace, Compression::None)?;
+    // Room for two relations per file.
+    sink.max_file_bytes = Some(2 * relation_size + 1);
+    sink.push_instance_message(&example_instance())?;
+    sink.push_witness_message(&example_witness())?;
+    sink.push_witness_message(&example_witness())?;
+    let mut expected = Messages::default();
+    expected.push_message(&Message::Instance(example_instance()));
+    expected.push_message(&Message::Witness(example_witness()));
+    expected.push_message(&Message::Witness(example_witness()));
+    for _ in 0..5 {
+        sink.push_message_buffer(&relation_buffer)?;
+        expected.push_message(&Message::Relation(example_relation()));
+    }
+    // 3 relation files, the instance and witness messages are smaller.
+    assert_eq!(sink.file_count(), 5);
+    sink.finish()?;
+
+    let mut filenames = read_dir(&workspace)?
+        .map(|entry| Ok(entry?.file_name().into_string().unwrap()))
+        .collect::<Result<Vec<_>>>()?;
+    filenames.sort();
+    assert_eq!(
+        filenames,
+        vec![
+            "000_instance.sieve",
+            "001_witness.sieve",
+            "002_relation.sieve",
+            "002_relation_00000000000000000001.sieve",
+            "002_relation_00000000000000000002.sieve",
+        ]
+    );
+    let source = Source::from_directory(&workspace)?;
+    assert_eq!(source.read_all_messages()?, expected);
+
+    // A message larger than the limit still gets a file, and a new sink cleans the workspace.
+    let mut sink = RotatingFilesSink::new_clean(&workspace, Compression::Gzip)?;
+    sink.max_file_bytes = Some(1);
+    sink.push_relation_message(&example_relation())?;
+    sink.push_relation_message(&example_relation())?;
+    assert_eq!(sink.file_count(), 4);
+    let source: Source = sink.into();
+    assert_eq!(source.read_all_messages()?.relations.len(), 2);
+    assert_eq!(read_dir(&workspace)?.count(), 4);
+    Ok(())
+}
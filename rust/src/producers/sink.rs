@@ -1,7 +1,8 @@
-use crate::{consumers::source::has_sieve_extension, Source};
-use crate::{Instance, Relation, Result, Witness, FILE_EXTENSION};
+use crate::consumers::source::{has_sieve_extension, Compression};
+use crate::{Instance, Relation, Result, Source, Witness};
+use flate2::write::GzEncoder;
 use std::fs::{create_dir_all, read_dir, remove_file, File};
-use std::io::Write;
+use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 
 pub trait Sink {
@@ -56,6 +57,7 @@ impl Into<Source> for MemorySink {
 }
 
 /// Store messages into files using conventional filenames inside of a workspace.
+/// See `CompressedFilesSink` to write compressed files.
 pub struct FilesSink {
     pub workspace: PathBuf,
 
@@ -82,21 +84,15 @@ impl FilesSink {
     }
 
     pub fn instance_path(workspace: &impl AsRef<Path>) -> PathBuf {
-        workspace
-            .as_ref()
-            .join(format!("000_instance.{}", FILE_EXTENSION))
+        CompressedFilesSink::instance_path(workspace, Compression::None)
     }
 
     pub fn witness_path(workspace: &impl AsRef<Path>) -> PathBuf {
-        workspace
-            .as_ref()
-            .join(format!("001_witness.{}", FILE_EXTENSION))
+        CompressedFilesSink::witness_path(workspace, Compression::None)
     }
 
     pub fn relation_path(workspace: &impl AsRef<Path>) -> PathBuf {
-        workspace
-            .as_ref()
-            .join(format!("002_relation.{}", FILE_EXTENSION))
+        CompressedFilesSink::relation_path(workspace, Compression::None)
     }
 
     pub fn print_filenames(&self) {
@@ -128,6 +124,184 @@ impl Into<Source> for FilesSink {
     }
 }
 
+/// Store messages into files like `FilesSink`, compressed with gzip or Zstandard,
+/// e.g. `000_instance.sieve.zst`. With `Compression::None`, the files are the same as those of
+/// `FilesSink`.
+pub struct CompressedFilesSink {
+    pub workspace: PathBuf,
+    pub compression: Compression,
+
+    instance_file: FileWriter,
+    witness_file: FileWriter,
+    relation_file: FileWriter,
+}
+
+impl CompressedFilesSink {
+    /// Create a workspace of compressed files.
+    /// Other files of messages are removed from the workspace, compressed or not.
+    pub fn new_clean(
+        workspace: &impl AsRef<Path>,
+        compression: Compression,
+    ) -> Result<CompressedFilesSink> {
+        create_dir_all(workspace)?;
+        clean_workspace(workspace)?;
+        Self::new_no_cleanup(workspace, compression)
+    }
+
+    pub fn new_no_cleanup(
+        workspace: &impl AsRef<Path>,
+        compression: Compression,
+    ) -> Result<CompressedFilesSink> {
+        Ok(CompressedFilesSink {
+            workspace: workspace.as_ref().to_path_buf(),
+            compression,
+
+            instance_file: FileWriter::create(&Self::instance_path(workspace, compression))?,
+            witness_file: FileWriter::create(&Self::witness_path(workspace, compression))?,
+            relation_file: FileWriter::create(&Self::relation_path(workspace, compression))?,
+        })
+    }
+
+    pub fn instance_path(workspace: &impl AsRef<Path>, compression: Compression) -> PathBuf {
+        Self::path(workspace, "000_instance", compression)
+    }
+
+    pub fn witness_path(workspace: &impl AsRef<Path>, compression: Compression) -> PathBuf {
+        Self::path(workspace, "001_witness", compression)
+    }
+
+    pub fn relation_path(workspace: &impl AsRef<Path>, compression: Compression) -> PathBuf {
+        Self::path(workspace, "002_relation", compression)
+    }
+
+    fn path(workspace: &impl AsRef<Path>, name: &str, compression: Compression) -> PathBuf {
+        workspace
+            .as_ref()
+            .join(format!("{}.{}", name, compression.extension()))
+    }
+
+    pub fn print_filenames(&self) {
+        let (workspace, compression) = (&self.workspace, self.compression);
+        eprintln!(
+            "Writing {}",
+            Self::instance_path(workspace, compression).display()
+        );
+        eprintln!(
+            "Writing {}",
+            Self::witness_path(workspace, compression).display()
+        );
+        eprintln!(
+            "Writing {}",
+            Self::relation_path(workspace, compression).display()
+        );
+    }
+
+    /// Flush the files and finish their compressed streams, reporting errors.
+    /// Otherwise, this is done when the sink is dropped, ignoring errors.
+    pub fn finish(self) -> Result<()> {
+        self.instance_file.finish()?;
+        self.witness_file.finish()?;
+        self.relation_file.finish()
+    }
+}
+
+impl Sink for CompressedFilesSink {
+    type Write = FileWriter;
+
+    fn get_instance_writer(&mut self) -> &mut FileWriter {
+        &mut self.instance_file
+    }
+
+    fn get_witness_writer(&mut self) -> &mut FileWriter {
+        &mut self.witness_file
+    }
+
+    fn get_relation_writer(&mut self) -> &mut FileWriter {
+        &mut self.relation_file
+    }
+}
+
+impl Into<Source> for CompressedFilesSink {
+    fn into(self) -> Source {
+        let workspace = self.workspace.clone();
+        // Complete the files before reading them.
+        drop(self);
+        Source::from_directory(&workspace).unwrap()
+    }
+}
+
+/// A file of messages, compressed according to its extension (see `Compression`).
+/// Compressed streams are finished by `finish`, or when the writer is dropped.
+pub struct FileWriter {
+    // None once finished.
+    encoder: Option<Encoder>,
+}
+
+enum Encoder {
+    Plain(File),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl FileWriter {
+    pub fn create(path: &Path) -> Result<FileWriter> {
+        let file = File::create(path)?;
+        let encoder = match Compression::from_path(path).unwrap_or(Compression::None) {
+            Compression::None => Encoder::Plain(file),
+            Compression::Gzip => Encoder::Gzip(GzEncoder::new(
+                BufWriter::new(file),
+                flate2::Compression::default(),
+            )),
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(BufWriter::new(file), 0)?),
+        };
+        Ok(FileWriter {
+            encoder: Some(encoder),
+        })
+    }
+
+    /// Finish the compressed stream and flush the file, reporting errors.
+    pub fn finish(mut self) -> Result<()> {
+        Self::finish_encoder(self.encoder.take())
+    }
+
+    fn finish_encoder(encoder: Option<Encoder>) -> Result<()> {
+        match encoder {
+            None => {}
+            Some(Encoder::Plain(mut file)) => file.flush()?,
+            Some(Encoder::Gzip(encoder)) => encoder.finish()?.flush()?,
+            Some(Encoder::Zstd(encoder)) => encoder.finish()?.flush()?,
+        }
+        Ok(())
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self.encoder.as_mut() {
+            Some(Encoder::Plain(file)) => file,
+            Some(Encoder::Gzip(encoder)) => encoder,
+            Some(Encoder::Zstd(encoder)) => encoder,
+            None => unreachable!("FileWriter used after finish"),
+        }
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        if let Err(err) = Self::finish_encoder(self.encoder.take()) {
+            eprintln!("Warning: failed to finish writing a file: {}", err);
+        }
+    }
+}
+
 pub fn clean_workspace(workspace: impl AsRef<Path>) -> Result<()> {
     let workspace = workspace.as_ref();
 
@@ -221,3 +395,64 @@ fn test_sink() {
     clean_workspace(&workspace).unwrap();
     assert!(get_file_sizes().0.is_empty());
 }
+
+#[test]
+fn test_compressed_sink() -> Result<()> {
+    use crate::producers::examples::*;
+    use crate::{Message, Messages};
+
+    let workspace = PathBuf::from("local/test_compressed_sink");
+    let mut expected = Messages::default();
+    expected.push_message(&Message::Instance(example_instance()));
+    expected.push_message(&Message::Witness(example_witness()));
+    expected.push_message(&Message::Relation(example_relation()));
+
+    for &compression in &[Compression::Gzip, Compression::Zstd] {
+        let mut sink = CompressedFilesSink::new_clean(&workspace, compression)?;
+        sink.push_instance_message(&example_instance())?;
+        sink.push_witness_message(&example_witness())?;
+        sink.push_relation_message(&example_relation())?;
+        sink.finish()?;
+
+        // Only the compressed files are in the workspace, after cleaning the previous ones.
+        let mut filenames = read_dir(&workspace)?
+            .map(|entry| Ok(entry?.file_name().into_string().unwrap()))
+            .collect::<Result<Vec<_>>>()?;
+        filenames.sort();
+        let extension = compression.extension();
+        assert_eq!(
+            filenames,
+            vec![
+                format!("000_instance.{}", extension),
+                format!("001_witness.{}", extension),
+                format!("002_relation.{}", extension),
+            ]
+        );
+
+        let path = CompressedFilesSink::relation_path(&workspace, compression);
+        assert!(path.ends_with(format!("002_relation.{}", extension)));
+
+        let source = Source::from_directory(&workspace)?;
+        assert_eq!(source.read_all_messages()?, expected);
+        assert!(!source.can_map_files());
+        assert!(source.map_files().is_err());
+    }
+
+    // Dropping the sink also finishes the compressed streams.
+    let mut sink = CompressedFilesSink::new_clean(&workspace, Compression::Zstd)?;
+    sink.push_instance_message(&example_instance())?;
+    sink.push_witness_message(&example_witness())?;
+    sink.push_relation_message(&example_relation())?;
+    let source: Source = sink.into();
+    assert_eq!(source.read_all_messages()?, expected);
+
+    // Without compression, the files are those of FilesSink.
+    assert_eq!(
+        CompressedFilesSink::instance_path(&workspace, Compression::None),
+        FilesSink::instance_path(&workspace)
+    );
+
+    clean_workspace(&workspace)?;
+    assert_eq!(read_dir(&workspace)?.count(), 0);
+    Ok(())
+}
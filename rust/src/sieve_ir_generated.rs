@@ -438,11 +438,12 @@ pub enum DirectiveSet {
   GateAnonCall = 15,
   GateSwitch = 16,
   GateFor = 17,
+  GateConvert = 18,
 
 }
 
 pub const ENUM_MIN_DIRECTIVE_SET: u8 = 0;
-pub const ENUM_MAX_DIRECTIVE_SET: u8 = 17;
+pub const ENUM_MAX_DIRECTIVE_SET: u8 = 18;
 
 impl<'a> flatbuffers::Follow<'a> for DirectiveSet {
   type Inner = Self;
@@ -476,7 +477,7 @@ impl flatbuffers::Push for DirectiveSet {
 }
 
 #[allow(non_camel_case_types)]
-pub const ENUM_VALUES_DIRECTIVE_SET:[DirectiveSet; 18] = [
+pub const ENUM_VALUES_DIRECTIVE_SET:[DirectiveSet; 19] = [
   DirectiveSet::NONE,
   DirectiveSet::GateConstant,
   DirectiveSet::GateAssertZero,
@@ -494,11 +495,12 @@ pub const ENUM_VALUES_DIRECTIVE_SET:[DirectiveSet; 18] = [
   DirectiveSet::GateCall,
   DirectiveSet::GateAnonCall,
   DirectiveSet::GateSwitch,
-  DirectiveSet::GateFor
+  DirectiveSet::GateFor,
+  DirectiveSet::GateConvert
 ];
 
 #[allow(non_camel_case_types)]
-pub const ENUM_NAMES_DIRECTIVE_SET:[&'static str; 18] = [
+pub const ENUM_NAMES_DIRECTIVE_SET:[&'static str; 19] = [
     "NONE",
     "GateConstant",
     "GateAssertZero",
@@ -516,7 +518,8 @@ pub const ENUM_NAMES_DIRECTIVE_SET:[&'static str; 18] = [
     "GateCall",
     "GateAnonCall",
     "GateSwitch",
-    "GateFor"
+    "GateFor",
+    "GateConvert"
 ];
 
 pub fn enum_name_directive_set(e: DirectiveSet) -> &'static str {
@@ -554,6 +557,7 @@ impl<'a> Header<'a> {
         _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
         args: &'args HeaderArgs<'args>) -> flatbuffers::WIPOffset<Header<'bldr>> {
       let mut builder = HeaderBuilder::new(_fbb);
+      if let Some(x) = args.types { builder.add_types(x); }
       builder.add_field_degree(args.field_degree);
       if let Some(x) = args.field_characteristic { builder.add_field_characteristic(x); }
       if let Some(x) = args.version { builder.add_version(x); }
@@ -563,6 +567,7 @@ impl<'a> Header<'a> {
     pub const VT_VERSION: flatbuffers::VOffsetT = 4;
     pub const VT_FIELD_CHARACTERISTIC: flatbuffers::VOffsetT = 6;
     pub const VT_FIELD_DEGREE: flatbuffers::VOffsetT = 8;
+    pub const VT_TYPES: flatbuffers::VOffsetT = 10;
 
   #[inline]
   pub fn version(&self) -> Option<&'a str> {
@@ -576,12 +581,17 @@ impl<'a> Header<'a> {
   pub fn field_degree(&self) -> u32 {
     self._tab.get::<u32>(Header::VT_FIELD_DEGREE, Some(0)).unwrap()
   }
+  #[inline]
+  pub fn types(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Value<'a>>>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<flatbuffers::ForwardsUOffset<Value<'a>>>>>(Header::VT_TYPES, None)
+  }
 }
 
 pub struct HeaderArgs<'a> {
     pub version: Option<flatbuffers::WIPOffset<&'a  str>>,
     pub field_characteristic: Option<flatbuffers::WIPOffset<Value<'a >>>,
     pub field_degree: u32,
+    pub types: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<Value<'a >>>>>,
 }
 impl<'a> Default for HeaderArgs<'a> {
     #[inline]
@@ -590,6 +600,7 @@ impl<'a> Default for HeaderArgs<'a> {
             version: None,
             field_characteristic: None,
             field_degree: 0,
+            types: None,
         }
     }
 }
@@ -611,6 +622,10 @@ impl<'a: 'b, 'b> HeaderBuilder<'a, 'b> {
     self.fbb_.push_slot::<u32>(Header::VT_FIELD_DEGREE, field_degree, 0);
   }
   #[inline]
+  pub fn add_types(&mut self, types: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<Value<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Header::VT_TYPES, types);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> HeaderBuilder<'a, 'b> {
     let start = _fbb.start_table();
     HeaderBuilder {
@@ -780,11 +795,13 @@ impl<'a> Instance<'a> {
       let mut builder = InstanceBuilder::new(_fbb);
       if let Some(x) = args.common_inputs { builder.add_common_inputs(x); }
       if let Some(x) = args.header { builder.add_header(x); }
+      builder.add_type_id(args.type_id);
       builder.finish()
     }
 
     pub const VT_HEADER: flatbuffers::VOffsetT = 4;
     pub const VT_COMMON_INPUTS: flatbuffers::VOffsetT = 6;
+    pub const VT_TYPE_ID: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub fn header(&self) -> Option<Header<'a>> {
@@ -794,11 +811,16 @@ impl<'a> Instance<'a> {
   pub fn common_inputs(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Value<'a>>>> {
     self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<flatbuffers::ForwardsUOffset<Value<'a>>>>>(Instance::VT_COMMON_INPUTS, None)
   }
+  #[inline]
+  pub fn type_id(&self) -> u8 {
+    self._tab.get::<u8>(Instance::VT_TYPE_ID, Some(0)).unwrap()
+  }
 }
 
 pub struct InstanceArgs<'a> {
     pub header: Option<flatbuffers::WIPOffset<Header<'a >>>,
     pub common_inputs: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<Value<'a >>>>>,
+    pub type_id: u8,
 }
 impl<'a> Default for InstanceArgs<'a> {
     #[inline]
@@ -806,6 +828,7 @@ impl<'a> Default for InstanceArgs<'a> {
         InstanceArgs {
             header: None,
             common_inputs: None,
+            type_id: 0,
         }
     }
 }
@@ -823,6 +846,10 @@ impl<'a: 'b, 'b> InstanceBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Instance::VT_COMMON_INPUTS, common_inputs);
   }
   #[inline]
+  pub fn add_type_id(&mut self, type_id: u8) {
+    self.fbb_.push_slot::<u8>(Instance::VT_TYPE_ID, type_id, 0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> InstanceBuilder<'a, 'b> {
     let start = _fbb.start_table();
     InstanceBuilder {
@@ -868,11 +895,13 @@ impl<'a> Witness<'a> {
       let mut builder = WitnessBuilder::new(_fbb);
       if let Some(x) = args.short_witness { builder.add_short_witness(x); }
       if let Some(x) = args.header { builder.add_header(x); }
+      builder.add_type_id(args.type_id);
       builder.finish()
     }
 
     pub const VT_HEADER: flatbuffers::VOffsetT = 4;
     pub const VT_SHORT_WITNESS: flatbuffers::VOffsetT = 6;
+    pub const VT_TYPE_ID: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub fn header(&self) -> Option<Header<'a>> {
@@ -882,11 +911,16 @@ impl<'a> Witness<'a> {
   pub fn short_witness(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Value<'a>>>> {
     self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<flatbuffers::ForwardsUOffset<Value<'a>>>>>(Witness::VT_SHORT_WITNESS, None)
   }
+  #[inline]
+  pub fn type_id(&self) -> u8 {
+    self._tab.get::<u8>(Witness::VT_TYPE_ID, Some(0)).unwrap()
+  }
 }
 
 pub struct WitnessArgs<'a> {
     pub header: Option<flatbuffers::WIPOffset<Header<'a >>>,
     pub short_witness: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a , flatbuffers::ForwardsUOffset<Value<'a >>>>>,
+    pub type_id: u8,
 }
 impl<'a> Default for WitnessArgs<'a> {
     #[inline]
@@ -894,6 +928,7 @@ impl<'a> Default for WitnessArgs<'a> {
         WitnessArgs {
             header: None,
             short_witness: None,
+            type_id: 0,
         }
     }
 }
@@ -911,6 +946,10 @@ impl<'a: 'b, 'b> WitnessBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Witness::VT_SHORT_WITNESS, short_witness);
   }
   #[inline]
+  pub fn add_type_id(&mut self, type_id: u8) {
+    self.fbb_.push_slot::<u8>(Witness::VT_TYPE_ID, type_id, 0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> WitnessBuilder<'a, 'b> {
     let start = _fbb.start_table();
     WitnessBuilder {
@@ -2529,6 +2568,118 @@ impl<'a: 'b, 'b> GateFreeBuilder<'a, 'b> {
   }
 }
 
+pub enum GateConvertOffset {}
+#[derive(Copy, Clone, Debug, PartialEq)]
+
+pub struct GateConvert<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for GateConvert<'a> {
+    type Inner = GateConvert<'a>;
+    #[inline]
+    fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+        Self {
+            _tab: flatbuffers::Table { buf: buf, loc: loc },
+        }
+    }
+}
+
+impl<'a> GateConvert<'a> {
+    #[inline]
+    pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+        GateConvert {
+            _tab: table,
+        }
+    }
+    #[allow(unused_mut)]
+    pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr>,
+        args: &'args GateConvertArgs<'args>) -> flatbuffers::WIPOffset<GateConvert<'bldr>> {
+      let mut builder = GateConvertBuilder::new(_fbb);
+      if let Some(x) = args.input_wires { builder.add_input_wires(x); }
+      if let Some(x) = args.output_wires { builder.add_output_wires(x); }
+      builder.add_in_type(args.in_type);
+      builder.add_out_type(args.out_type);
+      builder.finish()
+    }
+
+    pub const VT_OUTPUT_WIRES: flatbuffers::VOffsetT = 4;
+    pub const VT_OUT_TYPE: flatbuffers::VOffsetT = 6;
+    pub const VT_INPUT_WIRES: flatbuffers::VOffsetT = 8;
+    pub const VT_IN_TYPE: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub fn output_wires(&self) -> Option<WireList<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<WireList<'a>>>(GateConvert::VT_OUTPUT_WIRES, None)
+  }
+  #[inline]
+  pub fn out_type(&self) -> u8 {
+    self._tab.get::<u8>(GateConvert::VT_OUT_TYPE, Some(0)).unwrap()
+  }
+  #[inline]
+  pub fn input_wires(&self) -> Option<WireList<'a>> {
+    self._tab.get::<flatbuffers::ForwardsUOffset<WireList<'a>>>(GateConvert::VT_INPUT_WIRES, None)
+  }
+  #[inline]
+  pub fn in_type(&self) -> u8 {
+    self._tab.get::<u8>(GateConvert::VT_IN_TYPE, Some(0)).unwrap()
+  }
+}
+
+pub struct GateConvertArgs<'a> {
+    pub output_wires: Option<flatbuffers::WIPOffset<WireList<'a >>>,
+    pub out_type: u8,
+    pub input_wires: Option<flatbuffers::WIPOffset<WireList<'a >>>,
+    pub in_type: u8,
+}
+impl<'a> Default for GateConvertArgs<'a> {
+    #[inline]
+    fn default() -> Self {
+        GateConvertArgs {
+            output_wires: None,
+            out_type: 0,
+            input_wires: None,
+            in_type: 0,
+        }
+    }
+}
+pub struct GateConvertBuilder<'a: 'b, 'b> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b> GateConvertBuilder<'a, 'b> {
+  #[inline]
+  pub fn add_output_wires(&mut self, output_wires: flatbuffers::WIPOffset<WireList<'b >>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<WireList>>(GateConvert::VT_OUTPUT_WIRES, output_wires);
+  }
+  #[inline]
+  pub fn add_out_type(&mut self, out_type: u8) {
+    self.fbb_.push_slot::<u8>(GateConvert::VT_OUT_TYPE, out_type, 0);
+  }
+  #[inline]
+  pub fn add_input_wires(&mut self, input_wires: flatbuffers::WIPOffset<WireList<'b >>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<WireList>>(GateConvert::VT_INPUT_WIRES, input_wires);
+  }
+  #[inline]
+  pub fn add_in_type(&mut self, in_type: u8) {
+    self.fbb_.push_slot::<u8>(GateConvert::VT_IN_TYPE, in_type, 0);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> GateConvertBuilder<'a, 'b> {
+    let start = _fbb.start_table();
+    GateConvertBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<GateConvert<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
 pub enum FunctionOffset {}
 #[derive(Copy, Clone, Debug, PartialEq)]
 
@@ -4611,12 +4762,14 @@ impl<'a> Directive<'a> {
         args: &'args DirectiveArgs) -> flatbuffers::WIPOffset<Directive<'bldr>> {
       let mut builder = DirectiveBuilder::new(_fbb);
       if let Some(x) = args.directive { builder.add_directive(x); }
+      builder.add_type_id(args.type_id);
       builder.add_directive_type(args.directive_type);
       builder.finish()
     }
 
     pub const VT_DIRECTIVE_TYPE: flatbuffers::VOffsetT = 4;
     pub const VT_DIRECTIVE: flatbuffers::VOffsetT = 6;
+    pub const VT_TYPE_ID: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub fn directive_type(&self) -> DirectiveSet {
@@ -4627,6 +4780,10 @@ impl<'a> Directive<'a> {
     self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Table<'a>>>(Directive::VT_DIRECTIVE, None)
   }
   #[inline]
+  pub fn type_id(&self) -> u8 {
+    self._tab.get::<u8>(Directive::VT_TYPE_ID, Some(0)).unwrap()
+  }
+  #[inline]
   #[allow(non_snake_case)]
   pub fn directive_as_gate_constant(&self) -> Option<GateConstant<'a>> {
     if self.directive_type() == DirectiveSet::GateConstant {
@@ -4796,11 +4953,22 @@ impl<'a> Directive<'a> {
     }
   }
 
+  #[inline]
+  #[allow(non_snake_case)]
+  pub fn directive_as_gate_convert(&self) -> Option<GateConvert<'a>> {
+    if self.directive_type() == DirectiveSet::GateConvert {
+      self.directive().map(|u| GateConvert::init_from_table(u))
+    } else {
+      None
+    }
+  }
+
 }
 
 pub struct DirectiveArgs {
     pub directive_type: DirectiveSet,
     pub directive: Option<flatbuffers::WIPOffset<flatbuffers::UnionWIPOffset>>,
+    pub type_id: u8,
 }
 impl<'a> Default for DirectiveArgs {
     #[inline]
@@ -4808,6 +4976,7 @@ impl<'a> Default for DirectiveArgs {
         DirectiveArgs {
             directive_type: DirectiveSet::NONE,
             directive: None,
+            type_id: 0,
         }
     }
 }
@@ -4825,6 +4994,10 @@ impl<'a: 'b, 'b> DirectiveBuilder<'a, 'b> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Directive::VT_DIRECTIVE, directive);
   }
   #[inline]
+  pub fn add_type_id(&mut self, type_id: u8) {
+    self.fbb_.push_slot::<u8>(Directive::VT_TYPE_ID, type_id, 0);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>) -> DirectiveBuilder<'a, 'b> {
     let start = _fbb.start_table();
     DirectiveBuilder {
@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::function::{CaseInvoke, ForLoopBody, Function};
+use crate::{Gate, Result};
+
+/// CallGraph records the functions called by each function, through Call gates, switch cases and
+/// loop bodies, including those nested in anonymous calls.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CallGraph {
+    callees: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl CallGraph {
+    pub fn from_functions(functions: &[Function]) -> Self {
+        let mut graph = CallGraph::default();
+        for f in functions {
+            graph.add_function(&f.name, &f.body);
+        }
+        graph
+    }
+
+    /// Record the calls made by the body of a function.
+    pub fn add_function(&mut self, name: &str, body: &[Gate]) {
+        self.callees
+            .entry(name.to_string())
+            .or_default()
+            .extend(called_functions(body));
+    }
+
+    /// The functions called directly by a function, or None if the function is unknown.
+    pub fn callees(&self, name: &str) -> Option<&BTreeSet<String>> {
+        self.callees.get(name)
+    }
+
+    /// Find the cycles of calls. Each cycle is given as the path of calls from a function back to
+    /// itself, e.g. ["f", "g", "f"]. Calls to unknown functions are ignored.
+    pub fn find_cycles(&self) -> Vec<Vec<String>> {
+        self.visit(self.callees.keys()).1
+    }
+
+    /// Find the cycles of calls reachable from the given functions. When functions are added to a
+    /// graph without cycles, a new cycle goes through one of them, so it is enough to search from
+    /// the new functions.
+    pub fn find_cycles_from<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<Vec<String>> {
+        self.visit(names).1
+    }
+
+    /// Fails if a function reachable from the given functions calls itself, directly or not.
+    pub fn check_recursion_from<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a String>,
+    ) -> Result<()> {
+        match self.find_cycles_from(names).first() {
+            Some(cycle) => Err(recursion_error(cycle).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Order the functions so that each function comes after all the functions it calls. Fails if
+    /// a function calls itself, directly or not.
+    pub fn topological_order(&self) -> Result<Vec<String>> {
+        let (order, cycles) = self.visit(self.callees.keys());
+        match cycles.first() {
+            Some(cycle) => Err(recursion_error(cycle).into()),
+            None => Ok(order),
+        }
+    }
+
+    /// Depth-first search of the graph from the given roots, without recursion as the chains of
+    /// calls may be long. Return the functions in post-order, and the cycles found.
+    fn visit<'a>(
+        &self,
+        roots: impl IntoIterator<Item = &'a String>,
+    ) -> (Vec<String>, Vec<Vec<String>>) {
+        // false while the function is on the path, true once all its callees are visited.
+        let mut done = BTreeMap::<&str, bool>::new();
+        let mut order = vec![];
+        let mut cycles = vec![];
+
+        for root in roots {
+            let (root, root_callees) = match self.callees.get_key_value(root) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            if done.contains_key(root.as_str()) {
+                continue;
+            }
+            done.insert(root.as_str(), false);
+            let mut path = vec![(root.as_str(), root_callees.iter())];
+
+            while let Some((name, callees)) = path.last_mut() {
+                let name = *name;
+                let callee = match callees.next() {
+                    Some(callee) => callee,
+                    None => {
+                        done.insert(name, true);
+                        order.push(name.to_string());
+                        path.pop();
+                        continue;
+                    }
+                };
+                let callee_callees = match self.callees.get(callee) {
+                    Some(callee_callees) => callee_callees,
+                    None => continue,
+                };
+                match done.get(callee.as_str()) {
+                    None => {
+                        done.insert(callee.as_str(), false);
+                        path.push((callee.as_str(), callee_callees.iter()));
+                    }
+                    Some(false) => {
+                        let start = path
+                            .iter()
+                            .position(|(n, _)| *n == callee.as_str())
+                            .unwrap();
+                        let mut cycle: Vec<String> =
+                            path[start..].iter().map(|(n, _)| n.to_string()).collect();
+                        cycle.push(callee.clone());
+                        cycles.push(cycle);
+                    }
+                    Some(true) => {}
+                }
+            }
+        }
+        (order, cycles)
+    }
+}
+
+/// The message describing a cycle of calls, e.g. ["f", "g", "f"].
+pub fn recursion_error(cycle: &[String]) -> String {
+    format!(
+        "The function {} is recursive: {}.",
+        cycle[0],
+        cycle.join(" -> ")
+    )
+}
+
+/// The names of the functions called by a list of gates, including in nested anonymous calls.
+pub fn called_functions(gates: &[Gate]) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    add_called_functions(gates, &mut names);
+    names
+}
+
+fn add_called_functions(gates: &[Gate], names: &mut BTreeSet<String>) {
+    for gate in gates {
+        match gate {
+            Gate::Call(name, _, _) => {
+                names.insert(name.clone());
+            }
+            Gate::AnonCall(_, _, _, _, subcircuit) => add_called_functions(subcircuit, names),
+            Gate::Switch(_, _, _, branches) => {
+                for branch in branches {
+                    match branch {
+                        CaseInvoke::AbstractGateCall(name, _) => {
+                            names.insert(name.clone());
+                        }
+                        CaseInvoke::AbstractAnonCall(_, _, _, subcircuit) => {
+                            add_called_functions(subcircuit, names)
+                        }
+                    }
+                }
+            }
+            Gate::For(_, _, _, _, body) => match body {
+                ForLoopBody::IterExprCall(name, _, _) => {
+                    names.insert(name.clone());
+                }
+                ForLoopBody::IterExprAnonCall(_, _, _, _, subcircuit) => {
+                    add_called_functions(subcircuit, names)
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_call_graph() -> Result<()> {
+    use crate::structs::function::ForLoopBody::IterExprCall;
+    use crate::structs::wire::WireListElement::Wire;
+    use crate::Gate::*;
+
+    let function = |name: &str, body: Vec<Gate>| Function::new(name.to_string(), 0, 0, 0, 0, body);
+    let switch_call = |name: &str| {
+        Switch(
+            0,
+            vec![],
+            vec![vec![1]],
+            vec![CaseInvoke::AbstractAnonCall(
+                vec![Wire(0)],
+                0,
+                0,
+                vec![Call(name.to_string(), vec![], vec![])],
+            )],
+        )
+    };
+
+    let mut functions = vec![
+        function(
+            "main",
+            vec![Call("add".into(), vec![], vec![]), switch_call("mul")],
+        ),
+        function("mul", vec![Call("add".into(), vec![], vec![])]),
+        function("add", vec![Call("unknown".into(), vec![], vec![])]),
+    ];
+    let graph = CallGraph::from_functions(&functions);
+    assert_eq!(graph.callees("main").unwrap().len(), 2);
+    assert!(graph.find_cycles().is_empty());
+    assert_eq!(graph.topological_order()?, vec!["add", "mul", "main"]);
+
+    // add -> main -> add through a loop, add -> main -> mul -> add, and mul -> mul.
+    functions[1].body.push(switch_call("mul"));
+    functions[2].body.push(For(
+        "i".into(),
+        0,
+        1,
+        vec![],
+        IterExprCall("main".into(), vec![], vec![]),
+    ));
+    let graph = CallGraph::from_functions(&functions);
+    assert_eq!(
+        graph.find_cycles(),
+        vec![
+            vec!["add", "main", "add"],
+            vec!["add", "main", "mul", "add"],
+            vec!["mul", "mul"],
+        ]
+    );
+    assert!(graph.topological_order().is_err());
+
+    // Only the cycles reachable from the given functions are found.
+    assert_eq!(
+        graph.find_cycles_from(&["mul".to_string()]),
+        vec![
+            vec!["add", "main", "add"],
+            vec!["mul", "add", "main", "mul"],
+            vec!["mul", "mul"],
+        ]
+    );
+    assert!(graph.check_recursion_from(&["unknown".to_string()]).is_ok());
+
+    // A function added later closes a cycle through the functions already known.
+    let mut graph = CallGraph::from_functions(&functions[..1]);
+    assert!(graph.find_cycles().is_empty());
+    let add = &functions[2];
+    graph.add_function(&add.name, &add.body);
+    assert_eq!(
+        graph
+            .check_recursion_from(&[add.name.clone()])
+            .unwrap_err()
+            .to_string(),
+        "The function add is recursive: add -> main -> add."
+    );
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use crate::structs::field_value::Field;
+use crate::structs::value_serde::parse_value;
+use crate::{Header, Result};
+use num_bigint::BigUint;
+use num_traits::Num;
+
+/// A well-known prime field, to build headers by name.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FieldPreset {
+    /// The name of the preset, e.g. `bn254`.
+    pub name: &'static str,
+    /// Other names accepted by `FieldPreset::by_name`.
+    pub aliases: &'static [&'static str],
+    pub description: &'static str,
+    /// The characteristic in decimal.
+    characteristic: &'static str,
+}
+
+/// The known fields. Names are matched by `FieldPreset::by_name`.
+pub const FIELD_PRESETS: &[FieldPreset] = &[
+    FieldPreset {
+        name: "bn254",
+        aliases: &["bn128", "alt_bn128"],
+        description: "BN254 scalar field",
+        characteristic:
+            "21888242871839275222246405745257275088548364400416034343698204186575808495617",
+    },
+    FieldPreset {
+        name: "bls12-381",
+        aliases: &[],
+        description: "BLS12-381 scalar field",
+        characteristic:
+            "52435875175126190479447740508185965837690552500527637822603658699938581184513",
+    },
+    FieldPreset {
+        name: "goldilocks",
+        aliases: &["2^64-2^32+1"],
+        description: "Goldilocks field 2^64 - 2^32 + 1",
+        characteristic: "18446744069414584321",
+    },
+    FieldPreset {
+        name: "mersenne61",
+        aliases: &["m61", "2^61-1"],
+        description: "Mersenne prime field 2^61 - 1",
+        characteristic: "2305843009213693951",
+    },
+    FieldPreset {
+        name: "curve25519",
+        aliases: &["2^255-19", "ed25519"],
+        description: "Curve25519 base field 2^255 - 19",
+        characteristic:
+            "57896044618658097711785492504343953926634992332820282019728792003956564819949",
+    },
+    FieldPreset {
+        name: "gf2",
+        aliases: &["boolean", "binary"],
+        description: "Binary field GF(2)",
+        characteristic: "2",
+    },
+];
+
+impl FieldPreset {
+    /// Find a preset by its name or an alias, ignoring case, spaces, dashes and underscores.
+    pub fn by_name(name: &str) -> Option<&'static FieldPreset> {
+        let name = normalize_name(name);
+        FIELD_PRESETS.iter().find(|preset| {
+            normalize_name(preset.name) == name
+                || preset
+                    .aliases
+                    .iter()
+                    .any(|alias| normalize_name(alias) == name)
+        })
+    }
+
+    /// Find the preset with the field of a header, if any.
+    pub fn from_header(header: &Header) -> Option<&'static FieldPreset> {
+        if header.field_degree != 1 {
+            return None;
+        }
+        FieldPreset::from_characteristic(&BigUint::from_bytes_le(&header.field_characteristic))
+    }
+
+    /// Find the preset with the given characteristic, if any.
+    pub fn from_characteristic(characteristic: &BigUint) -> Option<&'static FieldPreset> {
+        FIELD_PRESETS
+            .iter()
+            .find(|preset| preset.characteristic() == *characteristic)
+    }
+
+    pub fn characteristic(&self) -> BigUint {
+        BigUint::from_str_radix(self.characteristic, 10).unwrap()
+    }
+
+    pub fn field(&self) -> Field {
+        Field::new(self.characteristic()).unwrap()
+    }
+
+    /// A header of this field, with the characteristic in its canonical encoding.
+    pub fn header(&self) -> Header {
+        self.field().to_header()
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Describe a characteristic for messages: the number, followed by the name of its preset if any.
+pub fn describe_characteristic(characteristic: &BigUint) -> String {
+    match FieldPreset::from_characteristic(characteristic) {
+        Some(preset) => format!("{} ({})", characteristic, preset.name),
+        None => characteristic.to_string(),
+    }
+}
+
+/// Parse a field characteristic from the name of a preset, or from a number in decimal or in
+/// hexadecimal starting with `0x`. Used by the `--field-order` option of the CLI.
+pub fn parse_field_order(s: &str) -> Result<BigUint> {
+    if let Some(preset) = FieldPreset::by_name(s) {
+        return Ok(preset.characteristic());
+    }
+    match parse_value(s) {
+        Ok(value) => Ok(BigUint::from_bytes_le(&value)),
+        Err(_) => {
+            let names: Vec<_> = FIELD_PRESETS.iter().map(|preset| preset.name).collect();
+            Err(format!(
+                "Unknown field {} (expected a number or one of: {})",
+                s,
+                names.join(", ")
+            )
+            .into())
+        }
+    }
+}
+
+#[test]
+fn test_field_presets() -> Result<()> {
+    use crate::producers::examples::example_header;
+    use crate::structs::value::is_probably_prime;
+
+    for preset in FIELD_PRESETS {
+        assert!(is_probably_prime(&preset.characteristic().to_bytes_le()));
+        assert_eq!(FieldPreset::by_name(preset.name), Some(preset));
+        assert_eq!(FieldPreset::from_header(&preset.header()), Some(preset));
+    }
+    assert_eq!(FieldPreset::by_name("BLS12_381").unwrap().name, "bls12-381");
+    assert_eq!(FieldPreset::by_name("2^255-19").unwrap().name, "curve25519");
+    assert_eq!(FieldPreset::by_name("unknown"), None);
+    assert_eq!(FieldPreset::from_header(&example_header()), None);
+
+    // Padded encodings match too.
+    let mut header = Header::from_preset("m61")?;
+    header.field_characteristic.extend(&[0, 0]);
+    assert_eq!(
+        FieldPreset::from_header(&header).unwrap().name,
+        "mersenne61"
+    );
+    assert_eq!(
+        header.field()?.characteristic(),
+        &BigUint::from((1u64 << 61) - 1)
+    );
+
+    assert_eq!(parse_field_order("101")?, BigUint::from(101u32));
+    assert_eq!(parse_field_order("0x65")?, BigUint::from(101u32));
+    assert_eq!(
+        parse_field_order("Goldilocks")?,
+        BigUint::from(18446744069414584321u64)
+    );
+    assert!(parse_field_order("bn255").is_err());
+    assert_eq!(
+        describe_characteristic(&BigUint::from(2u32)),
+        "2 (gf2)".to_string()
+    );
+    Ok(())
+}
@@ -0,0 +1,376 @@
+use crate::structs::value_serde::parse_value;
+use crate::{Header, Result, Value};
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::Arc;
+
+/// Field is the prime field of a Header, see `Header::field`. It creates the FieldValues of
+/// this field, checking that values are smaller than the characteristic.
+///
+/// # Example
+/// ```
+/// use zki_sieve::producers::examples::example_header;
+///
+/// let field = example_header().field().unwrap();
+/// let a = field.parse("100").unwrap();
+/// let b = field.element(&vec![3, 0, 0, 0]).unwrap();
+/// assert_eq!((&a + &b).to_value(), vec![2]);
+/// assert_eq!(a, field.minus_one());
+/// assert!(field.parse("0x65").is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Field {
+    characteristic: Arc<BigUint>,
+    byte_len: usize,
+}
+
+/// FieldValue is a value of a Field, i.e. an integer smaller than the field characteristic.
+/// Arithmetic is modulo the characteristic. The operators panic when values of different fields
+/// are mixed, while `checked_add`, `checked_sub` and `checked_mul` return an error.
+///
+/// FieldValues are written into messages as a Value with `to_value`, so the format of
+/// messages does not change.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FieldValue {
+    value: BigUint,
+    field: Field,
+}
+
+impl Field {
+    /// The field of the given characteristic, which must be a prime number. Primality is not
+    /// checked here, see `is_probably_prime`.
+    pub fn new(characteristic: BigUint) -> Result<Field> {
+        if characteristic < BigUint::from(2u32) {
+            return Err(format!("Invalid field characteristic {}", characteristic).into());
+        }
+        let byte_len = ((characteristic.bits() + 7) / 8) as usize;
+        Ok(Field {
+            characteristic: Arc::new(characteristic),
+            byte_len,
+        })
+    }
+
+    /// The field of a header. Only prime fields (field_degree = 1) are supported.
+    pub fn from_header(header: &Header) -> Result<Field> {
+        if header.field_degree != 1 {
+            return Err(format!(
+                "Unsupported field_degree {} (expected 1)",
+                header.field_degree
+            )
+            .into());
+        }
+        Field::new(BigUint::from_bytes_le(&header.field_characteristic))
+    }
+
+    /// A header of this field, with the characteristic in its canonical encoding.
+    pub fn to_header(&self) -> Header {
+        Header::new(self.characteristic.to_bytes_le())
+    }
+
+    pub fn characteristic(&self) -> &BigUint {
+        &self.characteristic
+    }
+
+    /// The number of bytes of the canonical encoding of values, i.e. of the characteristic.
+    pub fn byte_len(&self) -> usize {
+        self.byte_len
+    }
+
+    /// Whether a value is an element of this field.
+    pub fn contains(&self, value: &[u8]) -> bool {
+        BigUint::from_bytes_le(value) < *self.characteristic
+    }
+
+    /// Read an element from a value in any encoding, compact or padded with zeros.
+    pub fn element(&self, value: &[u8]) -> Result<FieldValue> {
+        self.from_biguint_checked(BigUint::from_bytes_le(value))
+    }
+
+    /// Parse an element from a decimal string, or a hexadecimal string starting with `0x`.
+    pub fn parse(&self, s: &str) -> Result<FieldValue> {
+        self.element(&parse_value(s)?)
+    }
+
+    /// The element equal to the given integer, which must be smaller than the characteristic.
+    pub fn from_biguint_checked(&self, value: BigUint) -> Result<FieldValue> {
+        if value >= *self.characteristic {
+            return Err(format!(
+                "The value {} is not in the field of characteristic {}",
+                value, self.characteristic
+            )
+            .into());
+        }
+        Ok(self.wrap(value))
+    }
+
+    /// The element equal to the given integer modulo the characteristic.
+    pub fn from_biguint(&self, value: &BigUint) -> FieldValue {
+        self.wrap(value % &*self.characteristic)
+    }
+
+    pub fn from_u64(&self, value: u64) -> FieldValue {
+        self.from_biguint(&BigUint::from(value))
+    }
+
+    /// The element equal to the given integer modulo the characteristic, e.g. -1 is `p - 1`.
+    pub fn from_i64(&self, value: i64) -> FieldValue {
+        let element = self.from_u64(value.unsigned_abs());
+        if value < 0 {
+            -element
+        } else {
+            element
+        }
+    }
+
+    pub fn zero(&self) -> FieldValue {
+        self.wrap(BigUint::zero())
+    }
+
+    pub fn one(&self) -> FieldValue {
+        self.from_u64(1)
+    }
+
+    pub fn minus_one(&self) -> FieldValue {
+        self.from_i64(-1)
+    }
+
+    fn wrap(&self, value: BigUint) -> FieldValue {
+        FieldValue {
+            value,
+            field: self.clone(),
+        }
+    }
+}
+
+impl FieldValue {
+    pub fn field(&self) -> &Field {
+        &self.field
+    }
+
+    pub fn as_biguint(&self) -> &BigUint {
+        &self.value
+    }
+
+    /// The canonical encoding of this element: little-endian, padded with zeros to the byte
+    /// length of the characteristic.
+    pub fn to_value(&self) -> Value {
+        self.to_value_with_len(self.field.byte_len)
+    }
+
+    /// The encoding of this element, padded with zeros to `len` bytes. This is useful to match
+    /// the width of other values, e.g. of the field characteristic in a header.
+    ///
+    /// Panics if the element does not fit in `len` bytes.
+    pub fn to_value_with_len(&self, len: usize) -> Value {
+        let mut value = self.value.to_bytes_le();
+        if self.value.is_zero() {
+            value.clear();
+        }
+        assert!(
+            value.len() <= len,
+            "The value {} does not fit in {} bytes",
+            self.value,
+            len
+        );
+        value.resize(len, 0);
+        value
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        self.value.to_u64()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.value.is_one()
+    }
+
+    pub fn pow(&self, exponent: &BigUint) -> FieldValue {
+        self.field
+            .wrap(self.value.modpow(exponent, &self.field.characteristic))
+    }
+
+    /// The multiplicative inverse of this element, or None for zero. The characteristic is
+    /// assumed to be prime.
+    pub fn inverse(&self) -> Option<FieldValue> {
+        if self.is_zero() {
+            return None;
+        }
+        let exponent = &*self.field.characteristic - BigUint::from(2u32);
+        Some(self.pow(&exponent))
+    }
+
+    /// The sum of two values, or an error if they belong to different fields.
+    pub fn checked_add(&self, other: &FieldValue) -> Result<FieldValue> {
+        self.check_same_field(other)?;
+        Ok(self.field.from_biguint(&(&self.value + &other.value)))
+    }
+
+    /// The difference of two values, or an error if they belong to different fields.
+    pub fn checked_sub(&self, other: &FieldValue) -> Result<FieldValue> {
+        self.checked_add(&-other)
+    }
+
+    /// The product of two values, or an error if they belong to different fields.
+    pub fn checked_mul(&self, other: &FieldValue) -> Result<FieldValue> {
+        self.check_same_field(other)?;
+        Ok(self.field.from_biguint(&(&self.value * &other.value)))
+    }
+
+    fn check_same_field(&self, other: &FieldValue) -> Result<()> {
+        if self.field != other.field {
+            return Err(format!(
+                "Arithmetic between values of fields of characteristic {} and {}",
+                self.field.characteristic, other.field.characteristic
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl From<FieldValue> for Value {
+    fn from(element: FieldValue) -> Value {
+        element.to_value()
+    }
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl<'a> Add for &'a FieldValue {
+    type Output = FieldValue;
+
+    /// Panics if the values belong to different fields, see `checked_add`.
+    fn add(self, other: &FieldValue) -> FieldValue {
+        self.checked_add(other)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<'a> Sub for &'a FieldValue {
+    type Output = FieldValue;
+
+    /// Panics if the values belong to different fields, see `checked_sub`.
+    fn sub(self, other: &FieldValue) -> FieldValue {
+        self.checked_sub(other)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<'a> Mul for &'a FieldValue {
+    type Output = FieldValue;
+
+    /// Panics if the values belong to different fields, see `checked_mul`.
+    fn mul(self, other: &FieldValue) -> FieldValue {
+        self.checked_mul(other)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<'a> Neg for &'a FieldValue {
+    type Output = FieldValue;
+
+    fn neg(self) -> FieldValue {
+        if self.is_zero() {
+            self.clone()
+        } else {
+            self.field.wrap(&*self.field.characteristic - &self.value)
+        }
+    }
+}
+
+impl Add for FieldValue {
+    type Output = FieldValue;
+
+    fn add(self, other: FieldValue) -> FieldValue {
+        &self + &other
+    }
+}
+
+impl Sub for FieldValue {
+    type Output = FieldValue;
+
+    fn sub(self, other: FieldValue) -> FieldValue {
+        &self - &other
+    }
+}
+
+impl Mul for FieldValue {
+    type Output = FieldValue;
+
+    fn mul(self, other: FieldValue) -> FieldValue {
+        &self * &other
+    }
+}
+
+impl Neg for FieldValue {
+    type Output = FieldValue;
+
+    fn neg(self) -> FieldValue {
+        -&self
+    }
+}
+
+#[test]
+fn test_field_value() -> Result<()> {
+    use crate::producers::examples::*;
+
+    let header = example_header();
+    let field = header.field()?;
+    assert_eq!(field.characteristic(), &BigUint::from(101u32));
+    assert_eq!(field.byte_len(), 1);
+    assert_eq!(field.to_header().field_characteristic, vec![101]);
+
+    // Range checks and parsing.
+    assert!(field.contains(&[100, 0, 0, 0]));
+    assert!(!field.contains(&[101]));
+    assert!(field.element(&[101, 0]).is_err());
+    assert_eq!(field.parse("0x0064")?, field.parse("100")?);
+    assert!(field.parse("0x65").is_err());
+    assert!(field.parse("abc").is_err());
+    assert_eq!(field.from_i64(-1), field.minus_one());
+    assert_eq!(field.from_u64(202), field.zero());
+
+    // Arithmetic.
+    let a = field.from_u64(3);
+    let b = field.from_u64(4);
+    assert_eq!(&a + &b, field.from_u64(7));
+    assert_eq!(&a - &b, field.minus_one());
+    assert_eq!(&a * &b, field.from_u64(12));
+    assert_eq!(-field.zero(), field.zero());
+    assert_eq!(a.pow(&BigUint::from(4u32)), field.from_u64(81));
+    assert_eq!(a.checked_sub(&b)?, field.minus_one());
+    let other = Field::new(BigUint::from(7u32))?;
+    assert!(a.checked_add(&other.one()).is_err());
+    assert!(a.checked_mul(&other.one()).is_err());
+    let inverse = a.inverse().unwrap();
+    assert!((&a * &inverse).is_one());
+    assert_eq!(field.zero().inverse(), None);
+
+    // Encodings.
+    assert_eq!(field.minus_one().to_value(), vec![100]);
+    assert_eq!(field.zero().to_value(), vec![0]);
+    assert_eq!(field.minus_one().to_value_with_len(4), vec![100, 0, 0, 0]);
+    assert_eq!(Value::from(b), vec![4]);
+    assert_eq!(a.to_string(), "3");
+    assert_eq!(a.to_u64(), Some(3));
+
+    let big = Field::new(BigUint::from(1u32) << 64)?;
+    assert_eq!(big.byte_len(), 9);
+    assert_eq!(big.one().to_value(), vec![1, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    assert!(Field::new(BigUint::from(1u32)).is_err());
+    let mut extension = header.clone();
+    extension.field_degree = 2;
+    assert!(extension.field().is_err());
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use crate::Result;
 use flatbuffers::{FlatBufferBuilder, ForwardsUOffset, Vector, WIPOffset};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::convert::TryFrom;
 use std::error::Error;
 
@@ -11,13 +11,13 @@ use super::wire::{build_wire, build_wire_list, from_id, WireList};
 use crate::sieve_ir_generated::sieve_ir as g;
 use crate::sieve_ir_generated::sieve_ir::DirectiveSet as ds;
 use crate::structs::wire::{replace_wire, replace_wire_in_wirelist};
-use crate::{Value, WireId};
+use crate::{TypeId, Value, WireId};
 
 /// This one correspond to Directive in the FlatBuffers schema
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
 pub enum Gate {
     /// Constant(output, constant)
-    Constant(WireId, Value),
+    Constant(WireId, #[serde(with = "crate::structs::value_serde")] Value),
     /// AssertZero(input)
     AssertZero(WireId),
     /// Copy(output, input)
@@ -27,9 +27,17 @@ pub enum Gate {
     /// Mul(output, input, input)
     Mul(WireId, WireId, WireId),
     /// AddConstant(output, input, constant)
-    AddConstant(WireId, WireId, Value),
+    AddConstant(
+        WireId,
+        WireId,
+        #[serde(with = "crate::structs::value_serde")] Value,
+    ),
     /// MulConstant(output, input, constant)
-    MulConstant(WireId, WireId, Value),
+    MulConstant(
+        WireId,
+        WireId,
+        #[serde(with = "crate::structs::value_serde")] Value,
+    ),
     /// And(output, input, input)
     And(WireId, WireId, WireId),
     /// Xor(output, input, input)
@@ -49,9 +57,25 @@ pub enum Gate {
     /// GateCall(name, output_wires, input_wires)
     Call(String, WireList, WireList),
     /// GateSwitch(condition, output_wires, cases, branches)
-    Switch(WireId, WireList, Vec<Value>, Vec<CaseInvoke>),
+    Switch(
+        WireId,
+        WireList,
+        #[serde(with = "crate::structs::value_serde::vec")] Vec<Value>,
+        Vec<CaseInvoke>,
+    ),
     /// GateFor(iterator_name, start_val, end_val, global_output_list, body)
     For(String, u64, u64, WireList, ForLoopBody),
+    /// Typed(type_id, gate): a gate on the wires of a type, since IR version 2.0.0.
+    /// Only basic gates are typed; other gates are on the wires of the type 0.
+    /// See `Gate::typed` to check this when creating the gate.
+    Typed(
+        TypeId,
+        #[serde(deserialize_with = "deserialize_basic_gate")] Box<Gate>,
+    ),
+    /// Convert(output_type, output_wires, input_type, input_wires), since IR version 2.0.0.
+    /// The inputs are the digits of a number in the base of the input field, most significant
+    /// first, and the outputs are the digits of the same number in the base of the output field.
+    Convert(TypeId, WireList, TypeId, WireList),
 }
 
 use crate::structs::iterators::build_iterexpr_list;
@@ -62,7 +86,7 @@ impl<'a> TryFrom<g::Directive<'a>> for Gate {
 
     /// Convert from Flatbuffers references to owned structure.
     fn try_from(gen_gate: g::Directive) -> Result<Gate> {
-        Ok(match gen_gate.directive_type() {
+        let gate = match gen_gate.directive_type() {
             ds::NONE => return Err("No gate type".into()),
 
             ds::GateConstant => {
@@ -207,6 +231,16 @@ impl<'a> TryFrom<g::Directive<'a>> for Gate {
                 )
             }
 
+            ds::GateConvert => {
+                let gate = gen_gate.directive_as_gate_convert().unwrap();
+                Convert(
+                    gate.out_type(),
+                    WireList::try_from(gate.output_wires().ok_or_else(|| "Missing output wires")?)?,
+                    gate.in_type(),
+                    WireList::try_from(gate.input_wires().ok_or_else(|| "Missing input wires")?)?,
+                )
+            }
+
             ds::GateFor => {
                 let gate = gen_gate.directive_as_gate_for().unwrap();
                 let output_list =
@@ -254,17 +288,77 @@ impl<'a> TryFrom<g::Directive<'a>> for Gate {
                     body,
                 )
             }
-        })
+        };
+        Gate::typed(gen_gate.type_id(), gate)
+    }
+}
+
+fn deserialize_basic_gate<'de, D>(deserializer: D) -> std::result::Result<Box<Gate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let gate = Box::<Gate>::deserialize(deserializer)?;
+    if !gate.is_basic() {
+        return Err(serde::de::Error::custom("Only basic gates can be typed."));
     }
+    Ok(gate)
 }
 
 impl Gate {
+    /// The gate `gate` on the wires of the type `type_id`. The type 0 leaves the gate as it is,
+    /// and only basic gates can be of another type.
+    pub fn typed(type_id: TypeId, gate: Gate) -> Result<Gate> {
+        if type_id == 0 {
+            return Ok(gate);
+        }
+        if !gate.is_basic() {
+            return Err(format!("Only basic gates can be of the type {}.", type_id).into());
+        }
+        Ok(Typed(type_id, Box::new(gate)))
+    }
+
+    /// Whether this gate is a basic gate, which can be of any type: an arithmetic or boolean gate,
+    /// or an Instance or Witness gate.
+    pub fn is_basic(&self) -> bool {
+        match self {
+            Constant(..) | AssertZero(..) | Copy(..) | Add(..) | Mul(..) | AddConstant(..)
+            | MulConstant(..) | And(..) | Xor(..) | Not(..) | Instance(..) | Witness(..) => true,
+            Free(..) | AnonCall(..) | Call(..) | Switch(..) | For(..) | Typed(..) | Convert(..) => {
+                false
+            }
+        }
+    }
+
+    /// The type of the wires of this gate, and the gate itself without its type.
+    pub fn split_type(&self) -> (TypeId, &Gate) {
+        match self {
+            Typed(type_id, gate) => (*type_id, gate),
+            gate => (0, gate),
+        }
+    }
+
     /// Add this structure into a Flatbuffers message builder.
     pub fn build<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
         &'args self,
         builder: &'mut_bldr mut FlatBufferBuilder<'bldr>,
+    ) -> WIPOffset<g::Directive<'bldr>> {
+        self.build_with_type(builder, 0)
+    }
+
+    fn build_with_type<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
+        &'args self,
+        builder: &'mut_bldr mut FlatBufferBuilder<'bldr>,
+        type_id: TypeId,
     ) -> WIPOffset<g::Directive<'bldr>> {
         match self {
+            Typed(inner_type, gate) => {
+                assert!(
+                    type_id == 0 && gate.is_basic(),
+                    "Only basic gates can be typed, see Gate::typed"
+                );
+                gate.build_with_type(builder, *inner_type)
+            }
+
             Constant(output, constant) => {
                 let constant = builder.create_vector(constant);
                 let g_output = build_wire(builder, *output);
@@ -281,6 +375,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateConstant,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -298,6 +393,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateAssertZero,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -317,6 +413,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateCopy,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -338,6 +435,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateAdd,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -359,6 +457,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateMul,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -380,6 +479,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateAddConstant,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -401,6 +501,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateMulConstant,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -422,6 +523,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateAnd,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -443,6 +545,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateXor,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -462,6 +565,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateNot,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -479,6 +583,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateInstance,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -496,6 +601,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateWitness,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -516,6 +622,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateFree,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -548,6 +655,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateAnonCall,
                         directive: Some(g_gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -571,6 +679,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateCall,
                         directive: Some(g_gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -596,6 +705,7 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateSwitch,
                         directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -672,6 +782,31 @@ impl Gate {
                     &g::DirectiveArgs {
                         directive_type: ds::GateFor,
                         directive: Some(gate.as_union_value()),
+                        type_id,
+                    },
+                )
+            }
+
+            Convert(out_type, output_wires, in_type, input_wires) => {
+                let g_outputs = build_wire_list(builder, output_wires);
+                let g_inputs = build_wire_list(builder, input_wires);
+
+                let gate = g::GateConvert::create(
+                    builder,
+                    &g::GateConvertArgs {
+                        output_wires: Some(g_outputs),
+                        out_type: *out_type,
+                        input_wires: Some(g_inputs),
+                        in_type: *in_type,
+                    },
+                );
+
+                g::Directive::create(
+                    builder,
+                    &g::DirectiveArgs {
+                        directive_type: ds::GateConvert,
+                        directive: Some(gate.as_union_value()),
+                        type_id,
                     },
                 )
             }
@@ -715,6 +850,7 @@ impl Gate {
             Not(w, _) => Some(w),
             Instance(w) => Some(w),
             Witness(w) => Some(w),
+            Typed(_, ref gate) => gate._get_output_wire_id(),
 
             AssertZero(_) => None,
             Free(_, _) => None,
@@ -723,6 +859,7 @@ impl Gate {
             Call(_, _, _) => unimplemented!("Call gate"),
             Switch(_, _, _, _) => unimplemented!("Switch gate"),
             For(_, _, _, _, _) => unimplemented!("For loop"),
+            Convert(_, _, _, _) => unimplemented!("Convert gate"),
         }
     }
 }
@@ -756,99 +893,111 @@ pub fn replace_output_wires(gates: &mut Vec<Gate>, output_wires: &Vec<WireId>) -
         let old_wire = output_wires[i];
         let new_wire = i as u64;
         for gate in &mut *gates {
-            match gate {
-                Constant(ref mut output, _) => {
-                    replace_wire(output, old_wire, new_wire);
-                }
-                Copy(ref mut output, ref mut input) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(input, old_wire, new_wire);
-                }
-                Add(ref mut output, ref mut left, ref mut right) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(left, old_wire, new_wire);
-                    replace_wire(right, old_wire, new_wire);
-                }
-                Mul(ref mut output, ref mut left, ref mut right) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(left, old_wire, new_wire);
-                    replace_wire(right, old_wire, new_wire);
-                }
-                AddConstant(ref mut output, ref mut input, _) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(input, old_wire, new_wire);
-                }
-                MulConstant(ref mut output, ref mut input, _) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(input, old_wire, new_wire);
-                }
-                And(ref mut output, ref mut left, ref mut right) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(left, old_wire, new_wire);
-                    replace_wire(right, old_wire, new_wire);
-                }
-                Xor(ref mut output, ref mut left, ref mut right) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(left, old_wire, new_wire);
-                    replace_wire(right, old_wire, new_wire);
-                }
-                Not(ref mut output, ref mut input) => {
-                    replace_wire(output, old_wire, new_wire);
-                    replace_wire(input, old_wire, new_wire);
-                }
-                Instance(ref mut output) => {
-                    replace_wire(output, old_wire, new_wire);
-                }
-                Witness(ref mut output) => {
-                    replace_wire(output, old_wire, new_wire);
-                }
-                AssertZero(ref mut wire) => {
-                    replace_wire(wire, old_wire, new_wire);
-                }
-                Free(ref mut first, ref mut option_last) => match option_last {
-                    Some(last) => {
-                        if *first <= old_wire && *last >= old_wire {
-                            return Err(format!("It is forbidden to free an output wire !").into());
-                        }
-                    }
-                    None => {
-                        if *first == old_wire {
-                            return Err(format!("It is forbidden to free an output wire !").into());
-                        }
-                    }
-                },
-                AnonCall(ref mut outputs, ref mut inputs, _, _, _) => {
-                    replace_wire_in_wirelist(outputs, old_wire, new_wire)?;
-                    replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
+            replace_wire_in_gate(gate, old_wire, new_wire)?;
+        }
+    }
+    Ok(())
+}
+
+fn replace_wire_in_gate(gate: &mut Gate, old_wire: WireId, new_wire: WireId) -> Result<()> {
+    match gate {
+        Constant(ref mut output, _) => {
+            replace_wire(output, old_wire, new_wire);
+        }
+        Copy(ref mut output, ref mut input) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(input, old_wire, new_wire);
+        }
+        Add(ref mut output, ref mut left, ref mut right) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(left, old_wire, new_wire);
+            replace_wire(right, old_wire, new_wire);
+        }
+        Mul(ref mut output, ref mut left, ref mut right) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(left, old_wire, new_wire);
+            replace_wire(right, old_wire, new_wire);
+        }
+        AddConstant(ref mut output, ref mut input, _) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(input, old_wire, new_wire);
+        }
+        MulConstant(ref mut output, ref mut input, _) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(input, old_wire, new_wire);
+        }
+        And(ref mut output, ref mut left, ref mut right) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(left, old_wire, new_wire);
+            replace_wire(right, old_wire, new_wire);
+        }
+        Xor(ref mut output, ref mut left, ref mut right) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(left, old_wire, new_wire);
+            replace_wire(right, old_wire, new_wire);
+        }
+        Not(ref mut output, ref mut input) => {
+            replace_wire(output, old_wire, new_wire);
+            replace_wire(input, old_wire, new_wire);
+        }
+        Instance(ref mut output) => {
+            replace_wire(output, old_wire, new_wire);
+        }
+        Witness(ref mut output) => {
+            replace_wire(output, old_wire, new_wire);
+        }
+        AssertZero(ref mut wire) => {
+            replace_wire(wire, old_wire, new_wire);
+        }
+        Free(ref mut first, ref mut option_last) => match option_last {
+            Some(last) => {
+                if *first <= old_wire && *last >= old_wire {
+                    return Err(format!("It is forbidden to free an output wire !").into());
                 }
-                Call(_, ref mut outputs, ref mut inputs) => {
-                    replace_wire_in_wirelist(outputs, old_wire, new_wire)?;
-                    replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
+            }
+            None => {
+                if *first == old_wire {
+                    return Err(format!("It is forbidden to free an output wire !").into());
                 }
-                Switch(ref mut condition, ref mut outputs, _, ref mut branches) => {
-                    if *condition == old_wire {
-                        *condition = new_wire;
+            }
+        },
+        AnonCall(ref mut outputs, ref mut inputs, _, _, _) => {
+            replace_wire_in_wirelist(outputs, old_wire, new_wire)?;
+            replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
+        }
+        Call(_, ref mut outputs, ref mut inputs) => {
+            replace_wire_in_wirelist(outputs, old_wire, new_wire)?;
+            replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
+        }
+        Switch(ref mut condition, ref mut outputs, _, ref mut branches) => {
+            if *condition == old_wire {
+                *condition = new_wire;
+            }
+            replace_wire_in_wirelist(outputs, old_wire, new_wire)?;
+            for branch in branches {
+                match *branch {
+                    CaseInvoke::AbstractAnonCall(ref mut inputs, _, _, _) => {
+                        replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
                     }
-                    replace_wire_in_wirelist(outputs, old_wire, new_wire)?;
-                    for branch in branches {
-                        match *branch {
-                            CaseInvoke::AbstractAnonCall(ref mut inputs, _, _, _) => {
-                                replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
-                            }
-                            CaseInvoke::AbstractGateCall(_, ref mut inputs) => {
-                                replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
-                            }
-                        };
+                    CaseInvoke::AbstractGateCall(_, ref mut inputs) => {
+                        replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
                     }
-                }
-                For(_, _, _, _, _) => {
-                    // At the beginning of this method, we check if there is at least one For gate.
-                    // If it is the case, we add Copy gates and return
-                    // Therefor, this case is unreachable !!!
-                    panic!("Unreachable case in replace_output_wires method.")
-                }
+                };
             }
         }
+        Typed(_, ref mut gate) => {
+            replace_wire_in_gate(gate, old_wire, new_wire)?;
+        }
+        Convert(_, ref mut outputs, _, ref mut inputs) => {
+            replace_wire_in_wirelist(outputs, old_wire, new_wire)?;
+            replace_wire_in_wirelist(inputs, old_wire, new_wire)?;
+        }
+        For(_, _, _, _, _) => {
+            // At the beginning of this method, we check if there is at least one For gate.
+            // If it is the case, we add Copy gates and return
+            // Therefor, this case is unreachable !!!
+            panic!("Unreachable case in replace_output_wires method.")
+        }
     }
     Ok(())
 }
@@ -988,3 +1137,28 @@ fn test_replace_output_wires_with_forbidden_free() {
     let test = replace_output_wires(&mut gates, &output_wires);
     assert!(test.is_err());
 }
+
+#[test]
+fn test_typed_gates() {
+    assert_eq!(
+        Gate::typed(1, Mul(3, 0, 2)).unwrap(),
+        Typed(1, Box::new(Mul(3, 0, 2)))
+    );
+    assert_eq!(Gate::typed(0, Free(0, None)).unwrap(), Free(0, None));
+    assert!(Gate::typed(1, Free(0, None)).is_err());
+    assert!(Gate::typed(1, Typed(2, Box::new(Mul(3, 0, 2)))).is_err());
+
+    // Typed gates are read as basic gates only.
+    let typed_mul: Gate = serde_json::from_str(r#"{"Typed":[1,{"Mul":[3,0,2]}]}"#).unwrap();
+    assert_eq!(typed_mul, Typed(1, Box::new(Mul(3, 0, 2))));
+    assert!(serde_json::from_str::<Gate>(r#"{"Typed":[1,{"Free":[0,null]}]}"#).is_err());
+    assert!(
+        serde_json::from_str::<Gate>(r#"{"Typed":[1,{"Typed":[2,{"Mul":[3,0,2]}]}]}"#).is_err()
+    );
+
+    let mut builder = FlatBufferBuilder::new();
+    let directive = Free(0, None).build_with_type(&mut builder, 1);
+    builder.finish(directive, None);
+    let directive = flatbuffers::get_root::<g::Directive>(builder.finished_data());
+    assert!(Gate::try_from(directive).is_err());
+}
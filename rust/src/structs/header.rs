@@ -4,15 +4,28 @@ use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use std::error::Error;
 
-use super::value::{build_value, try_from_value, Value};
+use super::field_presets::FieldPreset;
+use super::field_value::Field;
+use super::value::{
+    build_value, build_values_vector, try_from_value, try_from_values_vector, Value,
+};
 use crate::sieve_ir_generated::sieve_ir as g;
-use crate::structs::IR_VERSION;
+use crate::structs::{TypeId, IR_VERSION, IR_VERSION_2};
 
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Header {
     pub version: String,
+    #[serde(with = "crate::structs::value_serde")]
     pub field_characteristic: Value,
     pub field_degree: u32,
+    /// The fields of the types after the type 0, since IR version 2.0.0: the type 1 is the field
+    /// of characteristic `types[0]`, etc. The type 0 is the field of `field_characteristic`.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        with = "crate::structs::value_serde::vec"
+    )]
+    pub types: Vec<Value>,
 }
 
 impl Header {
@@ -22,6 +35,58 @@ impl Header {
             ..Self::default()
         }
     }
+
+    /// A header of IR version 2.0.0 with several types: the type 0 is the field of
+    /// `field_characteristic`, and the types 1, 2, etc. are the fields of `types`.
+    pub fn new_with_types(field_characteristic: Value, types: Vec<Value>) -> Self {
+        Self {
+            version: IR_VERSION_2.to_string(),
+            field_characteristic,
+            types,
+            ..Self::default()
+        }
+    }
+
+    /// The number of types, including the type 0.
+    pub fn type_count(&self) -> usize {
+        1 + self.types.len()
+    }
+
+    /// The field characteristic of a type, or None if the type is not declared.
+    pub fn type_characteristic(&self, type_id: TypeId) -> Option<&Value> {
+        match type_id {
+            0 => Some(&self.field_characteristic),
+            _ => self.types.get(type_id as usize - 1),
+        }
+    }
+
+    /// Whether the version of this header allows several types and conversions, i.e. whether it
+    /// is 2.0.0 or later.
+    pub fn supports_types(&self) -> bool {
+        self.version
+            .trim()
+            .split('.')
+            .next()
+            .and_then(|major| major.parse::<u32>().ok())
+            .map_or(false, |major| major >= 2)
+    }
+
+    /// The field of this header, to create and check FieldValues.
+    pub fn field(&self) -> Result<Field> {
+        Field::from_header(self)
+    }
+
+    /// A header of a well-known field by name, e.g. `bn254`, see `FIELD_PRESETS`.
+    pub fn from_preset(name: &str) -> Result<Self> {
+        FieldPreset::by_name(name)
+            .map(FieldPreset::header)
+            .ok_or_else(|| format!("Unknown field preset {}", name).into())
+    }
+
+    /// The well-known field of this header, if any.
+    pub fn field_preset(&self) -> Option<&'static FieldPreset> {
+        FieldPreset::from_header(self)
+    }
 }
 
 impl Default for Header {
@@ -30,6 +95,7 @@ impl Default for Header {
             version: IR_VERSION.to_string(),
             field_characteristic: vec![],
             field_degree: 1,
+            types: vec![],
         }
     }
 }
@@ -51,6 +117,10 @@ impl<'a> TryFrom<Option<g::Header<'a>>> for Header {
                     .ok_or_else(|| "Missing field characteristic")?,
             )?,
             field_degree: g_header.field_degree(),
+            types: match g_header.types() {
+                Some(types) => try_from_values_vector(types)?,
+                None => vec![],
+            },
         })
     }
 }
@@ -63,6 +133,12 @@ impl Header {
     ) -> WIPOffset<g::Header<'bldr>> {
         let version = Some(builder.create_string(&self.version));
         let field_characteristic = Some(build_value(builder, &self.field_characteristic));
+        // Headers without types are written as in IR version 1.
+        let types = if self.types.is_empty() {
+            None
+        } else {
+            Some(build_values_vector(builder, &self.types))
+        };
 
         g::Header::create(
             builder,
@@ -70,6 +146,7 @@ impl Header {
                 version,
                 field_characteristic,
                 field_degree: self.field_degree,
+                types,
             },
         )
     }
@@ -8,11 +8,16 @@ use std::io::Write;
 use super::header::Header;
 use crate::sieve_ir_generated::sieve_ir as g;
 use crate::structs::value::{build_values_vector, try_from_values_vector, Value};
+use crate::structs::TypeId;
 
 #[derive(Clone, Default, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Instance {
     pub header: Header,
+    #[serde(with = "crate::structs::value_serde::vec")]
     pub common_inputs: Vec<Value>,
+    /// The type of the values, since IR version 2.0.0. Each type has its own stream of values.
+    #[serde(default, skip_serializing_if = "crate::structs::is_default_type")]
+    pub type_id: TypeId,
 }
 
 impl<'a> TryFrom<g::Instance<'a>> for Instance {
@@ -27,6 +32,7 @@ impl<'a> TryFrom<g::Instance<'a>> for Instance {
                     .common_inputs()
                     .ok_or_else(|| "Missing common_input")?,
             )?,
+            type_id: g_instance.type_id(),
         })
     }
 }
@@ -57,6 +63,7 @@ impl Instance {
             &g::InstanceArgs {
                 header,
                 common_inputs,
+                type_id: self.type_id,
             },
         );
 
@@ -1,4 +1,5 @@
 use crate::sieve_ir_generated::sieve_ir as g;
+use crate::structs::wire::WireRanges;
 use crate::{Result, WireId};
 use flatbuffers::{FlatBufferBuilder, WIPOffset};
 use serde::{Deserialize, Serialize};
@@ -393,11 +394,316 @@ pub fn evaluate_iterexpr_list(
     iter_expr_list: &IterExprList,
     known_iterators: &HashMap<String, WireId>,
 ) -> Vec<WireId> {
-    iter_expr_list
-        .iter()
-        .flat_map(|element| {
-            evaluate_iterexpr_listelement(element, known_iterators)
-                .unwrap_or_else(|e| panic!("{}", e.to_string()))
-        })
+    IterExprListView::new(iter_expr_list, known_iterators)
+        .unwrap_or_else(|e| panic!("{}", e.to_string()))
+        .ids()
         .collect()
 }
+
+/// Evaluate an element of an IterExprList into a range of wires, or None if the range is empty.
+fn evaluate_iterexpr_range(
+    element: &IterExprListElement,
+    known_iterators: &HashMap<String, WireId>,
+) -> Result<Option<(WireId, WireId)>> {
+    Ok(match element {
+        Single(val) => {
+            let id = evaluate_iterexpr(val, known_iterators)?;
+            Some((id, id))
+        }
+        Range(first, last) => {
+            let first = evaluate_iterexpr(first, known_iterators)?;
+            let last = evaluate_iterexpr(last, known_iterators)?;
+            if first <= last {
+                Some((first, last))
+            } else {
+                None
+            }
+        }
+    })
+}
+
+/// A borrowed IterExprList with the values of its iterators, whose wires are evaluated on demand
+/// instead of being expanded.
+#[derive(Clone, Copy, Debug)]
+pub struct IterExprListView<'a> {
+    elements: &'a [IterExprListElement],
+    known_iterators: &'a HashMap<String, WireId>,
+}
+
+impl<'a> IterExprListView<'a> {
+    /// Check that all the expressions can be evaluated with these iterators.
+    pub fn new(
+        iter_expr_list: &'a [IterExprListElement],
+        known_iterators: &'a HashMap<String, WireId>,
+    ) -> Result<Self> {
+        for element in iter_expr_list {
+            evaluate_iterexpr_range(element, known_iterators)?;
+        }
+        Ok(IterExprListView {
+            elements: iter_expr_list,
+            known_iterators,
+        })
+    }
+}
+
+impl<'a> WireRanges for IterExprListView<'a> {
+    type Ranges = IterExprListRanges<'a>;
+
+    fn ranges(&self) -> IterExprListRanges<'a> {
+        IterExprListRanges {
+            elements: self.elements.iter(),
+            known_iterators: self.known_iterators,
+        }
+    }
+}
+
+/// The iterator over the ranges of an IterExprListView. Empty ranges are skipped.
+#[derive(Clone, Debug)]
+pub struct IterExprListRanges<'a> {
+    elements: std::slice::Iter<'a, IterExprListElement>,
+    known_iterators: &'a HashMap<String, WireId>,
+}
+
+impl<'a> Iterator for IterExprListRanges<'a> {
+    type Item = (WireId, WireId);
+
+    fn next(&mut self) -> Option<(WireId, WireId)> {
+        for element in &mut self.elements {
+            // The view checked that all the elements can be evaluated.
+            if let Ok(Some(range)) = evaluate_iterexpr_range(element, self.known_iterators) {
+                return Some(range);
+            }
+        }
+        None
+    }
+}
+
+/// An affine function `slope * iterator + offset` of the iterator of a loop.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AffineExpr {
+    pub slope: i128,
+    pub offset: i128,
+}
+
+impl AffineExpr {
+    pub fn constant(offset: i128) -> Self {
+        AffineExpr { slope: 0, offset }
+    }
+
+    /// Return the value for a given value of the iterator, or None on overflow.
+    pub fn evaluate(&self, iterator: i128) -> Option<i128> {
+        self.slope.checked_mul(iterator)?.checked_add(self.offset)
+    }
+
+    fn add(&self, other: &AffineExpr) -> Option<AffineExpr> {
+        Some(AffineExpr {
+            slope: self.slope.checked_add(other.slope)?,
+            offset: self.offset.checked_add(other.offset)?,
+        })
+    }
+
+    fn sub(&self, other: &AffineExpr) -> Option<AffineExpr> {
+        Some(AffineExpr {
+            slope: self.slope.checked_sub(other.slope)?,
+            offset: self.offset.checked_sub(other.offset)?,
+        })
+    }
+
+    fn scale(&self, factor: i128) -> Option<AffineExpr> {
+        Some(AffineExpr {
+            slope: self.slope.checked_mul(factor)?,
+            offset: self.offset.checked_mul(factor)?,
+        })
+    }
+}
+
+/// This function expresses an iterator expression as an affine function of the iterator
+/// `iterator_name`, replacing the other iterators by their value given in 'known_iterators'.
+/// It returns None if the expression is not affine (e.g. the square of the iterator), or if it
+/// uses an unknown iterator.
+pub fn affine_iterexpr(
+    iter_expr: &IterExprWireNumber,
+    iterator_name: &str,
+    known_iterators: &HashMap<String, WireId>,
+) -> Option<AffineExpr> {
+    let affine = |expr: &IterExprWireNumber| affine_iterexpr(expr, iterator_name, known_iterators);
+
+    match iter_expr {
+        IterExprConst(val) => Some(AffineExpr::constant(*val as i128)),
+        IterExprName(name) if name == iterator_name => Some(AffineExpr {
+            slope: 1,
+            offset: 0,
+        }),
+        IterExprName(name) => Some(AffineExpr::constant(*known_iterators.get(name)? as i128)),
+        IterExprAdd(left, right) => affine(left)?.add(&affine(right)?),
+        IterExprSub(left, right) => affine(left)?.sub(&affine(right)?),
+        IterExprMul(left, right) => {
+            let (left, right) = (affine(left)?, affine(right)?);
+            if left.slope == 0 {
+                right.scale(left.offset)
+            } else if right.slope == 0 {
+                left.scale(right.offset)
+            } else {
+                None
+            }
+        }
+        IterExprDivConst(numer, denom) => {
+            let (numer, denom) = (affine(numer)?, *denom as i128);
+            // The division is exact on the part which depends on the iterator.
+            if denom == 0 || numer.slope < 0 || numer.offset < 0 || numer.slope % denom != 0 {
+                return None;
+            }
+            Some(AffineExpr {
+                slope: numer.slope / denom,
+                offset: numer.offset / denom,
+            })
+        }
+    }
+}
+
+/// This function expresses each wire of an iterator expression list as an affine function of the
+/// iterator `iterator_name` (see `affine_iterexpr`). Ranges must have a constant length.
+/// It returns None if an expression is not affine, or if there are more than `max_wires` wires.
+pub fn affine_iterexpr_list(
+    iter_expr_list: &IterExprList,
+    iterator_name: &str,
+    known_iterators: &HashMap<String, WireId>,
+    max_wires: usize,
+) -> Option<Vec<AffineExpr>> {
+    let mut wires = vec![];
+    for element in iter_expr_list.iter() {
+        match element {
+            Single(val) => wires.push(affine_iterexpr(val, iterator_name, known_iterators)?),
+            Range(first, last) => {
+                let first = affine_iterexpr(first, iterator_name, known_iterators)?;
+                let last = affine_iterexpr(last, iterator_name, known_iterators)?;
+                if first.slope != last.slope {
+                    return None;
+                }
+                let mut offset = first.offset;
+                while offset <= last.offset {
+                    if wires.len() >= max_wires {
+                        return None;
+                    }
+                    wires.push(AffineExpr {
+                        slope: first.slope,
+                        offset,
+                    });
+                    offset += 1;
+                }
+            }
+        }
+        if wires.len() > max_wires {
+            return None;
+        }
+    }
+    Some(wires)
+}
+
+/// Return whether an iterator expression uses the iterator `iterator_name`.
+pub fn iterexpr_uses_iterator(iter_expr: &IterExprWireNumber, iterator_name: &str) -> bool {
+    match iter_expr {
+        IterExprConst(_) => false,
+        IterExprName(name) => name == iterator_name,
+        IterExprAdd(left, right) | IterExprSub(left, right) | IterExprMul(left, right) => {
+            iterexpr_uses_iterator(left, iterator_name)
+                || iterexpr_uses_iterator(right, iterator_name)
+        }
+        IterExprDivConst(numer, _) => iterexpr_uses_iterator(numer, iterator_name),
+    }
+}
+
+/// Return whether an iterator expression list uses the iterator `iterator_name`.
+pub fn iterexpr_list_uses_iterator(iter_expr_list: &IterExprList, iterator_name: &str) -> bool {
+    iter_expr_list.iter().any(|element| match element {
+        Single(val) => iterexpr_uses_iterator(val, iterator_name),
+        Range(first, last) => {
+            iterexpr_uses_iterator(first, iterator_name)
+                || iterexpr_uses_iterator(last, iterator_name)
+        }
+    })
+}
+
+#[test]
+fn test_affine_iterexpr() {
+    let mut known_iterators = HashMap::new();
+    known_iterators.insert("j".to_string(), 5);
+
+    // 2 * (i + j) - 3
+    let expr = IterExprSub(
+        Box::new(IterExprMul(
+            Box::new(IterExprConst(2)),
+            Box::new(IterExprAdd(
+                Box::new(IterExprName("i".into())),
+                Box::new(IterExprName("j".into())),
+            )),
+        )),
+        Box::new(IterExprConst(3)),
+    );
+    assert_eq!(
+        affine_iterexpr(&expr, "i", &known_iterators),
+        Some(AffineExpr {
+            slope: 2,
+            offset: 7
+        })
+    );
+
+    // i * i is not affine.
+    let square = IterExprMul(
+        Box::new(IterExprName("i".into())),
+        Box::new(IterExprName("i".into())),
+    );
+    assert_eq!(affine_iterexpr(&square, "i", &known_iterators), None);
+
+    // A range of 3 wires starting at 3 * i.
+    let list = vec![Range(
+        IterExprMul(
+            Box::new(IterExprConst(3)),
+            Box::new(IterExprName("i".into())),
+        ),
+        IterExprAdd(
+            Box::new(IterExprMul(
+                Box::new(IterExprConst(3)),
+                Box::new(IterExprName("i".into())),
+            )),
+            Box::new(IterExprConst(2)),
+        ),
+    )];
+    let wires = affine_iterexpr_list(&list, "i", &known_iterators, 10).unwrap();
+    assert_eq!(wires.len(), 3);
+    assert_eq!(wires[2].evaluate(4), Some(14));
+    assert_eq!(affine_iterexpr_list(&list, "i", &known_iterators, 2), None);
+
+    assert!(iterexpr_list_uses_iterator(&list, "i"));
+    assert!(!iterexpr_list_uses_iterator(&list, "j"));
+}
+
+#[test]
+fn test_iterexpr_list_view() -> Result<()> {
+    let mut known_iterators = HashMap::new();
+    known_iterators.insert("i".to_string(), 10);
+    let i = || Box::new(IterExprName("i".into()));
+
+    let list = vec![
+        Single(IterExprAdd(i(), Box::new(IterExprConst(1)))),
+        // An empty range.
+        Range(IterExprConst(5), IterExprConst(4)),
+        Range(
+            IterExprMul(i(), Box::new(IterExprConst(2))),
+            IterExprConst(u64::MAX),
+        ),
+    ];
+    let view = IterExprListView::new(&list, &known_iterators)?;
+    assert_eq!(
+        view.ranges().collect::<Vec<_>>(),
+        vec![(11, 11), (20, u64::MAX)]
+    );
+    assert_eq!(view.ids().take(3).collect::<Vec<_>>(), vec![11, 20, 21]);
+    assert_eq!(view.get(2), Some(21));
+    assert!(view.contains(1 << 40));
+    assert_eq!(view.find_duplicate(), None);
+
+    let unknown = vec![Single(IterExprName("j".into()))];
+    assert!(IterExprListView::new(&unknown, &known_iterators).is_err());
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use crate::sieve_ir_generated::sieve_ir as fb;
-use crate::{Instance, Relation, Result, Witness};
+use crate::structs::function::{CaseInvoke, ForLoopBody};
+use crate::structs::relation_view::RelationView;
+use crate::{Gate, Header, Instance, Relation, Result, TypeId, Value, Witness};
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use std::error::Error;
@@ -57,4 +59,153 @@ impl Message {
             Message::Relation(relation) => relation.write_into(writer),
         }
     }
+
+    /// Check that the values of this message are smaller than the field characteristic of its
+    /// header, or of their type, e.g. after reading it from JSON or YAML where values can be
+    /// written as numbers.
+    pub fn ensure_values_in_field(&self) -> Result<()> {
+        let (header, values): (&Header, Vec<(TypeId, &Value)>) = match self {
+            Message::Instance(instance) => (
+                &instance.header,
+                instance
+                    .common_inputs
+                    .iter()
+                    .map(|value| (instance.type_id, value))
+                    .collect(),
+            ),
+            Message::Witness(witness) => (
+                &witness.header,
+                witness
+                    .short_witness
+                    .iter()
+                    .map(|value| (witness.type_id, value))
+                    .collect(),
+            ),
+            Message::Relation(relation) => {
+                let mut values = vec![];
+                collect_gate_values(&relation.gates, 0, &mut values);
+                for function in &relation.functions {
+                    collect_gate_values(&function.body, 0, &mut values);
+                }
+                (&relation.header, values)
+            }
+        };
+        let mut fields = vec![];
+        for type_id in 0..header.type_count() {
+            let mut type_header = header.clone();
+            type_header.field_characteristic = header
+                .type_characteristic(type_id as TypeId)
+                .unwrap()
+                .clone();
+            fields.push(type_header.field()?);
+        }
+        for (type_id, value) in values {
+            let field = fields
+                .get(type_id as usize)
+                .ok_or_else(|| format!("Unknown type {}", type_id))?;
+            field.element(value)?;
+        }
+        Ok(())
+    }
+
+    /// Writes this Message as JSON on a single line, followed by a newline, as in JSON Lines.
+    /// The messages can be read back with `consumers::source::iterate_json_lines`.
+    pub fn write_json_line(&self, writer: &mut impl Write) -> Result<()> {
+        serde_json::to_writer(&mut *writer, self)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// The constants of gates with their type, including in nested loops, switches and anonymous
+/// calls.
+fn collect_gate_values<'a>(
+    gates: &'a [Gate],
+    type_id: TypeId,
+    values: &mut Vec<(TypeId, &'a Value)>,
+) {
+    for gate in gates {
+        match gate {
+            Gate::Constant(_, value)
+            | Gate::AddConstant(_, _, value)
+            | Gate::MulConstant(_, _, value) => values.push((type_id, value)),
+            Gate::AnonCall(_, _, _, _, subcircuit) => {
+                collect_gate_values(subcircuit, type_id, values)
+            }
+            Gate::Switch(_, _, cases, branches) => {
+                values.extend(cases.iter().map(|case| (type_id, case)));
+                for branch in branches {
+                    if let CaseInvoke::AbstractAnonCall(_, _, _, subcircuit) = branch {
+                        collect_gate_values(subcircuit, type_id, values);
+                    }
+                }
+            }
+            Gate::For(_, _, _, _, ForLoopBody::IterExprAnonCall(_, _, _, _, subcircuit)) => {
+                collect_gate_values(subcircuit, type_id, values)
+            }
+            Gate::Typed(type_id, gate) => {
+                collect_gate_values(std::slice::from_ref(gate.as_ref()), *type_id, values)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A Message read from a buffer, whose relation is not decoded upfront but read in place through
+/// a RelationView. Instances and witnesses are decoded as in Message.
+#[derive(Clone, Debug)]
+pub enum MessageView<'a> {
+    Instance(Instance),
+    Witness(Witness),
+    Relation(RelationView<'a>),
+}
+
+impl<'a> TryFrom<&'a [u8]> for MessageView<'a> {
+    type Error = Box<dyn Error>;
+
+    fn try_from(buffer: &'a [u8]) -> Result<Self> {
+        let msg = fb::get_size_prefixed_root_as_root(&buffer);
+
+        Ok(match msg.message_type() {
+            fb::Message::Instance => {
+                let fb_instance = msg.message_as_instance().unwrap();
+                MessageView::Instance(Instance::try_from(fb_instance)?)
+            }
+            fb::Message::Witness => {
+                let fb_witness = msg.message_as_witness().unwrap();
+                MessageView::Witness(Witness::try_from(fb_witness)?)
+            }
+            fb::Message::Relation => {
+                let fb_relation = msg.message_as_relation().unwrap();
+                MessageView::Relation(RelationView::from(fb_relation))
+            }
+            fb::Message::NONE => return Err("Invalid message type".into()),
+        })
+    }
+}
+
+#[test]
+fn test_ensure_values_in_field() -> Result<()> {
+    use crate::producers::examples::*;
+
+    for msg in &[
+        Message::Instance(example_instance()),
+        Message::Witness(example_witness()),
+        Message::Relation(example_relation()),
+    ] {
+        msg.ensure_values_in_field()?;
+    }
+
+    let mut witness = example_witness();
+    witness.short_witness.push(vec![101]);
+    assert!(Message::Witness(witness).ensure_values_in_field().is_err());
+
+    let mut relation = example_relation();
+    relation.functions[0]
+        .body
+        .push(Gate::Constant(10, vec![200, 0, 0, 0]));
+    assert!(Message::Relation(relation)
+        .ensure_values_in_field()
+        .is_err());
+    Ok(())
 }
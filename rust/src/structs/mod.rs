@@ -7,6 +7,9 @@ pub mod witness;
 /// A structure representing a message of type Relation.
 pub mod relation;
 
+/// RelationView reads a Relation message in place, decoding its gates on demand.
+pub mod relation_view;
+
 /// A common structure for message headers.
 pub mod header;
 
@@ -22,15 +25,40 @@ pub mod gates;
 /// Value holds a value assigned to a wire.
 pub mod value;
 
+/// Values can be written to JSON or YAML as decimal or hexadecimal strings instead of bytes.
+pub mod value_serde;
+
+/// FieldValue is a value tied to the field of a Header, with range checks and arithmetic.
+pub mod field_value;
+
+/// FieldPresets are well-known fields, to build headers by name.
+pub mod field_presets;
+
 /// WireList are used to defined a wire, a range of wires, or a mix of both
 pub mod wire;
 
 /// Handles the AnonGate stuff
 pub mod function;
+
+/// CallGraph finds recursive functions and orders functions after the functions they call.
+pub mod call_graph;
+
 /// Handles For loops related stuff (mainly because of iterator expression).
 pub mod iterators;
 
 /// Wires are identified by a numerical ID.
 pub type WireId = u64;
 
+/// Types are identified by a numerical ID since IR version 2.0.0. The type 0 is the field of the
+/// header, and the other types are listed in `Header::types`.
+pub type TypeId = u8;
+
 pub const IR_VERSION: &str = "1.0.0";
+
+/// Whether a type is the type 0, which is not written to JSON or YAML.
+pub(crate) fn is_default_type(type_id: &TypeId) -> bool {
+    *type_id == 0
+}
+
+/// The first version with several types, per-type instance and witness streams, and conversions.
+pub const IR_VERSION_2: &str = "2.0.0";
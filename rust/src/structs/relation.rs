@@ -29,6 +29,9 @@ pub const FUNCTION: u16 = 0x1000;
 pub const FOR: u16 = 0x2000;
 pub const SWITCH: u16 = 0x4000;
 pub const FOR_FUNCTION_SWITCH: u16 = FOR | FUNCTION | SWITCH;
+// Conversion gates between types, since IR version 2.0.0.
+pub const CONVERT: u16 = 0x8000;
+pub const ALL_FEATURES: u16 = FOR_FUNCTION_SWITCH | CONVERT;
 pub const SIMPLE: u16 = 0x0000;
 
 #[derive(Clone, Default, Debug, Eq, PartialEq, Deserialize, Serialize)]
@@ -226,13 +229,14 @@ fn create_gateset_string(gateset: u16) -> String {
 
 /// This helper function will parse the string stored in a FBS Relation::feature field
 /// and will translate it into a internal mask handling the same information.
-fn parse_feature_toggle(features: impl Into<String>) -> Result<u16> {
+pub fn parse_feature_toggle(features: impl Into<String>) -> Result<u16> {
     let mut ret: u16 = 0x0000;
     for substr in features.into().split(',') {
         match &substr.replace(" ", "")[..] {
             "@function" => ret |= FUNCTION,
             "@for" => ret |= FOR,
             "@switch" => ret |= SWITCH,
+            "@convert" => ret |= CONVERT,
             "simple" => return Ok(SIMPLE),
             "" => { /* DO NOTHING */ }
             _ => {
@@ -253,10 +257,10 @@ fn build_feature_toggle<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr>(
 /// This helper function will translate the features mask into an IR-compliant string by
 /// concatenating associated strings to activated features.
 fn create_feature_string(features: u16) -> String {
-    if (features & FOR_FUNCTION_SWITCH) == 0 {
+    if (features & ALL_FEATURES) == 0 {
         return "simple".into();
     }
-    let mut features_val = features & FOR_FUNCTION_SWITCH;
+    let mut features_val = features & ALL_FEATURES;
     let mut ret = String::new();
     while features_val != 0 {
         match features_val {
@@ -272,6 +276,10 @@ fn create_feature_string(features: u16) -> String {
                 ret += "@function,";
                 features_val ^= FUNCTION;
             }
+            _ if contains_feature(features_val, CONVERT) => {
+                ret += "@convert,";
+                features_val ^= CONVERT;
+            }
 
             _ => { /* DO NOTHING */ }
         }
@@ -391,6 +399,11 @@ fn test_parse_feature_toggles() -> Result<()> {
         FOR | SWITCH | FUNCTION
     );
 
+    assert_eq!(
+        parse_feature_toggle("@function,@convert").unwrap(),
+        FUNCTION | CONVERT
+    );
+
     assert!(parse_feature_toggle("for").is_err());
     assert!(parse_feature_toggle("@for, test").is_err());
 
@@ -412,6 +425,8 @@ fn test_create_feature_toggles() -> Result<()> {
     assert_eq!(create_feature_string(FOR), "@for,");
     assert_eq!(create_feature_string(SWITCH), "@switch,");
     assert_eq!(create_feature_string(FUNCTION), "@function,");
+    assert_eq!(create_feature_string(CONVERT), "@convert,");
+    assert_eq!(create_feature_string(FOR | CONVERT), "@for,@convert,");
 
     assert_eq!(create_feature_string(SIMPLE), "simple");
     assert_eq!(create_feature_string(0), "simple");
@@ -0,0 +1,296 @@
+use flatbuffers::{ForwardsUOffset, Vector};
+use std::convert::TryFrom;
+use std::error::Error;
+
+use super::function::Function;
+use super::gates::Gate;
+use super::header::Header;
+use super::relation::{parse_feature_toggle, parse_gate_set, Relation};
+use crate::sieve_ir_generated::sieve_ir as g;
+use crate::Result;
+
+/// RelationView reads a Relation message in place, without converting it into an owned
+/// `Relation`. Gates are decoded one at a time as they are iterated, and functions are looked up
+/// without decoding their bodies, so that consumers do not hold a second copy of large relations.
+///
+/// # Example
+/// ```
+/// use zki_sieve::producers::examples::example_relation;
+/// use zki_sieve::structs::relation_view::RelationView;
+/// use std::convert::TryFrom;
+///
+/// let relation = example_relation();
+/// let mut buffer = vec![];
+/// relation.write_into(&mut buffer).unwrap();
+///
+/// let view = RelationView::try_from(&buffer[..]).unwrap();
+/// assert_eq!(view.gates().unwrap().len(), relation.gates.len());
+/// assert_eq!(view.gates().unwrap().next().unwrap().unwrap(), relation.gates[0]);
+/// assert_eq!(view.function("com.example::mul").unwrap().input_count(), 2);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct RelationView<'a> {
+    g_relation: g::Relation<'a>,
+}
+
+impl<'a> From<g::Relation<'a>> for RelationView<'a> {
+    fn from(g_relation: g::Relation<'a>) -> Self {
+        RelationView { g_relation }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for RelationView<'a> {
+    type Error = Box<dyn Error>;
+
+    fn try_from(buffer: &'a [u8]) -> Result<Self> {
+        Ok(RelationView::from(
+            g::get_size_prefixed_root_as_root(buffer)
+                .message_as_relation()
+                .ok_or_else(|| "Not a Relation message.")?,
+        ))
+    }
+}
+
+impl<'a> RelationView<'a> {
+    pub fn header(&self) -> Result<Header> {
+        Header::try_from(self.g_relation.header())
+    }
+
+    pub fn gate_mask(&self) -> Result<u16> {
+        parse_gate_set(
+            self.g_relation
+                .gateset()
+                .ok_or_else(|| "Missing gateset description")?,
+        )
+    }
+
+    pub fn feat_mask(&self) -> Result<u16> {
+        parse_feature_toggle(
+            self.g_relation
+                .features()
+                .ok_or_else(|| "Missing feature toggles")?,
+        )
+    }
+
+    /// The functions defined by the relation, in order.
+    pub fn functions(&self) -> FunctionViews<'a> {
+        FunctionViews {
+            g_functions: self.g_relation.functions(),
+            next: 0,
+        }
+    }
+
+    /// Find a function by name. Only the names of the functions are read.
+    pub fn function(&self, name: &str) -> Option<FunctionView<'a>> {
+        self.functions()
+            .find(|function| function.name().map_or(false, |n| n == name))
+    }
+
+    /// The gates of the relation, decoded on demand.
+    pub fn gates(&self) -> Result<GateViews<'a>> {
+        let g_gates = self
+            .g_relation
+            .directives()
+            .ok_or_else(|| "Missing directives")?;
+        Ok(GateViews::new(g_gates))
+    }
+
+    /// Decode the whole relation.
+    pub fn to_relation(&self) -> Result<Relation> {
+        Relation::try_from(self.g_relation)
+    }
+}
+
+/// A function of a RelationView, whose body is decoded on demand.
+#[derive(Clone, Copy, Debug)]
+pub struct FunctionView<'a> {
+    g_function: g::Function<'a>,
+}
+
+impl<'a> FunctionView<'a> {
+    pub fn name(&self) -> Result<&'a str> {
+        self.g_function.name().ok_or_else(|| "Missing name".into())
+    }
+
+    pub fn output_count(&self) -> usize {
+        self.g_function.output_count() as usize
+    }
+
+    pub fn input_count(&self) -> usize {
+        self.g_function.input_count() as usize
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.g_function.instance_count() as usize
+    }
+
+    pub fn witness_count(&self) -> usize {
+        self.g_function.witness_count() as usize
+    }
+
+    /// The gates of the body, decoded on demand.
+    pub fn body(&self) -> Result<GateViews<'a>> {
+        let g_gates = self
+            .g_function
+            .body()
+            .ok_or_else(|| "Missing reference implementation")?;
+        Ok(GateViews::new(g_gates))
+    }
+
+    /// Decode the whole function, including its body.
+    pub fn to_function(&self) -> Result<Function> {
+        Function::try_from(self.g_function)
+    }
+}
+
+/// The iterator over the functions of a RelationView.
+pub struct FunctionViews<'a> {
+    g_functions: Option<Vector<'a, ForwardsUOffset<g::Function<'a>>>>,
+    next: usize,
+}
+
+impl<'a> Iterator for FunctionViews<'a> {
+    type Item = FunctionView<'a>;
+
+    fn next(&mut self) -> Option<FunctionView<'a>> {
+        let g_functions = self.g_functions.as_ref()?;
+        if self.next >= g_functions.len() {
+            return None;
+        }
+        let g_function = g_functions.get(self.next);
+        self.next += 1;
+        Some(FunctionView { g_function })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self
+            .g_functions
+            .as_ref()
+            .map_or(0, |g_functions| g_functions.len());
+        let remaining = len.saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for FunctionViews<'a> {}
+
+/// The iterator over a list of gates, which decodes each gate when it is reached. Gates nested
+/// in a loop, a switch or an anonymous call are decoded with the gate that contains them.
+pub struct GateViews<'a> {
+    g_gates: Vector<'a, ForwardsUOffset<g::Directive<'a>>>,
+    next: usize,
+}
+
+impl<'a> GateViews<'a> {
+    fn new(g_gates: Vector<'a, ForwardsUOffset<g::Directive<'a>>>) -> Self {
+        GateViews { g_gates, next: 0 }
+    }
+
+    /// Decode the gate at a position of the list, whatever the position of the iterator.
+    pub fn get(&self, index: usize) -> Option<Result<Gate>> {
+        if index >= self.g_gates.len() {
+            return None;
+        }
+        Some(Gate::try_from(self.g_gates.get(index)))
+    }
+}
+
+impl<'a> Iterator for GateViews<'a> {
+    type Item = Result<Gate>;
+
+    fn next(&mut self) -> Option<Result<Gate>> {
+        let gate = self.get(self.next)?;
+        self.next += 1;
+        Some(gate)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.g_gates.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for GateViews<'a> {}
+
+#[test]
+fn test_relation_view() -> Result<()> {
+    use crate::producers::examples::*;
+
+    let relation = example_relation();
+    let mut buffer = vec![];
+    relation.write_into(&mut buffer)?;
+    let view = RelationView::try_from(&buffer[..])?;
+
+    assert_eq!(view.header()?, relation.header);
+    assert_eq!(view.gate_mask()?, relation.gate_mask);
+    assert_eq!(view.feat_mask()?, relation.feat_mask);
+    assert_eq!(view.to_relation()?, relation);
+
+    let gates = view.gates()?;
+    assert_eq!(gates.len(), relation.gates.len());
+    assert_eq!(gates.get(2).unwrap()?, relation.gates[2]);
+    assert!(gates.get(relation.gates.len()).is_none());
+    assert_eq!(gates.collect::<Result<Vec<Gate>>>()?, relation.gates);
+
+    assert_eq!(view.functions().len(), relation.functions.len());
+    for (function, expected) in view.functions().zip(relation.functions.iter()) {
+        assert_eq!(function.name()?, expected.name);
+        assert_eq!(function.output_count(), expected.output_count);
+        assert_eq!(function.witness_count(), expected.witness_count);
+        assert_eq!(
+            function.body()?.collect::<Result<Vec<Gate>>>()?,
+            expected.body
+        );
+        assert_eq!(&function.to_function()?, expected);
+    }
+    assert!(view.function("unknown").is_none());
+
+    // Not a relation.
+    let mut buffer = vec![];
+    example_instance().write_into(&mut buffer)?;
+    assert!(RelationView::try_from(&buffer[..]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_consumers_over_relation_view() -> Result<()> {
+    use crate::consumers::evaluator::{Evaluator, PlaintextBackend};
+    use crate::consumers::stats::Stats;
+    use crate::consumers::validator::Validator;
+    use crate::producers::examples::*;
+    use crate::structs::message::MessageView;
+    use crate::Message;
+
+    let messages = vec![
+        Message::Instance(example_instance()),
+        Message::Witness(example_witness()),
+        Message::Relation(example_relation()),
+    ];
+    let mut buffers = vec![];
+    for msg in messages.iter() {
+        let mut buffer = vec![];
+        msg.write_into(&mut buffer)?;
+        buffers.push(buffer);
+    }
+
+    let mut validator = Validator::new_as_prover();
+    let mut evaluator = Evaluator::default();
+    let mut backend = PlaintextBackend::default();
+    let mut stats = Stats::effective();
+    for buffer in buffers.iter() {
+        let msg = MessageView::try_from(&buffer[..])?;
+        validator.ingest_message_view(&msg);
+        evaluator.ingest_message_view(&msg, &mut backend);
+        stats.ingest_message_view(&msg);
+    }
+    assert_eq!(validator.get_violations(), Vec::<String>::new());
+    assert_eq!(evaluator.get_violations(), Vec::<String>::new());
+
+    // The same statistics as from the decoded messages.
+    let mut expected_stats = Stats::effective();
+    for msg in messages.iter() {
+        expected_stats.ingest_message(msg);
+    }
+    assert_eq!(stats, expected_stats);
+    Ok(())
+}
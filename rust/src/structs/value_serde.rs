@@ -0,0 +1,502 @@
+use crate::{Result, Value};
+use num_bigint::BigUint;
+use num_traits::Num;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::{
+    SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// How values are written by Serde, e.g. to JSON or YAML. Values are read in any of these forms.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueFormat {
+    /// Arrays of bytes, least-significant first, e.g. `[101, 0]`. This is the default.
+    Bytes,
+    /// Decimal strings, e.g. `"101"`.
+    Decimal,
+    /// Hexadecimal strings, most-significant first, with all the bytes, e.g. `"0x0065"`.
+    Hex,
+}
+
+impl Default for ValueFormat {
+    fn default() -> Self {
+        ValueFormat::Bytes
+    }
+}
+
+impl std::str::FromStr for ValueFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, String> {
+        match name {
+            "bytes" => Ok(ValueFormat::Bytes),
+            "decimal" => Ok(ValueFormat::Decimal),
+            "hex" => Ok(ValueFormat::Hex),
+            _ => Err(format!(
+                "Unknown value format {} (expected bytes, decimal or hex)",
+                name
+            )),
+        }
+    }
+}
+
+/// Write a value as a decimal or hexadecimal string.
+pub fn value_to_string(value: &[u8], format: ValueFormat) -> String {
+    match format {
+        ValueFormat::Hex => {
+            let digits: String = value.iter().rev().map(|b| format!("{:02x}", b)).collect();
+            format!("0x{}", digits)
+        }
+        _ => BigUint::from_bytes_le(value).to_str_radix(10),
+    }
+}
+
+/// Parse a value from a decimal string, or a hexadecimal string starting with `0x`. The bytes of
+/// a hexadecimal string are kept, including leading zeros.
+pub fn parse_value(s: &str) -> Result<Value> {
+    let s = s.trim();
+    if let Some(digits) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid hexadecimal value {}", s).into());
+        }
+        let digits = digits.as_bytes();
+        let mut value = Vec::with_capacity((digits.len() + 1) / 2);
+        for chunk in digits.rchunks(2) {
+            let chunk = std::str::from_utf8(chunk)?;
+            value.push(u8::from_str_radix(chunk, 16)?);
+        }
+        Ok(value)
+    } else {
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Invalid decimal value {}", s).into());
+        }
+        Ok(BigUint::from_str_radix(s, 10)?.to_bytes_le())
+    }
+}
+
+/// Serialize a value as bytes, which `WithValueFormat` writes in its own format.
+/// Use with `#[serde(with = "crate::structs::value_serde")]`.
+pub fn serialize<S: Serializer>(
+    value: &Value,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(value)
+}
+
+/// Deserialize a value from an array of bytes, a number, or a decimal or hexadecimal string.
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Value, D::Error> {
+    deserializer.deserialize_any(ValueVisitor)
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of bytes, or a decimal or 0x hexadecimal string")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Value, A::Error> {
+        let mut value = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            value.push(byte);
+        }
+        Ok(value)
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> std::result::Result<Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> std::result::Result<Value, E> {
+        parse_value(v).map_err(E::custom)
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> std::result::Result<Value, E> {
+        Ok(BigUint::from(v).to_bytes_le())
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> std::result::Result<Value, E> {
+        if v < 0 {
+            return Err(E::custom(format!("Negative value {}", v)));
+        }
+        self.visit_u64(v as u64)
+    }
+}
+
+/// Serialize anything containing values, such as `Messages` or a single `Message`, with the
+/// values written in the given format.
+///
+/// # Example
+/// ```
+/// use zki_sieve::producers::examples::example_header;
+/// use zki_sieve::structs::value_serde::{ValueFormat, WithValueFormat};
+///
+/// let header = example_header();
+/// let json = serde_json::to_string(&WithValueFormat::new(ValueFormat::Decimal, &header)).unwrap();
+/// assert!(json.contains(r#""field_characteristic":"101""#));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct WithValueFormat<'a, T: ?Sized> {
+    pub format: ValueFormat,
+    pub inner: &'a T,
+}
+
+impl<'a, T: ?Sized> WithValueFormat<'a, T> {
+    pub fn new(format: ValueFormat, inner: &'a T) -> Self {
+        WithValueFormat { format, inner }
+    }
+}
+
+impl<'a, T: ?Sized + Serialize> Serialize for WithValueFormat<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.inner.serialize(FormatSerializer {
+            inner: serializer,
+            format: self.format,
+        })
+    }
+}
+
+/// Forward everything to the inner serializer, except that the bytes of values are written in
+/// `format`. Nested data is wrapped in `WithValueFormat` to keep the format.
+struct FormatSerializer<S> {
+    inner: S,
+    format: ValueFormat,
+}
+
+impl<S: Serializer> FormatSerializer<S> {
+    fn wrap<'a, T: ?Sized>(&self, inner: &'a T) -> WithValueFormat<'a, T> {
+        WithValueFormat::new(self.format, inner)
+    }
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> std::result::Result<S::Ok, S::Error> {
+                self.inner.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<S: Serializer> Serializer for FormatSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = FormatSerializer<S::SerializeSeq>;
+    type SerializeTuple = FormatSerializer<S::SerializeTuple>;
+    type SerializeTupleStruct = FormatSerializer<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = FormatSerializer<S::SerializeTupleVariant>;
+    type SerializeMap = FormatSerializer<S::SerializeMap>;
+    type SerializeStruct = FormatSerializer<S::SerializeStruct>;
+    type SerializeStructVariant = FormatSerializer<S::SerializeStructVariant>;
+
+    forward! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> std::result::Result<S::Ok, S::Error> {
+        match self.format {
+            ValueFormat::Bytes => self.inner.serialize_bytes(v),
+            format => self.inner.serialize_str(&value_to_string(v, format)),
+        }
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_some(&value)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner.serialize_newtype_struct(name, &value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let value = self.wrap(value);
+        self.inner
+            .serialize_newtype_variant(name, index, variant, &value)
+    }
+
+    fn serialize_seq(
+        self,
+        len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeSeq, S::Error> {
+        let format = self.format;
+        Ok(FormatSerializer {
+            inner: self.inner.serialize_seq(len)?,
+            format,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> std::result::Result<Self::SerializeTuple, S::Error> {
+        let format = self.format;
+        Ok(FormatSerializer {
+            inner: self.inner.serialize_tuple(len)?,
+            format,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> std::result::Result<Self::SerializeTupleStruct, S::Error> {
+        let format = self.format;
+        Ok(FormatSerializer {
+            inner: self.inner.serialize_tuple_struct(name, len)?,
+            format,
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> std::result::Result<Self::SerializeTupleVariant, S::Error> {
+        let format = self.format;
+        Ok(FormatSerializer {
+            inner: self
+                .inner
+                .serialize_tuple_variant(name, index, variant, len)?,
+            format,
+        })
+    }
+
+    fn serialize_map(
+        self,
+        len: Option<usize>,
+    ) -> std::result::Result<Self::SerializeMap, S::Error> {
+        let format = self.format;
+        Ok(FormatSerializer {
+            inner: self.inner.serialize_map(len)?,
+            format,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> std::result::Result<Self::SerializeStruct, S::Error> {
+        let format = self.format;
+        Ok(FormatSerializer {
+            inner: self.inner.serialize_struct(name, len)?,
+            format,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> std::result::Result<Self::SerializeStructVariant, S::Error> {
+        let format = self.format;
+        Ok(FormatSerializer {
+            inner: self
+                .inner
+                .serialize_struct_variant(name, index, variant, len)?,
+            format,
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+macro_rules! forward_compound {
+    ($trait:ident, $method:ident($($arg:ident: $ty:ty),*)) => {
+        impl<C: $trait> $trait for FormatSerializer<C> {
+            type Ok = C::Ok;
+            type Error = C::Error;
+
+            fn $method<T: ?Sized + Serialize>(
+                &mut self,
+                $($arg: $ty,)*
+                value: &T,
+            ) -> std::result::Result<(), C::Error> {
+                let value = WithValueFormat::new(self.format, value);
+                self.inner.$method($($arg,)* &value)
+            }
+
+            fn end(self) -> std::result::Result<C::Ok, C::Error> {
+                self.inner.end()
+            }
+        }
+    };
+}
+
+forward_compound!(SerializeSeq, serialize_element());
+forward_compound!(SerializeTuple, serialize_element());
+forward_compound!(SerializeTupleStruct, serialize_field());
+forward_compound!(SerializeTupleVariant, serialize_field());
+forward_compound!(SerializeStruct, serialize_field(key: &'static str));
+forward_compound!(SerializeStructVariant, serialize_field(key: &'static str));
+
+impl<C: SerializeMap> SerializeMap for FormatSerializer<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(
+        &mut self,
+        key: &T,
+    ) -> std::result::Result<(), C::Error> {
+        let key = WithValueFormat::new(self.format, key);
+        self.inner.serialize_key(&key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> std::result::Result<(), C::Error> {
+        let value = WithValueFormat::new(self.format, value);
+        self.inner.serialize_value(&value)
+    }
+
+    fn end(self) -> std::result::Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+/// Serialize and deserialize lists of values, as `value_serde` does for a single value.
+/// Use with `#[serde(with = "crate::structs::value_serde::vec")]`.
+pub mod vec {
+    use super::*;
+
+    struct ValueRef<'a>(&'a Value);
+
+    impl<'a> Serialize for ValueRef<'a> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            super::serialize(self.0, serializer)
+        }
+    }
+
+    struct OwnedValue(Value);
+
+    impl<'de> Deserialize<'de> for OwnedValue {
+        fn deserialize<D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Self, D::Error> {
+            super::deserialize(deserializer).map(OwnedValue)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        values: &[Value],
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(ValueRef))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Vec<Value>, D::Error> {
+        let values = Vec::<OwnedValue>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|v| v.0).collect())
+    }
+}
+
+#[test]
+fn test_value_serde() -> Result<()> {
+    use crate::producers::examples::*;
+    use crate::structs::messages::Messages;
+    use crate::Message;
+
+    assert_eq!(parse_value("101")?, vec![101]);
+    assert_eq!(parse_value("0x0065")?, vec![101, 0]);
+    assert_eq!(parse_value("0x100")?, vec![0, 1]);
+    assert_eq!(parse_value("0")?, vec![0]);
+    assert!(parse_value("-1").is_err());
+    assert_eq!(parse_value("0x")?, Vec::<u8>::new());
+    assert!(parse_value("12a").is_err());
+    assert_eq!(value_to_string(&[101, 0], ValueFormat::Decimal), "101");
+    assert_eq!(value_to_string(&[101, 0], ValueFormat::Hex), "0x0065");
+
+    let mut messages = Messages::default();
+    messages.push_message(&Message::Instance(example_instance()));
+    messages.push_message(&Message::Witness(example_witness()));
+    messages.push_message(&Message::Relation(example_relation()));
+
+    // The bytes are kept by the default format and the hexadecimal format.
+    for &format in &[ValueFormat::Bytes, ValueFormat::Hex] {
+        let json = serde_json::to_string(&WithValueFormat::new(format, &messages))?;
+        assert_eq!(serde_json::from_str::<Messages>(&json)?, messages);
+        let yaml = serde_yaml::to_string(&WithValueFormat::new(format, &messages))?;
+        assert_eq!(serde_yaml::from_str::<Messages>(&yaml)?, messages);
+    }
+    // The default format is the same as without the wrapper.
+    assert_eq!(
+        serde_json::to_string(&WithValueFormat::new(ValueFormat::Bytes, &messages))?,
+        serde_json::to_string(&messages)?
+    );
+    let json = serde_json::to_string(&WithValueFormat::new(ValueFormat::Hex, &messages))?;
+    assert!(json.contains(r#""field_characteristic":"0x00000065""#));
+
+    // Decimal strings are read back as compact values.
+    let json = serde_json::to_string(&WithValueFormat::new(
+        ValueFormat::Decimal,
+        &example_witness(),
+    ))?;
+    assert!(json.contains(r#""short_witness":["3","4","0","36"]"#));
+    let witness: crate::Witness = serde_json::from_str(&json)?;
+    assert_eq!(
+        witness.short_witness,
+        vec![vec![3], vec![4], vec![0], vec![36]]
+    );
+
+    // The forms can be mixed, and numbers are accepted.
+    let yaml = r#"
+header:
+  version: 1.0.0
+  field_characteristic: "0x65"
+  field_degree: 1
+short_witness: [[3, 0], "4", "0x00", 36]
+"#;
+    let witness: crate::Witness = serde_yaml::from_str(yaml)?;
+    assert_eq!(witness.header.field_characteristic, vec![101]);
+    assert_eq!(
+        witness.short_witness,
+        vec![vec![3, 0], vec![4], vec![0], vec![36]]
+    );
+    assert!(serde_yaml::from_str::<crate::Witness>(&yaml.replace("36", "-36")).is_err());
+    Ok(())
+}
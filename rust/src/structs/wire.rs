@@ -5,6 +5,8 @@ use flatbuffers::{FlatBufferBuilder, ForwardsUOffset, Vector, WIPOffset};
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 use std::error::Error;
+use std::iter::{Copied, FlatMap};
+use std::ops::RangeInclusive;
 
 /// A WireListElement is either a single wire, or a range.
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Deserialize, Serialize)]
@@ -195,13 +197,171 @@ pub fn expand_wirelistelement(wire: &WireListElement) -> Vec<Result<WireId>> {
 
 /// Expand a WireList into a vector of individual WireId.
 pub fn expand_wirelist(wirelist: &WireList) -> Result<Vec<WireId>> {
-    let res = wirelist
-        .iter()
-        .flat_map(|wire| expand_wirelistelement(wire))
-        .collect::<Result<Vec<WireId>>>()?;
-    Ok(res)
+    Ok(WireListView::new(wirelist)?.ids().collect())
+}
+
+// =========================================
+//       Lazy iteration over lists of wires
+// =========================================
+
+/// A list of wires made of ranges, such as a WireList or an evaluated IterExprList. Its wires can
+/// be counted, indexed and compared without expanding the ranges.
+pub trait WireRanges {
+    type Ranges: Iterator<Item = (WireId, WireId)>;
+
+    /// The ranges of wires as (first, last) included, in the order of the list.
+    fn ranges(&self) -> Self::Ranges;
+
+    /// The wires one by one, in the order of the list.
+    fn ids(&self) -> WireIds<Self::Ranges> {
+        self.ranges()
+            .flat_map(range_ids as fn((WireId, WireId)) -> RangeInclusive<WireId>)
+    }
+
+    /// The number of wires, or usize::MAX if there are more.
+    fn len(&self) -> usize {
+        self.ranges().fold(0usize, |len, (first, last)| {
+            let range_len = usize::try_from(last - first).unwrap_or(usize::MAX);
+            len.saturating_add(range_len).saturating_add(1)
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges().next().is_none()
+    }
+
+    /// The wire at a position of the list.
+    fn get(&self, index: usize) -> Option<WireId> {
+        let mut index = index as u64;
+        for (first, last) in self.ranges() {
+            if index <= last - first {
+                return Some(first + index);
+            }
+            index -= last - first + 1;
+        }
+        None
+    }
+
+    fn contains(&self, id: WireId) -> bool {
+        self.ranges().any(|(first, last)| first <= id && id <= last)
+    }
+
+    /// A wire which is in both lists, if any.
+    fn find_overlap(&self, other: &impl WireRanges) -> Option<WireId> {
+        for range in self.ranges() {
+            for other_range in other.ranges() {
+                if let Some(id) = overlap(range, other_range) {
+                    return Some(id);
+                }
+            }
+        }
+        None
+    }
+
+    /// A wire which appears more than once in the list, if any.
+    fn find_duplicate(&self) -> Option<WireId> {
+        for (i, range) in self.ranges().enumerate() {
+            for other_range in self.ranges().skip(i + 1) {
+                if let Some(id) = overlap(range, other_range) {
+                    return Some(id);
+                }
+            }
+        }
+        None
+    }
 }
 
+/// The iterator over the wires of a list, returned by `WireRanges::ids`.
+pub type WireIds<R> =
+    FlatMap<R, RangeInclusive<WireId>, fn((WireId, WireId)) -> RangeInclusive<WireId>>;
+
+fn range_ids((first, last): (WireId, WireId)) -> RangeInclusive<WireId> {
+    first..=last
+}
+
+/// The first wire of two ranges which overlap, if any.
+fn overlap(
+    (first, last): (WireId, WireId),
+    (other_first, other_last): (WireId, WireId),
+) -> Option<WireId> {
+    if first <= other_last && other_first <= last {
+        Some(first.max(other_first))
+    } else {
+        None
+    }
+}
+
+/// Ranges already evaluated, e.g. from an IterExprList which cannot be borrowed.
+impl<'a> WireRanges for &'a [(WireId, WireId)] {
+    type Ranges = Copied<std::slice::Iter<'a, (WireId, WireId)>>;
+
+    fn ranges(&self) -> Self::Ranges {
+        self.iter().copied()
+    }
+}
+
+/// A borrowed WireList whose ranges are valid, to iterate over its wires without expanding it.
+#[derive(Clone, Copy, Debug)]
+pub struct WireListView<'a> {
+    elements: &'a [WireListElement],
+}
+
+impl<'a> WireListView<'a> {
+    /// Check that the last wire of each WireRange is strictly greater than the first one.
+    pub fn new(wirelist: &'a [WireListElement]) -> Result<Self> {
+        for element in wirelist {
+            if let WireRange(first, last) = element {
+                if last <= first {
+                    return Err(format!(
+                        "In WireRange, last WireId ({}) must be strictly greater than first WireId ({}).",
+                        last, first
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(WireListView { elements: wirelist })
+    }
+
+    /// A view without wires.
+    pub fn empty() -> Self {
+        WireListView { elements: &[] }
+    }
+}
+
+impl<'a> WireRanges for WireListView<'a> {
+    type Ranges = WireListRanges<'a>;
+
+    fn ranges(&self) -> WireListRanges<'a> {
+        WireListRanges {
+            elements: self.elements.iter(),
+        }
+    }
+}
+
+/// The iterator over the ranges of a WireListView.
+#[derive(Clone, Debug)]
+pub struct WireListRanges<'a> {
+    elements: std::slice::Iter<'a, WireListElement>,
+}
+
+impl<'a> Iterator for WireListRanges<'a> {
+    type Item = (WireId, WireId);
+
+    fn next(&mut self) -> Option<(WireId, WireId)> {
+        self.elements.next().map(|element| match element {
+            Wire(id) => (*id, *id),
+            WireRange(first, last) => (*first, *last),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for WireListRanges<'a> {}
+
 #[test]
 fn test_expand_wirelist() {
     let wirelist = vec![WireRange(0, 2), Wire(5)];
@@ -218,6 +378,43 @@ fn test_expand_wirelist() {
     assert!(new_wirelist.is_err());
 }
 
+#[test]
+fn test_wirelist_view() -> Result<()> {
+    let wirelist = vec![Wire(7), WireRange(10, 12), WireRange(1 << 40, u64::MAX)];
+    let view = WireListView::new(&wirelist)?;
+    assert_eq!(
+        view.ranges().collect::<Vec<_>>(),
+        vec![(7, 7), (10, 12), (1 << 40, u64::MAX)]
+    );
+    assert_eq!(
+        view.ids().take(5).collect::<Vec<_>>(),
+        vec![7, 10, 11, 12, 1 << 40]
+    );
+    assert_eq!(view.len(), usize::MAX);
+    assert_eq!(view.get(3), Some(12));
+    assert_eq!(view.get(6), Some((1 << 40) + 2));
+    assert!(view.contains(11));
+    assert!(!view.contains(8));
+    assert_eq!(view.find_duplicate(), None);
+
+    let other = vec![WireRange(0, 3), WireRange(11, 20)];
+    let other = WireListView::new(&other)?;
+    assert_eq!(other.len(), 14);
+    assert_eq!(other.get(14), None);
+    assert_eq!(view.find_overlap(&other), Some(11));
+
+    let duplicated = vec![WireRange(0, 3), Wire(5), WireRange(3, 4)];
+    assert_eq!(WireListView::new(&duplicated)?.find_duplicate(), Some(3));
+
+    let ranges: &[(WireId, WireId)] = &[(5, 6)];
+    assert_eq!(ranges.ids().collect::<Vec<_>>(), vec![5, 6]);
+    assert_eq!(ranges.find_overlap(&other), None);
+
+    assert!(WireListView::new(&[WireRange(4, 2)]).is_err());
+    assert!(WireListView::empty().is_empty());
+    Ok(())
+}
+
 pub fn wirelist_len(wirelist: &WireList) -> usize {
     wirelist
         .iter()
@@ -235,6 +432,9 @@ pub(crate) fn replace_wire_in_wirelist(
     old_wire: WireId,
     new_wire: WireId,
 ) -> Result<()> {
+    if !WireListView::new(wirelist)?.contains(old_wire) {
+        return Ok(());
+    }
     let mut wires = expand_wirelist(wirelist)?;
     let mut updated = false;
     for wire in wires.iter_mut() {